@@ -0,0 +1,230 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! automount_autofs talks to the kernel autofs: mount the autofs, read the requests from
+//! the pipe, and answer them by the ioctls of /dev/autofs.
+//!
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::mount::{mount, MsFlags};
+use nix::sys::stat::stat;
+use nix::unistd::{self, getpgrp};
+use std::fs::File;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use sysmaster::error::*;
+
+const AUTOFS_DEV: &str = "/dev/autofs";
+const AUTOFS_PROTO_VERSION: u32 = 5;
+
+const AUTOFS_DEV_IOCTL_VERSION_MAJOR: u32 = 1;
+const AUTOFS_DEV_IOCTL_VERSION_MINOR: u32 = 0;
+const AUTOFS_IOCTL: u8 = 0x93;
+const AUTOFS_DEV_IOCTL_OPENMOUNT_CMD: u8 = 0x74;
+const AUTOFS_DEV_IOCTL_READY_CMD: u8 = 0x76;
+const AUTOFS_DEV_IOCTL_FAIL_CMD: u8 = 0x77;
+const AUTOFS_DEV_IOCTL_TIMEOUT_CMD: u8 = 0x7a;
+const AUTOFS_DEV_IOCTL_EXPIRE_CMD: u8 = 0x7c;
+
+/// a direct mount point is accessed and should be mounted
+pub(super) const AUTOFS_PTYPE_MISSING_DIRECT: i32 = 5;
+/// a direct mount point is idle and should be unmounted
+pub(super) const AUTOFS_PTYPE_EXPIRE_DIRECT: i32 = 6;
+
+/// struct autofs_dev_ioctl in linux/auto_dev-ioctl.h, the union of the
+/// parameters is 8 bytes, a path follows it for some commands, only the layout is used
+#[allow(dead_code)]
+#[repr(C)]
+struct AutofsDevIoctl {
+    ver_major: u32,
+    ver_minor: u32,
+    size: u32,
+    ioctlfd: i32,
+    args: [u32; 2],
+}
+
+/// struct autofs_v5_packet in linux/auto_fs.h, only the layout is used
+#[allow(dead_code)]
+#[repr(C)]
+struct AutofsV5Packet {
+    proto_version: i32,
+    ptype: i32,
+    wait_queue_token: u32,
+    dev: u32,
+    ino: u64,
+    uid: u32,
+    gid: u32,
+    pid: u32,
+    tgid: u32,
+    len: u32,
+    name: [u8; 256],
+}
+
+/// the request read from the autofs pipe
+pub(super) struct AutofsPacket {
+    pub(super) ptype: i32,
+    pub(super) token: u32,
+}
+
+fn dev_ioctl(cmd: u8, ioctlfd: RawFd, args: [u32; 2], path: Option<&str>) -> Result<RawFd> {
+    let dev = File::open(AUTOFS_DEV).context(IoSnafu)?;
+
+    let header_size = mem::size_of::<AutofsDevIoctl>();
+    let mut buf = Vec::with_capacity(header_size);
+    for v in [
+        AUTOFS_DEV_IOCTL_VERSION_MAJOR,
+        AUTOFS_DEV_IOCTL_VERSION_MINOR,
+        0,
+        ioctlfd as u32,
+        args[0],
+        args[1],
+    ] {
+        buf.extend_from_slice(&v.to_ne_bytes());
+    }
+    if let Some(p) = path {
+        buf.extend_from_slice(p.as_bytes());
+        buf.push(0);
+    }
+    let size = buf.len() as u32;
+    buf[8..12].copy_from_slice(&size.to_ne_bytes());
+
+    let request = nix::request_code_readwrite!(AUTOFS_IOCTL, cmd, header_size);
+    let ret = unsafe { libc::ioctl(dev.as_raw_fd(), request as _, buf.as_mut_ptr()) };
+    Errno::result(ret).context(NixSnafu)?;
+
+    let mut fd = [0u8; 4];
+    fd.copy_from_slice(&buf[12..16]);
+    Ok(i32::from_ne_bytes(fd))
+}
+
+/// mount the autofs at the mount point, the requests are written to pipe_fd
+pub(super) fn autofs_mount(mount_where: &str, pipe_fd: RawFd) -> Result<()> {
+    let options = format!(
+        "fd={},pgrp={},minproto={ver},maxproto={ver},direct",
+        pipe_fd,
+        getpgrp(),
+        ver = AUTOFS_PROTO_VERSION
+    );
+    mount(
+        Some("sysmaster"),
+        mount_where,
+        Some("autofs"),
+        MsFlags::empty(),
+        Some(options.as_str()),
+    )
+    .context(NixSnafu)
+}
+
+/// open the fd used to control the autofs mounted at the mount point
+pub(super) fn autofs_open_ioctl_fd(mount_where: &str) -> Result<RawFd> {
+    let st = stat(mount_where).context(NixSnafu)?;
+    dev_ioctl(
+        AUTOFS_DEV_IOCTL_OPENMOUNT_CMD,
+        -1,
+        [st.st_dev as u32, 0],
+        Some(mount_where),
+    )
+}
+
+/// the autofs expires the mount point which is not accessed for timeout seconds
+pub(super) fn autofs_set_timeout(ioctl_fd: RawFd, timeout: u64) -> Result<()> {
+    let t = timeout.to_ne_bytes();
+    let args = [
+        u32::from_ne_bytes([t[0], t[1], t[2], t[3]]),
+        u32::from_ne_bytes([t[4], t[5], t[6], t[7]]),
+    ];
+    dev_ioctl(AUTOFS_DEV_IOCTL_TIMEOUT_CMD, ioctl_fd, args, None).map(|_| ())
+}
+
+/// wake up the process waiting on the token, status is 0 or a negative errno
+pub(super) fn autofs_send_ready(ioctl_fd: RawFd, token: u32, status: i32) -> Result<()> {
+    if status == 0 {
+        dev_ioctl(AUTOFS_DEV_IOCTL_READY_CMD, ioctl_fd, [token, 0], None).map(|_| ())
+    } else {
+        dev_ioctl(
+            AUTOFS_DEV_IOCTL_FAIL_CMD,
+            ioctl_fd,
+            [token, status as u32],
+            None,
+        )
+        .map(|_| ())
+    }
+}
+
+/// ask the kernel to expire the idle mount point, it blocks until the expire request is answered
+pub(super) fn autofs_expire(ioctl_fd: RawFd) -> Result<()> {
+    dev_ioctl(AUTOFS_DEV_IOCTL_EXPIRE_CMD, ioctl_fd, [0, 0], None).map(|_| ())
+}
+
+/// create the pipe, the read end is non-blocking
+pub(super) fn autofs_pipe() -> Result<(RawFd, RawFd)> {
+    let (r, w) = unistd::pipe2(OFlag::O_CLOEXEC).context(NixSnafu)?;
+    let flags =
+        OFlag::from_bits_truncate(nix::fcntl::fcntl(r, nix::fcntl::F_GETFL).context(NixSnafu)?);
+    if let Err(e) = nix::fcntl::fcntl(r, nix::fcntl::F_SETFL(flags | OFlag::O_NONBLOCK)) {
+        let _ = unistd::close(r);
+        let _ = unistd::close(w);
+        return Err(Error::Nix { source: e });
+    }
+    Ok((r, w))
+}
+
+/// read one request from the pipe, None if there is no more
+pub(super) fn autofs_read_packet(pipe_fd: RawFd) -> Result<Option<AutofsPacket>> {
+    let size = mem::size_of::<AutofsV5Packet>();
+    let mut buf = vec![0u8; size];
+    let n = match unistd::read(pipe_fd, &mut buf) {
+        Ok(n) => n,
+        Err(Errno::EAGAIN) | Err(Errno::EINTR) => return Ok(None),
+        Err(e) => return Err(Error::Nix { source: e }),
+    };
+    if n == 0 {
+        return Ok(None);
+    }
+    if n != size {
+        return Err(Error::Other {
+            msg: format!("invalid autofs packet size {n}, expected {size}"),
+        });
+    }
+    Ok(Some(parse_packet(&buf)))
+}
+
+fn parse_packet(buf: &[u8]) -> AutofsPacket {
+    let field = |offset: usize| {
+        let mut v = [0u8; 4];
+        v.copy_from_slice(&buf[offset..offset + 4]);
+        v
+    };
+    AutofsPacket {
+        ptype: i32::from_ne_bytes(field(mem::offset_of!(AutofsV5Packet, ptype))),
+        token: u32::from_ne_bytes(field(mem::offset_of!(AutofsV5Packet, wait_queue_token))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autofs_layout() {
+        assert_eq!(mem::size_of::<AutofsDevIoctl>(), 24);
+        assert_eq!(mem::size_of::<AutofsV5Packet>(), 304);
+
+        let mut buf = vec![0u8; 304];
+        buf[0..4].copy_from_slice(&5i32.to_ne_bytes());
+        buf[4..8].copy_from_slice(&AUTOFS_PTYPE_MISSING_DIRECT.to_ne_bytes());
+        buf[8..12].copy_from_slice(&42u32.to_ne_bytes());
+        let packet = parse_packet(&buf);
+        assert_eq!(packet.ptype, AUTOFS_PTYPE_MISSING_DIRECT);
+        assert_eq!(packet.token, 42);
+    }
+}
@@ -0,0 +1,20 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use basic::mount_util::mount_point_fstypes;
+
+pub(super) const PLUGIN_NAME: &str = "AutomountUnit";
+
+/// return true if an autofs is mounted at the path
+pub(super) fn autofs_is_mounted(path: &str) -> bool {
+    mount_point_fstypes(path).iter().any(|t| t == "autofs")
+}
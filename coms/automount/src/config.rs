@@ -0,0 +1,112 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! automount_config mod load the conf file list and convert it to structure which is defined in this mod.
+//!
+#![allow(non_snake_case)]
+use super::comm::AutomountUnitComm;
+use super::rentry::SectionAutomount;
+use basic::time_util::USEC_PER_SEC;
+use confique::Config;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::rel::ReStation;
+
+pub(super) struct AutomountConfig {
+    // associated objects
+    comm: Rc<AutomountUnitComm>,
+
+    // owned objects
+    data: Rc<RefCell<AutomountConfigData>>,
+}
+
+impl ReStation for AutomountConfig {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        if reload {
+            return;
+        }
+        if let Some(automount) = self.comm.rentry_conf_get() {
+            self.data.replace(AutomountConfigData::new(automount));
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm.rentry_conf_insert(&self.data.borrow().Automount);
+    }
+
+    // reload: no external connections, no entry
+}
+
+impl AutomountConfig {
+    pub(super) fn new(commr: &Rc<AutomountUnitComm>) -> Self {
+        AutomountConfig {
+            comm: Rc::clone(commr),
+            data: Rc::new(RefCell::new(AutomountConfigData::default())),
+        }
+    }
+
+    pub(super) fn load(&self, paths: Vec<PathBuf>, update: bool) -> Result<()> {
+        if paths.is_empty() {
+            return Err(Error::ConfigureError {
+                msg: format!("no config file found for {}", self.comm.get_owner_id()),
+            });
+        }
+
+        let mut builder = AutomountConfigData::builder().env();
+        for v in paths {
+            builder = builder.file(v);
+        }
+        let data = builder.load().context(ConfiqueSnafu)?;
+        *self.data.borrow_mut() = data;
+
+        if update {
+            self.db_update();
+        }
+        Ok(())
+    }
+
+    pub(super) fn automount_where(&self) -> String {
+        self.data.borrow().Automount.Where.clone()
+    }
+
+    pub(super) fn directory_mode(&self) -> u32 {
+        self.data.borrow().Automount.DirectoryMode
+    }
+
+    /// the idle timeout in seconds, 0 means never
+    pub(super) fn timeout_idle(&self) -> u64 {
+        self.data.borrow().Automount.TimeoutIdleSec
+    }
+
+    /// the interval of checking the idle mount point in usec, a tenth of the idle timeout
+    /// but at least one second, the kernel does the checking with the timeout set on autofs.
+    pub(super) fn expire_interval(&self) -> u64 {
+        (self.timeout_idle() * USEC_PER_SEC / 10).max(USEC_PER_SEC)
+    }
+}
+
+#[derive(Config, Default, Debug)]
+pub(super) struct AutomountConfigData {
+    #[config(nested)]
+    pub Automount: SectionAutomount,
+}
+
+impl AutomountConfigData {
+    pub(self) fn new(Automount: SectionAutomount) -> AutomountConfigData {
+        AutomountConfigData { Automount }
+    }
+}
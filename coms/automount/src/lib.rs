@@ -0,0 +1,49 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Automount is the entry for the automount management of sysmaster. sysmaster refers to systemd, but it is not the same.
+//! An autofs is mounted at the path of the .automount unit when it is started, the first access to the path starts the
+//! .mount unit of the same path, and the mount is stopped again after it is idle for TimeoutIdleSec.
+//! The Automount configuration file contains three sections: Unit, Automount and Install.
+//!
+//! ```toml
+//! [Unit]
+//! Description="automount of /data"
+//!
+//! [Automount]
+//! Where="/data"
+//! DirectoryMode="0755"
+//! TimeoutIdleSec=300
+//!
+//! [Install]
+//! WantedBy="local-fs.target"
+//! ```
+//!
+//! The name of the unit must be the escaped Where, "/data" is managed by data.automount and data.mount.
+//!
+//! ## Automatic dependency
+//! NA
+//! ### Implicit dependency
+//! Before= and Triggers= on the .mount unit of Where.
+//! ### Default Dependency
+//! Before= on local-fs.target, Before= and Conflicts= on shutdown.target.
+
+// dependency: automount_base -> automount_autofs -> automount_rentry -> automount_comm -> automount_config -> {automount_mng -> automount_load -> automount_unit} -> automount_manager
+mod autofs;
+mod base;
+mod comm;
+mod config;
+mod load;
+mod manager;
+mod mng;
+mod rentry;
+mod unit;
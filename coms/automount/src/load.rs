@@ -0,0 +1,109 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! automount_load mod verify the section Automount and add the dependencies.
+//!
+use super::comm::AutomountUnitComm;
+use super::config::AutomountConfig;
+use basic::special::{LOCAL_FS_TARGET, SHUTDOWN_TARGET};
+use basic::unit_name::unit_name_from_path;
+use std::path::Path;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::unit::{UnitDependencyMask, UnitRelations};
+
+pub(super) struct AutomountLoad {
+    config: Rc<AutomountConfig>,
+    comm: Rc<AutomountUnitComm>,
+}
+
+impl AutomountLoad {
+    pub(super) fn new(configr: &Rc<AutomountConfig>, commr: &Rc<AutomountUnitComm>) -> Self {
+        AutomountLoad {
+            config: configr.clone(),
+            comm: commr.clone(),
+        }
+    }
+
+    pub(super) fn verify(&self) -> Result<()> {
+        let owner = match self.comm.owner() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        let where_ = self.config.automount_where();
+        if !Path::new(&where_).is_absolute() {
+            return Err(Error::ConfigureError {
+                msg: format!("Where={where_} is not an absolute path"),
+            });
+        }
+        if unit_name_from_path(&where_, ".automount") != *owner.id() {
+            return Err(Error::ConfigureError {
+                msg: format!(
+                    "Where={} doesn't match the unit name {}",
+                    where_,
+                    owner.id()
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// the automount triggers the mount unit of the same path
+    pub(super) fn automount_add_trigger_dependencies(&self) -> Result<()> {
+        let u = match self.comm.owner() {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        let mount = unit_name_from_path(&self.config.automount_where(), ".mount");
+        log::debug!("Adding trigger dependency for {}: {}", u.id(), mount);
+        self.comm.um().unit_add_two_dependency(
+            u.id(),
+            UnitRelations::UnitBefore,
+            UnitRelations::UnitTriggers,
+            &mount,
+            true,
+            UnitDependencyMask::Implicit,
+        )
+    }
+
+    /// the automount point is set up before local-fs.target and removed before shutdown.target
+    pub(super) fn automount_add_default_dependencies(&self) -> Result<()> {
+        let u = match self.comm.owner() {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        if !u.default_dependencies() {
+            return Ok(());
+        }
+
+        log::debug!("Adding default dependencies for automount: {}", u.id());
+        let um = self.comm.um();
+        um.unit_add_dependency(
+            u.id(),
+            UnitRelations::UnitBefore,
+            LOCAL_FS_TARGET,
+            true,
+            UnitDependencyMask::Default,
+        )?;
+        um.unit_add_two_dependency(
+            u.id(),
+            UnitRelations::UnitBefore,
+            UnitRelations::UnitConflicts,
+            SHUTDOWN_TARGET,
+            true,
+            UnitDependencyMask::Default,
+        )
+    }
+}
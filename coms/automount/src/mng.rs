@@ -0,0 +1,751 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//!  The core logic of the automount subclass
+use super::autofs::{
+    autofs_expire, autofs_mount, autofs_open_ioctl_fd, autofs_pipe, autofs_read_packet,
+    autofs_send_ready, autofs_set_timeout, AUTOFS_PTYPE_EXPIRE_DIRECT, AUTOFS_PTYPE_MISSING_DIRECT,
+};
+use super::base::autofs_is_mounted;
+use super::comm::AutomountUnitComm;
+use super::config::AutomountConfig;
+use super::rentry::{AutomountReMng, AutomountResult, AutomountState};
+use basic::fd_util;
+use basic::mount_util::mount_point_fstypes;
+use basic::unit_name::unit_name_from_path;
+use basic::IN_SET;
+use event::{EventState, EventType, Events, Source};
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg};
+use nix::mount::{umount2, MntFlags};
+use std::cell::RefCell;
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::prelude::RawFd;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use sysmaster::error::*;
+use sysmaster::rel::ReStation;
+use sysmaster::unit::{UnitActiveState, UnitNotifyFlags};
+
+const INVALID_FD: RawFd = -1;
+
+impl AutomountState {
+    fn automount_state_to_unit_state(&self) -> UnitActiveState {
+        match *self {
+            AutomountState::Dead => UnitActiveState::UnitInActive,
+            AutomountState::Waiting | AutomountState::Running => UnitActiveState::UnitActive,
+            AutomountState::Failed => UnitActiveState::UnitFailed,
+        }
+    }
+}
+
+pub(super) struct AutomountMng {
+    comm: Rc<AutomountUnitComm>,
+    config: Rc<AutomountConfig>,
+    state: RefCell<AutomountState>,
+    result: RefCell<AutomountResult>,
+    pipe_fd: RefCell<RawFd>,
+    ioctl_fd: RefCell<RawFd>,
+    tokens: RefCell<Vec<u32>>,
+    expire_tokens: RefCell<Vec<u32>>,
+    io: Rc<AutomountIo>,
+    timer: Rc<AutomountTimer>,
+    // the expire ioctl blocks until the request is answered, it runs in another thread
+    expiring: Arc<AtomicBool>,
+}
+
+impl ReStation for AutomountMng {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, _reload: bool) {
+        if let Some(mng) = self.comm.rentry_mng_get() {
+            *self.state.borrow_mut() = mng.state;
+            *self.result.borrow_mut() = mng.result;
+            *self.pipe_fd.borrow_mut() = mng.pipe_fd;
+            *self.ioctl_fd.borrow_mut() = mng.ioctl_fd;
+            *self.tokens.borrow_mut() = mng.tokens;
+            *self.expire_tokens.borrow_mut() = mng.expire_tokens;
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm.rentry_mng_insert(AutomountReMng {
+            state: self.state(),
+            result: self.result(),
+            pipe_fd: self.pipe_fd(),
+            ioctl_fd: self.ioctl_fd(),
+            tokens: self.tokens.borrow().clone(),
+            expire_tokens: self.expire_tokens.borrow().clone(),
+        });
+    }
+
+    // reload: the fds are kept open, only the event sources are rebuilt
+    fn entry_coldplug(&self) {
+        if !IN_SET!(
+            self.state(),
+            AutomountState::Waiting,
+            AutomountState::Running
+        ) {
+            return;
+        }
+
+        if let Err(e) = self.enable_io() {
+            log::error!(
+                "Failed to watch the autofs pipe of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+            return;
+        }
+
+        if self.state() == AutomountState::Running {
+            self.enable_timer();
+        }
+    }
+
+    fn entry_clear(&self) {
+        self.delete_io();
+        self.delete_timer();
+    }
+}
+
+impl AutomountMng {
+    pub(super) fn new(commr: &Rc<AutomountUnitComm>, configr: &Rc<AutomountConfig>) -> Self {
+        AutomountMng {
+            comm: Rc::clone(commr),
+            config: Rc::clone(configr),
+            state: RefCell::new(AutomountState::Dead),
+            result: RefCell::new(AutomountResult::Success),
+            pipe_fd: RefCell::new(INVALID_FD),
+            ioctl_fd: RefCell::new(INVALID_FD),
+            tokens: RefCell::new(Vec::new()),
+            expire_tokens: RefCell::new(Vec::new()),
+            io: Rc::new(AutomountIo::new()),
+            timer: Rc::new(AutomountTimer::new()),
+            expiring: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(super) fn attach_sources(mng: &Rc<AutomountMng>) {
+        mng.io.attach_mng(Rc::downgrade(mng));
+        mng.timer.attach_mng(Rc::downgrade(mng));
+    }
+
+    pub(super) fn start_check(&self) -> Result<bool> {
+        if IN_SET!(
+            self.state(),
+            AutomountState::Waiting,
+            AutomountState::Running
+        ) {
+            return Ok(true);
+        }
+
+        let ret = self.comm.owner().is_some_and(|u| u.test_start_limit());
+        if !ret {
+            self.enter_dead(AutomountResult::FailureStartLimitHit, true);
+            return Err(Error::UnitActionECanceled);
+        }
+
+        Ok(false)
+    }
+
+    pub(super) fn start_action(&self) {
+        self.set_result(AutomountResult::Success);
+        self.enter_waiting();
+    }
+
+    pub(super) fn stop_action(&self) {
+        if IN_SET!(self.state(), AutomountState::Dead, AutomountState::Failed) {
+            return;
+        }
+        self.enter_dead(AutomountResult::Success, true);
+    }
+
+    fn enter_waiting(&self) {
+        if let Err(e) = self.setup_autofs() {
+            log::error!(
+                "Failed to set up the automount point of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+            self.enter_dead(AutomountResult::FailureResources, true);
+            return;
+        }
+        self.set_state(AutomountState::Waiting, true);
+    }
+
+    fn setup_autofs(&self) -> Result<()> {
+        let mount_where = self.config.automount_where();
+        // the autofs hides what is mounted there already, refuse it
+        if !mount_point_fstypes(&mount_where).is_empty() {
+            return Err(Error::Other {
+                msg: format!("{mount_where} is mounted already"),
+            });
+        }
+
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(self.config.directory_mode())
+            .create(&mount_where)
+            .context(IoSnafu)?;
+
+        let (pipe_fd, write_fd) = autofs_pipe()?;
+        let ret = autofs_mount(&mount_where, write_fd);
+        // the kernel holds the write end after mounting
+        fd_util::close(write_fd);
+        if let Err(e) = ret {
+            fd_util::close(pipe_fd);
+            return Err(e);
+        }
+        self.pipe_fd.replace(pipe_fd);
+
+        let ioctl_fd = match autofs_open_ioctl_fd(&mount_where) {
+            Ok(fd) => fd,
+            Err(e) => {
+                self.release_autofs();
+                return Err(e);
+            }
+        };
+        self.ioctl_fd.replace(ioctl_fd);
+
+        if let Err(e) = self.retain_fds() {
+            self.release_autofs();
+            return Err(e);
+        }
+
+        if let Err(e) = autofs_set_timeout(ioctl_fd, self.config.timeout_idle()) {
+            self.release_autofs();
+            return Err(e);
+        }
+
+        if let Err(e) = self.enable_io() {
+            self.release_autofs();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn retain_fds(&self) -> Result<()> {
+        let reli = self.comm.reli();
+        reli.fd_cloexec(self.pipe_fd(), false)?;
+        reli.fd_cloexec(self.ioctl_fd(), false)
+    }
+
+    /// close the fds and unmount the autofs, the mount on top of it is left alone
+    fn release_autofs(&self) {
+        self.delete_io();
+        self.delete_timer();
+
+        for fd in [
+            self.pipe_fd.replace(INVALID_FD),
+            self.ioctl_fd.replace(INVALID_FD),
+        ] {
+            if fd < 0 {
+                continue;
+            }
+            if let Err(e) = self.comm.reli().fd_cloexec(fd, true) {
+                log::error!("Failed to remark the fd {} of the autofs: {}", fd, e);
+            }
+            fd_util::close(fd);
+        }
+
+        let mount_where = self.config.automount_where();
+        // the autofs may be mounted more than once, bound the retries in case of failure
+        for _ in 0..16 {
+            if !autofs_is_mounted(&mount_where) {
+                break;
+            }
+            if let Err(e) = umount2(mount_where.as_str(), MntFlags::MNT_DETACH) {
+                log::error!("Failed to unmount the autofs at {}: {}", mount_where, e);
+                break;
+            }
+        }
+    }
+
+    pub(super) fn enter_dead(&self, res: AutomountResult, notify: bool) {
+        log::debug!("enter dead state, res {:?}", res);
+        if self.result() == AutomountResult::Success {
+            self.set_result(res);
+        }
+
+        self.answer_all(-(Errno::ENODEV as i32));
+        self.release_autofs();
+
+        let state = if self.result() == AutomountResult::Success {
+            AutomountState::Dead
+        } else {
+            AutomountState::Failed
+        };
+        self.set_state(state, notify);
+    }
+
+    /// the requests are read out until the pipe is empty
+    pub(super) fn io_event(&self) {
+        loop {
+            let packet = match autofs_read_packet(self.pipe_fd()) {
+                Ok(Some(p)) => p,
+                Ok(None) => break,
+                Err(e) => {
+                    log::error!(
+                        "Failed to read the autofs request of {}: {}",
+                        self.comm.get_owner_id(),
+                        e
+                    );
+                    self.enter_dead(AutomountResult::FailureResources, true);
+                    return;
+                }
+            };
+
+            match packet.ptype {
+                AUTOFS_PTYPE_MISSING_DIRECT => self.request_mount(packet.token),
+                AUTOFS_PTYPE_EXPIRE_DIRECT => self.request_umount(packet.token),
+                _ => log::warn!("Unknown autofs request type {}, ignoring", packet.ptype),
+            }
+        }
+        self.db_update();
+    }
+
+    fn request_mount(&self, token: u32) {
+        let mount = self.mount_unit();
+        log::debug!("Got automount request for {}", mount);
+        if self.comm.um().current_active_state(&mount) == UnitActiveState::UnitActive {
+            self.send_ready(token, 0);
+            return;
+        }
+
+        self.tokens.borrow_mut().push(token);
+        if let Err(e) = self.comm.um().unit_start_by_job(&mount) {
+            log::error!("Failed to start {}: {:?}", mount, e);
+            self.answer_tokens(-(Errno::ENOENT as i32));
+        }
+    }
+
+    fn request_umount(&self, token: u32) {
+        let mount = self.mount_unit();
+        log::debug!("Got expire request for {}", mount);
+        if self
+            .comm
+            .um()
+            .current_active_state(&mount)
+            .is_inactive_or_failed()
+        {
+            self.send_ready(token, 0);
+            return;
+        }
+
+        self.expire_tokens.borrow_mut().push(token);
+        if let Err(e) = self.comm.um().unit_stop(&mount, false) {
+            log::error!("Failed to stop {}: {:?}", mount, e);
+            self.answer_expire_tokens(-(Errno::EBUSY as i32));
+        }
+    }
+
+    /// the state of the mount unit is changed, answer the pending requests
+    pub(super) fn trigger_notify(&self, other: &str) {
+        if !IN_SET!(
+            self.state(),
+            AutomountState::Waiting,
+            AutomountState::Running
+        ) || other != self.mount_unit()
+        {
+            return;
+        }
+
+        let state = self.comm.um().current_active_state(other);
+        if state == UnitActiveState::UnitActive {
+            self.answer_tokens(0);
+            self.answer_expire_tokens(-(Errno::EBUSY as i32));
+            self.enable_timer();
+            self.set_state(AutomountState::Running, true);
+        } else if state.is_inactive_or_failed() {
+            self.answer_tokens(-(Errno::ENODEV as i32));
+            self.answer_expire_tokens(0);
+            self.delete_timer();
+            self.set_state(AutomountState::Waiting, true);
+        }
+    }
+
+    /// the mount point is checked for idle by the kernel, the expire request comes from the pipe
+    pub(super) fn timer_event(&self) {
+        let ioctl_fd = self.ioctl_fd();
+        if ioctl_fd < 0 || self.expiring.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // the thread owns a duplicate of the fd, release_autofs may close ours at any time
+        let expire_fd = match fcntl(ioctl_fd, FcntlArg::F_DUPFD_CLOEXEC(0)) {
+            Ok(fd) => fd,
+            Err(e) => {
+                log::error!(
+                    "Failed to duplicate the ioctl fd of {}: {}",
+                    self.config.automount_where(),
+                    e
+                );
+                self.expiring.store(false, Ordering::SeqCst);
+                return;
+            }
+        };
+
+        let expiring = self.expiring.clone();
+        std::thread::spawn(move || {
+            while autofs_expire(expire_fd).is_ok() {}
+            fd_util::close(expire_fd);
+            expiring.store(false, Ordering::SeqCst);
+        });
+    }
+
+    fn mount_unit(&self) -> String {
+        unit_name_from_path(&self.config.automount_where(), ".mount")
+    }
+
+    fn send_ready(&self, token: u32, status: i32) {
+        if let Err(e) = autofs_send_ready(self.ioctl_fd(), token, status) {
+            log::error!(
+                "Failed to answer the autofs request {} of {}: {}",
+                token,
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+    }
+
+    fn answer_tokens(&self, status: i32) {
+        let tokens = self.tokens.take();
+        for token in tokens {
+            self.send_ready(token, status);
+        }
+    }
+
+    fn answer_expire_tokens(&self, status: i32) {
+        let tokens = self.expire_tokens.take();
+        for token in tokens {
+            self.send_ready(token, status);
+        }
+    }
+
+    fn answer_all(&self, status: i32) {
+        if self.ioctl_fd() < 0 {
+            self.tokens.borrow_mut().clear();
+            self.expire_tokens.borrow_mut().clear();
+            return;
+        }
+        self.answer_tokens(status);
+        self.answer_expire_tokens(status);
+    }
+
+    pub fn get_state(&self) -> String {
+        let state = *self.state.borrow();
+        state.to_string()
+    }
+
+    fn set_state(&self, new_state: AutomountState, notify: bool) {
+        let old_state = self.state();
+        self.change_state(new_state);
+
+        if notify {
+            self.state_notify(new_state, old_state);
+        }
+    }
+
+    fn state_notify(&self, new_state: AutomountState, old_state: AutomountState) {
+        if new_state != old_state {
+            log::debug!(
+                "{} original state[{:?}] -> new state[{:?}]",
+                self.comm.get_owner_id(),
+                old_state,
+                new_state,
+            );
+        }
+
+        let old_unit_state = old_state.automount_state_to_unit_state();
+        let new_unit_state = new_state.automount_state_to_unit_state();
+        if let Some(u) = self.comm.owner() {
+            u.notify(
+                old_unit_state,
+                new_unit_state,
+                UnitNotifyFlags::UNIT_NOTIFY_RELOAD_FAILURE,
+            )
+        }
+
+        self.db_update();
+    }
+
+    fn change_state(&self, new_state: AutomountState) {
+        self.state.replace(new_state);
+    }
+
+    fn state(&self) -> AutomountState {
+        *self.state.borrow()
+    }
+
+    fn result(&self) -> AutomountResult {
+        *self.result.borrow()
+    }
+
+    fn set_result(&self, res: AutomountResult) {
+        self.result.replace(res);
+    }
+
+    fn pipe_fd(&self) -> RawFd {
+        *self.pipe_fd.borrow()
+    }
+
+    fn ioctl_fd(&self) -> RawFd {
+        *self.ioctl_fd.borrow()
+    }
+
+    fn enable_io(&self) -> Result<()> {
+        if self.io.armed() {
+            return Ok(());
+        }
+
+        let events = self.comm.um().events();
+        self.io.set_fd(self.pipe_fd());
+        events.add_source(self.io.clone())?;
+        self.io.set_armed(true);
+        events.set_enabled(self.io.clone(), EventState::On)?;
+        Ok(())
+    }
+
+    fn delete_io(&self) {
+        if !self.io.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.del_source(self.io.clone()) {
+            log::error!(
+                "Failed to delete the autofs io of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        self.io.set_armed(false);
+    }
+
+    fn enable_timer(&self) {
+        if self.config.timeout_idle() == 0 {
+            return;
+        }
+
+        let events = self.comm.um().events();
+        let timer = self.timer.clone();
+        let ret = (|| -> Result<()> {
+            if timer.armed() {
+                events.set_enabled(timer.clone(), EventState::Off)?;
+            } else {
+                events.add_source(timer.clone())?;
+                timer.set_armed(true);
+            }
+            timer.set_time(self.config.expire_interval());
+            events.set_enabled(timer.clone(), EventState::On)?;
+            Ok(())
+        })();
+        if let Err(e) = ret {
+            log::error!(
+                "Failed to enable the expire timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+    }
+
+    fn delete_timer(&self) {
+        if !self.timer.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.del_source(self.timer.clone()) {
+            log::error!(
+                "Failed to delete the expire timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        self.timer.set_armed(false);
+    }
+
+    pub(super) fn automount_state_to_unit_state(&self) -> UnitActiveState {
+        self.state().automount_state_to_unit_state()
+    }
+}
+
+pub(super) struct AutomountIo {
+    fd: RefCell<RawFd>,
+    armed: RefCell<bool>,
+    mng: RefCell<Weak<AutomountMng>>,
+}
+
+impl AutomountIo {
+    fn new() -> Self {
+        AutomountIo {
+            fd: RefCell::new(INVALID_FD),
+            armed: RefCell::new(false),
+            mng: RefCell::new(Weak::new()),
+        }
+    }
+
+    fn attach_mng(&self, mng: Weak<AutomountMng>) {
+        *self.mng.borrow_mut() = mng;
+    }
+
+    fn set_fd(&self, fd: RawFd) {
+        *self.fd.borrow_mut() = fd
+    }
+
+    fn armed(&self) -> bool {
+        *self.armed.borrow()
+    }
+
+    fn set_armed(&self, armed: bool) {
+        *self.armed.borrow_mut() = armed
+    }
+}
+
+impl Source for AutomountIo {
+    fn fd(&self) -> RawFd {
+        *self.fd.borrow()
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::Io
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        if let Some(mng) = self.mng.borrow().upgrade() {
+            mng.io_event();
+        }
+        0
+    }
+
+    fn priority(&self) -> i8 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
+pub(super) struct AutomountTimer {
+    time: RefCell<u64>,
+    armed: RefCell<bool>,
+    mng: RefCell<Weak<AutomountMng>>,
+}
+
+impl AutomountTimer {
+    fn new() -> Self {
+        AutomountTimer {
+            time: RefCell::new(u64::MAX),
+            armed: RefCell::new(false),
+            mng: RefCell::new(Weak::new()),
+        }
+    }
+
+    fn attach_mng(&self, mng: Weak<AutomountMng>) {
+        *self.mng.borrow_mut() = mng;
+    }
+
+    fn set_time(&self, usec: u64) {
+        *self.time.borrow_mut() = usec
+    }
+
+    fn armed(&self) -> bool {
+        *self.armed.borrow()
+    }
+
+    fn set_armed(&self, armed: bool) {
+        *self.armed.borrow_mut() = armed
+    }
+}
+
+impl Source for AutomountTimer {
+    fn fd(&self) -> RawFd {
+        0
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::TimerMonotonic
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn time_relative(&self) -> u64 {
+        *self.time.borrow()
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        if let Some(mng) = self.mng.borrow().upgrade() {
+            mng.timer_event();
+        }
+        0
+    }
+
+    fn priority(&self) -> i8 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AutomountMng;
+    use super::AutomountUnitComm;
+    use super::{AutomountResult, AutomountState};
+    use crate::config::AutomountConfig;
+    use std::rc::Rc;
+    use sysmaster::unit::UnitActiveState;
+
+    fn new_mng() -> AutomountMng {
+        let _comm = Rc::new(AutomountUnitComm::new());
+        let _config = Rc::new(AutomountConfig::new(&_comm));
+        AutomountMng::new(&_comm, &_config)
+    }
+
+    #[test]
+    fn test_automount_state_to_unit_state() {
+        assert_eq!(
+            AutomountState::Waiting.automount_state_to_unit_state(),
+            UnitActiveState::UnitActive
+        );
+        assert_eq!(
+            AutomountState::Running.automount_state_to_unit_state(),
+            UnitActiveState::UnitActive
+        );
+        assert_eq!(
+            AutomountState::Failed.automount_state_to_unit_state(),
+            UnitActiveState::UnitFailed
+        );
+    }
+
+    #[test]
+    fn test_automount_trigger_notify_ignored() {
+        let tm = new_mng();
+        tm.trigger_notify("-.mount");
+        assert_eq!(tm.state(), AutomountState::Dead);
+        assert_eq!(tm.result(), AutomountResult::Success);
+        assert!(tm.tokens.borrow().is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! automount unit is entry of automount type of unit，need impl
+//! UnitObj,UnitMngUtil, UnitSubClass trait
+
+use super::base::PLUGIN_NAME;
+use super::comm::AutomountUnitComm;
+use super::config::AutomountConfig;
+use super::load::AutomountLoad;
+use super::mng::AutomountMng;
+use basic::logger;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::rel::{ReStation, Reliability};
+use sysmaster::unit::{SubUnit, UmIf, UnitActiveState, UnitBase, UnitMngUtil};
+
+struct AutomountUnit {
+    comm: Rc<AutomountUnitComm>,
+    config: Rc<AutomountConfig>,
+    mng: Rc<AutomountMng>,
+    load: AutomountLoad,
+}
+
+impl ReStation for AutomountUnit {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        self.config.db_map(reload);
+        self.mng.db_map(reload);
+    }
+
+    fn db_insert(&self) {
+        self.config.db_insert();
+        self.mng.db_insert();
+    }
+
+    // reload: no external connections, entry-only
+    fn entry_coldplug(&self) {
+        // rebuild external connections, like: timer, ...
+        self.mng.entry_coldplug();
+    }
+
+    fn entry_clear(&self) {
+        // release external connection, like: timer, ...
+        self.mng.entry_clear();
+    }
+}
+
+impl AutomountUnit {
+    fn new(_um: Rc<dyn UmIf>) -> AutomountUnit {
+        let _comm = Rc::new(AutomountUnitComm::new());
+        let _config = Rc::new(AutomountConfig::new(&_comm));
+        let _mng = Rc::new(AutomountMng::new(&_comm, &_config));
+        AutomountMng::attach_sources(&_mng);
+        AutomountUnit {
+            comm: Rc::clone(&_comm),
+            config: Rc::clone(&_config),
+            mng: _mng,
+            load: AutomountLoad::new(&_config, &_comm),
+        }
+    }
+}
+
+impl SubUnit for AutomountUnit {
+    fn load(&self, paths: Vec<PathBuf>) -> Result<()> {
+        self.config.load(paths, true)?;
+        self.load.verify()?;
+        self.load.automount_add_trigger_dependencies()?;
+        self.load.automount_add_default_dependencies()
+    }
+
+    fn current_active_state(&self) -> UnitActiveState {
+        self.mng.automount_state_to_unit_state()
+    }
+
+    fn get_subunit_state(&self) -> String {
+        self.mng.get_state()
+    }
+
+    fn attach_unit(&self, unit: Rc<dyn UnitBase>) {
+        self.comm.attach_unit(unit);
+        self.db_insert();
+    }
+
+    fn init(&self) {}
+
+    fn done(&self) {}
+
+    fn dump(&self) {}
+
+    fn start(&self) -> Result<()> {
+        let started = self.mng.start_check()?;
+        if started {
+            log::debug!("automount already in starting, just return immediately");
+            return Ok(());
+        }
+
+        self.mng.start_action();
+
+        Ok(())
+    }
+
+    fn stop(&self, _force: bool) -> Result<()> {
+        self.mng.stop_action();
+        Ok(())
+    }
+
+    fn kill(&self) {}
+
+    fn release_resources(&self) {}
+
+    fn trigger_notify(&self, other: &str) {
+        self.mng.trigger_notify(other)
+    }
+
+    fn reset_failed(&self) {}
+}
+
+impl UnitMngUtil for AutomountUnit {
+    fn attach_um(&self, um: Rc<dyn UmIf>) {
+        self.comm.attach_um(um);
+    }
+
+    fn attach_reli(&self, reli: Rc<Reliability>) {
+        self.comm.attach_reli(reli);
+    }
+}
+
+/*impl Default for AutomountUnit {
+    fn default() -> Self {
+        AutomountUnit::new()
+    }
+}*/
+
+use sysmaster::declure_unitobj_plugin_with_param;
+declure_unitobj_plugin_with_param!(AutomountUnit, AutomountUnit::new, PLUGIN_NAME);
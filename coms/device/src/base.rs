@@ -0,0 +1,112 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use basic::conf_parser::parse_boolean;
+use basic::unit_name::unit_name_from_path;
+
+pub(super) const PLUGIN_NAME: &str = "DeviceUnit";
+
+/// the devices with one of the tags are exposed as device units
+pub(super) const DEVICE_TAGS: [&str; 2] = ["systemd", "sysmaster"];
+
+/// the names longer than this are not valid unit names
+const UNIT_NAME_MAX: usize = 256;
+
+/// the units of a device: the one named by the sysfs path comes first, then the ones named by
+/// the device node and the symlinks to it.
+pub(super) fn device_unit_names(
+    syspath: &str,
+    devname: Option<&str>,
+    devlinks: &[&str],
+) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let paths = std::iter::once(syspath)
+        .chain(devname)
+        .chain(devlinks.iter().copied());
+    for path in paths {
+        let name = unit_name_from_path(path, ".device");
+        if name.len() > UNIT_NAME_MAX {
+            log::debug!("The unit name of {} is too long, ignoring", path);
+            continue;
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// SYSTEMD_WANTS is a list of unit names separated by spaces, ".service" is implied if
+/// there is no suffix
+pub(super) fn parse_wants(value: &str) -> Vec<String> {
+    value
+        .split_whitespace()
+        .map(|w| {
+            if w.contains('.') {
+                w.to_string()
+            } else {
+                format!("{w}.service")
+            }
+        })
+        .collect()
+}
+
+/// the device is not ready if SYSTEMD_READY is set to false, it's ready if not set
+pub(super) fn parse_ready(value: Option<&str>) -> bool {
+    value.is_none_or(|v| parse_boolean(v).unwrap_or(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_unit_names() {
+        assert_eq!(
+            device_unit_names(
+                "/sys/devices/virtual/block/loop0",
+                Some("/dev/loop0"),
+                &["/dev/disk/by-uuid/1234-abcd", "/dev/loop0"]
+            ),
+            vec![
+                "sys-devices-virtual-block-loop0.device",
+                "dev-loop0.device",
+                "dev-disk-by\\x2duuid-1234\\x2dabcd.device"
+            ]
+        );
+        assert_eq!(
+            device_unit_names("/sys/devices/virtual/net/lo", None, &[]),
+            vec!["sys-devices-virtual-net-lo.device"]
+        );
+
+        let long = format!("/dev/disk/by-id/{}", "x".repeat(300));
+        assert_eq!(
+            device_unit_names("/sys/devices/virtual/block/loop0", None, &[&long]).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_wants_ready() {
+        assert_eq!(
+            parse_wants(" foo  bar.target\tbaz@sda.service "),
+            vec!["foo.service", "bar.target", "baz@sda.service"]
+        );
+        assert!(parse_wants("").is_empty());
+
+        assert!(parse_ready(None));
+        assert!(parse_ready(Some("1")));
+        assert!(!parse_ready(Some("0")));
+        assert!(!parse_ready(Some("no")));
+        assert!(parse_ready(Some("garbage")));
+    }
+}
@@ -0,0 +1,33 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Device is the entry for the device management of sysmaster. sysmaster refers to systemd, but it is not the same.
+//! The devices tagged with "systemd" or "sysmaster" by devmaster are exposed as .device units, they are found by
+//! enumerating /run/udev/tags at startup and then followed by the device monitor.
+//! A .device unit is plugged when its device appears and ready, and dead when it's removed or marked with SYSTEMD_READY=0.
+//! A device is exposed as several units, named by the escaped sysfs path, the device node and each of its symlinks.
+//! The device unit has no configuration file, a drop-in like dev-sda.device.d/timeout.conf can change the [Unit] section.
+//!
+//! ## Automatic dependency
+//! Wants= on the units listed in the SYSTEMD_WANTS property of the device.
+//! ### Implicit dependency
+//! NA
+//! ### Default Dependency
+//! NA
+
+// dependency: device_base -> device_rentry -> device_comm -> {device_mng -> device_unit} -> device_manager
+mod base;
+mod comm;
+mod manager;
+mod mng;
+mod rentry;
+mod unit;
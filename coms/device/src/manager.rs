@@ -0,0 +1,496 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use super::base::{device_unit_names, parse_ready, parse_wants, DEVICE_TAGS, PLUGIN_NAME};
+use super::comm::DeviceUmComm;
+use super::rentry::{DeviceRe, DeviceReFrame};
+use basic::logger;
+use device::device_enumerator::DeviceEnumerator;
+use device::device_monitor::{DeviceMonitor as NetlinkMonitor, MonitorNetlinkGroup};
+use device::{Device, DeviceAction};
+use event::{EventState, EventType, Events, Source};
+use nix::errno::Errno;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::sync::Arc;
+use sysmaster::error::*;
+use sysmaster::rel::{ReStation, ReliLastFrame, Reliability};
+use sysmaster::unit::{
+    UmIf, UnitActiveState, UnitDependencyMask, UnitManagerObj, UnitMngUtil, UnitRelations, UnitType,
+};
+
+struct DeviceManager {
+    // owned objects
+    // data
+    comm: Arc<DeviceUmComm>,
+    monitor: DeviceMonitor,
+}
+
+impl ReStation for DeviceManager {
+    // input
+    fn input_rebuild(&self) {
+        self.monitor.defer_enable(true);
+    }
+
+    // compensate
+    fn db_compensate_last(&self, _lframe: (u32, Option<u32>, Option<u32>), lunit: Option<&String>) {
+        assert!(lunit.is_some());
+
+        let frame = self.comm.rentry().last_frame();
+        if frame.is_none() {
+            // debug
+            return;
+        }
+
+        let unit_id = lunit.unwrap();
+        match frame.unwrap() {
+            DeviceReFrame::Monitor => self.comm.um().rentry_trigger_merge(unit_id, true), // merge to trigger
+        }
+    }
+
+    fn do_compensate_last(&self, _lframe: (u32, Option<u32>, Option<u32>), lunit: Option<&String>) {
+        assert!(lunit.is_some());
+
+        let frame = self.comm.rentry().last_frame();
+        if frame.is_none() {
+            // debug
+            return;
+        }
+
+        let unit_id = lunit.unwrap();
+        match frame.unwrap() {
+            DeviceReFrame::Monitor => self.comm.um().trigger_unit(unit_id), // re-run
+        }
+    }
+
+    // no data
+
+    // reload
+    fn register_ex(&self) {
+        self.monitor.register_ex();
+    }
+}
+
+impl UnitManagerObj for DeviceManager {}
+
+// the declaration "pub(self)" is for identification only.
+impl DeviceManager {
+    pub fn new() -> DeviceManager {
+        let _comm = DeviceUmComm::get_instance();
+        DeviceManager {
+            comm: Arc::clone(&_comm),
+            monitor: DeviceMonitor::new(&_comm),
+        }
+    }
+
+    fn register(&self) {
+        self.monitor.register();
+    }
+}
+
+struct DeviceMonitor {
+    // owned objects
+    data: Rc<DeviceMonitorData>,
+    defer: Rc<DeviceMonitorDefer>,
+    io: Rc<DeviceMonitorIo>,
+}
+
+// the declaration "pub(self)" is for identification only.
+impl DeviceMonitor {
+    pub fn new(commr: &Arc<DeviceUmComm>) -> DeviceMonitor {
+        let _data = Rc::new(DeviceMonitorData::new(commr));
+        let _defer = Rc::new(DeviceMonitorDefer::new(&_data));
+        DeviceMonitor {
+            data: Rc::clone(&_data),
+            defer: Rc::clone(&_defer),
+            io: Rc::new(DeviceMonitorIo::new(&_data, &_defer)),
+        }
+    }
+
+    pub fn register_ex(&self) {
+        let events = self.data.comm.um().events();
+
+        // io
+        let io = Rc::clone(&self.io);
+        events.add_source(io.clone()).unwrap();
+        events.set_enabled(io, EventState::On).unwrap();
+    }
+
+    pub fn defer_enable(&self, enable: bool) -> i32 {
+        self.io.defer_enable(enable)
+    }
+
+    fn register(&self) {
+        let events = self.data.comm.um().events();
+
+        // defer
+        let defer = Rc::clone(&self.defer);
+        events.add_source(defer).unwrap();
+    }
+}
+
+struct DeviceMonitorIo {
+    // associated objects
+    data: Rc<DeviceMonitorData>,
+    defer: Rc<DeviceMonitorDefer>,
+}
+
+// the declaration "pub(self)" is for identification only.
+impl DeviceMonitorIo {
+    pub fn new(datar: &Rc<DeviceMonitorData>, deferr: &Rc<DeviceMonitorDefer>) -> DeviceMonitorIo {
+        DeviceMonitorIo {
+            data: Rc::clone(datar),
+            defer: Rc::clone(deferr),
+        }
+    }
+
+    pub fn defer_enable(&self, enable: bool) -> i32 {
+        let source = Rc::clone(&self.defer);
+        let state = match enable {
+            true => EventState::OneShot,
+            false => EventState::Off,
+        };
+        let events = self.data.comm.um().events();
+        match events.set_enabled(source, state) {
+            Ok(_) => 0,
+            Err(_) => -1,
+        }
+    }
+
+    fn reli(&self) -> Rc<Reliability> {
+        self.data.comm.reli()
+    }
+
+    fn rentry(&self) -> Rc<DeviceRe> {
+        self.data.comm.rentry()
+    }
+}
+
+impl Source for DeviceMonitorIo {
+    fn event_type(&self) -> EventType {
+        EventType::Io
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn dispatch(&self, _e: &Events) -> i32 {
+        loop {
+            let mut device = match self.data.monitor.receive_device() {
+                Ok(device) => device,
+                Err(e) => match e.get_errno() {
+                    Errno::EAGAIN => break,
+                    Errno::EINVAL => continue, // not a device message of devmaster
+                    _ => {
+                        log::error!("Failed to receive the device message: {}", e);
+                        break;
+                    }
+                },
+            };
+
+            self.reli().set_last_frame2(
+                ReliLastFrame::SubManager as u32,
+                UnitType::UnitDevice as u32,
+            );
+            self.rentry().set_last_frame(DeviceReFrame::Monitor);
+            self.data.dispatch_device(&mut device);
+            self.rentry().clear_last_frame();
+            self.reli().clear_last_frame();
+        }
+
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+
+    fn fd(&self) -> RawFd {
+        self.data.monitor.fd()
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+}
+
+struct DeviceMonitorDefer {
+    // associated objects
+    data: Rc<DeviceMonitorData>,
+}
+
+impl DeviceMonitorDefer {
+    pub fn new(datar: &Rc<DeviceMonitorData>) -> DeviceMonitorDefer {
+        DeviceMonitorDefer {
+            data: Rc::clone(datar),
+        }
+    }
+
+    fn reli(&self) -> Rc<Reliability> {
+        self.data.comm.reli()
+    }
+
+    fn rentry(&self) -> Rc<DeviceRe> {
+        self.data.comm.rentry()
+    }
+}
+
+impl Source for DeviceMonitorDefer {
+    fn event_type(&self) -> EventType {
+        EventType::Defer
+    }
+
+    fn epoll_event(&self) -> u32 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+
+    fn dispatch(&self, _event: &Events) -> i32 {
+        self.reli().set_last_frame2(
+            ReliLastFrame::SubManager as u32,
+            UnitType::UnitDevice as u32,
+        );
+        self.rentry().set_last_frame(DeviceReFrame::Monitor);
+        let ret = self.data.enumerate_devices();
+        self.rentry().clear_last_frame();
+        self.reli().clear_last_frame();
+
+        match ret {
+            Ok(_) => 0,
+            Err(e) => {
+                log::error!("Failed to enumerate the devices: {}", e);
+                -1
+            }
+        }
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+}
+
+struct DeviceMonitorData {
+    // associated objects
+    comm: Arc<DeviceUmComm>,
+
+    // owned objects
+    monitor: NetlinkMonitor,
+}
+
+impl DeviceMonitorData {
+    pub fn new(commr: &Arc<DeviceUmComm>) -> Self {
+        let monitor = NetlinkMonitor::new(MonitorNetlinkGroup::Userspace, None);
+        log::debug!("Monitoring the devices by netlink fd {}", monitor.fd());
+        DeviceMonitorData {
+            comm: Arc::clone(commr),
+            monitor,
+        }
+    }
+
+    /// find all devices exposed to us, and stop the device units whose devices are gone
+    pub fn enumerate_devices(&self) -> Result<()> {
+        // forget the devices found before, they are found again below if still there.
+        self.comm.clear_found();
+
+        let mut enumerator = DeviceEnumerator::new();
+        for tag in DEVICE_TAGS {
+            enumerator
+                .add_match_tag(tag.to_string())
+                .map_err(|e| Error::Other {
+                    msg: format!("failed to add the match tag {}: {}", tag, e),
+                })?;
+        }
+
+        for device in enumerator.iter_mut() {
+            let mut device = device.lock().unwrap();
+            if !device_is_ready(&mut device) {
+                continue;
+            }
+
+            if let Some(syspath) = device.get_syspath().map(|s| s.to_string()) {
+                self.device_found(&mut device, &syspath);
+            }
+        }
+
+        let um = self.comm.um();
+        for unit_name in um.units_get_all(Some(UnitType::UnitDevice)).iter() {
+            if um.current_active_state(unit_name) == UnitActiveState::UnitActive
+                && self.comm.device_found(unit_name).is_none()
+            {
+                self.stop_device(unit_name);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn dispatch_device(&self, device: &mut Device) {
+        let syspath = match device.get_syspath() {
+            Some(syspath) => syspath.to_string(),
+            None => return,
+        };
+
+        let action = match device.get_action() {
+            Ok(action) => action,
+            Err(e) => {
+                log::error!("Failed to get the action of {}: {}", syspath, e);
+                return;
+            }
+        };
+        log::debug!("Received the device event: {} {}", action, syspath);
+
+        if action == DeviceAction::Move {
+            if let Ok(devpath_old) = device.get_property_value("DEVPATH_OLD".to_string()) {
+                self.device_lost(&format!("/sys{}", devpath_old));
+            }
+        }
+
+        if action == DeviceAction::Remove || !device_is_ready(device) {
+            self.device_lost(&syspath);
+            return;
+        }
+
+        self.device_found(device, &syspath);
+    }
+
+    fn device_found(&self, device: &mut Device, syspath: &str) {
+        let devname = device.get_devname().ok();
+        let devlinks = device
+            .get_property_value("DEVLINKS".to_string())
+            .unwrap_or_default();
+        let devlinks: Vec<&str> = devlinks.split_whitespace().collect();
+        let units = device_unit_names(syspath, devname.as_deref(), &devlinks);
+
+        // the symlinks of the device may change, stop the units which are gone.
+        let old_units = self.comm.set_found(syspath, units.clone());
+        for unit_name in old_units.iter().filter(|u| !units.contains(u)) {
+            self.stop_device(unit_name);
+        }
+
+        let wants = device
+            .get_property_value("SYSTEMD_WANTS".to_string())
+            .map(|w| parse_wants(&w))
+            .unwrap_or_default();
+        for (i, unit_name) in units.iter().enumerate() {
+            if !self.comm.um().load_unit_success(unit_name) {
+                log::error!("Failed to load the device unit {}", unit_name);
+                continue;
+            }
+
+            // the units wanted by the device are started along with its main unit
+            if i == 0 {
+                self.add_wants(unit_name, &wants);
+            }
+            self.start_device(unit_name);
+        }
+    }
+
+    fn device_lost(&self, syspath: &str) {
+        for unit_name in self.comm.remove_found(syspath).iter() {
+            self.stop_device(unit_name);
+        }
+    }
+
+    fn add_wants(&self, unit_name: &str, wants: &[String]) {
+        for want in wants.iter() {
+            if let Err(e) = self.comm.um().unit_add_dependency(
+                unit_name,
+                UnitRelations::UnitWants,
+                want,
+                true,
+                UnitDependencyMask::Implicit,
+            ) {
+                log::error!(
+                    "Failed to add the wanted unit {} of {}: {}",
+                    want,
+                    unit_name,
+                    e
+                );
+            }
+        }
+    }
+
+    fn start_device(&self, unit_name: &str) {
+        if !matches!(
+            self.comm.um().current_active_state(unit_name),
+            UnitActiveState::UnitInActive | UnitActiveState::UnitFailed
+        ) {
+            return;
+        }
+
+        // record + action
+        self.comm.reli().set_last_unit(unit_name);
+        let ret = self.comm.um().unit_start_directly(unit_name);
+        self.comm.reli().clear_last_unit();
+
+        match ret {
+            Ok(_) => log::debug!("{} change to plugged.", unit_name),
+            Err(e) => log::error!("Failed to start {}: {}", unit_name, e),
+        }
+    }
+
+    fn stop_device(&self, unit_name: &str) {
+        // the unit is shared by another device still there
+        if self.comm.device_found(unit_name).is_some() {
+            return;
+        }
+
+        // record + action
+        self.comm.reli().set_last_unit(unit_name);
+        let ret = self.comm.um().unit_stop(unit_name, false);
+        self.comm.reli().clear_last_unit();
+
+        match ret {
+            Ok(_) => log::debug!("{} change to dead.", unit_name),
+            Err(e) => log::error!("Failed to stop {}: {}", unit_name, e),
+        }
+    }
+}
+
+/// the device is exposed as device units if it's tagged and not marked as not ready
+fn device_is_ready(device: &mut Device) -> bool {
+    let tagged = DEVICE_TAGS
+        .iter()
+        .any(|tag| device.has_tag(tag.to_string()).unwrap_or(false));
+    if !tagged {
+        return false;
+    }
+
+    let ready = device.get_property_value("SYSTEMD_READY".to_string()).ok();
+    parse_ready(ready.as_deref())
+}
+
+impl UnitMngUtil for DeviceManager {
+    fn attach_um(&self, um: Rc<dyn UmIf>) {
+        self.comm.attach_um(um);
+        self.register();
+    }
+
+    fn attach_reli(&self, reli: Rc<Reliability>) {
+        self.comm.attach_reli(reli);
+    }
+}
+
+impl Default for DeviceManager {
+    fn default() -> Self {
+        DeviceManager::new()
+    }
+}
+
+use sysmaster::declure_umobj_plugin;
+declure_umobj_plugin!(DeviceManager, DeviceManager::default, PLUGIN_NAME);
@@ -0,0 +1,168 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//!  The core logic of the device subclass
+use super::comm::DeviceUnitComm;
+use super::rentry::DeviceState;
+use std::cell::RefCell;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::rel::ReStation;
+use sysmaster::unit::{UnitActiveState, UnitNotifyFlags};
+
+impl DeviceState {
+    fn device_state_to_unit_state(&self) -> UnitActiveState {
+        match *self {
+            DeviceState::Dead => UnitActiveState::UnitInActive,
+            DeviceState::Plugged => UnitActiveState::UnitActive,
+        }
+    }
+}
+
+pub(super) struct DeviceMng {
+    comm: Rc<DeviceUnitComm>,
+    state: RefCell<DeviceState>,
+    sysfs: RefCell<String>,
+}
+
+impl ReStation for DeviceMng {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, _reload: bool) {
+        if let Some((state, sysfs)) = self.comm.rentry_mng_get() {
+            *self.state.borrow_mut() = state;
+            *self.sysfs.borrow_mut() = sysfs;
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm.rentry_mng_insert(self.state(), self.sysfs());
+    }
+
+    // reload: no external connections, entry-only
+}
+
+impl DeviceMng {
+    pub(super) fn new(commr: &Rc<DeviceUnitComm>) -> Self {
+        DeviceMng {
+            comm: Rc::clone(commr),
+            state: RefCell::new(DeviceState::Dead),
+            sysfs: RefCell::new(String::new()),
+        }
+    }
+
+    /// the device unit is plugged once its device is found, a start job of a device which
+    /// is not found yet waits until the device appears.
+    pub(super) fn start_action(&self) {
+        if let Some(sysfs) = self.comm.device_found() {
+            self.enter_plugged(sysfs, true);
+        }
+    }
+
+    /// the device can't be stopped while it's still there
+    pub(super) fn stop_action(&self) -> Result<()> {
+        if self.comm.device_found().is_some() {
+            return Err(Error::UnitActionEOpNotSupp);
+        }
+
+        self.enter_dead(true);
+        Ok(())
+    }
+
+    pub(super) fn enter_plugged(&self, sysfs: String, notify: bool) {
+        self.sysfs.replace(sysfs);
+        self.set_state(DeviceState::Plugged, notify);
+    }
+
+    pub(super) fn enter_dead(&self, notify: bool) {
+        self.sysfs.replace(String::new());
+        self.set_state(DeviceState::Dead, notify);
+    }
+
+    pub(super) fn get_state(&self) -> String {
+        let state = *self.state.borrow();
+        state.to_string()
+    }
+
+    pub(super) fn device_state_to_unit_state(&self) -> UnitActiveState {
+        self.state().device_state_to_unit_state()
+    }
+
+    fn set_state(&self, new_state: DeviceState, notify: bool) {
+        let old_state = self.state();
+        self.state.replace(new_state);
+
+        if notify {
+            self.state_notify(new_state, old_state);
+        }
+    }
+
+    fn state_notify(&self, new_state: DeviceState, old_state: DeviceState) {
+        if new_state != old_state {
+            log::debug!(
+                "{} original state[{:?}] -> new state[{:?}]",
+                self.comm.get_owner_id(),
+                old_state,
+                new_state,
+            );
+        }
+
+        let old_unit_state = old_state.device_state_to_unit_state();
+        let new_unit_state = new_state.device_state_to_unit_state();
+        if let Some(u) = self.comm.owner() {
+            u.notify(
+                old_unit_state,
+                new_unit_state,
+                UnitNotifyFlags::UNIT_NOTIFY_RELOAD_FAILURE,
+            )
+        }
+
+        self.db_update();
+    }
+
+    fn state(&self) -> DeviceState {
+        *self.state.borrow()
+    }
+
+    fn sysfs(&self) -> String {
+        self.sysfs.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceMng;
+    use super::DeviceState;
+    use super::DeviceUnitComm;
+    use std::rc::Rc;
+    use sysmaster::unit::UnitActiveState;
+
+    #[test]
+    fn test_device_enter_plugged_dead() {
+        let tm = DeviceMng::new(&Rc::new(DeviceUnitComm::new()));
+        assert_eq!(tm.state(), DeviceState::Dead);
+
+        tm.enter_plugged("/sys/devices/virtual/block/loop0".to_string(), false);
+        assert_eq!(tm.state(), DeviceState::Plugged);
+        assert_eq!(tm.sysfs(), "/sys/devices/virtual/block/loop0");
+        assert_eq!(tm.device_state_to_unit_state(), UnitActiveState::UnitActive);
+
+        tm.enter_dead(false);
+        assert_eq!(tm.state(), DeviceState::Dead);
+        assert!(tm.sysfs().is_empty());
+        assert_eq!(
+            tm.device_state_to_unit_state(),
+            UnitActiveState::UnitInActive
+        );
+    }
+}
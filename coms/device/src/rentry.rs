@@ -0,0 +1,132 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use macros::EnumDisplay;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use sysmaster::rel::{ReDb, ReDbRoTxn, ReDbRwTxn, ReDbTable, Reliability};
+
+const RELI_DB_HDEVICE_MNG: &str = "devmng";
+const RELI_DB_HDEVICEM_FRAME: &str = "devm-frame";
+const RELI_LAST_KEY: u32 = 0; // singleton
+
+// The device is Plugged when it's announced by devmaster and ready, or Dead otherwise.
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize, EnumDisplay)]
+pub(super) enum DeviceState {
+    Dead,
+    Plugged,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct DeviceReMng {
+    state: DeviceState,
+    sysfs: String,
+}
+
+impl DeviceReMng {
+    fn new(state: DeviceState, sysfs: String) -> DeviceReMng {
+        DeviceReMng { state, sysfs }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(super) enum DeviceReFrame {
+    Monitor,
+}
+
+struct DeviceReDb<K, V>(ReDb<K, V>);
+
+pub(super) struct DeviceRe {
+    // database: multi-instance(N)
+    mng: Rc<DeviceReDb<String, DeviceReMng>>, // RELI_DB_HDEVICE_MNG; key: unit_id, data: state+sysfs;
+
+    // database: singleton(1)
+    frame: Rc<DeviceReDb<u32, DeviceReFrame>>, // RELI_DB_HDEVICEM_FRAME; key: RELI_LAST_KEY, data: DeviceReFrame;
+}
+
+impl DeviceRe {
+    pub(super) fn new(relir: &Reliability) -> DeviceRe {
+        let mng = Rc::new(DeviceReDb(ReDb::new(relir, RELI_DB_HDEVICE_MNG)));
+        let frame = Rc::new(DeviceReDb(ReDb::new(relir, RELI_DB_HDEVICEM_FRAME)));
+        let rentry = DeviceRe { mng, frame };
+        rentry.register(relir);
+        rentry
+    }
+
+    pub(super) fn mng_insert(&self, unit_id: &str, state: DeviceState, sysfs: String) {
+        let mng = DeviceReMng::new(state, sysfs);
+        self.mng.0.insert(unit_id.to_string(), mng);
+    }
+
+    pub(super) fn mng_get(&self, unit_id: &String) -> Option<(DeviceState, String)> {
+        let mng = self.mng.0.get(unit_id);
+        mng.map(|m| (m.state, m.sysfs))
+    }
+
+    pub(super) fn set_last_frame(&self, frame: DeviceReFrame) {
+        self.frame.0.insert(RELI_LAST_KEY, frame);
+    }
+
+    pub(super) fn clear_last_frame(&self) {
+        self.frame.0.remove(&RELI_LAST_KEY);
+    }
+
+    pub(super) fn last_frame(&self) -> Option<DeviceReFrame> {
+        self.frame.0.get(&RELI_LAST_KEY)
+    }
+
+    fn register(&self, relir: &Reliability) {
+        // rel-db: RELI_DB_HDEVICE_MNG
+        let db = Rc::clone(&self.mng);
+        relir.history_db_register(RELI_DB_HDEVICE_MNG, db);
+
+        // rel-db: RELI_DB_HDEVICEM_FRAME
+        let db = Rc::clone(&self.frame);
+        relir.history_db_register(RELI_DB_HDEVICEM_FRAME, db);
+    }
+}
+
+impl ReDbTable for DeviceReDb<String, DeviceReMng> {
+    fn clear(&self, wtxn: &mut ReDbRwTxn) {
+        self.0.do_clear(wtxn);
+    }
+
+    fn export(&self, db_wtxn: &mut ReDbRwTxn) {
+        self.0.cache_2_db(db_wtxn);
+    }
+
+    fn import<'a>(&self, db_rtxn: &ReDbRoTxn) {
+        self.0.db_2_cache(db_rtxn);
+    }
+
+    fn ignore_set(&self, ignore: bool) {
+        self.0.set_ignore(ignore);
+    }
+}
+
+impl ReDbTable for DeviceReDb<u32, DeviceReFrame> {
+    fn clear(&self, wtxn: &mut ReDbRwTxn) {
+        self.0.do_clear(wtxn);
+    }
+
+    fn export(&self, db_wtxn: &mut ReDbRwTxn) {
+        self.0.cache_2_db(db_wtxn);
+    }
+
+    fn import<'a>(&self, db_rtxn: &ReDbRoTxn) {
+        self.0.db_2_cache(db_rtxn);
+    }
+
+    fn ignore_set(&self, ignore: bool) {
+        self.0.set_ignore(ignore);
+    }
+}
@@ -0,0 +1,114 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! device unit is entry of device type of unit，need impl
+//! UnitObj,UnitMngUtil, UnitSubClass trait
+
+use super::base::PLUGIN_NAME;
+use super::comm::DeviceUnitComm;
+use super::mng::DeviceMng;
+use basic::logger;
+use nix::sys::wait::WaitStatus;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::rel::{ReStation, Reliability};
+use sysmaster::unit::{SubUnit, UmIf, UnitActiveState, UnitBase, UnitMngUtil};
+
+struct DeviceUnit {
+    comm: Rc<DeviceUnitComm>,
+    mng: DeviceMng,
+}
+
+impl ReStation for DeviceUnit {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        self.mng.db_map(reload);
+    }
+
+    fn db_insert(&self) {
+        self.mng.db_insert();
+    }
+
+    // reload: no external connections, no entry
+}
+
+impl DeviceUnit {
+    fn new(_um: Rc<dyn UmIf>) -> DeviceUnit {
+        let _comm = Rc::new(DeviceUnitComm::new());
+        DeviceUnit {
+            comm: Rc::clone(&_comm),
+            mng: DeviceMng::new(&_comm),
+        }
+    }
+}
+
+impl SubUnit for DeviceUnit {
+    fn load(&self, _paths: Vec<PathBuf>) -> Result<()> {
+        // the device unit has no [Device] section, it's defined by the device itself.
+        if let Some(u) = self.comm.owner() {
+            u.set_ignore_on_isolate(true)
+        }
+        Ok(())
+    }
+
+    fn current_active_state(&self) -> UnitActiveState {
+        self.mng.device_state_to_unit_state()
+    }
+
+    fn get_subunit_state(&self) -> String {
+        self.mng.get_state()
+    }
+
+    fn attach_unit(&self, unit: Rc<dyn UnitBase>) {
+        self.comm.attach_unit(unit);
+        self.db_insert();
+    }
+
+    fn init(&self) {}
+
+    fn done(&self) {}
+
+    fn dump(&self) {}
+
+    fn start(&self) -> Result<()> {
+        self.mng.start_action();
+        Ok(())
+    }
+
+    fn stop(&self, _force: bool) -> Result<()> {
+        self.mng.stop_action()
+    }
+
+    fn kill(&self) {}
+
+    fn release_resources(&self) {}
+
+    fn sigchld_events(&self, _wait_status: WaitStatus) {}
+
+    fn reset_failed(&self) {}
+}
+
+impl UnitMngUtil for DeviceUnit {
+    fn attach_um(&self, um: Rc<dyn UmIf>) {
+        self.comm.attach_um(um);
+    }
+
+    fn attach_reli(&self, reli: Rc<Reliability>) {
+        self.comm.attach_reli(reli);
+    }
+}
+
+use sysmaster::declure_unitobj_plugin_with_param;
+declure_unitobj_plugin_with_param!(DeviceUnit, DeviceUnit::new, PLUGIN_NAME);
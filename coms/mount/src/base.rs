@@ -10,4 +10,15 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
+use basic::mount_util::mount_point_fstypes;
+
 pub(super) const PLUGIN_NAME: &str = "MountUnit";
+
+pub(super) const MOUNT_BIN: &str = "/usr/bin/mount";
+pub(super) const UMOUNT_BIN: &str = "/usr/bin/umount";
+
+/// return true if the path is a mount point listed in /proc/self/mountinfo, the autofs
+/// placed there by an automount unit doesn't count
+pub(super) fn mount_point_is_mounted(path: &str) -> bool {
+    mount_point_fstypes(path).iter().any(|t| t != "autofs")
+}
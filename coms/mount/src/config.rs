@@ -0,0 +1,239 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! mount_config mod load the conf file list and convert it to structure which is defined in this mod.
+//!
+#![allow(non_snake_case)]
+use super::base::{MOUNT_BIN, UMOUNT_BIN};
+use super::comm::MountUnitComm;
+use super::rentry::SectionMount;
+use confique::Config;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::exec::{ExecCommand, ExecFlag};
+use sysmaster::rel::ReStation;
+use sysmaster::unit::KillContext;
+
+pub(super) struct MountConfig {
+    // associated objects
+    comm: Rc<MountUnitComm>,
+
+    // owned objects
+    data: Rc<RefCell<MountConfigData>>,
+    kill_context: Rc<KillContext>,
+}
+
+impl ReStation for MountConfig {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        if reload {
+            return;
+        }
+        if let Some(mount) = self.comm.rentry_conf_get() {
+            self.data.replace(MountConfigData::new(mount));
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm.rentry_conf_insert(&self.data.borrow().Mount);
+    }
+
+    // reload: no external connections, no entry
+}
+
+impl MountConfig {
+    pub(super) fn new(commr: &Rc<MountUnitComm>) -> Self {
+        MountConfig {
+            comm: Rc::clone(commr),
+            data: Rc::new(RefCell::new(MountConfigData::default())),
+            kill_context: Rc::new(KillContext::default()),
+        }
+    }
+
+    pub(super) fn load(&self, paths: Vec<PathBuf>, update: bool) -> Result<()> {
+        // a mount point without config file is only monitored
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = MountConfigData::builder().env();
+        for v in paths {
+            builder = builder.file(v);
+        }
+        let data = builder.load().context(ConfiqueSnafu)?;
+        *self.data.borrow_mut() = data;
+
+        if update {
+            self.db_update();
+        }
+        Ok(())
+    }
+
+    pub(super) fn config_data(&self) -> Rc<RefCell<MountConfigData>> {
+        self.data.clone()
+    }
+
+    pub(super) fn kill_context(&self) -> Rc<KillContext> {
+        self.kill_context.clone()
+    }
+
+    /// whether the mount point is configured by a .mount file, or only monitored.
+    pub(super) fn configured(&self) -> bool {
+        !self.data.borrow().Mount.Where.is_empty()
+    }
+
+    pub(super) fn mount_where(&self) -> String {
+        self.data.borrow().Mount.Where.clone()
+    }
+
+    pub(super) fn mount_command(&self) -> ExecCommand {
+        self.data.borrow().mount_command()
+    }
+
+    pub(super) fn remount_command(&self) -> ExecCommand {
+        self.data.borrow().remount_command()
+    }
+
+    pub(super) fn umount_command(&self) -> ExecCommand {
+        self.data.borrow().umount_command()
+    }
+}
+
+#[derive(Config, Default, Debug)]
+pub(super) struct MountConfigData {
+    #[config(nested)]
+    pub Mount: SectionMount,
+}
+
+impl MountConfigData {
+    pub(self) fn new(Mount: SectionMount) -> MountConfigData {
+        MountConfigData { Mount }
+    }
+
+    // mount What Where [-t Type] [-o Options]
+    pub(self) fn mount_command(&self) -> ExecCommand {
+        let m = &self.Mount;
+        let mut argv = vec![m.What.clone(), m.Where.clone()];
+        if !m.Type.is_empty() && m.Type != "auto" {
+            argv.extend(["-t".to_string(), m.Type.clone()]);
+        }
+        if !m.Options.is_empty() {
+            argv.extend(["-o".to_string(), m.Options.clone()]);
+        }
+        raw_command(MOUNT_BIN, argv)
+    }
+
+    // mount Where -o remount[,Options]
+    pub(self) fn remount_command(&self) -> ExecCommand {
+        let m = &self.Mount;
+        let options = match m.Options.is_empty() {
+            true => "remount".to_string(),
+            false => format!("remount,{}", m.Options),
+        };
+        let mut argv = vec![m.Where.clone(), "-o".to_string(), options];
+        if !m.Type.is_empty() && m.Type != "auto" {
+            argv.extend(["-t".to_string(), m.Type.clone()]);
+        }
+        raw_command(MOUNT_BIN, argv)
+    }
+
+    // umount Where [-l] [-f]
+    pub(self) fn umount_command(&self) -> ExecCommand {
+        let m = &self.Mount;
+        let mut argv = vec![m.Where.clone()];
+        if m.LazyUnmount {
+            argv.push("-l".to_string());
+        }
+        if m.ForceUnmount {
+            argv.push("-f".to_string());
+        }
+        raw_command(UMOUNT_BIN, argv)
+    }
+}
+
+// the device and options are passed to mount as they are, no specifier or environment expanding
+fn raw_command(path: &str, argv: Vec<String>) -> ExecCommand {
+    let mut cmd = ExecCommand::new(path.to_string(), argv);
+    cmd.add_exec_flag(ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MountConfigData;
+    use crate::rentry::SectionMount;
+
+    fn argv(cmd: &sysmaster::exec::ExecCommand) -> Vec<String> {
+        let mut res = vec![cmd.path().clone()];
+        res.extend(cmd.argv().into_iter().cloned());
+        res
+    }
+
+    #[test]
+    fn test_mount_commands() {
+        let data = MountConfigData::new(SectionMount {
+            What: "/dev/sdb1".to_string(),
+            Where: "/mnt/data".to_string(),
+            Type: "ext4".to_string(),
+            Options: "noatime,ro".to_string(),
+            LazyUnmount: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            argv(&data.mount_command()),
+            vec![
+                "/usr/bin/mount",
+                "/dev/sdb1",
+                "/mnt/data",
+                "-t",
+                "ext4",
+                "-o",
+                "noatime,ro"
+            ]
+        );
+        assert_eq!(
+            argv(&data.remount_command()),
+            vec![
+                "/usr/bin/mount",
+                "/mnt/data",
+                "-o",
+                "remount,noatime,ro",
+                "-t",
+                "ext4"
+            ]
+        );
+        assert_eq!(
+            argv(&data.umount_command()),
+            vec!["/usr/bin/umount", "/mnt/data", "-l"]
+        );
+
+        let data = MountConfigData::new(SectionMount {
+            What: "tmpfs".to_string(),
+            Where: "/tmp".to_string(),
+            Type: "auto".to_string(),
+            ForceUnmount: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            argv(&data.mount_command()),
+            vec!["/usr/bin/mount", "tmpfs", "/tmp"]
+        );
+        assert_eq!(
+            argv(&data.umount_command()),
+            vec!["/usr/bin/umount", "/tmp", "-f"]
+        );
+    }
+}
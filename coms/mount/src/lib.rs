@@ -10,21 +10,42 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
-//! Mount is the entry for the mount point monitoring of sysmaster. sysmaster refers to systemd, but it is not the same.
-//! sysmaster mainly provides the monitoring function and does not actively mount/unmount the mount point, which is implemented by other processes.
-//! Mount does not support configuration files.
+//! Mount is the entry for the mount point management of sysmaster. sysmaster refers to systemd, but it is not the same.
+//! All mount points in /proc/self/mountinfo are monitored, a .mount unit is started when its mount point appears and stopped when it disappears.
+//! A .mount unit with a configuration file also mounts/unmounts its mount point by running mount/umount as the control process.
+//! The Mount configuration file contains three sections: Unit, Mount and Install.
+//!
+//! ```toml
+//! [Unit]
+//! Description="data disk"
+//!
+//! [Mount]
+//! What="/dev/sdb1"
+//! Where="/mnt/data"
+//! Type="ext4"
+//! Options="noatime"
+//!
+//! [Install]
+//! WantedBy="multi-user.target"
+//! ```
+//!
+//! The name of the unit must be the escaped Where, "/mnt/data" is managed by mnt-data.mount.
 //!
 //! ## Automatic dependency
 //! NA
 //! ### Implicit dependency
-//! NA
+//! After= and Requires= on the mount units of the parent paths of Where, and of What if it is a path to be bound,
+//! only the parent paths which are mounted or configured are taken into account.
 //! ### Default Dependency
 //! NA
 
-// dependency: mount_base -> mount_rentry -> mount_comm -> {mount_mng -> mount_unit} -> mount_manager
+// dependency: mount_base -> mount_rentry -> mount_comm -> mount_config -> {mount_spawn -> mount_mng -> mount_load -> mount_unit} -> mount_manager
 mod base;
 mod comm;
+mod config;
+mod load;
 mod manager;
 mod mng;
 mod rentry;
+mod spawn;
 mod unit;
@@ -0,0 +1,152 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! mount_load mod verify the section Mount and add the implicit dependencies.
+//!
+use super::base::mount_point_is_mounted;
+use super::comm::MountUnitComm;
+use super::config::MountConfig;
+use basic::path_lookup::LookupPaths;
+use basic::unit_name::unit_name_from_path;
+use std::path::Path;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::unit::{UnitDependencyMask, UnitRelations};
+
+pub(super) struct MountLoad {
+    config: Rc<MountConfig>,
+    comm: Rc<MountUnitComm>,
+}
+
+impl MountLoad {
+    pub(super) fn new(configr: &Rc<MountConfig>, commr: &Rc<MountUnitComm>) -> Self {
+        MountLoad {
+            config: configr.clone(),
+            comm: commr.clone(),
+        }
+    }
+
+    pub(super) fn verify(&self) -> Result<()> {
+        let owner = match self.comm.owner() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        let config = self.config.config_data();
+        let mount = &config.borrow().Mount;
+        if !Path::new(&mount.Where).is_absolute() {
+            return Err(Error::ConfigureError {
+                msg: format!("Where={} is not an absolute path", mount.Where),
+            });
+        }
+        if unit_name_from_path(&mount.Where, ".mount") != *owner.id() {
+            return Err(Error::ConfigureError {
+                msg: format!(
+                    "Where={} doesn't match the unit name {}",
+                    mount.Where,
+                    owner.id()
+                ),
+            });
+        }
+        if mount.What.is_empty() {
+            return Err(Error::ConfigureError {
+                msg: "What= is not configured".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub(super) fn mount_add_extras(&self) -> Result<()> {
+        let (what, mount_where) = {
+            let config = self.config.config_data();
+            let mount = &config.borrow().Mount;
+            (mount.What.clone(), mount.Where.clone())
+        };
+
+        // the mount point must be mounted after the mount points above it
+        self.add_mounts_for(&mount_where, false)?;
+
+        // a bind mount source must be mounted before it is bound
+        if Path::new(&what).is_absolute() && !what.starts_with("/dev/") {
+            self.add_mounts_for(&what, true)?;
+        }
+
+        self.add_device_dependency(&what, &mount_where)
+    }
+
+    /// add After= and Requires= to the device unit of What, so the mount waits for the device.
+    /// The root file system is mounted already, and /dev/root is not a real device.
+    fn add_device_dependency(&self, what: &str, mount_where: &str) -> Result<()> {
+        let owner = match self.comm.owner() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        if !what.starts_with("/dev/") || what == "/dev/root" || mount_where == "/" {
+            return Ok(());
+        }
+
+        let name = unit_name_from_path(what, ".device");
+        log::debug!("Adding implicit dependency on {} for {}", name, owner.id());
+        self.comm.um().unit_add_two_dependency(
+            owner.id(),
+            UnitRelations::UnitAfter,
+            UnitRelations::UnitRequires,
+            &name,
+            true,
+            UnitDependencyMask::Implicit,
+        )
+    }
+
+    /// add After= and Requires= to the mount units of the path prefixes, the path itself is
+    /// included if self_included. Only the prefixes which are mounted now or configured by
+    /// a .mount file are taken into account.
+    fn add_mounts_for(&self, path: &str, self_included: bool) -> Result<()> {
+        let owner = match self.comm.owner() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        let mut lookup_path = LookupPaths::new();
+        lookup_path.init_lookup_paths();
+
+        let um = self.comm.um();
+        for prefix in Path::new(path).ancestors() {
+            if prefix == Path::new(path) && !self_included {
+                continue;
+            }
+            let prefix = prefix.to_string_lossy();
+            let name = unit_name_from_path(&prefix, ".mount");
+            if name == *owner.id() {
+                continue;
+            }
+            let configured = lookup_path
+                .search_path
+                .iter()
+                .any(|dir| Path::new(dir).join(&name).exists());
+            if !configured && !mount_point_is_mounted(&prefix) {
+                continue;
+            }
+
+            log::debug!("Adding implicit dependency on {} for {}", name, owner.id());
+            um.unit_add_two_dependency(
+                owner.id(),
+                UnitRelations::UnitAfter,
+                UnitRelations::UnitRequires,
+                &name,
+                true,
+                UnitDependencyMask::Implicit,
+            )?;
+        }
+        Ok(())
+    }
+}
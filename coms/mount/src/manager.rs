@@ -14,6 +14,7 @@ use super::base::PLUGIN_NAME;
 use super::comm::MountUmComm;
 use super::rentry::{MountRe, MountReFrame};
 use basic::logger;
+use basic::unit_name::unit_name_from_path;
 use event::{EventState, EventType, Events, Source};
 use libmount::mountinfo;
 use std::collections::HashSet;
@@ -358,10 +359,20 @@ impl MountMonitorData {
                     if mount.fstype.to_str() == Some("autofs") {
                         continue;
                     }
-                    let unit_name = mount_point_to_unit_name(mount.mount_point.to_str().unwrap());
+                    let unit_name =
+                        unit_name_from_path(&mount.mount_point.to_string_lossy(), ".mount");
                     if dead_mount_set.contains(unit_name.as_str()) {
                         dead_mount_set.remove(unit_name.as_str());
                     } else if self.comm.um().load_unit_success(unit_name.as_str()) {
+                        // the mount/umount control process of the unit is still running,
+                        // the unit finishes its job when the process exits.
+                        if !matches!(
+                            self.comm.um().current_active_state(&unit_name),
+                            UnitActiveState::UnitInActive | UnitActiveState::UnitFailed
+                        ) {
+                            continue;
+                        }
+
                         // record + action
                         self.comm.reli().set_last_unit(&unit_name);
                         let start_ok = self.comm.um().unit_start_directly(&unit_name).is_ok();
@@ -404,14 +415,6 @@ fn drain_out(epfd: i32) {
     while epoll::wait(epfd, 0, &mut me_events).unwrap() > 0 {}
 }
 
-fn mount_point_to_unit_name(mount_point: &str) -> String {
-    let mut res = String::from(mount_point).replace('/', "-") + ".mount";
-    if res != "-.mount" {
-        res = String::from(&res[1..])
-    }
-    res
-}
-
 impl UnitMngUtil for MountManager {
     fn attach_um(&self, um: Rc<dyn UmIf>) {
         self.comm.attach_um(um);
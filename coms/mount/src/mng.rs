@@ -11,25 +11,59 @@
 // See the Mulan PSL v2 for more details.
 
 //!  The core logic of the mount subclass
+use super::base::mount_point_is_mounted;
 use super::comm::MountUnitComm;
-use super::rentry::MountState;
-use std::{cell::RefCell, rc::Rc};
+use super::config::MountConfig;
+use super::rentry::{MountResult, MountState};
+use super::spawn::MountSpawn;
+use basic::IN_SET;
+use event::{EventState, EventType, Events, Source};
+use nix::sys::wait::WaitStatus;
+use nix::unistd::Pid;
+use std::cell::RefCell;
+use std::fs::DirBuilder;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::prelude::RawFd;
+use std::rc::{Rc, Weak};
 use sysmaster::error::*;
+use sysmaster::exec::{ExecCommand, ExecContext};
 use sysmaster::rel::ReStation;
-use sysmaster::unit::{UnitActiveState, UnitNotifyFlags};
+use sysmaster::unit::{KillOperation, UnitActiveState, UnitNotifyFlags};
 
 impl MountState {
     fn mount_state_to_unit_state(&self) -> UnitActiveState {
         match *self {
             MountState::Dead => UnitActiveState::UnitInActive,
+            MountState::Mounting => UnitActiveState::UnitActivating,
             MountState::Mounted => UnitActiveState::UnitActive,
+            MountState::Remounting
+            | MountState::RemountingSigterm
+            | MountState::RemountingSigkill => UnitActiveState::UnitReloading,
+            MountState::Unmounting
+            | MountState::UnmountingSigterm
+            | MountState::UnmountingSigkill => UnitActiveState::UnitDeActivating,
+            MountState::Failed => UnitActiveState::UnitFailed,
+        }
+    }
+
+    fn to_kill_operation(self) -> KillOperation {
+        match self {
+            MountState::RemountingSigterm | MountState::UnmountingSigterm => {
+                KillOperation::KillTerminate
+            }
+            _ => KillOperation::KillKill,
         }
     }
 }
 
 pub(super) struct MountMng {
     comm: Rc<MountUnitComm>,
+    config: Rc<MountConfig>,
+    spawn: MountSpawn,
     state: RefCell<MountState>,
+    result: RefCell<MountResult>,
+    control_pid: RefCell<Option<Pid>>,
+    timer: Rc<MountTimer>,
 }
 
 impl ReStation for MountMng {
@@ -37,47 +71,314 @@ impl ReStation for MountMng {
 
     // data
     fn db_map(&self, _reload: bool) {
-        if let Some(state) = self.comm.rentry_mng_get() {
+        if let Some((state, result, control_pid)) = self.comm.rentry_mng_get() {
             *self.state.borrow_mut() = state;
+            *self.result.borrow_mut() = result;
+            *self.control_pid.borrow_mut() = control_pid;
         }
     }
 
     fn db_insert(&self) {
-        self.comm.rentry_mng_insert(self.state());
+        self.comm
+            .rentry_mng_insert(self.state(), self.result(), self.control_pid());
+    }
+
+    // reload: no external connections
+    fn entry_coldplug(&self) {
+        if self.control_pid().is_some() {
+            if let Err(e) = self.enable_timer(self.timeout_usec()) {
+                log::error!(
+                    "Failed to enable the timer of {}: {}",
+                    self.comm.get_owner_id(),
+                    e
+                );
+            }
+        }
     }
 
-    // reload: no external connections, no entry
+    fn entry_clear(&self) {
+        self.delete_timer();
+    }
 }
 
 impl MountMng {
-    pub(super) fn new(_comm: &Rc<MountUnitComm>) -> Self {
+    pub(super) fn new(
+        commr: &Rc<MountUnitComm>,
+        configr: &Rc<MountConfig>,
+        exec_ctx: &Rc<ExecContext>,
+    ) -> Self {
         MountMng {
-            comm: Rc::clone(_comm),
+            comm: Rc::clone(commr),
+            config: Rc::clone(configr),
+            spawn: MountSpawn::new(commr, exec_ctx),
             state: RefCell::new(MountState::Dead),
+            result: RefCell::new(MountResult::Success),
+            control_pid: RefCell::new(None),
+            timer: Rc::new(MountTimer::new(u64::MAX)),
         }
     }
 
-    // process doesn't support manually mount/umount like systemd.
-    // We only monitor the state of mountpoint.
-
-    pub(super) fn enter_dead(&self, notify: bool) {
-        self.set_state(MountState::Dead, notify);
-    }
-
-    pub(super) fn enter_mounted(&self, notify: bool) {
-        self.set_state(MountState::Mounted, notify);
+    pub(super) fn attach_timer(mng: &Rc<MountMng>) {
+        mng.timer.attach_mng(Rc::downgrade(mng));
     }
 
     pub(super) fn start_check(&self) -> Result<bool> {
+        // the mount point is being unmounted, try again later
+        if IN_SET!(
+            self.state(),
+            MountState::Unmounting,
+            MountState::UnmountingSigterm,
+            MountState::UnmountingSigkill
+        ) {
+            return Err(Error::UnitActionEAgain);
+        }
+
+        if self.state() == MountState::Mounting {
+            return Ok(true);
+        }
+
         let ret = self.comm.owner().map_or(false, |u| u.test_start_limit());
         if !ret {
-            self.enter_dead(true);
+            self.enter_dead(MountResult::FailureStartLimitHit, true);
             return Err(Error::UnitActionECanceled);
         }
 
         Ok(false)
     }
 
+    pub(super) fn start_action(&self) {
+        self.set_result(MountResult::Success);
+
+        // the mount point without config file is only monitored, it is started when it
+        // appears in /proc/self/mountinfo. And there is nothing to do if it's mounted already.
+        if !self.config.configured() || mount_point_is_mounted(&self.config.mount_where()) {
+            self.enter_mounted(MountResult::Success, true);
+            return;
+        }
+
+        self.enter_mounting();
+    }
+
+    pub(super) fn stop_action(&self) {
+        match self.state() {
+            MountState::Unmounting
+            | MountState::UnmountingSigterm
+            | MountState::UnmountingSigkill => {}
+            MountState::Mounting
+            | MountState::Remounting
+            | MountState::RemountingSigterm
+            | MountState::RemountingSigkill => {
+                self.enter_signal(MountState::UnmountingSigterm, MountResult::Success)
+            }
+            MountState::Mounted => self.enter_unmounting(),
+            MountState::Dead | MountState::Failed => {}
+        }
+    }
+
+    pub(super) fn reload_action(&self) {
+        if self.state() != MountState::Mounted {
+            return;
+        }
+        self.enter_remounting();
+    }
+
+    pub(super) fn can_reload(&self) -> bool {
+        self.config.configured()
+    }
+
+    fn enter_mounting(&self) {
+        let mount_where = self.config.mount_where();
+        let mode = self.config.config_data().borrow().Mount.DirectoryMode;
+        if let Err(e) = DirBuilder::new()
+            .recursive(true)
+            .mode(mode)
+            .create(&mount_where)
+        {
+            log::error!("Failed to create mount point {}: {}", mount_where, e);
+            self.enter_dead(MountResult::FailureResources, true);
+            return;
+        }
+
+        if self.spawn_control(&self.config.mount_command()).is_err() {
+            self.enter_dead(MountResult::FailureResources, true);
+            return;
+        }
+        self.set_state(MountState::Mounting, true);
+    }
+
+    fn enter_remounting(&self) {
+        if self.spawn_control(&self.config.remount_command()).is_err() {
+            log::error!("Failed to remount {}", self.comm.get_owner_id());
+            self.enter_mounted(MountResult::Success, true);
+            return;
+        }
+        self.set_state(MountState::Remounting, true);
+    }
+
+    fn enter_unmounting(&self) {
+        if !self.config.configured() || !mount_point_is_mounted(&self.config.mount_where()) {
+            self.enter_dead(MountResult::Success, true);
+            return;
+        }
+
+        if self.spawn_control(&self.config.umount_command()).is_err() {
+            self.enter_mounted(MountResult::FailureResources, true);
+            return;
+        }
+        self.set_state(MountState::Unmounting, true);
+    }
+
+    fn spawn_control(&self, cmd: &ExecCommand) -> Result<()> {
+        self.unwatch_control();
+        let pid = self.spawn.spawn_cmd(cmd)?;
+        self.control_pid.replace(Some(pid));
+        if let Err(e) = self.enable_timer(self.timeout_usec()) {
+            log::error!(
+                "Failed to enable the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn enter_signal(&self, state: MountState, res: MountResult) {
+        log::debug!("enter signal {:?}, res: {:?}", state, res);
+        if self.result() == MountResult::Success {
+            self.set_result(res);
+        }
+
+        let op = state.to_kill_operation();
+        if let Some(u) = self.comm.owner() {
+            match u.kill_context(
+                self.config.kill_context(),
+                None,
+                self.control_pid(),
+                op,
+                false,
+            ) {
+                Ok(true) => {
+                    // wait for the control process to exit
+                    if let Err(e) = self.enable_timer(self.timeout_usec()) {
+                        log::error!("Failed to enable the timer of {}: {}", u.id(), e);
+                    }
+                    self.set_state(state, true);
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Failed to kill the control process of {}: {}", u.id(), e);
+                    self.enter_dead_or_mounted(MountResult::FailureResources);
+                    return;
+                }
+            }
+        }
+
+        match state {
+            MountState::RemountingSigterm => {
+                self.enter_signal(MountState::RemountingSigkill, MountResult::Success)
+            }
+            MountState::UnmountingSigterm => {
+                self.enter_signal(MountState::UnmountingSigkill, MountResult::Success)
+            }
+            MountState::RemountingSigkill => self.enter_mounted(MountResult::Success, true),
+            _ => self.enter_dead_or_mounted(MountResult::Success),
+        }
+    }
+
+    pub(super) fn enter_dead(&self, res: MountResult, notify: bool) {
+        log::debug!("enter dead state, res {:?}", res);
+        if self.result() == MountResult::Success {
+            self.set_result(res);
+        }
+
+        let state = if self.result() == MountResult::Success {
+            MountState::Dead
+        } else {
+            MountState::Failed
+        };
+        self.set_state(state, notify);
+    }
+
+    pub(super) fn enter_mounted(&self, res: MountResult, notify: bool) {
+        if self.result() == MountResult::Success {
+            self.set_result(res);
+        }
+        self.set_state(MountState::Mounted, notify);
+    }
+
+    fn enter_dead_or_mounted(&self, res: MountResult) {
+        if self.config.configured() && mount_point_is_mounted(&self.config.mount_where()) {
+            self.enter_mounted(res, true);
+        } else {
+            self.enter_dead(res, true);
+        }
+    }
+
+    pub(super) fn sigchld_event(&self, wait_status: WaitStatus) {
+        self.unwatch_control();
+        self.disable_timer();
+
+        let res = sigchld_result(wait_status);
+        match self.state() {
+            MountState::Mounting => {
+                if res == MountResult::Success || mount_point_is_mounted(&self.config.mount_where())
+                {
+                    self.enter_mounted(res, true);
+                } else {
+                    self.enter_dead(res, true);
+                }
+            }
+            MountState::Remounting
+            | MountState::RemountingSigterm
+            | MountState::RemountingSigkill => {
+                if res != MountResult::Success {
+                    log::warn!("Failed to remount {}: {:?}", self.comm.get_owner_id(), res);
+                }
+                self.enter_mounted(MountResult::Success, true);
+            }
+            MountState::Unmounting
+            | MountState::UnmountingSigterm
+            | MountState::UnmountingSigkill => self.enter_dead_or_mounted(res),
+            _ => {
+                log::error!(
+                    "control process should not exit, current state is : {:?}",
+                    self.state()
+                );
+            }
+        }
+
+        self.db_update();
+    }
+
+    pub(super) fn timer_event(&self) {
+        log::warn!(
+            "{} {} operation time out",
+            self.comm.get_owner_id(),
+            self.state()
+        );
+        match self.state() {
+            MountState::Mounting | MountState::Unmounting => {
+                self.enter_signal(MountState::UnmountingSigterm, MountResult::FailureTimeout)
+            }
+            MountState::Remounting => {
+                self.enter_signal(MountState::RemountingSigterm, MountResult::Success)
+            }
+            MountState::RemountingSigterm => {
+                self.enter_signal(MountState::RemountingSigkill, MountResult::Success)
+            }
+            MountState::RemountingSigkill => self.enter_mounted(MountResult::Success, true),
+            MountState::UnmountingSigterm => {
+                self.enter_signal(MountState::UnmountingSigkill, MountResult::FailureTimeout)
+            }
+            MountState::UnmountingSigkill => {
+                self.enter_dead_or_mounted(MountResult::FailureTimeout)
+            }
+            _ => {}
+        }
+        self.db_update();
+    }
+
     pub fn get_state(&self) -> String {
         let state = *self.state.borrow();
         state.to_string()
@@ -87,6 +388,20 @@ impl MountMng {
         let old_state = self.state();
         self.change_state(new_state);
 
+        if !IN_SET!(
+            new_state,
+            MountState::Mounting,
+            MountState::Remounting,
+            MountState::Unmounting,
+            MountState::RemountingSigterm,
+            MountState::RemountingSigkill,
+            MountState::UnmountingSigterm,
+            MountState::UnmountingSigkill
+        ) {
+            self.disable_timer();
+            self.unwatch_control();
+        }
+
         if notify {
             self.state_notify(new_state, old_state);
         }
@@ -123,39 +438,238 @@ impl MountMng {
         *self.state.borrow()
     }
 
+    fn result(&self) -> MountResult {
+        *self.result.borrow()
+    }
+
+    fn set_result(&self, res: MountResult) {
+        self.result.replace(res);
+    }
+
+    fn control_pid(&self) -> Option<Pid> {
+        *self.control_pid.borrow()
+    }
+
+    fn unwatch_control(&self) {
+        if let Some(pid) = self.control_pid.take() {
+            if let Some(u) = self.comm.owner() {
+                self.comm.um().child_unwatch_pid(u.id(), pid)
+            }
+        }
+    }
+
+    fn timeout_usec(&self) -> u64 {
+        self.config.config_data().borrow().Mount.TimeoutSec
+    }
+
+    fn enable_timer(&self, usec: u64) -> Result<()> {
+        if usec == u64::MAX {
+            self.disable_timer();
+            return Ok(());
+        }
+
+        let events = self.comm.um().events();
+        let timer = self.timer.clone();
+        if timer.armed() {
+            events.set_enabled(timer.clone(), EventState::Off)?;
+        } else {
+            events.add_source(timer.clone())?;
+            timer.set_armed(true);
+        }
+        timer.set_time(usec);
+        events.set_enabled(timer, EventState::OneShot)?;
+        Ok(())
+    }
+
+    fn disable_timer(&self) {
+        if !self.timer.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.set_enabled(self.timer.clone(), EventState::Off) {
+            log::error!(
+                "Failed to disable the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+    }
+
+    fn delete_timer(&self) {
+        if !self.timer.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.del_source(self.timer.clone()) {
+            log::error!(
+                "Failed to delete the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        self.timer.set_armed(false);
+    }
+
     pub(super) fn mount_state_to_unit_state(&self) -> UnitActiveState {
         self.state().mount_state_to_unit_state()
     }
 }
 
+fn sigchld_result(wait_status: WaitStatus) -> MountResult {
+    match wait_status {
+        WaitStatus::Exited(_, 0) => MountResult::Success,
+        WaitStatus::Exited(_, _) => MountResult::FailureExitCode,
+        WaitStatus::Signaled(_, _, true) => MountResult::FailureCoreDump,
+        WaitStatus::Signaled(_, _, false) => MountResult::FailureSignal,
+        _ => MountResult::FailureResources,
+    }
+}
+
+pub(super) struct MountTimer {
+    time: RefCell<u64>,
+    armed: RefCell<bool>,
+    mng: RefCell<Weak<MountMng>>,
+}
+
+impl MountTimer {
+    fn new(usec: u64) -> Self {
+        MountTimer {
+            time: RefCell::new(usec),
+            armed: RefCell::new(false),
+            mng: RefCell::new(Weak::new()),
+        }
+    }
+
+    fn attach_mng(&self, mng: Weak<MountMng>) {
+        *self.mng.borrow_mut() = mng;
+    }
+
+    fn set_time(&self, usec: u64) {
+        *self.time.borrow_mut() = usec
+    }
+
+    fn armed(&self) -> bool {
+        *self.armed.borrow()
+    }
+
+    fn set_armed(&self, armed: bool) {
+        *self.armed.borrow_mut() = armed
+    }
+}
+
+impl Source for MountTimer {
+    fn fd(&self) -> RawFd {
+        0
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::TimerMonotonic
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn time_relative(&self) -> u64 {
+        *self.time.borrow()
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        if let Some(mng) = self.mng.borrow().upgrade() {
+            mng.timer_event();
+        }
+        0
+    }
+
+    fn priority(&self) -> i8 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::MountMng;
-    use super::MountState;
     use super::MountUnitComm;
+    use super::{MountResult, MountState};
+    use crate::config::MountConfig;
+    use nix::sys::wait::WaitStatus;
+    use nix::unistd::Pid;
     use std::rc::Rc;
+    use sysmaster::exec::ExecContext;
+    use sysmaster::unit::UnitActiveState;
+
+    fn new_mng() -> MountMng {
+        let _comm = Rc::new(MountUnitComm::new());
+        let _config = Rc::new(MountConfig::new(&_comm));
+        MountMng::new(&_comm, &_config, &Rc::new(ExecContext::new()))
+    }
 
     #[test]
     fn test_mount_set_state() {
-        let _comm = Rc::new(MountUnitComm::new());
-        let tm = MountMng::new(&_comm);
+        let tm = new_mng();
         tm.set_state(MountState::Mounted, false);
         assert_eq!(tm.state(), MountState::Mounted)
     }
 
     #[test]
     fn test_mount_enter_dead() {
-        let _comm = Rc::new(MountUnitComm::new());
-        let tm = MountMng::new(&_comm);
-        tm.enter_dead(false);
-        assert_eq!(tm.state(), MountState::Dead)
+        let tm = new_mng();
+        tm.enter_dead(MountResult::Success, false);
+        assert_eq!(tm.state(), MountState::Dead);
+        tm.enter_dead(MountResult::FailureExitCode, false);
+        assert_eq!(tm.state(), MountState::Failed)
     }
 
     #[test]
     fn test_mount_enter_mounted() {
-        let _comm = Rc::new(MountUnitComm::new());
-        let tm = MountMng::new(&_comm);
-        tm.enter_mounted(false);
+        let tm = new_mng();
+        tm.enter_mounted(MountResult::Success, false);
         assert_eq!(tm.state(), MountState::Mounted)
     }
+
+    #[test]
+    fn test_mount_state_to_unit_state() {
+        assert_eq!(
+            MountState::Mounting.mount_state_to_unit_state(),
+            UnitActiveState::UnitActivating
+        );
+        assert_eq!(
+            MountState::RemountingSigterm.mount_state_to_unit_state(),
+            UnitActiveState::UnitReloading
+        );
+        assert_eq!(
+            MountState::UnmountingSigkill.mount_state_to_unit_state(),
+            UnitActiveState::UnitDeActivating
+        );
+        assert_eq!(
+            MountState::Failed.mount_state_to_unit_state(),
+            UnitActiveState::UnitFailed
+        );
+    }
+
+    #[test]
+    fn test_mount_sigchld_result() {
+        let pid = Pid::from_raw(1);
+        assert_eq!(
+            super::sigchld_result(WaitStatus::Exited(pid, 0)),
+            MountResult::Success
+        );
+        assert_eq!(
+            super::sigchld_result(WaitStatus::Exited(pid, 32)),
+            MountResult::FailureExitCode
+        );
+        assert_eq!(
+            super::sigchld_result(WaitStatus::Signaled(
+                pid,
+                nix::sys::signal::Signal::SIGKILL,
+                false
+            )),
+            MountResult::FailureSignal
+        );
+    }
 }
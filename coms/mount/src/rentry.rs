@@ -10,30 +10,111 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
+#![allow(non_snake_case)]
+use basic::time_util::USEC_PER_SEC;
+use confique::Config;
 use macros::EnumDisplay;
-use serde::{Deserialize, Serialize};
+use nix::unistd::Pid;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::rc::Rc;
 use sysmaster::rel::{ReDb, ReDbRoTxn, ReDbRwTxn, ReDbTable, Reliability};
+use sysmaster::serialize::deserialize_directory_mode;
 
+const RELI_DB_HMOUNT_CONF: &str = "mntconf";
 const RELI_DB_HMOUNT_MNG: &str = "mntmng";
 const RELI_DB_HMOUNTM_FRAME: &str = "mntm-frame";
 const RELI_LAST_KEY: u32 = 0; // singleton
 
-//Mount contains two states: unmounted Dead and mounted Mounted. The corresponding unit status is inactive and active
+fn deserialize_timeout<'de, D>(de: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timeout = u64::deserialize(de)?;
+    if timeout == 0 {
+        return Ok(u64::MAX);
+    }
+    if timeout >= u64::MAX / USEC_PER_SEC {
+        return Ok(u64::MAX);
+    }
+    Ok(timeout * USEC_PER_SEC)
+}
+
+#[derive(Config, Default, Clone, Debug, Serialize, Deserialize)]
+pub(super) struct SectionMount {
+    #[config(default = "")]
+    pub What: String,
+    #[config(default = "")]
+    pub Where: String,
+    #[config(default = "")]
+    pub Type: String,
+    #[config(default = "")]
+    pub Options: String,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub DirectoryMode: u32,
+    #[config(deserialize_with = deserialize_timeout)]
+    #[config(default = 90)]
+    pub TimeoutSec: u64,
+    #[config(default = false)]
+    pub LazyUnmount: bool,
+    #[config(default = false)]
+    pub ForceUnmount: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct MountReConf {
+    mount: SectionMount,
+}
+
+impl MountReConf {
+    fn new(mountr: &SectionMount) -> MountReConf {
+        MountReConf {
+            mount: mountr.clone(),
+        }
+    }
+}
+
+// The mount point is Dead(inactive) or Mounted(active), the other states are the transitions
+// between them, in which a mount/umount control process is running or being killed.
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize, EnumDisplay)]
 pub(super) enum MountState {
     Dead,
+    Mounting,
     Mounted,
+    Remounting,
+    Unmounting,
+    RemountingSigterm,
+    RemountingSigkill,
+    UnmountingSigterm,
+    UnmountingSigkill,
+    Failed,
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize, EnumDisplay)]
+pub(super) enum MountResult {
+    Success,
+    FailureResources,
+    FailureTimeout,
+    FailureExitCode,
+    FailureSignal,
+    FailureCoreDump,
+    FailureStartLimitHit,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct MountReMng {
     state: MountState,
+    result: MountResult,
+    control_pid: Option<i32>, // i32 ==> nix::unistd::Pid ==> libc::pid_t
 }
 
 impl MountReMng {
-    fn new(state: MountState) -> MountReMng {
-        MountReMng { state }
+    fn new(state: MountState, result: MountResult, control_pid: Option<i32>) -> MountReMng {
+        MountReMng {
+            state,
+            result,
+            control_pid,
+        }
     }
 }
 
@@ -46,7 +127,8 @@ struct MountReDb<K, V>(ReDb<K, V>);
 
 pub(super) struct MountRe {
     // database: multi-instance(N)
-    mng: Rc<MountReDb<String, MountReMng>>, // RELI_DB_HMOUNT_MNG; key: unit_id, data: state;
+    conf: Rc<MountReDb<String, MountReConf>>, // RELI_DB_HMOUNT_CONF; key: unit_id, data: config;
+    mng: Rc<MountReDb<String, MountReMng>>, // RELI_DB_HMOUNT_MNG; key: unit_id, data: state+result+control_pid;
 
     // database: singleton(1)
     frame: Rc<MountReDb<u32, MountReFrame>>, // RELI_DB_HMOUNTM_FRAME; key: RELI_LAST_KEY, data: MountReFrame;
@@ -54,21 +136,41 @@ pub(super) struct MountRe {
 
 impl MountRe {
     pub(super) fn new(relir: &Reliability) -> MountRe {
+        let conf = Rc::new(MountReDb(ReDb::new(relir, RELI_DB_HMOUNT_CONF)));
         let mng = Rc::new(MountReDb(ReDb::new(relir, RELI_DB_HMOUNT_MNG)));
         let frame = Rc::new(MountReDb(ReDb::new(relir, RELI_DB_HMOUNTM_FRAME)));
-        let rentry = MountRe { mng, frame };
+        let rentry = MountRe { conf, mng, frame };
         rentry.register(relir);
         rentry
     }
 
-    pub(super) fn mng_insert(&self, unit_id: &str, state: MountState) {
-        let mng = MountReMng::new(state);
+    pub(super) fn conf_insert(&self, unit_id: &str, mount: &SectionMount) {
+        let conf = MountReConf::new(mount);
+        self.conf.0.insert(unit_id.to_string(), conf);
+    }
+
+    pub(super) fn conf_get(&self, unit_id: &String) -> Option<SectionMount> {
+        let conf = self.conf.0.get(unit_id);
+        conf.map(|c| c.mount)
+    }
+
+    pub(super) fn mng_insert(
+        &self,
+        unit_id: &str,
+        state: MountState,
+        result: MountResult,
+        control_pid: Option<Pid>,
+    ) {
+        let mng = MountReMng::new(state, result, control_pid.map(|x| x.as_raw()));
         self.mng.0.insert(unit_id.to_string(), mng);
     }
 
-    pub(super) fn mng_get(&self, unit_id: &String) -> Option<MountState> {
+    pub(super) fn mng_get(
+        &self,
+        unit_id: &String,
+    ) -> Option<(MountState, MountResult, Option<Pid>)> {
         let mng = self.mng.0.get(unit_id);
-        mng.map(|m| m.state)
+        mng.map(|m| (m.state, m.result, m.control_pid.map(Pid::from_raw)))
     }
 
     pub(super) fn set_last_frame(&self, frame: MountReFrame) {
@@ -84,6 +186,10 @@ impl MountRe {
     }
 
     fn register(&self, relir: &Reliability) {
+        // rel-db: RELI_DB_HMOUNT_CONF
+        let db = Rc::clone(&self.conf);
+        relir.history_db_register(RELI_DB_HMOUNT_CONF, db);
+
         // rel-db: RELI_DB_HMOUNT_MNG
         let db = Rc::clone(&self.mng);
         relir.history_db_register(RELI_DB_HMOUNT_MNG, db);
@@ -94,6 +200,24 @@ impl MountRe {
     }
 }
 
+impl ReDbTable for MountReDb<String, MountReConf> {
+    fn clear(&self, wtxn: &mut ReDbRwTxn) {
+        self.0.do_clear(wtxn);
+    }
+
+    fn export(&self, db_wtxn: &mut ReDbRwTxn) {
+        self.0.cache_2_db(db_wtxn);
+    }
+
+    fn import<'a>(&self, db_rtxn: &ReDbRoTxn) {
+        self.0.db_2_cache(db_rtxn);
+    }
+
+    fn ignore_set(&self, ignore: bool) {
+        self.0.set_ignore(ignore);
+    }
+}
+
 impl ReDbTable for MountReDb<String, MountReMng> {
     fn clear(&self, wtxn: &mut ReDbRwTxn) {
         self.0.do_clear(wtxn);
@@ -15,18 +15,23 @@
 
 use super::base::PLUGIN_NAME;
 use super::comm::MountUnitComm;
+use super::config::MountConfig;
+use super::load::MountLoad;
 use super::mng::MountMng;
 use basic::logger;
 use nix::sys::wait::WaitStatus;
 use std::path::PathBuf;
 use std::rc::Rc;
 use sysmaster::error::*;
+use sysmaster::exec::ExecContext;
 use sysmaster::rel::{ReStation, Reliability};
 use sysmaster::unit::{SubUnit, UmIf, UnitActiveState, UnitBase, UnitMngUtil};
 
 struct MountUnit {
     comm: Rc<MountUnitComm>,
+    config: Rc<MountConfig>,
     mng: Rc<MountMng>,
+    load: MountLoad,
 }
 
 impl ReStation for MountUnit {
@@ -34,41 +39,57 @@ impl ReStation for MountUnit {
 
     // data
     fn db_map(&self, reload: bool) {
+        self.config.db_map(reload);
         self.mng.db_map(reload);
     }
 
     fn db_insert(&self) {
+        self.config.db_insert();
         self.mng.db_insert();
     }
 
     // reload: no external connections, entry-only
     fn entry_coldplug(&self) {
         // rebuild external connections, like: timer, ...
-        // do nothing now
+        self.mng.entry_coldplug();
     }
 
     fn entry_clear(&self) {
         // release external connection, like: timer, ...
-        // do nothing now
+        self.mng.entry_clear();
     }
 }
 
 impl MountUnit {
     fn new(_um: Rc<dyn UmIf>) -> MountUnit {
+        let context = Rc::new(ExecContext::new());
         let _comm = Rc::new(MountUnitComm::new());
+        let _config = Rc::new(MountConfig::new(&_comm));
+        let _mng = Rc::new(MountMng::new(&_comm, &_config, &context));
+        MountMng::attach_timer(&_mng);
         MountUnit {
             comm: Rc::clone(&_comm),
-            mng: Rc::new(MountMng::new(&_comm)),
+            config: Rc::clone(&_config),
+            mng: _mng,
+            load: MountLoad::new(&_config, &_comm),
         }
     }
 }
 
 impl SubUnit for MountUnit {
-    fn load(&self, _paths: Vec<PathBuf>) -> Result<()> {
+    fn load(&self, paths: Vec<PathBuf>) -> Result<()> {
         if let Some(u) = self.comm.owner() {
             u.set_ignore_on_isolate(true)
         }
-        Ok(())
+
+        // the mount point without config file is only monitored
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        self.config.load(paths, true)?;
+        self.load.verify()?;
+        self.load.mount_add_extras()
     }
 
     fn current_active_state(&self) -> UnitActiveState {
@@ -97,21 +118,32 @@ impl SubUnit for MountUnit {
             return Ok(());
         }
 
-        self.mng.enter_mounted(true);
+        self.mng.start_action();
 
         Ok(())
     }
 
     fn stop(&self, _force: bool) -> Result<()> {
-        self.mng.enter_dead(true);
+        self.mng.stop_action();
         Ok(())
     }
 
+    fn reload(&self) -> Result<()> {
+        self.mng.reload_action();
+        Ok(())
+    }
+
+    fn can_reload(&self) -> bool {
+        self.mng.can_reload()
+    }
+
     fn kill(&self) {}
 
     fn release_resources(&self) {}
 
-    fn sigchld_events(&self, _wait_status: WaitStatus) {}
+    fn sigchld_events(&self, wait_status: WaitStatus) {
+        self.mng.sigchld_event(wait_status)
+    }
 
     fn reset_failed(&self) {}
 }
@@ -97,6 +97,8 @@ impl ServiceUnitComm {
         restarts: u32,
         exit_status: ExitStatus,
         monitor: ServiceMonitor,
+        dynamic_uid: Option<u32>,
+        socket_fd: Option<i32>,
     ) {
         if let Some(u) = self.owner() {
             self.rentry().mng_insert(
@@ -114,6 +116,8 @@ impl ServiceUnitComm {
                 restarts,
                 exit_status,
                 monitor,
+                dynamic_uid,
+                socket_fd,
             )
         }
     }
@@ -135,12 +139,14 @@ impl ServiceUnitComm {
         u32,
         ExitStatus,
         ServiceMonitor,
+        Option<u32>,
+        Option<i32>,
     )> {
         self.owner().map(|u| self.rentry().mng_get(u.id()))?
     }
 
-    pub(super) fn _reli(&self) -> Rc<Reliability> {
-        self.umcomm._reli()
+    pub(super) fn reli(&self) -> Rc<Reliability> {
+        self.umcomm.reli()
     }
 
     pub(super) fn log(&self, level: Level, msg: &str) {
@@ -200,9 +206,9 @@ impl ServiceUmComm {
         SERVICE_UM_COMM.clone()
     }
 
-    pub(super) fn _reli(&self) -> Rc<Reliability> {
+    pub(super) fn reli(&self) -> Rc<Reliability> {
         let rdata = self.data.read().unwrap();
-        rdata._reli()
+        rdata.reli()
     }
 
     pub(super) fn um(&self) -> Rc<dyn UmIf> {
@@ -219,7 +225,7 @@ impl ServiceUmComm {
 struct ServiceUmCommData {
     // associated objects
     um: Option<Rc<dyn UmIf>>,
-    _reli: Weak<Reliability>,
+    reli: Weak<Reliability>,
     rentry: Option<Rc<ServiceRe>>,
 }
 
@@ -228,7 +234,7 @@ impl ServiceUmCommData {
     pub(self) fn new() -> ServiceUmCommData {
         ServiceUmCommData {
             um: None,
-            _reli: Weak::new(),
+            reli: Weak::new(),
             rentry: None,
         }
     }
@@ -241,10 +247,10 @@ impl ServiceUmCommData {
     }
 
     pub(self) fn attach_reli(&mut self, reli: Rc<Reliability>) {
-        let old = self._reli.clone().upgrade();
+        let old = self.reli.clone().upgrade();
         if old.is_none() {
             log::debug!("ServiceUmComm attach_reli action.");
-            self._reli = Rc::downgrade(&reli);
+            self.reli = Rc::downgrade(&reli);
             self.rentry.replace(Rc::new(ServiceRe::new(&reli)));
         }
     }
@@ -257,8 +263,8 @@ impl ServiceUmCommData {
         }
     }
 
-    pub(self) fn _reli(&self) -> Rc<Reliability> {
-        self._reli.clone().upgrade().unwrap()
+    pub(self) fn reli(&self) -> Rc<Reliability> {
+        self.reli.clone().upgrade().unwrap()
     }
 
     pub(self) fn rentry(&self) -> Rc<ServiceRe> {
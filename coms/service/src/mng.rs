@@ -20,7 +20,8 @@ use super::rentry::{
 };
 use super::spawn::ServiceSpawn;
 use crate::rentry::{ExitStatus, PreserveMode};
-use basic::{fd_util, IN_SET};
+use basic::user_group_util::{self, get_user_creds};
+use basic::{fd_util, ipc_util, IN_SET};
 use basic::{file_util, process_util};
 use event::{EventState, EventType, Events, Source};
 use log::Level;
@@ -30,7 +31,7 @@ use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor};
 use nix::sys::signal::Signal;
 use nix::sys::socket::UnixCredentials;
 use nix::sys::wait::WaitStatus;
-use nix::unistd::Pid;
+use nix::unistd::{Gid, Pid, Uid};
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 use std::fmt;
@@ -42,7 +43,9 @@ use std::{
     rc::Weak,
 };
 use sysmaster::error::*;
-use sysmaster::exec::{ExecCommand, ExecContext, ExecFlag, ExecFlags};
+use sysmaster::exec::{
+    credentials_dir, private_tmp_dirs, ExecCommand, ExecContext, ExecFlag, ExecFlags,
+};
 use sysmaster::rel::ReStation;
 use sysmaster::unit::{KillOperation, UnitActiveState, UnitNotifyFlags};
 
@@ -87,6 +90,8 @@ impl ReStation for ServiceMng {
             restarts,
             exit_status,
             monitor,
+            dynamic_uid,
+            socket_fd,
         )) = self.comm.rentry_mng_get()
         {
             *self.state.borrow_mut() = state;
@@ -101,6 +106,8 @@ impl ReStation for ServiceMng {
             self.rd.set_restarts(restarts);
             self.rd.set_wait_status(WaitStatus::from(exit_status));
             *self.monitor.borrow_mut() = monitor;
+            self.rd.set_dynamic_uid(dynamic_uid);
+            self.rd.map_socket_fd(socket_fd);
         }
     }
 
@@ -120,6 +127,8 @@ impl ReStation for ServiceMng {
             self.rd.restarts(),
             exit_status,
             *self.monitor.borrow(),
+            self.rd.dynamic_uid(),
+            self.rd.socket_fd(),
         );
     }
 
@@ -589,10 +598,7 @@ impl ServiceMng {
         self.set_state(state);
         if restart {
             self.rd.set_will_auto_restart(false);
-            if let Err(e) = self
-                .rd
-                .enable_timer(self.config.config_data().borrow().Service.RestartSec)
-            {
+            if let Err(e) = self.rd.enable_timer(self.restart_usec_next()) {
                 self.log(
                     Level::Warn,
                     &format!("auto restart start timer error: {}", e),
@@ -845,6 +851,10 @@ impl ServiceMng {
             self.set_cmd_type(None);
         }
 
+        if IN_SET!(state, ServiceState::Dead, ServiceState::Failed) {
+            self.release_resources();
+        }
+
         // todo!()
         // trigger the unit the dependency trigger_by
 
@@ -864,6 +874,65 @@ impl ServiceMng {
         }
     }
 
+    /// release the resources that live as long as the service runs
+    fn release_resources(&self) {
+        // the connection is owned by the instance until it finishes
+        self.rd.release_socket_fd();
+
+        let (remove_ipc, private_tmp, user) = {
+            let config = self.config.config_data();
+            let service = &config.borrow().Service;
+            (
+                service.RemoveIPC || service.DynamicUser,
+                service.PrivateTmp || service.DynamicUser,
+                service.User.clone(),
+            )
+        };
+
+        if remove_ipc {
+            let ids = match self.rd.dynamic_uid() {
+                Some(uid) => Some((Uid::from_raw(uid), Gid::from_raw(uid))),
+                None if user.is_empty() => None,
+                None => get_user_creds(&user).ok().map(|u| (u.uid, u.gid)),
+            };
+            if let Some((uid, gid)) = ids {
+                if let Err(e) = ipc_util::remove_ipc(uid, gid) {
+                    self.log(Level::Warn, &format!("Failed to remove IPC objects: {e}"));
+                }
+            }
+        }
+
+        if private_tmp {
+            for d in private_tmp_dirs(&self.comm.get_owner_id()) {
+                if d.exists() {
+                    if let Err(e) = std::fs::remove_dir_all(&d) {
+                        self.log(Level::Warn, &format!("Failed to remove {:?}: {e}", d));
+                    }
+                }
+            }
+        }
+
+        let credentials = credentials_dir(&self.comm.get_owner_id());
+        if credentials.exists() {
+            if let Err(e) = nix::mount::umount2(&credentials, nix::mount::MntFlags::MNT_DETACH) {
+                if e != Errno::EINVAL {
+                    self.log(
+                        Level::Warn,
+                        &format!("Failed to unmount {:?}: {e}", credentials),
+                    );
+                }
+            }
+            if let Err(e) = std::fs::remove_dir_all(&credentials) {
+                self.log(
+                    Level::Warn,
+                    &format!("Failed to remove {:?}: {e}", credentials),
+                );
+            }
+        }
+
+        self.rd.release_dynamic_user();
+    }
+
     fn set_cmd_type(&self, cmd_type: Option<ServiceCommand>) {
         *self.control_cmd_type.borrow_mut() = cmd_type;
     }
@@ -943,6 +1012,21 @@ impl ServiceMng {
         *self.state.borrow()
     }
 
+    pub(super) fn set_socket_fd(&self, fd: RawFd) -> Result<()> {
+        if !IN_SET!(self.state(), ServiceState::Dead, ServiceState::Failed) {
+            return Err(Error::UnitActionEBusy);
+        }
+
+        self.rd.set_socket_fd(fd)?;
+        self.db_update();
+        Ok(())
+    }
+
+    pub(super) fn release_socket_fd(&self) {
+        self.rd.release_socket_fd();
+        self.db_update();
+    }
+
     fn set_result(&self, result: ServiceResult) {
         *self.result.borrow_mut() = result;
     }
@@ -1197,6 +1281,19 @@ impl ServiceMng {
             return false;
         }
 
+        // the exit status or signal configured in RestartForceExitStatus always
+        // restarts the service, no matter what Restart= is configured.
+        if self
+            .config
+            .config_data()
+            .borrow()
+            .Service
+            .RestartForceExitStatus
+            .exit_status_enabled(self.rd.wait_status())
+        {
+            return true;
+        }
+
         match self.config.config_data().borrow().Service.Restart {
             ServiceRestart::No => false,
             ServiceRestart::OnSuccess => self.result() == ServiceResult::Success,
@@ -1221,6 +1318,19 @@ impl ServiceMng {
         }
     }
 
+    // the delay before the next automatic restart, grows from RestartSec to
+    // RestartMaxDelaySec in RestartSteps steps.
+    fn restart_usec_next(&self) -> u64 {
+        let config = self.config.config_data();
+        let service = &config.borrow().Service;
+        restart_usec_next(
+            service.RestartSec,
+            service.RestartMaxDelaySec,
+            service.RestartSteps,
+            self.rd.restarts() + 1,
+        )
+    }
+
     fn restart_watchdog(&self) {
         self.monitor
             .borrow_mut()
@@ -1308,7 +1418,7 @@ impl ServiceMng {
                 self.config.config_data().borrow().Service.TimeoutStopSec
             }
 
-            ServiceState::AutoRestart => self.config.config_data().borrow().Service.RestartSec,
+            ServiceState::AutoRestart => self.restart_usec_next(),
 
             ServiceState::Cleaning => todo!(), // TimeoutCleanSec,
 
@@ -1324,9 +1434,16 @@ impl ServiceMng {
     }
 
     fn sigchld_result(&self, wait_status: WaitStatus) -> ServiceResult {
+        let success_status = self
+            .config
+            .config_data()
+            .borrow()
+            .Service
+            .SuccessExitStatus
+            .exit_status_enabled(wait_status);
         match wait_status {
             WaitStatus::Exited(_, status) => {
-                if status == 0 {
+                if status == 0 || success_status {
                     ServiceResult::Success
                 } else {
                     ServiceResult::FailureExitCode
@@ -1340,11 +1457,12 @@ impl ServiceMng {
                     || *self.control_cmd_type.borrow() == Some(ServiceCommand::Start);
                 if core_dump {
                     ServiceResult::FailureCoreDump
-                } else if is_daemon
-                    && matches!(
-                        sig,
-                        Signal::SIGHUP | Signal::SIGINT | Signal::SIGTERM | Signal::SIGPIPE
-                    )
+                } else if success_status
+                    || is_daemon
+                        && matches!(
+                            sig,
+                            Signal::SIGHUP | Signal::SIGINT | Signal::SIGTERM | Signal::SIGPIPE
+                        )
                 {
                     ServiceResult::Success
                 } else {
@@ -1759,6 +1877,22 @@ impl ServiceState {
     }
 }
 
+// same as service_restart_usec_next() in systemd: the delay of the n-th restart
+// grows exponentially from restart_usec, and reaches max_delay_usec at the last step.
+fn restart_usec_next(restart_usec: u64, max_delay_usec: u64, steps: u32, n_restarts: u32) -> u64 {
+    if n_restarts <= 1 || steps == 0 || restart_usec == 0 || max_delay_usec <= restart_usec {
+        return restart_usec;
+    }
+
+    if n_restarts > steps {
+        return max_delay_usec;
+    }
+
+    let r = max_delay_usec as f64 / restart_usec as f64;
+    let delay = restart_usec as f64 * r.powf((n_restarts - 1) as f64 / steps as f64);
+    (delay.round() as u64).min(max_delay_usec)
+}
+
 fn service_state_to_unit_state(service_type: ServiceType, state: ServiceState) -> UnitActiveState {
     if service_type == ServiceType::Idle {
         return state.to_unit_active_state_idle();
@@ -1937,6 +2071,65 @@ impl RunningData {
     pub(self) fn armd_watchdog(&self) -> bool {
         self.data.borrow().armd_watchdog()
     }
+
+    pub(super) fn set_socket_fd(&self, fd: RawFd) -> Result<()> {
+        self.release_socket_fd();
+        // keep the connection open across the reexecution of the manager
+        self.comm.reli().fd_cloexec(fd, false)?;
+        self.data.borrow_mut().set_socket_fd(fd);
+        Ok(())
+    }
+
+    /// take back the connection fd kept across the reexecution of the manager
+    pub(self) fn map_socket_fd(&self, fd: Option<RawFd>) {
+        if let Some(fd) = fd {
+            let fd = self.comm.reli().fd_take(fd);
+            self.data.borrow_mut().set_socket_fd(fd);
+        }
+    }
+
+    /// the connection fd accepted by the socket which spawned this service instance
+    pub(super) fn socket_fd(&self) -> Option<RawFd> {
+        let fd = self.data.borrow().socket_fd();
+        if fd < 0 {
+            return None;
+        }
+        Some(fd)
+    }
+
+    pub(super) fn release_socket_fd(&self) {
+        if let Some(fd) = self.socket_fd() {
+            if let Err(e) = self.comm.reli().fd_cloexec(fd, true) {
+                log::error!("Failed to remark the connection fd {fd}: {e}");
+            }
+            fd_util::close(fd);
+            self.data.borrow_mut().set_socket_fd(-1);
+        }
+    }
+
+    /// allocate the uid of the dynamic user, the uid is kept until the service stops
+    pub(super) fn dynamic_user(&self, name: &str) -> Result<Uid> {
+        if let Some(uid) = self.dynamic_uid() {
+            return Ok(Uid::from_raw(uid));
+        }
+        let uid = user_group_util::dynamic_user_acquire(name).context(UtilSnafu)?;
+        self.set_dynamic_uid(Some(uid.as_raw()));
+        Ok(uid)
+    }
+
+    pub(super) fn set_dynamic_uid(&self, uid: Option<u32>) {
+        self.data.borrow_mut().dynamic_uid = uid;
+    }
+
+    pub(super) fn dynamic_uid(&self) -> Option<u32> {
+        self.data.borrow().dynamic_uid
+    }
+
+    pub(self) fn release_dynamic_user(&self) {
+        if let Some(uid) = self.data.borrow_mut().dynamic_uid.take() {
+            user_group_util::dynamic_user_release(Uid::from_raw(uid));
+        }
+    }
 }
 
 struct Rtdata {
@@ -1953,6 +2146,9 @@ struct Rtdata {
     exec_status: WaitStatus,
 
     watchdog: Option<Rc<ServiceMonitorData>>,
+
+    socket_fd: RawFd,
+    dynamic_uid: Option<u32>,
 }
 
 impl Rtdata {
@@ -1969,6 +2165,8 @@ impl Rtdata {
             timer: None,
             exec_status: WaitStatus::StillAlive,
             watchdog: None,
+            socket_fd: -1,
+            dynamic_uid: None,
         }
     }
 
@@ -2068,6 +2266,14 @@ impl Rtdata {
     pub(self) fn armd_watchdog(&self) -> bool {
         self.watchdog.is_some()
     }
+
+    pub(self) fn set_socket_fd(&mut self, fd: RawFd) {
+        self.socket_fd = fd;
+    }
+
+    pub(self) fn socket_fd(&self) -> RawFd {
+        self.socket_fd
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Hash)]
@@ -2495,7 +2701,7 @@ impl Source for ServiceMonitorData {
 
 #[cfg(test)]
 mod tests {
-    use super::{RunningData, ServiceMng};
+    use super::{restart_usec_next, RunningData, ServiceMng, ServiceResult};
     use crate::{comm::ServiceUnitComm, config::ServiceConfig};
     use std::{collections::HashMap, rc::Rc};
     use sysmaster::{exec::ExecContext, UmIf};
@@ -2525,6 +2731,22 @@ mod tests {
         (mng, rt, config)
     }
 
+    #[test]
+    fn test_sigterm_result() {
+        use nix::sys::{signal::Signal, wait::WaitStatus};
+        use nix::unistd::Pid;
+        let (mng, _rt, _config) = create_mng();
+
+        // the main process of PrivatePIDs is killed by the signal that killed the command
+        let pid = Pid::from_raw(1000);
+        let status = WaitStatus::Signaled(pid, Signal::SIGTERM, false);
+        assert_eq!(mng.sigchld_result(status), ServiceResult::Success);
+        let status = WaitStatus::Exited(pid, 128 + Signal::SIGTERM as i32);
+        assert_eq!(mng.sigchld_result(status), ServiceResult::FailureExitCode);
+        let status = WaitStatus::Signaled(pid, Signal::SIGKILL, false);
+        assert_eq!(mng.sigchld_result(status), ServiceResult::FailureSignal);
+    }
+
     #[test]
     fn test_watchdog_on() {
         use nix::sys::socket::UnixCredentials;
@@ -2571,4 +2793,19 @@ mod tests {
         assert!(rt.armd_watchdog());
         assert_eq!(rt.watchdog().time(), 15);
     }
+
+    #[test]
+    fn test_restart_usec_next() {
+        // back-off disabled
+        assert_eq!(restart_usec_next(100, 0, 0, 3), 100);
+        assert_eq!(restart_usec_next(100, 1000, 0, 3), 100);
+        assert_eq!(restart_usec_next(100, 50, 5, 3), 100);
+
+        // the first restart always uses RestartSec
+        assert_eq!(restart_usec_next(100, 10000, 2, 1), 100);
+        // 100 * (10000 / 100) ^ (1 / 2)
+        assert_eq!(restart_usec_next(100, 10000, 2, 2), 1000);
+        assert_eq!(restart_usec_next(100, 10000, 2, 3), 10000);
+        assert_eq!(restart_usec_next(100, 10000, 2, 10), 10000);
+    }
 }
@@ -18,7 +18,7 @@ use macros::EnumDisplay;
 use nix::sys::signal::Signal;
 use nix::sys::wait::WaitStatus;
 use nix::unistd::Pid;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 use std::path::PathBuf;
@@ -26,9 +26,12 @@ use std::rc::Rc;
 use std::str::FromStr;
 
 use sysmaster::error::*;
-use sysmaster::exec::{ExecCommand, Rlimit};
+use sysmaster::exec::{
+    CpuSchedulingPolicy, CpuSet, ExecCommand, ExecDirectoryType, IoSchedulingClass, NumaPolicy,
+    ProtectSystem, RestrictAddressFamilies, RestrictNamespaces, Rlimit,
+};
 use sysmaster::rel::{ReDb, ReDbRoTxn, ReDbRwTxn, ReDbTable, Reliability};
-use sysmaster::serialize::DeserializeWith;
+use sysmaster::serialize::{deserialize_directory_mode, DeserializeWith};
 use sysmaster::unit::KillMode;
 
 use basic::special::EXEC_RUNTIME_PREFIX;
@@ -156,7 +159,7 @@ impl DeserializeWith for ExitStatusSet {
                 status_set.add_signal(cmd.to_string());
                 continue;
             }
-            log::warn!("RestartPreventExitStatus: invalid config value {}", cmd);
+            log::warn!("ExitStatusSet: invalid config value {}", cmd);
         }
 
         Ok(status_set)
@@ -192,6 +195,34 @@ impl DeserializeWith for PreserveMode {
     }
 }
 
+/// where the standard input and output of the service come from
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum StandardInput {
+    /// inherited from sysmaster
+    #[default]
+    Inherit,
+    /// the connection fd of a socket with Accept=true
+    Socket,
+}
+
+impl DeserializeWith for StandardInput {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> std::result::Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        match s.as_str() {
+            "inherit" => Ok(StandardInput::Inherit),
+            "socket" => Ok(StandardInput::Socket),
+            _ => Err(de::Error::custom(format!(
+                "StandardInput: invalid value {s}, expected inherit or socket"
+            ))),
+        }
+    }
+}
+
 fn deserialize_pidfile<'de, D>(de: D) -> Result<PathBuf, D::Error>
 where
     D: Deserializer<'de>,
@@ -219,6 +250,58 @@ where
     Ok(timeout * USEC_PER_SEC)
 }
 
+fn deserialize_nice<'de, D>(de: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let nice = i32::deserialize(de)?;
+    if !(-20..=19).contains(&nice) {
+        return Err(de::Error::custom(format!(
+            "Nice: {nice} is out of range [-20, 19]"
+        )));
+    }
+    Ok(nice)
+}
+
+fn deserialize_cpu_sched_priority<'de, D>(de: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let priority = i32::deserialize(de)?;
+    if !(0..=99).contains(&priority) {
+        return Err(de::Error::custom(format!(
+            "CPUSchedulingPriority: {priority} is out of range [0, 99]"
+        )));
+    }
+    Ok(priority)
+}
+
+fn deserialize_io_sched_priority<'de, D>(de: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let priority = u32::deserialize(de)?;
+    if priority > 7 {
+        return Err(de::Error::custom(format!(
+            "IOSchedulingPriority: {priority} is out of range [0, 7]"
+        )));
+    }
+    Ok(priority)
+}
+
+fn deserialize_oom_score_adjust<'de, D>(de: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let adjust = i32::deserialize(de)?;
+    if !(-1000..=1000).contains(&adjust) {
+        return Err(de::Error::custom(format!(
+            "OOMScoreAdjust: {adjust} is out of range [-1000, 1000]"
+        )));
+    }
+    Ok(adjust)
+}
+
 #[derive(Config, Default, Clone, Debug, Serialize, Deserialize)]
 pub(super) struct SectionService {
     #[config(deserialize_with = ServiceType::deserialize_with)]
@@ -249,6 +332,9 @@ pub(super) struct SectionService {
     pub NotifyAccess: Option<NotifyAccess>,
     #[config(default = false)]
     pub NonBlocking: bool,
+    #[config(deserialize_with = StandardInput::deserialize_with)]
+    #[config(default = "inherit")]
+    pub StandardInput: StandardInput,
     pub Environment: Option<HashMap<String, String>>,
     #[config(deserialize_with = KillMode::deserialize_with)]
     #[config(default = "none")]
@@ -259,11 +345,84 @@ pub(super) struct SectionService {
     pub WorkingDirectory: String,
     #[config(deserialize_with = Vec::<String>::deserialize_with)]
     pub RuntimeDirectory: Option<Vec<String>>,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub RuntimeDirectoryMode: u32,
     #[config(deserialize_with = PreserveMode::deserialize_with)]
     #[config(default = "no")]
     pub RuntimeDirectoryPreserve: PreserveMode,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub StateDirectory: Option<Vec<String>>,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub StateDirectoryMode: u32,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub CacheDirectory: Option<Vec<String>>,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub CacheDirectoryMode: u32,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub LogsDirectory: Option<Vec<String>>,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub LogsDirectoryMode: u32,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub ConfigurationDirectory: Option<Vec<String>>,
+    #[config(deserialize_with = deserialize_directory_mode)]
+    #[config(default = "0755")]
+    pub ConfigurationDirectoryMode: u32,
     #[config(default = "")]
     pub User: String,
+    #[config(default = false)]
+    pub DynamicUser: bool,
+    #[config(deserialize_with = ProtectSystem::deserialize_with)]
+    #[config(default = "no")]
+    pub ProtectSystem: ProtectSystem,
+    #[config(default = false)]
+    pub PrivateTmp: bool,
+    #[config(default = false)]
+    pub PrivateUsers: bool,
+    #[config(default = false)]
+    pub PrivatePIDs: bool,
+    #[config(default = false)]
+    pub PrivateIPC: bool,
+    #[config(default = false)]
+    pub RemoveIPC: bool,
+    #[config(default = false)]
+    pub ProtectKernelTunables: bool,
+    #[config(default = false)]
+    pub ProtectKernelModules: bool,
+    #[config(default = false)]
+    pub ProtectKernelLogs: bool,
+    #[config(default = false)]
+    pub ProtectControlGroups: bool,
+    #[config(default = false)]
+    pub ProtectHostname: bool,
+    #[config(deserialize_with = RestrictNamespaces::deserialize_with)]
+    #[config(default = "no")]
+    pub RestrictNamespaces: RestrictNamespaces,
+    #[config(default = false)]
+    pub LockPersonality: bool,
+    #[config(default = false)]
+    pub MemoryDenyWriteExecute: bool,
+    #[config(deserialize_with = RestrictAddressFamilies::deserialize_with)]
+    #[config(default = "")]
+    pub RestrictAddressFamilies: RestrictAddressFamilies,
+    pub IPAddressAllow: Option<String>,
+    pub IPAddressDeny: Option<String>,
+    #[config(default = false)]
+    pub IPAccounting: bool,
+    pub SELinuxContext: Option<String>,
+    #[config(default = false)]
+    pub SELinuxContextFromNet: bool,
+    pub AppArmorProfile: Option<String>,
+    pub SmackProcessLabel: Option<String>,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub LoadCredential: Option<Vec<String>>,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub LoadCredentialEncrypted: Option<Vec<String>>,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub SetCredential: Option<Vec<String>>,
     #[config(default = "")]
     pub Group: String,
     #[config(default = "0022")]
@@ -273,8 +432,18 @@ pub(super) struct SectionService {
     #[config(deserialize_with = ExitStatusSet::deserialize_with)]
     #[config(default = "")]
     pub RestartPreventExitStatus: ExitStatusSet,
+    #[config(deserialize_with = ExitStatusSet::deserialize_with)]
+    #[config(default = "")]
+    pub RestartForceExitStatus: ExitStatusSet,
+    #[config(deserialize_with = ExitStatusSet::deserialize_with)]
+    #[config(default = "")]
+    pub SuccessExitStatus: ExitStatusSet,
     #[config(default = 100000)]
     pub RestartSec: u64,
+    #[config(default = 0)]
+    pub RestartSteps: u32,
+    #[config(default = 0)]
+    pub RestartMaxDelaySec: u64,
     #[config(deserialize_with = Vec::<String>::deserialize_with)]
     #[config(default = "")]
     pub EnvironmentFile: Vec<String>,
@@ -289,15 +458,95 @@ pub(super) struct SectionService {
     #[config(deserialize_with = deserialize_timeout)]
     #[config(default = 0)]
     pub TimeoutStopSec: u64,
-    #[config(deserialize_with = Rlimit::deserialize_with)]
+    #[config(deserialize_with = Rlimit::deserialize_sec)]
+    pub LimitCPU: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitFSIZE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitDATA: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitSTACK: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
     pub LimitCORE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitRSS: Option<Rlimit>,
     #[config(deserialize_with = Rlimit::deserialize_with)]
     pub LimitNOFILE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitAS: Option<Rlimit>,
     #[config(deserialize_with = Rlimit::deserialize_with)]
     pub LimitNPROC: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitMEMLOCK: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub LimitLOCKS: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub LimitSIGPENDING: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub LimitMSGQUEUE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_nice)]
+    pub LimitNICE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub LimitRTPRIO: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_usec)]
+    pub LimitRTTIME: Option<Rlimit>,
+    #[config(deserialize_with = deserialize_nice)]
+    pub Nice: Option<i32>,
+    #[config(deserialize_with = CpuSchedulingPolicy::deserialize_with)]
+    pub CPUSchedulingPolicy: Option<CpuSchedulingPolicy>,
+    #[config(deserialize_with = deserialize_cpu_sched_priority)]
+    pub CPUSchedulingPriority: Option<i32>,
+    #[config(deserialize_with = CpuSet::deserialize_with)]
+    pub CPUAffinity: Option<CpuSet>,
+    #[config(deserialize_with = NumaPolicy::deserialize_with)]
+    pub NUMAPolicy: Option<NumaPolicy>,
+    #[config(deserialize_with = CpuSet::deserialize_with)]
+    pub NUMAMask: Option<CpuSet>,
+    #[config(deserialize_with = IoSchedulingClass::deserialize_with)]
+    pub IOSchedulingClass: Option<IoSchedulingClass>,
+    #[config(deserialize_with = deserialize_io_sched_priority)]
+    pub IOSchedulingPriority: Option<u32>,
+    #[config(deserialize_with = deserialize_oom_score_adjust)]
+    pub OOMScoreAdjust: Option<i32>,
 }
 
 impl SectionService {
+    pub(super) fn exec_directory(&self, kind: ExecDirectoryType) -> (Option<&Vec<String>>, u32) {
+        match kind {
+            ExecDirectoryType::Runtime => {
+                (self.RuntimeDirectory.as_ref(), self.RuntimeDirectoryMode)
+            }
+            ExecDirectoryType::State => (self.StateDirectory.as_ref(), self.StateDirectoryMode),
+            ExecDirectoryType::Cache => (self.CacheDirectory.as_ref(), self.CacheDirectoryMode),
+            ExecDirectoryType::Logs => (self.LogsDirectory.as_ref(), self.LogsDirectoryMode),
+            ExecDirectoryType::Config => (
+                self.ConfigurationDirectory.as_ref(),
+                self.ConfigurationDirectoryMode,
+            ),
+        }
+    }
+
+    pub(super) fn rlimits(&self) -> Vec<(u8, Option<Rlimit>)> {
+        vec![
+            (libc::RLIMIT_CPU as u8, self.LimitCPU),
+            (libc::RLIMIT_FSIZE as u8, self.LimitFSIZE),
+            (libc::RLIMIT_DATA as u8, self.LimitDATA),
+            (libc::RLIMIT_STACK as u8, self.LimitSTACK),
+            (libc::RLIMIT_CORE as u8, self.LimitCORE),
+            (libc::RLIMIT_RSS as u8, self.LimitRSS),
+            (libc::RLIMIT_NOFILE as u8, self.LimitNOFILE),
+            (libc::RLIMIT_AS as u8, self.LimitAS),
+            (libc::RLIMIT_NPROC as u8, self.LimitNPROC),
+            (libc::RLIMIT_MEMLOCK as u8, self.LimitMEMLOCK),
+            (libc::RLIMIT_LOCKS as u8, self.LimitLOCKS),
+            (libc::RLIMIT_SIGPENDING as u8, self.LimitSIGPENDING),
+            (libc::RLIMIT_MSGQUEUE as u8, self.LimitMSGQUEUE),
+            (libc::RLIMIT_NICE as u8, self.LimitNICE),
+            (libc::RLIMIT_RTPRIO as u8, self.LimitRTPRIO),
+            (libc::RLIMIT_RTTIME as u8, self.LimitRTTIME),
+        ]
+    }
+
     pub(super) fn set_notify_access(&mut self, v: NotifyAccess) {
         self.NotifyAccess = Some(v);
     }
@@ -429,6 +678,10 @@ struct ServiceReMng {
     restarts: u32,
     exit_status: ExitStatus,
     monitor: ServiceMonitor,
+    #[serde(default)]
+    dynamic_uid: Option<u32>,
+    #[serde(default)]
+    socket_fd: Option<i32>,
 }
 
 impl ServiceReMng {
@@ -447,6 +700,8 @@ impl ServiceReMng {
         restarts: u32,
         exit_status: ExitStatus,
         monitor: ServiceMonitor,
+        dynamic_uid: Option<u32>,
+        socket_fd: Option<i32>,
     ) -> ServiceReMng {
         ServiceReMng {
             state,
@@ -462,6 +717,8 @@ impl ServiceReMng {
             restarts,
             exit_status,
             monitor,
+            dynamic_uid,
+            socket_fd,
         }
     }
 }
@@ -512,6 +769,8 @@ impl ServiceRe {
         restarts: u32,
         exit_status: ExitStatus,
         monitor: ServiceMonitor,
+        dynamic_uid: Option<u32>,
+        socket_fd: Option<i32>,
     ) {
         let m_pid = main_pid.map(|x| x.as_raw());
         let c_pid = control_pid.map(|x| x.as_raw());
@@ -529,6 +788,8 @@ impl ServiceRe {
             restarts,
             exit_status,
             monitor,
+            dynamic_uid,
+            socket_fd,
         );
         self.mng.0.insert(unit_id.to_string(), mng);
     }
@@ -555,6 +816,8 @@ impl ServiceRe {
         u32,
         ExitStatus,
         ServiceMonitor,
+        Option<u32>,
+        Option<i32>,
     )> {
         let mng = self.mng.0.get(unit_id);
         mng.map(|m| {
@@ -572,6 +835,8 @@ impl ServiceRe {
                 m.restarts,
                 m.exit_status,
                 m.monitor,
+                m.dynamic_uid,
+                m.socket_fd,
             )
         })
     }
@@ -15,14 +15,14 @@ use crate::mng::RunningData;
 use super::comm::ServiceUnitComm;
 use super::config::ServiceConfig;
 use super::pid::ServicePid;
-use super::rentry::ServiceType;
+use super::rentry::{ServiceType, StandardInput};
 use nix::unistd::Pid;
 use std::cell::RefCell;
 use std::env;
 use std::path::PathBuf;
 use std::rc::Rc;
 use sysmaster::error::*;
-use sysmaster::exec::{ExecCommand, ExecContext, ExecFlags, ExecParameters};
+use sysmaster::exec::{ExecCommand, ExecContext, ExecDirectoryType, ExecFlags, ExecParameters};
 
 pub(super) struct ServiceSpawn {
     comm: Rc<ServiceUnitComm>,
@@ -86,7 +86,17 @@ impl ServiceSpawn {
         self.rd.enable_timer(time_out)?;
 
         if ec_flags.contains(ExecFlags::PASS_FDS) {
-            params.insert_fds(self.collect_socket_fds());
+            if let Some(fd) = self.rd.socket_fd() {
+                params.set_socket_fd(fd);
+            }
+            match self.rd.socket_fd() {
+                // the instance spawned for one connection only gets the connection
+                Some(fd) if service_config.StandardInput == StandardInput::Socket => {
+                    params.set_stdio_fd(fd)
+                }
+                Some(fd) => params.insert_fds(vec![fd]),
+                None => params.insert_fds(self.collect_socket_fds()),
+            }
         }
 
         if self.config.service_type() == ServiceType::Notify || service_config.WatchdogSec > 0 {
@@ -96,14 +106,26 @@ impl ServiceSpawn {
             params.set_notify_sock(notify_sock);
         }
 
-        if let Err(e) = params.add_user(service_config.User.clone()) {
-            log::error!("Failed to add user to execute parameters: {e}");
-            return Err(e);
-        }
+        if service_config.DynamicUser {
+            let name = dynamic_user_name(&service_config.User, unit.id());
+            let uid = match self.rd.dynamic_user(&name) {
+                Ok(v) => v,
+                Err(e) => {
+                    log::error!("Failed to allocate the dynamic user {name}: {e}");
+                    return Err(e);
+                }
+            };
+            params.add_dynamic_user(&name, uid);
+        } else {
+            if let Err(e) = params.add_user(service_config.User.clone()) {
+                log::error!("Failed to add user to execute parameters: {e}");
+                return Err(e);
+            }
 
-        if let Err(e) = params.add_group(service_config.Group.clone()) {
-            log::error!("Failed to add group to execute parameters: {e}");
-            return Err(e);
+            if let Err(e) = params.add_group(service_config.Group.clone()) {
+                log::error!("Failed to add group to execute parameters: {e}");
+                return Err(e);
+            }
         }
 
         if let Err(e) = params.add_umask(service_config.UMask.clone()) {
@@ -116,11 +138,15 @@ impl ServiceSpawn {
             return Err(e);
         }
 
-        if let Some(runtime_directories) = &service_config.RuntimeDirectory {
-            if let Err(e) = params.add_runtime_directory(runtime_directories) {
-                log::error!("Failed to add runtime directories: {e}");
-                return Err(e);
+        for kind in ExecDirectoryType::ALL {
+            let (directories, mode) = service_config.exec_directory(kind);
+            if let Some(directories) = directories {
+                if let Err(e) = params.add_exec_directory(kind, directories) {
+                    log::error!("Failed to add {:?} directories: {e}", kind);
+                    return Err(e);
+                }
             }
+            params.set_exec_directory_mode(kind, mode);
         }
 
         if let Err(e) = params.add_working_directory(service_config.WorkingDirectory.clone()) {
@@ -161,3 +187,17 @@ impl ServiceSpawn {
         self.config.config_data().borrow().Service.WatchdogSec
     }
 }
+
+/// the name of the dynamic user is User if configured, or derived from the unit name
+fn dynamic_user_name(user: &str, unit_id: &str) -> String {
+    if !user.is_empty() {
+        return user.to_string();
+    }
+    let stem = unit_id.strip_suffix(".service").unwrap_or(unit_id);
+    stem.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+            _ => '-',
+        })
+        .collect()
+}
@@ -20,6 +20,7 @@ use super::mng::ServiceMng;
 use super::rentry::{NotifyAccess, ServiceCommand, ServiceType};
 use basic::logger;
 use basic::special::{BASIC_TARGET, SHUTDOWN_TARGET, SYSINIT_TARGET};
+use cgroup::IpPrefix;
 use nix::sys::signal::Signal;
 use nix::sys::socket::UnixCredentials;
 use nix::sys::wait::WaitStatus;
@@ -33,7 +34,10 @@ use sysmaster::unit::{
     SubUnit, UmIf, UnitActiveState, UnitBase, UnitDependencyMask, UnitMngUtil, UnitRelations,
 };
 
-use sysmaster::exec::ExecContext;
+use sysmaster::exec::{
+    exposure_summary, ExecContext, ExecCredential, ExecCredentialType, ExecDirectoryType,
+    ExecHardening, ProtectSystem,
+};
 
 struct ServiceUnit {
     comm: Rc<ServiceUnitComm>,
@@ -142,6 +146,55 @@ impl SubUnit for ServiceUnit {
         todo!()
     }
 
+    fn get_status_extra(&self) -> Vec<(String, String)> {
+        let mut switches = self.exec_ctx.exposure();
+        {
+            let config_data = self.config.config_data();
+            let service = &config_data.borrow().Service;
+            let user = service.User.trim();
+            let non_root =
+                service.DynamicUser || !(user.is_empty() || user == "root" || user == "0");
+            switches.insert(0, ("User", non_root));
+            switches.push(("IPAddressDeny", service.IPAddressDeny.is_some()));
+        }
+        vec![("Exposure:".to_string(), exposure_summary(&switches))]
+    }
+
+    fn clean(&self, what: &[ExecDirectoryType]) -> Result<()> {
+        let config_data = self.config.config_data();
+        let data = config_data.borrow();
+        for kind in what {
+            let directories = match data.Service.exec_directory(*kind).0 {
+                None => continue,
+                Some(v) => v,
+            };
+            for d in directories {
+                let path = kind.path(d)?;
+                // the directories of the dynamic user are linked to the private ones
+                let private = kind.private_path(&path);
+                for path in [private, path] {
+                    if path.symlink_metadata().is_err() {
+                        continue;
+                    }
+                    log::info!("Removing {:?} of {}", path, self.comm.get_owner_id());
+                    if let Err(e) = std::fs::remove_dir_all(&path) {
+                        log::error!("Failed to remove {:?}: {e}", path);
+                        return Err(Error::Io { source: e });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_socket_fd(&self, fd: i32) -> Result<()> {
+        self.mng.set_socket_fd(fd)
+    }
+
+    fn release_socket_fd(&self) {
+        self.mng.release_socket_fd()
+    }
+
     fn current_active_state(&self) -> UnitActiveState {
         self.mng.current_active_state()
     }
@@ -209,6 +262,115 @@ impl ServiceUnit {
         Ok(())
     }
 
+    fn parse_sched_context(&self) {
+        let config_data = self.config.config_data();
+        let data = config_data.borrow();
+        let service = &data.Service;
+        self.exec_ctx.set_nice(service.Nice);
+        self.exec_ctx
+            .set_cpu_sched(service.CPUSchedulingPolicy, service.CPUSchedulingPriority);
+        self.exec_ctx.set_cpu_affinity(service.CPUAffinity.clone());
+        self.exec_ctx
+            .set_numa(service.NUMAPolicy, service.NUMAMask.clone());
+        self.exec_ctx
+            .set_io_sched(service.IOSchedulingClass, service.IOSchedulingPriority);
+        self.exec_ctx.set_oom_score_adjust(service.OOMScoreAdjust);
+    }
+
+    fn parse_sandbox_context(&self) -> Result<()> {
+        let config_data = self.config.config_data();
+        let data = config_data.borrow();
+        let service = &data.Service;
+        // the dynamic user must not leave files behind, so the file system is read-only for it
+        let protect_system = match service.DynamicUser {
+            true => ProtectSystem::Strict,
+            false => service.ProtectSystem,
+        };
+        self.exec_ctx.set_protect_system(protect_system);
+        self.exec_ctx
+            .set_private_tmp(service.PrivateTmp || service.DynamicUser);
+        // the user namespace can't be created inside a chroot
+        if service.PrivateUsers && !service.RootDirectory.is_empty() {
+            return Err(Error::ConfigureError {
+                msg: "PrivateUsers can't be used together with RootDirectory".to_string(),
+            });
+        }
+        self.exec_ctx.set_private_users(service.PrivateUsers);
+        self.exec_ctx.set_private_pids(service.PrivatePIDs);
+        self.exec_ctx.set_private_ipc(service.PrivateIPC);
+
+        let mut hardening = ExecHardening::empty();
+        for (on, flag) in [
+            (
+                service.ProtectKernelTunables,
+                ExecHardening::PROTECT_KERNEL_TUNABLES,
+            ),
+            (
+                service.ProtectKernelModules,
+                ExecHardening::PROTECT_KERNEL_MODULES,
+            ),
+            (
+                service.ProtectKernelLogs,
+                ExecHardening::PROTECT_KERNEL_LOGS,
+            ),
+            (
+                service.ProtectControlGroups,
+                ExecHardening::PROTECT_CONTROL_GROUPS,
+            ),
+            (service.LockPersonality, ExecHardening::LOCK_PERSONALITY),
+            (
+                service.MemoryDenyWriteExecute,
+                ExecHardening::MEMORY_DENY_WRITE_EXECUTE,
+            ),
+            (service.ProtectHostname, ExecHardening::PROTECT_HOSTNAME),
+        ] {
+            hardening.set(flag, on);
+        }
+        self.exec_ctx.set_hardening(hardening);
+        self.exec_ctx
+            .set_restrict_namespaces(service.RestrictNamespaces);
+        self.exec_ctx
+            .set_restrict_address_families(service.RestrictAddressFamilies.clone());
+
+        self.exec_ctx
+            .set_selinux_context(service.SELinuxContext.clone().filter(|s| !s.is_empty()));
+        self.exec_ctx
+            .set_selinux_context_from_net(service.SELinuxContextFromNet);
+        self.exec_ctx
+            .set_apparmor_profile(service.AppArmorProfile.clone().filter(|s| !s.is_empty()));
+        self.exec_ctx
+            .set_smack_process_label(service.SmackProcessLabel.clone().filter(|s| !s.is_empty()));
+
+        let parse_ip_list = |list: &Option<String>| match list {
+            None => Ok(Vec::new()),
+            Some(s) => IpPrefix::parse_list(s).map_err(|e| Error::ConfigureError {
+                msg: format!("invalid IP address list \"{s}\": {e}"),
+            }),
+        };
+        let allow = parse_ip_list(&service.IPAddressAllow)?;
+        let deny = parse_ip_list(&service.IPAddressDeny)?;
+        if let Some(owner) = self.comm.owner() {
+            owner.set_ip_access(allow, deny, service.IPAccounting);
+        }
+
+        // a loaded credential takes precedence over SetCredential with the same id
+        let credentials = [
+            (&service.SetCredential, ExecCredentialType::Set),
+            (&service.LoadCredential, ExecCredentialType::Load),
+            (
+                &service.LoadCredentialEncrypted,
+                ExecCredentialType::LoadEncrypted,
+            ),
+        ];
+        for (values, kind) in credentials {
+            for value in values.iter().flatten() {
+                let (id, credential) = ExecCredential::parse(value, kind)?;
+                self.exec_ctx.insert_credential(id, credential);
+            }
+        }
+        Ok(())
+    }
+
     fn parse(&self) -> Result<()> {
         // if TimeoutSec is set, flush it's value to TimeoutStartSec and TimeoutStopSec
         self.config.flush_timeout();
@@ -228,19 +390,14 @@ impl ServiceUnit {
                 .clone(),
         );
 
-        if let Some(rlimit) = self.config.config_data().borrow().Service.LimitCORE {
-            self.exec_ctx.insert_rlimit(libc::RLIMIT_CORE as u8, rlimit);
-        }
-
-        if let Some(rlimit) = self.config.config_data().borrow().Service.LimitNOFILE {
-            self.exec_ctx
-                .insert_rlimit(libc::RLIMIT_NOFILE as u8, rlimit);
+        for (resource, rlimit) in self.config.config_data().borrow().Service.rlimits() {
+            if let Some(rlimit) = rlimit {
+                self.exec_ctx.insert_rlimit(resource, rlimit);
+            }
         }
 
-        if let Some(rlimit) = self.config.config_data().borrow().Service.LimitNPROC {
-            self.exec_ctx
-                .insert_rlimit(libc::RLIMIT_NPROC as u8, rlimit);
-        }
+        self.parse_sched_context();
+        self.parse_sandbox_context()?;
 
         if let Some(owner) = self.comm.owner() {
             if let Some(sockets) = self.config.sockets() {
@@ -350,6 +507,79 @@ impl ServiceUnit {
         });
         }
 
+        let restart_sec = self.config.config_data().borrow().Service.RestartSec;
+        let restart_max_delay = self
+            .config
+            .config_data()
+            .borrow()
+            .Service
+            .RestartMaxDelaySec;
+        if self.config.config_data().borrow().Service.RestartSteps > 0
+            && restart_max_delay != 0
+            && restart_max_delay < restart_sec
+        {
+            return Err(Error::ConfigureError {
+                msg: "RestartMaxDelaySec is smaller than RestartSec".to_string(),
+            });
+        }
+
+        for kind in ExecDirectoryType::ALL {
+            if let Some(directories) = self
+                .config
+                .config_data()
+                .borrow()
+                .Service
+                .exec_directory(kind)
+                .0
+            {
+                if directories.iter().any(|d| kind.path(d).is_err()) {
+                    return Err(Error::ConfigureError {
+                        msg: format!(
+                            "{:?} directory should be a relative path in its prefix",
+                            kind
+                        ),
+                    });
+                }
+            }
+        }
+
+        let cpu_sched_policy = self
+            .config
+            .config_data()
+            .borrow()
+            .Service
+            .CPUSchedulingPolicy;
+        let cpu_sched_priority = self
+            .config
+            .config_data()
+            .borrow()
+            .Service
+            .CPUSchedulingPriority;
+        if let (Some(policy), Some(priority)) = (cpu_sched_policy, cpu_sched_priority) {
+            if !policy.priority_range().contains(&priority) {
+                return Err(Error::ConfigureError {
+                    msg: format!(
+                        "CPUSchedulingPriority {} is out of the range {:?} of CPUSchedulingPolicy",
+                        priority,
+                        policy.priority_range()
+                    ),
+                });
+            }
+        }
+
+        if let Some(policy) = self.config.config_data().borrow().Service.NUMAPolicy {
+            if policy.need_nodes()
+                && !matches!(
+                    self.config.config_data().borrow().Service.NUMAMask.as_ref(),
+                    Some(mask) if !mask.is_empty()
+                )
+            {
+                return Err(Error::ConfigureError {
+                    msg: "NUMAPolicy bind or interleave is configured without NUMAMask".to_string(),
+                });
+            }
+        }
+
         Ok(())
     }
 
@@ -87,7 +87,9 @@ impl SocketUnitComm {
         control_cmd_type: Option<SocketCommand>,
         control_cmd_len: usize,
         refused: i32,
-        ports: Vec<(PortType, String, RawFd)>,
+        n_accepted: u32,
+        connections: Vec<(String, String)>,
+        ports: Vec<(PortType, String, RawFd, Vec<RawFd>)>,
     ) {
         if let Some(u) = self.owner() {
             self.rentry().mng_insert(
@@ -98,6 +100,8 @@ impl SocketUnitComm {
                 control_cmd_type,
                 control_cmd_len,
                 refused,
+                n_accepted,
+                connections,
                 ports,
             )
         };
@@ -113,7 +117,9 @@ impl SocketUnitComm {
         Option<SocketCommand>,
         usize,
         i32,
-        Vec<(PortType, String, RawFd)>,
+        u32,
+        Vec<(String, String)>,
+        Vec<(PortType, String, RawFd, Vec<RawFd>)>,
     )> {
         self.owner().map(|u| self.rentry().mng_get(u.id()))?
     }
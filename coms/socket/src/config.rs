@@ -14,25 +14,28 @@
 //!
 #![allow(non_snake_case)]
 use super::comm::SocketUnitComm;
-use super::rentry::{PortType, SectionSocket, SocketCommand};
+use super::rentry::{BindIPv6Only, PortType, SectionSocket, SocketCommand};
 use crate::base::NetlinkProtocol;
 use basic::{fd_util, socket_util};
 use confique::Config;
 use nix::errno::Errno;
 use nix::fcntl::{open, OFlag};
+use nix::libc;
 use nix::sys::signal::Signal;
 use nix::sys::socket::sockopt::ReuseAddr;
 use nix::sys::socket::{
     self, AddressFamily, NetlinkAddr, SockFlag, SockProtocol, SockType, SockaddrIn, SockaddrIn6,
-    SockaddrLike, UnixAddr,
+    SockaddrLike, UnixAddr, VsockAddr,
 };
 use nix::sys::stat::{self, fstat};
 use nix::unistd::{Gid, Uid};
 use std::cell::RefCell;
 use std::collections::VecDeque;
+use std::ffi::CString;
 use std::fmt;
 use std::fs;
 use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::prelude::RawFd;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::str::FromStr;
@@ -222,6 +225,12 @@ impl SocketConfig {
         if let Some(v) = config.ListenSpecial.as_ref() {
             self.parse_special(v)?;
         }
+        if let Some(v) = config.ListenMessageQueue.as_ref() {
+            self.parse_mqueue(v)?;
+        }
+        if let Some(v) = config.ListenUSBFunction.as_ref() {
+            self.parse_usb_function(v)?;
+        }
         Ok(())
     }
 
@@ -273,6 +282,29 @@ impl SocketConfig {
         Ok(())
     }
 
+    fn parse_mqueue(&self, listens: &Vec<String>) -> Result<()> {
+        for v in listens {
+            // the name of a message queue is like "/name", no more '/' is allowed
+            if !v.starts_with('/') || v.len() < 2 || v[1..].contains('/') {
+                return Err(format!("Invalid message queue name: {v}").into());
+            }
+            let port = SocketPortConf::new(PortType::MQueue, SocketAddress::empty(), v);
+            self.push_port(Rc::new(port));
+        }
+        Ok(())
+    }
+
+    fn parse_usb_function(&self, listens: &Vec<String>) -> Result<()> {
+        for v in listens {
+            if !v.starts_with('/') {
+                return Err(format!("USB function path must be absolute: {v}").into());
+            }
+            let port = SocketPortConf::new(PortType::UsbFunction, SocketAddress::empty(), v);
+            self.push_port(Rc::new(port));
+        }
+        Ok(())
+    }
+
     fn set_ref(&self, target: String) {
         if let Some(u) = self.comm.owner() {
             self.service
@@ -369,10 +401,10 @@ impl SocketPortConf {
         &self,
         flags: SockFlag,
         backlog: usize,
-        socket_mode: u32,
+        socket: &SectionSocket,
     ) -> Result<i32, Errno> {
         if self.p_type() == PortType::Socket {
-            self.sa.socket_listen(flags, backlog, socket_mode)
+            self.sa.socket_listen(flags, backlog, socket)
         } else {
             Err(Errno::ENOTSUP)
         }
@@ -444,6 +476,93 @@ impl SocketPortConf {
         /* Do noting for ListenSpecial */
     }
 
+    pub(super) fn open_mqueue(
+        &self,
+        socket_mode: u32,
+        maxmsg: Option<i64>,
+        msgsize: Option<i64>,
+    ) -> Result<i32, Errno> {
+        let name = CString::new(self.listen()).map_err(|_| Errno::EINVAL)?;
+        // the attributes take effect only if both are configured
+        let attr = match (maxmsg, msgsize) {
+            (Some(maxmsg), Some(msgsize)) => {
+                let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+                attr.mq_maxmsg = maxmsg as libc::c_long;
+                attr.mq_msgsize = msgsize as libc::c_long;
+                Some(attr)
+            }
+            _ => None,
+        };
+        let attr_ptr = match attr.as_ref() {
+            Some(a) => a as *const libc::mq_attr,
+            None => std::ptr::null(),
+        };
+
+        let old_mask = stat::umask(stat::Mode::from_bits_truncate(!socket_mode & 0o777));
+        let fd = unsafe {
+            libc::mq_open(
+                name.as_ptr(),
+                libc::O_RDONLY | libc::O_CLOEXEC | libc::O_NONBLOCK | libc::O_CREAT,
+                socket_mode as libc::mode_t,
+                attr_ptr,
+            )
+        };
+        stat::umask(old_mask);
+
+        Errno::result(fd)
+    }
+
+    pub(super) fn unlink_mqueue(&self) {
+        let name = match CString::new(self.listen()) {
+            Err(_) => return,
+            Ok(v) => v,
+        };
+        if let Err(e) = Errno::result(unsafe { libc::mq_unlink(name.as_ptr()) }) {
+            log::error!("Failed to unlink message queue {}: {e}", self.listen());
+        }
+    }
+
+    /// open ep0 of the FunctionFS mounted on the directory, write the descriptors and strings
+    /// then open the other endpoints, return (ep0, endpoints)
+    pub(super) fn open_usb_function(
+        &self,
+        descriptors: Option<&String>,
+        strings: Option<&String>,
+    ) -> Result<(i32, Vec<i32>), Errno> {
+        let dir = PathBuf::from(self.listen());
+        let oflag = OFlag::O_RDWR | OFlag::O_CLOEXEC | OFlag::O_NOCTTY | OFlag::O_NONBLOCK;
+        let ep0 = open(&dir.join("ep0"), oflag, stat::Mode::empty())?;
+
+        let mut endpoints = Vec::new();
+        let ret = (|| {
+            for file in [descriptors, strings].into_iter().flatten() {
+                let content = fs::read(file).map_err(|_| Errno::EINVAL)?;
+                nix::unistd::write(ep0, &content)?;
+            }
+
+            let mut names = fs::read_dir(&dir)
+                .map_err(|_| Errno::EINVAL)?
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|n| n.starts_with("ep") && n != "ep0")
+                .collect::<Vec<_>>();
+            names.sort();
+            for name in names {
+                endpoints.push(open(&dir.join(name), oflag, stat::Mode::empty())?);
+            }
+            Ok(())
+        })();
+
+        if let Err(e) = ret {
+            for fd in endpoints {
+                fd_util::close(fd);
+            }
+            fd_util::close(ep0);
+            return Err(e);
+        }
+        Ok((ep0, endpoints))
+    }
+
     pub(super) fn can_be_symlinked(&self) -> bool {
         if ![PortType::Socket, PortType::Fifo].contains(&self.p_type()) {
             return false;
@@ -454,7 +573,15 @@ impl SocketPortConf {
         true
     }
 
-    pub(super) fn chown(&self, uid: Uid, gid: Gid) -> Result<()> {
+    pub(super) fn chown(&self, fd: RawFd, uid: Uid, gid: Gid) -> Result<()> {
+        // message queues have no path in the file system
+        if self.p_type == PortType::MQueue {
+            if fd >= 0 {
+                nix::unistd::fchown(fd, Some(uid), Some(gid))?;
+            }
+            return Ok(());
+        }
+
         let path = if self.p_type == PortType::Fifo {
             PathBuf::from(&self.listen)
         } else if let Some(path) = self.sa.path() {
@@ -518,12 +645,50 @@ impl SocketAddress {
         self.sock_addr.family().unwrap()
     }
 
+    // the options which must be set before bind, failures are not fatal
+    fn apply_bind_opts(&self, fd: RawFd, socket: &SectionSocket) {
+        let family = self.family();
+        let is_inet = matches!(family, AddressFamily::Inet | AddressFamily::Inet6);
+
+        if family == AddressFamily::Inet6 && socket.BindIPv6Only != BindIPv6Only::Default {
+            let only = socket.BindIPv6Only == BindIPv6Only::Ipv6Only;
+            if let Err(e) = socket_util::set_ipv6_only(fd, only) {
+                log::warn!("set socket ipv6 only errno: {}", e);
+            }
+        }
+
+        if let Some(device) = socket.BindToDevice.as_ref() {
+            if let Err(e) = socket_util::set_bind_to_device(fd, device) {
+                log::warn!("set socket bind to device {} errno: {}", device, e);
+            }
+        }
+
+        if socket.FreeBind && is_inet {
+            if let Err(e) = socket_util::set_free_bind(fd, family, true) {
+                log::warn!("set socket free bind errno: {}", e);
+            }
+        }
+
+        if socket.Transparent && is_inet {
+            if let Err(e) = socket_util::set_transparent(fd, family, true) {
+                log::warn!("set socket transparent errno: {}", e);
+            }
+        }
+
+        if socket.ReusePort {
+            if let Err(e) = socket_util::set_reuse_port(fd, true) {
+                log::warn!("set socket reuse port errno: {}", e);
+            }
+        }
+    }
+
     pub(super) fn socket_listen(
         &self,
         flags: SockFlag,
         backlog: usize,
-        socket_mode: u32,
+        socket: &SectionSocket,
     ) -> Result<i32, Errno> {
+        let socket_mode = socket.SocketMode;
         log::debug!(
             "create socket, family: {:?}, type: {:?}, protocol: {:?}",
             self.sock_addr.family().unwrap(),
@@ -538,6 +703,7 @@ impl SocketAddress {
         )?;
 
         socket::setsockopt(fd, ReuseAddr, &true)?;
+        self.apply_bind_opts(fd, socket);
 
         if let Some(path) = self.path() {
             let parent_path = path.as_path().parent();
@@ -619,6 +785,29 @@ fn parse_netlink_address(item: &str, socket_type: SockType) -> Result<SocketAddr
     ))
 }
 
+// "CID:PORT", an empty CID means VMADDR_CID_ANY
+fn parse_vsock_address(item: &str, socket_type: SockType) -> Result<SocketAddress> {
+    let (cid, port) = match item.split_once(':') {
+        None => return Err(format!("vsock address must be CID:PORT: {item}").into()),
+        Some(v) => v,
+    };
+    let cid = if cid.is_empty() {
+        libc::VMADDR_CID_ANY
+    } else {
+        cid.parse::<u32>()
+            .map_err(|_| format!("invalid vsock cid: {cid}"))?
+    };
+    let port = port
+        .parse::<u32>()
+        .map_err(|_| format!("invalid vsock port: {port}"))?;
+
+    Ok(SocketAddress::new(
+        Box::new(VsockAddr::new(cid, port)),
+        socket_type,
+        None,
+    ))
+}
+
 fn parse_socket_address(item: &str, socket_type: SockType) -> Result<SocketAddress> {
     if item.starts_with('/') {
         let unix_addr = UnixAddr::new(&PathBuf::from(item)).context(NixSnafu)?;
@@ -632,6 +821,10 @@ fn parse_socket_address(item: &str, socket_type: SockType) -> Result<SocketAddre
         return Ok(SocketAddress::new(Box::new(unix_addr), socket_type, None));
     }
 
+    if let Some(vsock) = item.strip_prefix("vsock:") {
+        return parse_vsock_address(vsock, socket_type);
+    }
+
     if let Ok(port) = item.parse::<u16>() {
         if port == 0 {
             return Err("invalid port number".to_string().into());
@@ -665,9 +858,11 @@ fn parse_socket_address(item: &str, socket_type: SockType) -> Result<SocketAddre
 
 #[cfg(test)]
 mod tests {
+    use super::parse_socket_address;
     use crate::comm::SocketUnitComm;
     use crate::config::SocketConfig;
     use libtests::get_project_root;
+    use nix::sys::socket::{AddressFamily, SockType};
     use std::rc::Rc;
 
     #[test]
@@ -682,4 +877,17 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_socket_address_parse() {
+        let addr = parse_socket_address("@test-abstract", SockType::Stream).unwrap();
+        assert_eq!(addr.family(), AddressFamily::Unix);
+        assert!(addr.path().is_none());
+
+        let addr = parse_socket_address("vsock:2:1234", SockType::Stream).unwrap();
+        assert_eq!(addr.family(), AddressFamily::Vsock);
+        assert!(parse_socket_address("vsock::1234", SockType::Stream).is_ok());
+        assert!(parse_socket_address("vsock:1234", SockType::Stream).is_err());
+        assert!(parse_socket_address("vsock:x:1", SockType::Stream).is_err());
+    }
 }
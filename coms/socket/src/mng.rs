@@ -21,25 +21,36 @@ use super::{
     spawn::SocketSpawn,
 };
 use basic::{
+    fd_util,
+    ratelimit::RateLimit,
+    time_util::USEC_PER_SEC,
+    unit_name::unit_name_build,
     user_group_util::{get_group_creds, get_user_creds},
     IN_SET,
 };
 use event::EventState;
 use event::{EventType, Events, Source};
+use nix::errno::Errno;
+use nix::sys::socket::{self, sockopt, AddressFamily, SockaddrLike, SockaddrStorage};
 use nix::sys::wait::WaitStatus;
 use nix::unistd::{Gid, Uid};
 use nix::{
     libc::{self},
     unistd::unlink,
 };
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
 use std::os::unix::prelude::RawFd;
+use std::path::Path;
 use std::rc::{Rc, Weak};
 use std::{cell::RefCell, collections::VecDeque};
 use sysmaster::error::*;
 use sysmaster::exec::{ExecCommand, ExecContext};
 use sysmaster::rel::ReliLastFrame;
 use sysmaster::rel::{ReStation, Reliability};
-use sysmaster::unit::{KillOperation, UnitActiveState, UnitNotifyFlags, UnitType};
+use sysmaster::unit::{
+    KillOperation, UnitActiveState, UnitDependencyMask, UnitNotifyFlags, UnitRelations, UnitType,
+};
 
 impl SocketState {
     pub(super) fn to_unit_active_state(self) -> UnitActiveState {
@@ -80,14 +91,25 @@ impl ReStation for SocketMng {
 
     // data
     fn db_map(&self, _reload: bool) {
-        if let Some((state, result, c_pid, control_cmd_type, control_cmd_len, refused, rports)) =
-            self.comm.rentry_mng_get()
+        if let Some((
+            state,
+            result,
+            c_pid,
+            control_cmd_type,
+            control_cmd_len,
+            refused,
+            n_accepted,
+            connections,
+            rports,
+        )) = self.comm.rentry_mng_get()
         {
             *self.state.borrow_mut() = state;
             *self.result.borrow_mut() = result;
             self.pid.update_control(c_pid);
             self.control_command_update(control_cmd_type, control_cmd_len);
             *self.refused.borrow_mut() = refused;
+            *self.n_accepted.borrow_mut() = n_accepted;
+            *self.connections.borrow_mut() = connections.into_iter().collect();
             self.map_ports_fd(rports);
         }
     }
@@ -100,9 +122,15 @@ impl ReStation for SocketMng {
             *self.control_cmd_type.borrow(),
             self.control_command.borrow().len(),
             *self.refused.borrow(),
+            *self.n_accepted.borrow(),
+            self.connections
+                .borrow()
+                .iter()
+                .map(|(i, s)| (i.clone(), s.clone()))
+                .collect::<_>(),
             self.ports()
                 .iter()
-                .map(|p| (p.p_type(), String::from(p.listen()), p.fd()))
+                .map(|p| (p.p_type(), String::from(p.listen()), p.fd(), p.aux_fds()))
                 .collect::<_>(),
         );
     }
@@ -133,6 +161,13 @@ pub(crate) struct SocketMng {
     control_cmd_type: RefCell<Option<SocketCommand>>,
     control_command: RefCell<VecDeque<ExecCommand>>,
     refused: RefCell<i32>,
+    n_accepted: RefCell<u32>,
+    // the running instances spawned for each connection, instance name => source of the connection
+    connections: RefCell<HashMap<String, String>>,
+    trigger_limit: RefCell<RateLimit>,
+    poll_limit: RefCell<RateLimit>,
+    // armed while the polling is paused by PollLimit*
+    poll_timer: RefCell<Option<Rc<SocketPollTimer>>>,
 }
 
 // the declaration "pub(self)" is for identification only.
@@ -154,6 +189,11 @@ impl SocketMng {
             control_cmd_type: RefCell::new(None),
             control_command: RefCell::new(VecDeque::new()),
             refused: RefCell::new(0),
+            n_accepted: RefCell::new(0),
+            connections: RefCell::new(HashMap::new()),
+            trigger_limit: RefCell::new(RateLimit::new(0, 0)),
+            poll_limit: RefCell::new(RateLimit::new(0, 0)),
+            poll_timer: RefCell::new(None),
         }
     }
 
@@ -197,10 +237,29 @@ impl SocketMng {
     pub(crate) fn start_action(&self) {
         /* make sure the former failure doesn't disturb later action. */
         self.set_result(SocketResult::Success);
+        self.init_ratelimit();
         self.enter_start_pre();
         self.db_update();
     }
 
+    fn init_ratelimit(&self) {
+        let config = self.config.config_data();
+        let socket = &config.borrow().Socket;
+        // accepting sockets trigger once for each connection, so allow more by default
+        let trigger_burst =
+            socket
+                .TriggerLimitBurst
+                .unwrap_or(if socket.Accept { 200 } else { 20 });
+        *self.trigger_limit.borrow_mut() = RateLimit::new(
+            socket.TriggerLimitIntervalSec.saturating_mul(USEC_PER_SEC),
+            trigger_burst,
+        );
+        *self.poll_limit.borrow_mut() = RateLimit::new(
+            socket.PollLimitIntervalSec.saturating_mul(USEC_PER_SEC),
+            socket.PollLimitBurst,
+        );
+    }
+
     pub(crate) fn stop_action(&self) {
         self.enter_stop_pre(SocketResult::Success);
         self.db_update();
@@ -236,6 +295,42 @@ impl SocketMng {
         self.state().to_unit_active_state()
     }
 
+    pub(crate) fn status_extra(&self) -> Vec<(String, String)> {
+        if !self.config.config_data().borrow().Socket.Accept {
+            return Vec::new();
+        }
+
+        vec![(
+            "Accepted:".to_string(),
+            format!(
+                "{}; Connected: {}; Refused: {}",
+                self.n_accepted.borrow(),
+                self.connections.borrow().len(),
+                self.refused.borrow()
+            ),
+        )]
+    }
+
+    /// release the connection when the instance spawned for it is finished
+    pub(crate) fn trigger_notify(&self, other: &str) {
+        if !self.connections.borrow().contains_key(other) {
+            return;
+        }
+
+        if !self
+            .comm
+            .um()
+            .current_active_state(other)
+            .is_inactive_or_failed()
+        {
+            return;
+        }
+
+        log::debug!("connection instance {} finished", other);
+        self.connections.borrow_mut().remove(other);
+        self.db_update();
+    }
+
     #[allow(dead_code)]
     pub(self) fn clear_ports(&self) {
         self.ports.borrow_mut().clear();
@@ -244,9 +339,7 @@ impl SocketMng {
     pub(crate) fn collect_fds(&self) -> Vec<i32> {
         let mut fds = Vec::new();
         for port in self.ports().iter() {
-            if port.fd() >= 0 {
-                fds.push(port.fd());
-            }
+            fds.extend(port.fds());
         }
 
         fds
@@ -356,6 +449,7 @@ impl SocketMng {
             if self.comm.um().has_stop_job(u.id()) {
                 if fd >= 0 {
                     *self.refused.borrow_mut() += 1;
+                    fd_util::close(fd);
                     return;
                 }
                 self.flush_ports();
@@ -380,10 +474,147 @@ impl SocketMng {
                 }
                 self.set_state(SocketState::Running);
             } else {
-                // template support
-                todo!()
+                self.enter_running_instance(u.id(), fd);
+            }
+        }
+    }
+
+    /// spawn one service instance for the accepted connection, the instance owns the fd if it is spawned
+    fn enter_running_instance(&self, owner: &str, fd: RawFd) {
+        let (max, max_per_source) = {
+            let config = self.config.config_data();
+            let socket = &config.borrow().Socket;
+            (socket.MaxConnections, socket.MaxConnectionsPerSource)
+        };
+
+        if self.connections.borrow().len() >= max as usize {
+            log::warn!(
+                "{}: too many incoming connections ({}), dropping connection.",
+                owner,
+                self.connections.borrow().len()
+            );
+            self.refuse(fd);
+            return;
+        }
+
+        let (instance, source) = match instance_from_socket(fd, *self.n_accepted.borrow()) {
+            Ok(v) => v,
+            Err(e) => {
+                log::warn!("{}: failed to get the peer of the connection: {}", owner, e);
+                self.refuse(fd);
+                return;
+            }
+        };
+
+        let per_source = self
+            .connections
+            .borrow()
+            .values()
+            .filter(|s| **s == source)
+            .count();
+        if max_per_source > 0 && per_source >= max_per_source as usize {
+            log::warn!(
+                "{}: too many incoming connections ({}) from source {}, dropping connection.",
+                owner,
+                per_source,
+                source
+            );
+            self.refuse(fd);
+            return;
+        }
+
+        let prefix = Path::new(owner).file_stem().unwrap().to_str().unwrap();
+        let service = unit_name_build(prefix, &instance, "service");
+        *self.n_accepted.borrow_mut() += 1;
+
+        let um = self.comm.um();
+        if !um.load_unit_success(&service) {
+            log::error!("{}: failed to load instance {}", owner, service);
+            fd_util::close(fd);
+            self.enter_stop_pre(SocketResult::FailureResources);
+            return;
+        }
+
+        if let Err(e) = um.unit_add_two_dependency(
+            owner,
+            UnitRelations::UnitBefore,
+            UnitRelations::UnitTriggers,
+            &service,
+            true,
+            UnitDependencyMask::Implicit,
+        ) {
+            log::error!("{}: failed to add dependency on {}: {}", owner, service, e);
+            fd_util::close(fd);
+            self.enter_stop_pre(SocketResult::FailureResources);
+            return;
+        }
+
+        if let Err(e) = um.unit_set_socket_fd(&service, fd) {
+            log::error!("{}: failed to pass connection to {}: {}", owner, service, e);
+            fd_util::close(fd);
+            self.enter_stop_pre(SocketResult::FailureResources);
+            return;
+        }
+
+        self.connections
+            .borrow_mut()
+            .insert(service.clone(), source);
+
+        self.rentry().set_last_frame(SocketReFrame::FdListen(false)); // protect 'start_unit'
+        let ret = um.unit_start_by_job(&service);
+        self.rentry().set_last_frame(SocketReFrame::FdListen(true));
+        if let Err(e) = ret {
+            log::error!("{}: failed to start instance {}: {}", owner, service, e);
+            // the instance is not running, give back its connection slot and fd
+            self.connections.borrow_mut().remove(&service);
+            um.unit_release_socket_fd(&service);
+            self.enter_stop_pre(SocketResult::FailureResources);
+        }
+    }
+
+    /// stop polling the fds until the current interval of PollLimit* ends
+    fn pause_polling(&self, mng: Weak<SocketMng>) {
+        let usec = self.poll_limit.borrow().ratelimit_left();
+        log::warn!(
+            "{}: poll limit hit, pausing polling for {}us",
+            self.comm
+                .owner()
+                .map_or(String::new(), |u| u.id().to_string()),
+            usec
+        );
+
+        self.unwatch_fds();
+        let timer = Rc::new(SocketPollTimer::new(mng, usec));
+        let events = self.comm.um().events();
+        if let Err(e) = events.add_source(timer.clone()) {
+            log::error!("Failed to add the poll limit timer: {e}");
+            self.watch_fds();
+            return;
+        }
+        if let Err(e) = events.set_enabled(timer.clone(), EventState::OneShot) {
+            // the timer would never resume the polling, keep polling instead
+            log::error!("Failed to enable the poll limit timer: {e}");
+            let _ = events.del_source(timer);
+            self.watch_fds();
+            return;
+        }
+        *self.poll_timer.borrow_mut() = Some(timer);
+    }
+
+    fn resume_polling(&self) {
+        if let Some(timer) = self.poll_timer.take() {
+            if let Err(e) = self.comm.um().events().del_source(timer) {
+                log::warn!("Failed to remove the poll limit timer: {e}");
             }
         }
+        if self.state() == SocketState::Listening {
+            self.watch_fds();
+        }
+    }
+
+    fn refuse(&self, fd: RawFd) {
+        *self.refused.borrow_mut() += 1;
+        fd_util::close(fd);
     }
 
     fn enter_stop_pre(&self, res: SocketResult) {
@@ -541,9 +772,19 @@ impl SocketMng {
     fn close_fds(&self) {
         // event
         let events = self.comm.um().events();
+        if let Some(timer) = self.poll_timer.take() {
+            if let Err(e) = events.del_source(timer) {
+                log::warn!("Failed to remove the poll limit timer: {e}");
+            }
+        }
         for mport in self.mports().iter() {
             let source = Rc::clone(mport);
-            events.del_source(source).unwrap();
+            if let Err(e) = events.del_source(source) {
+                log::warn!(
+                    "Failed to remove the event source of fd {}: {e}",
+                    mport.fd()
+                );
+            }
         }
 
         for port in self.ports().iter() {
@@ -569,6 +810,11 @@ impl SocketMng {
     }
 
     fn watch_fds(&self) {
+        if self.poll_timer.borrow().is_some() {
+            // the polling is paused, the timer watches the fds again when it expires
+            return;
+        }
+
         let events = self.comm.um().events();
         for mport in self.mports().iter() {
             if mport.fd() < 0 {
@@ -689,12 +935,20 @@ impl SocketMng {
         *self.result.borrow_mut() = res;
     }
 
-    fn map_ports_fd(&self, rports: Vec<(PortType, String, RawFd)>) {
+    fn map_ports_fd(&self, rports: Vec<(PortType, String, RawFd, Vec<RawFd>)>) {
         assert_eq!(rports.len(), self.ports().len());
 
-        for (p_type, listen, fd) in rports.iter() {
+        for (p_type, listen, fd, aux_fds) in rports.iter() {
             match self.ports_find(*p_type, listen) {
-                Some(port) => port.set_fd(self.comm.reli().fd_take(*fd)),
+                Some(port) => {
+                    port.set_fd(self.comm.reli().fd_take(*fd));
+                    port.set_aux_fds(
+                        aux_fds
+                            .iter()
+                            .map(|fd| self.comm.reli().fd_take(*fd))
+                            .collect(),
+                    );
+                }
                 None => log::debug!("Not find {:?}:{:?}", *p_type, listen),
             }
         }
@@ -821,6 +1075,50 @@ impl SocketMng {
     }
 }
 
+fn format_inet(addr: &SockaddrStorage) -> Option<(String, u16)> {
+    if let Some(v4) = addr.as_sockaddr_in() {
+        return Some((Ipv4Addr::from(v4.ip()).to_string(), v4.port()));
+    }
+    if let Some(v6) = addr.as_sockaddr_in6() {
+        let ip = v6.ip();
+        let ip = match ip.to_ipv4_mapped() {
+            Some(v4) => v4.to_string(),
+            None => ip.to_string(),
+        };
+        return Some((ip, v6.port()));
+    }
+    None
+}
+
+/// build the instance name and the source of an accepted connection
+/// inet: "<n>-<local ip>:<local port>-<remote ip>:<remote port>", the source is the remote ip
+/// unix: "<n>-<peer pid>-<peer uid>", the source is the peer uid
+fn instance_from_socket(fd: RawFd, n: u32) -> Result<(String, String)> {
+    let local = socket::getsockname::<SockaddrStorage>(fd).context(NixSnafu)?;
+    match local.family() {
+        Some(AddressFamily::Inet) | Some(AddressFamily::Inet6) => {
+            let remote = socket::getpeername::<SockaddrStorage>(fd).context(NixSnafu)?;
+            let (l_ip, l_port) = format_inet(&local).ok_or(Error::Nix {
+                source: Errno::EAFNOSUPPORT,
+            })?;
+            let (r_ip, r_port) = format_inet(&remote).ok_or(Error::Nix {
+                source: Errno::EAFNOSUPPORT,
+            })?;
+            Ok((format!("{n}-{l_ip}:{l_port}-{r_ip}:{r_port}"), r_ip))
+        }
+        Some(AddressFamily::Unix) => {
+            let cred = socket::getsockopt(fd, sockopt::PeerCredentials).context(NixSnafu)?;
+            Ok((
+                format!("{n}-{}-{}", cred.pid(), cred.uid()),
+                cred.uid().to_string(),
+            ))
+        }
+        _ => Err(Error::Nix {
+            source: Errno::EAFNOSUPPORT,
+        }),
+    }
+}
+
 pub(crate) struct SocketMngPort {
     // associated objects
     mng: Weak<SocketMng>,
@@ -881,17 +1179,36 @@ impl SocketMngPort {
     }
 
     fn dispatch_io(&self) -> Result<i32> {
-        let afd: i32 = -1;
+        let mut afd: i32 = -1;
+
+        if !self.mng().poll_limit.borrow_mut().ratelimit_below() {
+            self.mng().pause_polling(self.mng.clone());
+            return Ok(0);
+        }
 
         if self.mng().state() != SocketState::Listening {
             return Ok(0);
         }
 
+        if !self.mng().trigger_limit.borrow_mut().ratelimit_below() {
+            log::warn!(
+                "{}: trigger limit hit, refusing further activation.",
+                self.mng()
+                    .comm
+                    .owner()
+                    .map_or(String::new(), |u| u.id().to_string())
+            );
+            self.mng()
+                .enter_stop_pre(SocketResult::FailureTriggerLimitHit);
+            self.mng().db_update();
+            return Ok(0);
+        }
+
         if self.mng().config.config_data().borrow().Socket.Accept
             && self.port.p_type() == PortType::Socket
             && self.port.can_accept()
         {
-            let afd = self.port.accept().map_err(|_e| Error::Other {
+            afd = self.port.accept().map_err(|_e| Error::Other {
                 msg: "accept err".to_string(),
             })?;
 
@@ -917,10 +1234,264 @@ impl SocketMngPort {
     }
 }
 
+/// the timer to resume polling after the poll limit is hit
+pub(crate) struct SocketPollTimer {
+    mng: Weak<SocketMng>,
+    time: u64,
+}
+
+impl SocketPollTimer {
+    fn new(mng: Weak<SocketMng>, usec: u64) -> SocketPollTimer {
+        SocketPollTimer { mng, time: usec }
+    }
+}
+
+impl Source for SocketPollTimer {
+    fn fd(&self) -> RawFd {
+        0
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::TimerMonotonic
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn time_relative(&self) -> u64 {
+        self.time
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        if let Some(mng) = self.mng.upgrade() {
+            mng.resume_polling();
+        }
+        0
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SocketState;
-    use sysmaster::unit::UnitActiveState;
+    use super::{instance_from_socket, SocketMng, SocketMngPort, SocketResult, SocketState};
+    use crate::config::{SocketAddress, SocketPortConf};
+    use crate::port::SocketPort;
+    use crate::rentry::PortType;
+    use crate::{comm::SocketUnitComm, config::SocketConfig};
+    use event::{Events, Source};
+    use nix::sys::socket::{socketpair, AddressFamily, SockFlag, SockType, SockaddrIn};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream};
+    use std::os::unix::prelude::{AsRawFd, RawFd};
+    use std::rc::Rc;
+    use sysmaster::error::*;
+    use sysmaster::exec::ExecContext;
+    use sysmaster::rel::Reliability;
+    use sysmaster::unit::{UmIf, UnitActiveState, UnitDependencyMask, UnitRelations};
+
+    thread_local! {
+        static EVENTS: Rc<Events> = Rc::new(Events::new().unwrap());
+        // the connection fds passed to the instances, instance name => fd
+        static INSTANCE_FDS: RefCell<HashMap<String, RawFd>> = RefCell::new(HashMap::new());
+    }
+
+    // the UmIf is shared by all the socket units, so the state is kept per test thread
+    struct UmIfD;
+    impl UmIf for UmIfD {
+        fn load_unit_success(&self, _name: &str) -> bool {
+            true
+        }
+
+        fn unit_add_two_dependency(
+            &self,
+            _unit_name: &str,
+            _ra: UnitRelations,
+            _rb: UnitRelations,
+            _target_name: &str,
+            _add_ref: bool,
+            _mask: UnitDependencyMask,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        fn unit_set_socket_fd(&self, name: &str, fd: i32) -> Result<()> {
+            INSTANCE_FDS.with(|fds| fds.borrow_mut().insert(name.to_string(), fd));
+            Ok(())
+        }
+
+        fn unit_release_socket_fd(&self, name: &str) {
+            if let Some(fd) = INSTANCE_FDS.with(|fds| fds.borrow_mut().remove(name)) {
+                basic::fd_util::close(fd);
+            }
+        }
+
+        fn unit_start_by_job(&self, _name: &str) -> Result<()> {
+            Err(Error::UnitActionENoent)
+        }
+
+        fn events(&self) -> Rc<Events> {
+            EVENTS.with(Rc::clone)
+        }
+    }
+
+    fn create_mng() -> (Rc<SocketMng>, Rc<SocketConfig>, Rc<Reliability>) {
+        let reli = Rc::new(Reliability::new(26));
+        let comm = Rc::new(SocketUnitComm::new());
+        comm.attach_um(Rc::new(UmIfD));
+        comm.attach_reli(Rc::clone(&reli));
+
+        let config = Rc::new(SocketConfig::new(&comm));
+        let mng = Rc::new(SocketMng::new(&comm, &config, &Rc::new(ExecContext::new())));
+        (mng, config, reli)
+    }
+
+    /// a datagram socket on the loopback, the socket unit is listening on it
+    fn listen_datagram(mng: &Rc<SocketMng>, config: &Rc<SocketConfig>) -> Rc<SocketMngPort> {
+        let sock_addr = SockaddrIn::from(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0));
+        let socket_addr = SocketAddress::new(Box::new(sock_addr), SockType::Datagram, None);
+        let p_conf = Rc::new(SocketPortConf::new(
+            PortType::Socket,
+            socket_addr,
+            "127.0.0.1:0",
+        ));
+        let port = Rc::new(SocketPort::new(&mng.comm, config, &p_conf));
+        assert!(port.open_port(false).is_ok());
+
+        let mport = Rc::new(SocketMngPort::new(mng, port));
+        mng.push_port(Rc::clone(&mport));
+        mng.init_ratelimit();
+        mng.enter_listening();
+        assert_eq!(mng.state(), SocketState::Listening);
+        mport
+    }
+
+    #[test]
+    fn test_instance_start_failure() {
+        let (mng, config, _reli) = create_mng();
+        config.config_data().borrow_mut().Socket.MaxConnections = 1;
+        let (fd0, fd1) = socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::SOCK_CLOEXEC | SockFlag::SOCK_NONBLOCK,
+        )
+        .unwrap();
+
+        // the instance fails to start, its connection does not hold a slot any more
+        mng.enter_running_instance("test.socket", fd0);
+        assert!(mng.connections.borrow().is_empty());
+        assert!(INSTANCE_FDS.with(|fds| fds.borrow().is_empty()));
+        assert_eq!(mng.result(), SocketResult::FailureResources);
+
+        // and the connection is closed
+        let mut buf = [0u8; 1];
+        assert_eq!(nix::unistd::read(fd1, &mut buf), Ok(0));
+        basic::fd_util::close(fd1);
+
+        // MaxConnections=1 does not refuse the next connection
+        let (fd0, fd1) = socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::SOCK_CLOEXEC,
+        )
+        .unwrap();
+        mng.enter_running_instance("test.socket", fd0);
+        assert_eq!(*mng.refused.borrow(), 0);
+        assert!(mng.connections.borrow().is_empty());
+        basic::fd_util::close(fd1);
+    }
+
+    #[test]
+    fn test_instance_from_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local = listener.local_addr().unwrap();
+        let client = TcpStream::connect(local).unwrap();
+        let remote = client.local_addr().unwrap();
+        let (conn, _) = listener.accept().unwrap();
+        let (instance, source) = instance_from_socket(conn.as_raw_fd(), 3).unwrap();
+        assert_eq!(
+            instance,
+            format!("3-127.0.0.1:{}-127.0.0.1:{}", local.port(), remote.port())
+        );
+        assert_eq!(source, "127.0.0.1");
+
+        let (fd0, fd1) = socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::SOCK_CLOEXEC,
+        )
+        .unwrap();
+        let (instance, source) = instance_from_socket(fd0, 0).unwrap();
+        let uid = nix::unistd::getuid();
+        assert_eq!(instance, format!("0-{}-{}", nix::unistd::getpid(), uid));
+        assert_eq!(source, uid.to_string());
+        basic::fd_util::close(fd0);
+        basic::fd_util::close(fd1);
+    }
+    #[test]
+    fn test_trigger_limit() {
+        let (mng, config, _reli) = create_mng();
+        {
+            let data = config.config_data();
+            let mut data = data.borrow_mut();
+            data.Socket.TriggerLimitIntervalSec = 60;
+            data.Socket.TriggerLimitBurst = Some(2);
+        }
+        let mport = listen_datagram(&mng, &config);
+
+        for _ in 0..2 {
+            assert!(mport.dispatch_io().is_ok());
+            assert_eq!(mng.state(), SocketState::Listening);
+        }
+
+        // the third trigger in the interval fails the socket
+        assert!(mport.dispatch_io().is_ok());
+        assert_eq!(mng.result(), SocketResult::FailureTriggerLimitHit);
+        assert_eq!(mng.state(), SocketState::Failed);
+    }
+
+    #[test]
+    fn test_poll_limit() {
+        let (mng, config, _reli) = create_mng();
+        {
+            let data = config.config_data();
+            let mut data = data.borrow_mut();
+            data.Socket.PollLimitIntervalSec = 60;
+            data.Socket.PollLimitBurst = 2;
+        }
+        let mport = listen_datagram(&mng, &config);
+
+        for _ in 0..2 {
+            assert!(mport.dispatch_io().is_ok());
+            assert!(mng.poll_timer.borrow().is_none());
+        }
+
+        // the third wakeup in the interval pauses the polling, the socket keeps listening
+        assert!(mport.dispatch_io().is_ok());
+        let timer = mng.poll_timer.borrow().clone().unwrap();
+        assert!(timer.time_relative() > 0);
+        assert_eq!(mng.state(), SocketState::Listening);
+        assert_eq!(mng.result(), SocketResult::Success);
+
+        // the polling is resumed when the timer expires
+        let events = EVENTS.with(Rc::clone);
+        assert_eq!(timer.dispatch(&events), 0);
+        assert!(mng.poll_timer.borrow().is_none());
+        assert_eq!(mng.state(), SocketState::Listening);
+    }
+
     #[test]
     fn test_socket_active_state() {
         assert_eq!(
@@ -18,10 +18,11 @@ use crate::{
     config::{SocketConfig, SocketPortConf},
     rentry::PortType,
 };
-use basic::{fd_util, fs_util, io_util, socket_util};
+use basic::{fd_util, fs_util, io_util, socket_util, IN_SET};
 use nix::unistd::{Gid, Uid};
 use nix::{
     errno::Errno,
+    libc,
     poll::PollFlags,
     sys::socket::{
         self,
@@ -43,6 +44,8 @@ pub(crate) struct SocketPort {
 
     // owned objects
     fd: RefCell<RawFd>,
+    // the endpoints of ListenUSBFunction, passed to the service together with ep0
+    aux_fds: RefCell<Vec<RawFd>>,
 }
 
 impl SocketPort {
@@ -57,6 +60,7 @@ impl SocketPort {
             p_conf: Rc::clone(p_confr),
 
             fd: RefCell::new(SOCKET_INVALID_FD),
+            aux_fds: RefCell::new(Vec::new()),
         }
     }
 
@@ -64,6 +68,10 @@ impl SocketPort {
         *self.fd.borrow_mut() = fd;
     }
 
+    pub(super) fn set_aux_fds(&self, fds: Vec<RawFd>) {
+        *self.aux_fds.borrow_mut() = fds;
+    }
+
     pub(super) fn accept(&self) -> Result<i32> {
         socket::accept4(self.fd(), SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC)
             .context(NixSnafu)
@@ -77,12 +85,14 @@ impl SocketPort {
             return Ok(());
         }
 
-        let socket_mode = self.config.config_data().borrow().Socket.SocketMode;
+        let config = self.config.config_data();
+        let socket = &config.borrow().Socket;
+        let socket_mode = socket.SocketMode;
 
         let fd = match self.p_conf.p_type() {
             PortType::Socket => {
                 let flag = SockFlag::SOCK_CLOEXEC | SockFlag::SOCK_NONBLOCK;
-                let fd = match self.p_conf.socket_listen(flag, 128, socket_mode) {
+                let fd = match self.p_conf.socket_listen(flag, 128, socket) {
                     Err(e) => {
                         log::error!("Failed to listen {}: {e}", self.p_conf.listen());
                         return Err(Error::Nix { source: e });
@@ -110,43 +120,83 @@ impl SocketPort {
                 }
                 Ok(v) => v,
             },
+            PortType::MQueue => match self.p_conf.open_mqueue(
+                socket_mode,
+                socket.MessageQueueMaxMessages,
+                socket.MessageQueueMessageSize,
+            ) {
+                Err(e) => {
+                    log::error!("Failed to open message queue {}: {e}", self.p_conf.listen());
+                    return Err(Error::Nix { source: e });
+                }
+                Ok(v) => v,
+            },
+            PortType::UsbFunction => match self.p_conf.open_usb_function(
+                socket.USBFunctionDescriptors.as_ref(),
+                socket.USBFunctionStrings.as_ref(),
+            ) {
+                Err(e) => {
+                    log::error!("Failed to open USB function {}: {e}", self.p_conf.listen());
+                    return Err(Error::Nix { source: e });
+                }
+                Ok((ep0, endpoints)) => {
+                    *self.aux_fds.borrow_mut() = endpoints;
+                    ep0
+                }
+            },
             PortType::Invalid => todo!(),
         };
 
+        self.set_fd(fd);
         if update {
-            if let Err(e) = self.comm.reli().fd_cloexec(fd, false) {
-                self.close(update);
-                return Err(e);
+            for fd in self.fds() {
+                if let Err(e) = self.comm.reli().fd_cloexec(fd, false) {
+                    self.close(update);
+                    return Err(e);
+                }
             }
         }
-        self.set_fd(fd);
 
         Ok(())
     }
 
     pub(super) fn close(&self, update: bool) {
-        let fd = self.fd();
-        if fd < 0 {
+        if self.fd() < 0 {
             // debug
             return;
         }
 
-        if update {
-            let ret = self.comm.reli().fd_cloexec(fd, true);
-            if ret.is_err() {
-                log::error!("close socket, remark fd[{}] failed, ret: {:?}", fd, ret);
+        for fd in self.fds() {
+            if update {
+                let ret = self.comm.reli().fd_cloexec(fd, true);
+                if ret.is_err() {
+                    log::error!("close socket, remark fd[{}] failed, ret: {:?}", fd, ret);
+                }
             }
-        }
 
-        fd_util::close(fd);
+            fd_util::close(fd);
+        }
+        self.aux_fds.borrow_mut().clear();
         self.set_fd(SOCKET_INVALID_FD);
     }
 
+    /// the fd and the auxiliary fds, all of them are passed to the service
+    pub(super) fn fds(&self) -> Vec<RawFd> {
+        if self.fd() < 0 {
+            return Vec::new();
+        }
+        let mut fds = vec![self.fd()];
+        fds.extend(self.aux_fds.borrow().iter());
+        fds
+    }
+
     pub(super) fn unlink(&self) {
         match self.p_conf.p_type() {
             PortType::Socket => self.p_conf.unlink_socket(),
             PortType::Fifo => self.p_conf.unlink_fifo(),
             PortType::Special => self.p_conf.unlink_special(),
+            PortType::MQueue => self.p_conf.unlink_mqueue(),
+            PortType::UsbFunction => {}
             PortType::Invalid => todo!(),
         }
     }
@@ -177,6 +227,11 @@ impl SocketPort {
     }
 
     pub(super) fn flush_fd(&self) {
+        if self.p_type() == PortType::MQueue {
+            self.flush_mqueue();
+            return;
+        }
+
         loop {
             let v = io_util::wait_for_events(self.fd(), PollFlags::POLLIN, 0).unwrap_or(0);
             if v == 0 {
@@ -193,7 +248,33 @@ impl SocketPort {
         }
     }
 
+    fn flush_mqueue(&self) {
+        let mut attr: libc::mq_attr = unsafe { std::mem::zeroed() };
+        if unsafe { libc::mq_getattr(self.fd(), &mut attr) } < 0 {
+            return;
+        }
+
+        let mut buf = vec![0u8; attr.mq_msgsize as usize];
+        loop {
+            let ret = unsafe {
+                libc::mq_receive(
+                    self.fd(),
+                    buf.as_mut_ptr() as *mut libc::c_char,
+                    buf.len(),
+                    std::ptr::null_mut(),
+                )
+            };
+            if ret < 0 && Errno::last() != Errno::EINTR {
+                return;
+            }
+        }
+    }
+
     pub(super) fn apply_sock_opt(&self, fd: RawFd) {
+        if self.p_type() != PortType::Socket {
+            return;
+        }
+
         if let Some(v) = self.config.config_data().borrow().Socket.PassPacketInfo {
             if let Err(e) = socket_util::set_pkginfo(fd, self.family(), v) {
                 log::warn!("set socket pkginfo errno: {}", e);
@@ -253,12 +334,38 @@ impl SocketPort {
                 log::warn!("set broadcast state errno: {}", e);
             }
         }
+
+        if let Some(v) = self.config.config_data().borrow().Socket.IPTOS {
+            if self.family() == AddressFamily::Inet {
+                if let Err(e) = socket_util::set_ip_tos(fd, v) {
+                    log::warn!("set ip tos errno: {}", e);
+                }
+            }
+        }
+
+        if let Some(v) = self.config.config_data().borrow().Socket.IPTTL {
+            if IN_SET!(self.family(), AddressFamily::Inet, AddressFamily::Inet6) {
+                if let Err(e) = socket_util::set_ip_ttl(fd, self.family(), v) {
+                    log::warn!("set ip ttl errno: {}", e);
+                }
+            }
+        }
+
+        if let Some(v) = self.config.config_data().borrow().Socket.Mark {
+            if let Err(e) = socket_util::set_mark(fd, v) {
+                log::warn!("set socket mark errno: {}", e);
+            }
+        }
     }
 
     pub(super) fn fd(&self) -> RawFd {
         *self.fd.borrow()
     }
 
+    pub(super) fn aux_fds(&self) -> Vec<RawFd> {
+        self.aux_fds.borrow().clone()
+    }
+
     pub(super) fn p_type(&self) -> PortType {
         self.p_conf.p_type()
     }
@@ -297,7 +404,7 @@ impl SocketPort {
     }
 
     pub(super) fn chown(&self, uid: Uid, gid: Gid) -> Result<()> {
-        self.p_conf.chown(uid, gid)
+        self.p_conf.chown(self.fd(), uid, gid)
     }
 
     fn family(&self) -> AddressFamily {
@@ -327,7 +434,7 @@ mod tests {
     use nix::sys::socket::{
         AddressFamily, NetlinkAddr, SockProtocol, SockType, SockaddrIn, UnixAddr,
     };
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
     use std::{
         net::{Ipv4Addr, SocketAddrV4},
         rc::Rc,
@@ -429,6 +536,37 @@ mod tests {
         port.close(false);
     }
 
+    #[test]
+    fn test_socket_mqueue() {
+        let comm = Rc::new(SocketUnitComm::new());
+        let config = Rc::new(SocketConfig::new(&comm));
+        let p_conf = Rc::new(SocketPortConf::new(
+            PortType::MQueue,
+            SocketAddress::empty(),
+            "/test-socket-mqueue",
+        ));
+
+        let p = SocketPort::new(&comm, &config, &p_conf);
+        let port = Rc::new(p);
+        assert_eq!(port.fd(), SOCKET_INVALID_FD);
+
+        // a kernel built without POSIX message queues has no /proc/sys/fs/mqueue
+        if !Path::new("/proc/sys/fs/mqueue").exists() {
+            assert!(port.open_port(false).is_err());
+            assert_eq!(port.fd(), SOCKET_INVALID_FD);
+            return;
+        }
+
+        assert!(port.open_port(false).is_ok());
+        assert_ne!(port.fd(), SOCKET_INVALID_FD);
+        assert_eq!(port.fds(), vec![port.fd()]);
+
+        port.flush_fd();
+        port.close(false);
+        assert_eq!(port.fd(), SOCKET_INVALID_FD);
+        port.unlink();
+    }
+
     #[test]
     fn test_apply_sock_opt() {
         let recv_buff_size = 4096;
@@ -14,7 +14,7 @@
 use confique::Config;
 use macros::EnumDisplay;
 use nix::unistd::Pid;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use std::collections::VecDeque;
 use std::os::unix::prelude::RawFd;
 use std::rc::Rc;
@@ -30,6 +30,95 @@ const RELI_DB_HSOCKET_MNG: &str = "sockmng";
 const RELI_DB_HSOCKETM_FRAME: &str = "sockm-frame";
 const RELI_LAST_KEY: u32 = 0; // singleton
 
+/// whether the IPv6 sockets accept IPv4 connections too
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, Default)]
+pub(super) enum BindIPv6Only {
+    /// follow /proc/sys/net/ipv6/bindv6only
+    #[default]
+    Default,
+    /// accept both IPv4 and IPv6
+    Both,
+    /// accept IPv6 only
+    Ipv6Only,
+}
+
+impl DeserializeWith for BindIPv6Only {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        match s.as_str() {
+            "default" => Ok(BindIPv6Only::Default),
+            "both" => Ok(BindIPv6Only::Both),
+            "ipv6-only" => Ok(BindIPv6Only::Ipv6Only),
+            _ => Err(de::Error::custom(format!(
+                "BindIPv6Only: invalid value {s}, expected default, both or ipv6-only"
+            ))),
+        }
+    }
+}
+
+fn deserialize_bind_to_device<'de, D>(de: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(de)?;
+    // IFNAMSIZ includes the trailing NUL
+    if s.is_empty()
+        || s.len() >= 16
+        || s == "."
+        || s == ".."
+        || s.contains(|c: char| c == '/' || c == ':' || c.is_whitespace())
+    {
+        return Err(de::Error::custom(format!(
+            "BindToDevice: invalid interface name {s}"
+        )));
+    }
+    Ok(s)
+}
+
+fn deserialize_ip_tos<'de, D>(de: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(de)?;
+    let tos = match s.as_str() {
+        "low-delay" => 0x10,
+        "throughput" => 0x08,
+        "reliability" => 0x04,
+        "low-cost" => 0x02,
+        _ => {
+            let v = match s.strip_prefix("0x") {
+                Some(hex) => i32::from_str_radix(hex, 16),
+                None => s.parse::<i32>(),
+            };
+            match v {
+                Ok(v) if (0..=0xff).contains(&v) => v,
+                _ => {
+                    return Err(de::Error::custom(format!("IPTOS: invalid value {s}")));
+                }
+            }
+        }
+    };
+    Ok(tos)
+}
+
+fn deserialize_ip_ttl<'de, D>(de: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let ttl = i32::deserialize(de)?;
+    if !(1..=255).contains(&ttl) {
+        return Err(de::Error::custom(format!(
+            "IPTTL: {ttl} is out of range [1, 255]"
+        )));
+    }
+    Ok(ttl)
+}
+
 #[derive(Config, Default, Clone, Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub(super) struct SectionSocket {
@@ -56,9 +145,17 @@ pub(super) struct SectionSocket {
     pub ListenFIFO: Option<Vec<String>>,
     #[config(deserialize_with = Vec::<String>::deserialize_with)]
     pub ListenSpecial: Option<Vec<String>>,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub ListenMessageQueue: Option<Vec<String>>,
+    #[config(deserialize_with = Vec::<String>::deserialize_with)]
+    pub ListenUSBFunction: Option<Vec<String>>,
 
     #[config(default = false)]
     pub Accept: bool,
+    #[config(default = 64)]
+    pub MaxConnections: u32,
+    #[config(default = 0)]
+    pub MaxConnectionsPerSource: u32,
     pub Service: Option<String>,
     pub ReceiveBuffer: Option<u64>,
     pub SendBuffer: Option<u64>,
@@ -69,6 +166,26 @@ pub(super) struct SectionSocket {
     pub KeepAliveIntervalSec: Option<u32>,
     pub KeepAliveProbes: Option<u32>,
     pub Broadcast: Option<bool>,
+    #[config(deserialize_with = BindIPv6Only::deserialize_with)]
+    #[config(default = "default")]
+    pub BindIPv6Only: BindIPv6Only,
+    #[config(deserialize_with = deserialize_bind_to_device)]
+    pub BindToDevice: Option<String>,
+    #[config(default = false)]
+    pub FreeBind: bool,
+    #[config(default = false)]
+    pub Transparent: bool,
+    #[config(default = false)]
+    pub ReusePort: bool,
+    #[config(deserialize_with = deserialize_ip_tos)]
+    pub IPTOS: Option<i32>,
+    #[config(deserialize_with = deserialize_ip_ttl)]
+    pub IPTTL: Option<i32>,
+    pub Mark: Option<u32>,
+    pub MessageQueueMaxMessages: Option<i64>,
+    pub MessageQueueMessageSize: Option<i64>,
+    pub USBFunctionDescriptors: Option<String>,
+    pub USBFunctionStrings: Option<String>,
     #[config(default = false)]
     pub RemoveOnStop: bool,
     #[config(deserialize_with = Vec::<String>::deserialize_with)]
@@ -80,6 +197,13 @@ pub(super) struct SectionSocket {
     pub SocketUser: String,
     #[config(default = "")]
     pub SocketGroup: String,
+    #[config(default = 2)]
+    pub TriggerLimitIntervalSec: u64,
+    pub TriggerLimitBurst: Option<u32>,
+    #[config(default = 2)]
+    pub PollLimitIntervalSec: u64,
+    #[config(default = 150)]
+    pub PollLimitBurst: u32,
     #[config(deserialize_with = KillMode::deserialize_with)]
     #[config(default = "none")]
     pub KillMode: KillMode,
@@ -152,6 +276,8 @@ pub(super) enum PortType {
     Socket,
     Fifo,
     Special,
+    MQueue,
+    UsbFunction,
     Invalid,
 }
 
@@ -163,10 +289,15 @@ struct SocketReMng {
     control_cmd_type: Option<SocketCommand>,
     control_cmd_len: usize,
     refused: i32,
+    n_accepted: u32,
+    connections: Vec<(String, String)>,  // (instance, source)
     ports: Vec<(PortType, String, i32)>, // i32 ==> std::os::unix::prelude::RawFd ==> std::os::raw::c_int
+    #[serde(default)]
+    aux_fds: Vec<Vec<i32>>, // the auxiliary fds of each port, in the order of ports
 }
 
 impl SocketReMng {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         state: SocketState,
         result: SocketResult,
@@ -174,7 +305,10 @@ impl SocketReMng {
         control_cmd_type: Option<SocketCommand>,
         control_cmd_len: usize,
         refused: i32,
+        n_accepted: u32,
+        connections: Vec<(String, String)>,
         ports: Vec<(PortType, String, i32)>,
+        aux_fds: Vec<Vec<i32>>,
     ) -> SocketReMng {
         SocketReMng {
             state,
@@ -183,7 +317,10 @@ impl SocketReMng {
             control_cmd_type,
             control_cmd_len,
             refused,
+            n_accepted,
+            connections,
             ports,
+            aux_fds,
         }
     }
 }
@@ -241,13 +378,16 @@ impl SocketRe {
         control_cmd_type: Option<SocketCommand>,
         control_cmd_len: usize,
         refused: i32,
-        ports: Vec<(PortType, String, RawFd)>,
+        n_accepted: u32,
+        connections: Vec<(String, String)>,
+        ports: Vec<(PortType, String, RawFd, Vec<RawFd>)>,
     ) {
         let c_pid = control_pid.map(|x| x.as_raw());
         let ps = ports
             .iter()
-            .map(|(t, l, id)| (*t, l.clone(), *id))
+            .map(|(t, l, id, _)| (*t, l.clone(), *id))
             .collect::<_>();
+        let aux_fds = ports.into_iter().map(|(_, _, _, aux)| aux).collect::<_>();
         let mng = SocketReMng::new(
             state,
             result,
@@ -255,7 +395,10 @@ impl SocketRe {
             control_cmd_type,
             control_cmd_len,
             refused,
+            n_accepted,
+            connections,
             ps,
+            aux_fds,
         );
         self.mng.0.insert(unit_id.to_string(), mng);
     }
@@ -275,7 +418,9 @@ impl SocketRe {
         Option<SocketCommand>,
         usize,
         i32,
-        Vec<(PortType, String, RawFd)>,
+        u32,
+        Vec<(String, String)>,
+        Vec<(PortType, String, RawFd, Vec<RawFd>)>,
     )> {
         let mng = self.mng.0.get(unit_id);
         mng.map(|m| {
@@ -286,9 +431,15 @@ impl SocketRe {
                 m.control_cmd_type,
                 m.control_cmd_len,
                 m.refused,
+                m.n_accepted,
+                m.connections,
                 m.ports
                     .iter()
-                    .map(|(t, l, id)| (*t, l.clone(), *id as RawFd))
+                    .enumerate()
+                    .map(|(i, (t, l, id))| {
+                        let aux = m.aux_fds.get(i).cloned().unwrap_or_default();
+                        (*t, l.clone(), *id as RawFd, aux)
+                    })
                     .collect::<_>(),
             )
         })
@@ -122,6 +122,14 @@ impl SubUnit for SocketUnit {
         self.mng.state().to_string()
     }
 
+    fn get_status_extra(&self) -> Vec<(String, String)> {
+        self.mng.status_extra()
+    }
+
+    fn trigger_notify(&self, other: &str) {
+        self.mng.trigger_notify(other)
+    }
+
     fn collect_fds(&self) -> Vec<i32> {
         self.mng.collect_fds()
     }
@@ -184,6 +192,12 @@ impl SocketUnit {
 
     fn verify(&self) -> Result<()> {
         let config = self.config.config_data();
+        if config.borrow().Socket.Accept && config.borrow().Socket.Service.is_some() {
+            return Err(Error::ConfigureError {
+                msg: "Service can not be configured with Accept=true, the instance is named after the socket".to_string(),
+            });
+        }
+
         if config.borrow().Socket.Symlinks.is_some()
             && !config.borrow().Socket.Symlinks.as_ref().unwrap().is_empty()
             && self.find_symlink_target().is_none()
@@ -0,0 +1,36 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use basic::mount_util::parse_proc_swaps;
+use std::fs;
+use std::path::Path;
+
+pub(super) const PLUGIN_NAME: &str = "SwapUnit";
+
+pub(super) const SWAPON_BIN: &str = "/usr/sbin/swapon";
+pub(super) const SWAPOFF_BIN: &str = "/usr/sbin/swapoff";
+
+pub(super) const PROC_SWAPS: &str = "/proc/swaps";
+
+/// return true if the swap device or file is listed in /proc/swaps
+pub(super) fn swap_is_active(what: &str) -> bool {
+    let content = match fs::read_to_string(PROC_SWAPS) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+
+    // /proc/swaps shows the resolved path, /dev/mapper/xxx is listed as /dev/dm-N
+    let what = fs::canonicalize(what).unwrap_or_else(|_| Path::new(what).to_path_buf());
+    parse_proc_swaps(&content)
+        .iter()
+        .any(|s| Path::new(s) == what)
+}
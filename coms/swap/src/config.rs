@@ -0,0 +1,187 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! swap_config mod load the conf file list and convert it to structure which is defined in this mod.
+//!
+#![allow(non_snake_case)]
+use super::base::{SWAPOFF_BIN, SWAPON_BIN};
+use super::comm::SwapUnitComm;
+use super::rentry::SectionSwap;
+use confique::Config;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::exec::{ExecCommand, ExecFlag};
+use sysmaster::rel::ReStation;
+use sysmaster::unit::KillContext;
+
+pub(super) struct SwapConfig {
+    // associated objects
+    comm: Rc<SwapUnitComm>,
+
+    // owned objects
+    data: Rc<RefCell<SwapConfigData>>,
+    kill_context: Rc<KillContext>,
+}
+
+impl ReStation for SwapConfig {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        if reload {
+            return;
+        }
+        if let Some(swap) = self.comm.rentry_conf_get() {
+            self.data.replace(SwapConfigData::new(swap));
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm.rentry_conf_insert(&self.data.borrow().Swap);
+    }
+
+    // reload: no external connections, no entry
+}
+
+impl SwapConfig {
+    pub(super) fn new(commr: &Rc<SwapUnitComm>) -> Self {
+        SwapConfig {
+            comm: Rc::clone(commr),
+            data: Rc::new(RefCell::new(SwapConfigData::default())),
+            kill_context: Rc::new(KillContext::default()),
+        }
+    }
+
+    pub(super) fn load(&self, paths: Vec<PathBuf>, update: bool) -> Result<()> {
+        // a swap without config file is only monitored
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = SwapConfigData::builder().env();
+        for v in paths {
+            builder = builder.file(v);
+        }
+        let data = builder.load().context(ConfiqueSnafu)?;
+        *self.data.borrow_mut() = data;
+
+        if update {
+            self.db_update();
+        }
+        Ok(())
+    }
+
+    pub(super) fn config_data(&self) -> Rc<RefCell<SwapConfigData>> {
+        self.data.clone()
+    }
+
+    pub(super) fn kill_context(&self) -> Rc<KillContext> {
+        self.kill_context.clone()
+    }
+
+    /// whether the swap is configured by a .swap file, or only monitored.
+    pub(super) fn configured(&self) -> bool {
+        !self.data.borrow().Swap.What.is_empty()
+    }
+
+    pub(super) fn swap_what(&self) -> String {
+        self.data.borrow().Swap.What.clone()
+    }
+
+    pub(super) fn swapon_command(&self) -> ExecCommand {
+        self.data.borrow().swapon_command()
+    }
+
+    pub(super) fn swapoff_command(&self) -> ExecCommand {
+        self.data.borrow().swapoff_command()
+    }
+}
+
+#[derive(Config, Default, Debug)]
+pub(super) struct SwapConfigData {
+    #[config(nested)]
+    pub Swap: SectionSwap,
+}
+
+impl SwapConfigData {
+    pub(self) fn new(Swap: SectionSwap) -> SwapConfigData {
+        SwapConfigData { Swap }
+    }
+
+    // swapon [-p Priority] [-o Options] What
+    pub(self) fn swapon_command(&self) -> ExecCommand {
+        let s = &self.Swap;
+        let mut argv = Vec::new();
+        if s.Priority >= 0 {
+            argv.extend(["-p".to_string(), s.Priority.to_string()]);
+        }
+        if !s.Options.is_empty() {
+            argv.extend(["-o".to_string(), s.Options.clone()]);
+        }
+        argv.push(s.What.clone());
+        raw_command(SWAPON_BIN, argv)
+    }
+
+    // swapoff What
+    pub(self) fn swapoff_command(&self) -> ExecCommand {
+        raw_command(SWAPOFF_BIN, vec![self.Swap.What.clone()])
+    }
+}
+
+// the device and options are passed to swapon as they are, no specifier or environment expanding
+fn raw_command(path: &str, argv: Vec<String>) -> ExecCommand {
+    let mut cmd = ExecCommand::new(path.to_string(), argv);
+    cmd.add_exec_flag(ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SwapConfigData;
+    use crate::rentry::SectionSwap;
+
+    fn argv(cmd: &sysmaster::exec::ExecCommand) -> Vec<String> {
+        let mut res = vec![cmd.path().clone()];
+        res.extend(cmd.argv().into_iter().cloned());
+        res
+    }
+
+    #[test]
+    fn test_swap_commands() {
+        let data = SwapConfigData::new(SectionSwap {
+            What: "/dev/sdb2".to_string(),
+            Priority: 10,
+            Options: "discard".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(
+            argv(&data.swapon_command()),
+            vec!["/usr/sbin/swapon", "-p", "10", "-o", "discard", "/dev/sdb2"]
+        );
+        assert_eq!(
+            argv(&data.swapoff_command()),
+            vec!["/usr/sbin/swapoff", "/dev/sdb2"]
+        );
+
+        let data = SwapConfigData::new(SectionSwap {
+            What: "/swapfile".to_string(),
+            Priority: -1,
+            ..Default::default()
+        });
+        assert_eq!(
+            argv(&data.swapon_command()),
+            vec!["/usr/sbin/swapon", "/swapfile"]
+        );
+    }
+}
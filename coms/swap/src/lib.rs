@@ -0,0 +1,49 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Swap is the entry for the swap management of sysmaster. sysmaster refers to systemd, but it is not the same.
+//! All swaps in /proc/swaps are monitored, a .swap unit is started when its swap appears and stopped when it disappears.
+//! A .swap unit with a configuration file also turns on/off its swap by running swapon/swapoff as the control process.
+//! The Swap configuration file contains three sections: Unit, Swap and Install.
+//!
+//! ```toml
+//! [Unit]
+//! Description="swap partition"
+//!
+//! [Swap]
+//! What="/dev/sdb2"
+//! Priority=10
+//! Options="discard"
+//!
+//! [Install]
+//! WantedBy="swap.target"
+//! ```
+//!
+//! The name of the unit must be the escaped What, "/dev/sdb2" is managed by dev-sdb2.swap.
+//!
+//! ## Automatic dependency
+//! NA
+//! ### Implicit dependency
+//! After= on the swap turned on just before it, in the order of /proc/swaps, so the swaps are turned off in the reverse order.
+//! ### Default Dependency
+//! Before= and Conflicts= on shutdown.target.
+
+// dependency: swap_base -> swap_rentry -> swap_comm -> swap_config -> {swap_spawn -> swap_mng -> swap_load -> swap_unit} -> swap_manager
+mod base;
+mod comm;
+mod config;
+mod load;
+mod manager;
+mod mng;
+mod rentry;
+mod spawn;
+mod unit;
@@ -0,0 +1,78 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! swap_load mod verify the section Swap and add the default dependencies.
+//!
+use super::comm::SwapUnitComm;
+use super::config::SwapConfig;
+use basic::special::SHUTDOWN_TARGET;
+use basic::unit_name::unit_name_from_path;
+use std::path::Path;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::unit::{UnitDependencyMask, UnitRelations};
+
+pub(super) struct SwapLoad {
+    config: Rc<SwapConfig>,
+    comm: Rc<SwapUnitComm>,
+}
+
+impl SwapLoad {
+    pub(super) fn new(configr: &Rc<SwapConfig>, commr: &Rc<SwapUnitComm>) -> Self {
+        SwapLoad {
+            config: configr.clone(),
+            comm: commr.clone(),
+        }
+    }
+
+    pub(super) fn verify(&self) -> Result<()> {
+        let owner = match self.comm.owner() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+
+        let what = self.config.swap_what();
+        if !Path::new(&what).is_absolute() {
+            return Err(Error::ConfigureError {
+                msg: format!("What={what} is not an absolute path"),
+            });
+        }
+        if unit_name_from_path(&what, ".swap") != *owner.id() {
+            return Err(Error::ConfigureError {
+                msg: format!("What={} doesn't match the unit name {}", what, owner.id()),
+            });
+        }
+        Ok(())
+    }
+
+    /// the swap is turned off before shutdown.target
+    pub(super) fn swap_add_default_dependencies(&self) -> Result<()> {
+        let u = match self.comm.owner() {
+            None => return Ok(()),
+            Some(u) => u,
+        };
+
+        if !u.default_dependencies() {
+            return Ok(());
+        }
+
+        log::debug!("Adding default dependencies for swap: {}", u.id());
+        self.comm.um().unit_add_two_dependency(
+            u.id(),
+            UnitRelations::UnitBefore,
+            UnitRelations::UnitConflicts,
+            SHUTDOWN_TARGET,
+            true,
+            UnitDependencyMask::Default,
+        )
+    }
+}
@@ -0,0 +1,464 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use super::base::{PLUGIN_NAME, PROC_SWAPS};
+use super::comm::SwapUmComm;
+use super::rentry::{SwapRe, SwapReFrame};
+use basic::logger;
+use basic::mount_util::parse_proc_swaps;
+use basic::unit_name::{unit_name_from_path, unit_name_to_path};
+use event::{EventState, EventType, Events, Source};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use sysmaster::error::*;
+use sysmaster::rel::{ReStation, ReliLastFrame, Reliability};
+use sysmaster::unit::{
+    UmIf, UnitActiveState, UnitDependencyMask, UnitManagerObj, UnitMngUtil, UnitRelationAtom,
+    UnitRelations, UnitType,
+};
+
+struct SwapManager {
+    // owned objects
+    // data
+    comm: Arc<SwapUmComm>,
+    // none if the kernel doesn't support swap
+    monitor: Option<SwapMonitor>,
+}
+
+impl ReStation for SwapManager {
+    // input
+    fn input_rebuild(&self) {
+        if let Some(monitor) = &self.monitor {
+            monitor.defer_enable(true);
+        }
+    }
+
+    // compensate
+    fn db_compensate_last(&self, _lframe: (u32, Option<u32>, Option<u32>), lunit: Option<&String>) {
+        assert!(lunit.is_some());
+
+        let frame = self.comm.rentry().last_frame();
+        if frame.is_none() {
+            // debug
+            return;
+        }
+
+        let unit_id = lunit.unwrap();
+        match frame.unwrap() {
+            SwapReFrame::Monitor => self.comm.um().rentry_trigger_merge(unit_id, true), // merge to trigger
+        }
+    }
+
+    fn do_compensate_last(&self, _lframe: (u32, Option<u32>, Option<u32>), lunit: Option<&String>) {
+        assert!(lunit.is_some());
+
+        let frame = self.comm.rentry().last_frame();
+        if frame.is_none() {
+            // debug
+            return;
+        }
+
+        let unit_id = lunit.unwrap();
+        match frame.unwrap() {
+            SwapReFrame::Monitor => self.comm.um().trigger_unit(unit_id), // re-run
+        }
+    }
+
+    // no data
+
+    // reload
+    fn register_ex(&self) {
+        if let Some(monitor) = &self.monitor {
+            monitor.register_ex();
+        }
+    }
+}
+
+impl UnitManagerObj for SwapManager {}
+
+// the declaration "pub(self)" is for identification only.
+impl SwapManager {
+    pub fn new() -> SwapManager {
+        let _comm = SwapUmComm::get_instance();
+        let monitor = match SwapMonitor::new(&_comm, PROC_SWAPS.to_string()) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                log::error!(
+                    "Failed to monitor {}, the swaps are not tracked: {}",
+                    PROC_SWAPS,
+                    e
+                );
+                None
+            }
+        };
+        SwapManager {
+            comm: Arc::clone(&_comm),
+            monitor,
+        }
+    }
+
+    fn register(&self) {
+        if let Some(monitor) = &self.monitor {
+            monitor.register();
+        }
+    }
+}
+
+struct SwapMonitor {
+    // owned objects
+    data: Rc<SwapMonitorData>,
+    defer: Rc<SwapMonitorDefer>,
+    io: Rc<SwapMonitorIo>,
+}
+
+// the declaration "pub(self)" is for identification only.
+impl SwapMonitor {
+    pub fn new(commr: &Arc<SwapUmComm>, filename: String) -> Result<SwapMonitor> {
+        let _data = Rc::new(SwapMonitorData::new(commr, filename)?);
+        let _defer = Rc::new(SwapMonitorDefer::new(&_data));
+        Ok(SwapMonitor {
+            data: Rc::clone(&_data),
+            defer: Rc::clone(&_defer),
+            io: Rc::new(SwapMonitorIo::new(&_data, &_defer)),
+        })
+    }
+
+    pub fn register_ex(&self) {
+        let events = self.data.comm.um().events();
+
+        // io
+        let io = Rc::clone(&self.io);
+        events.add_source(io.clone()).unwrap();
+        events.set_enabled(io, EventState::On).unwrap();
+    }
+
+    pub fn defer_enable(&self, enable: bool) -> i32 {
+        self.io.defer_enable(enable)
+    }
+
+    fn register(&self) {
+        let events = self.data.comm.um().events();
+
+        // defer
+        let defer = Rc::clone(&self.defer);
+        events.add_source(defer).unwrap();
+    }
+}
+
+struct SwapMonitorIo {
+    // associated objects
+    data: Rc<SwapMonitorData>,
+    defer: Rc<SwapMonitorDefer>,
+}
+
+// the declaration "pub(self)" is for identification only.
+impl SwapMonitorIo {
+    pub fn new(datar: &Rc<SwapMonitorData>, deferr: &Rc<SwapMonitorDefer>) -> SwapMonitorIo {
+        SwapMonitorIo {
+            data: Rc::clone(datar),
+            defer: Rc::clone(deferr),
+        }
+    }
+
+    pub fn defer_enable(&self, enable: bool) -> i32 {
+        let source = Rc::clone(&self.defer);
+        let state = match enable {
+            true => EventState::OneShot,
+            false => EventState::Off,
+        };
+        let events = self.data.comm.um().events();
+        match events.set_enabled(source, state) {
+            Ok(_) => 0,
+            Err(_) => -1,
+        }
+    }
+
+    fn reli(&self) -> Rc<Reliability> {
+        self.data.comm.reli()
+    }
+
+    fn rentry(&self) -> Rc<SwapRe> {
+        self.data.comm.rentry()
+    }
+}
+
+impl Source for SwapMonitorIo {
+    fn event_type(&self) -> EventType {
+        EventType::Io
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn dispatch(&self, _e: &Events) -> i32 {
+        drain_out(self.data.epfd);
+
+        self.reli()
+            .set_last_frame2(ReliLastFrame::SubManager as u32, UnitType::UnitSwap as u32);
+        self.rentry().set_last_frame(SwapReFrame::Monitor);
+        let ret = self.data.dispatch_swaps();
+        self.rentry().clear_last_frame();
+        self.reli().clear_last_frame();
+
+        if let Err(e) = ret {
+            log::error!("Failed to dispatch {}, ignoring: {:?}", PROC_SWAPS, e);
+        }
+
+        self.defer_enable(false)
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+
+    fn fd(&self) -> RawFd {
+        self.data.epfd
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+}
+
+struct SwapMonitorDefer {
+    // associated objects
+    data: Rc<SwapMonitorData>,
+}
+
+impl SwapMonitorDefer {
+    pub fn new(datar: &Rc<SwapMonitorData>) -> SwapMonitorDefer {
+        SwapMonitorDefer {
+            data: Rc::clone(datar),
+        }
+    }
+
+    fn reli(&self) -> Rc<Reliability> {
+        self.data.comm.reli()
+    }
+
+    fn rentry(&self) -> Rc<SwapRe> {
+        self.data.comm.rentry()
+    }
+}
+
+impl Source for SwapMonitorDefer {
+    fn event_type(&self) -> EventType {
+        EventType::Defer
+    }
+
+    fn epoll_event(&self) -> u32 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+
+    fn dispatch(&self, _event: &Events) -> i32 {
+        self.reli()
+            .set_last_frame2(ReliLastFrame::SubManager as u32, UnitType::UnitSwap as u32);
+        self.rentry().set_last_frame(SwapReFrame::Monitor);
+        let ret = self.data.dispatch_swaps();
+        self.rentry().clear_last_frame();
+        self.reli().clear_last_frame();
+
+        match ret {
+            Ok(_) => 0,
+            Err(_) => -1,
+        }
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+}
+
+struct SwapMonitorData {
+    // associated objects
+    comm: Arc<SwapUmComm>,
+
+    // owned objects
+    _file: File,
+    epfd: i32,
+    _epevent: epoll::Event,
+    _events: Vec<epoll::Event>,
+}
+
+impl SwapMonitorData {
+    pub fn new(commr: &Arc<SwapUmComm>, filename: String) -> Result<Self> {
+        // /proc/swaps is missing if the kernel is built without CONFIG_SWAP
+        let me_file = File::open(&filename).context(IoSnafu)?;
+
+        let me_fd = me_file.as_raw_fd();
+        let me_epfd = epoll::create(false).context(IoSnafu)?;
+        let event = epoll::Events::EPOLLET | epoll::Events::EPOLLIN;
+        let me_epevent = epoll::Event::new(event, me_fd as u64);
+        let op = epoll::ControlOptions::EPOLL_CTL_ADD;
+        drain_out(me_epfd);
+        if let Err(e) = epoll::ctl(me_epfd, op, me_fd, me_epevent) {
+            basic::fd_util::close(me_epfd);
+            return Err(e).context(IoSnafu);
+        }
+
+        log::debug!("Monitoring {} by epool fd {}", filename, me_epfd);
+        Ok(SwapMonitorData {
+            comm: Arc::clone(commr),
+            _file: me_file,
+            epfd: me_epfd,
+            _epevent: me_epevent,
+            _events: Vec::new(),
+        })
+    }
+
+    pub fn dispatch_swaps(&self) -> Result<()> {
+        // First mark all active swaps we have as dead.
+        let mut dead_swap_set: HashSet<String> = HashSet::new();
+        // /proc/swaps shows the resolved path, /dev/disk/by-uuid/xxx or /dev/mapper/xxx is
+        // listed as /dev/sdX or /dev/dm-N, map it back to the unit whose What= resolves to it.
+        let mut unit_paths: HashMap<PathBuf, String> = HashMap::new();
+        let unit_type = Some(UnitType::UnitSwap);
+        for unit in self.comm.um().units_get_all(unit_type).into_iter() {
+            let active = self.comm.um().current_active_state(&unit) == UnitActiveState::UnitActive;
+            if active {
+                dead_swap_set.insert(unit.clone());
+            }
+
+            if let Some(what) = unit_name_to_path(&unit) {
+                let path = fs::canonicalize(&what).unwrap_or_else(|_| PathBuf::from(what));
+                if active || !unit_paths.contains_key(&path) {
+                    unit_paths.insert(path, unit);
+                }
+            }
+        }
+
+        // Then start swaps we don't know.
+        let content = fs::read_to_string(PROC_SWAPS).context(IoSnafu)?;
+        let mut prev: Option<String> = None;
+        for what in parse_proc_swaps(&content) {
+            let unit_name = match unit_paths.get(Path::new(&what)) {
+                Some(unit) => unit.clone(),
+                None => unit_name_from_path(&what, ".swap"),
+            };
+            if dead_swap_set.contains(unit_name.as_str()) {
+                dead_swap_set.remove(unit_name.as_str());
+            } else if self.comm.um().load_unit_success(unit_name.as_str()) {
+                self.start_swap(&unit_name);
+            }
+
+            if let Some(p) = prev {
+                self.order_after(&unit_name, &p);
+            }
+            prev = Some(unit_name);
+        }
+
+        // Finally stop swaps in dead_swap_set.
+        for unit_name in dead_swap_set.into_iter() {
+            // pop
+            // record + action
+            self.comm.reli().set_last_unit(&unit_name);
+            let ret = self.comm.um().unit_stop(&unit_name, false);
+            self.comm.reli().clear_last_unit();
+
+            if ret.is_ok() {
+                log::debug!("{} change to dead.", unit_name);
+            } else {
+                log::error!("Failed to stop {}.", unit_name);
+            }
+        }
+        Ok(())
+    }
+
+    fn start_swap(&self, unit_name: &str) {
+        // the swapon/swapoff control process of the unit is still running,
+        // the unit finishes its job when the process exits.
+        if !matches!(
+            self.comm.um().current_active_state(unit_name),
+            UnitActiveState::UnitInActive | UnitActiveState::UnitFailed
+        ) {
+            return;
+        }
+
+        // record + action
+        self.comm.reli().set_last_unit(unit_name);
+        let start_ok = self.comm.um().unit_start_directly(unit_name).is_ok();
+        self.comm.reli().clear_last_unit();
+
+        if start_ok {
+            log::debug!("{} change to active.", unit_name);
+        } else {
+            log::error!("Failed to start {}", unit_name);
+        }
+    }
+
+    /// /proc/swaps lists the swaps in the order they are turned on, order the later one after
+    /// the earlier one, so that they are turned off in the reverse order at shutdown.
+    fn order_after(&self, unit_name: &str, prev: &str) {
+        let um = self.comm.um();
+        if um.unit_has_dependecy(prev, UnitRelationAtom::UnitAtomAfter, unit_name) {
+            return;
+        }
+        if let Err(e) = um.unit_add_dependency(
+            unit_name,
+            UnitRelations::UnitAfter,
+            prev,
+            true,
+            UnitDependencyMask::Implicit,
+        ) {
+            log::debug!("Failed to order {} after {}: {}", unit_name, prev, e);
+        }
+    }
+}
+
+fn drain_out(epfd: i32) {
+    // drain out all events.
+    let mut me_events: Vec<epoll::Event> = vec![epoll::Event::new(epoll::Events::empty(), 0)];
+    while matches!(epoll::wait(epfd, 0, &mut me_events), Ok(n) if n > 0) {}
+}
+
+impl UnitMngUtil for SwapManager {
+    fn attach_um(&self, um: Rc<dyn UmIf>) {
+        self.comm.attach_um(um);
+        self.register();
+    }
+
+    fn attach_reli(&self, reli: Rc<Reliability>) {
+        self.comm.attach_reli(reli);
+    }
+}
+
+impl Default for SwapManager {
+    fn default() -> Self {
+        SwapManager::new()
+    }
+}
+
+use sysmaster::declure_umobj_plugin;
+declure_umobj_plugin!(SwapManager, SwapManager::default, PLUGIN_NAME);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monitor_without_proc_swaps() {
+        let comm = SwapUmComm::get_instance();
+        let ret = SwapMonitor::new(&comm, "/proc/swaps-not-exist".to_string());
+        assert!(ret.is_err());
+    }
+}
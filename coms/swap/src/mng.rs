@@ -0,0 +1,589 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//!  The core logic of the swap subclass
+use super::base::swap_is_active;
+use super::comm::SwapUnitComm;
+use super::config::SwapConfig;
+use super::rentry::{SwapResult, SwapState};
+use super::spawn::SwapSpawn;
+use basic::IN_SET;
+use event::{EventState, EventType, Events, Source};
+use nix::sys::wait::WaitStatus;
+use nix::unistd::Pid;
+use std::cell::RefCell;
+use std::os::unix::prelude::RawFd;
+use std::rc::{Rc, Weak};
+use sysmaster::error::*;
+use sysmaster::exec::{ExecCommand, ExecContext};
+use sysmaster::rel::ReStation;
+use sysmaster::unit::{KillOperation, UnitActiveState, UnitNotifyFlags};
+
+impl SwapState {
+    fn swap_state_to_unit_state(&self) -> UnitActiveState {
+        match *self {
+            SwapState::Dead => UnitActiveState::UnitInActive,
+            SwapState::Activating => UnitActiveState::UnitActivating,
+            SwapState::Active => UnitActiveState::UnitActive,
+            SwapState::Deactivating
+            | SwapState::DeactivatingSigterm
+            | SwapState::DeactivatingSigkill => UnitActiveState::UnitDeActivating,
+            SwapState::Failed => UnitActiveState::UnitFailed,
+        }
+    }
+
+    fn to_kill_operation(self) -> KillOperation {
+        match self {
+            SwapState::DeactivatingSigterm => KillOperation::KillTerminate,
+            _ => KillOperation::KillKill,
+        }
+    }
+}
+
+pub(super) struct SwapMng {
+    comm: Rc<SwapUnitComm>,
+    config: Rc<SwapConfig>,
+    spawn: SwapSpawn,
+    state: RefCell<SwapState>,
+    result: RefCell<SwapResult>,
+    control_pid: RefCell<Option<Pid>>,
+    timer: Rc<SwapTimer>,
+}
+
+impl ReStation for SwapMng {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, _reload: bool) {
+        if let Some((state, result, control_pid)) = self.comm.rentry_mng_get() {
+            *self.state.borrow_mut() = state;
+            *self.result.borrow_mut() = result;
+            *self.control_pid.borrow_mut() = control_pid;
+        }
+    }
+
+    fn db_insert(&self) {
+        self.comm
+            .rentry_mng_insert(self.state(), self.result(), self.control_pid());
+    }
+
+    // reload: no external connections
+    fn entry_coldplug(&self) {
+        if self.control_pid().is_some() {
+            if let Err(e) = self.enable_timer(self.timeout_usec()) {
+                log::error!(
+                    "Failed to enable the timer of {}: {}",
+                    self.comm.get_owner_id(),
+                    e
+                );
+            }
+        }
+    }
+
+    fn entry_clear(&self) {
+        self.delete_timer();
+    }
+}
+
+impl SwapMng {
+    pub(super) fn new(
+        commr: &Rc<SwapUnitComm>,
+        configr: &Rc<SwapConfig>,
+        exec_ctx: &Rc<ExecContext>,
+    ) -> Self {
+        SwapMng {
+            comm: Rc::clone(commr),
+            config: Rc::clone(configr),
+            spawn: SwapSpawn::new(commr, exec_ctx),
+            state: RefCell::new(SwapState::Dead),
+            result: RefCell::new(SwapResult::Success),
+            control_pid: RefCell::new(None),
+            timer: Rc::new(SwapTimer::new(u64::MAX)),
+        }
+    }
+
+    pub(super) fn attach_timer(mng: &Rc<SwapMng>) {
+        mng.timer.attach_mng(Rc::downgrade(mng));
+    }
+
+    pub(super) fn start_check(&self) -> Result<bool> {
+        // the swap is being turned off, try again later
+        if IN_SET!(
+            self.state(),
+            SwapState::Deactivating,
+            SwapState::DeactivatingSigterm,
+            SwapState::DeactivatingSigkill
+        ) {
+            return Err(Error::UnitActionEAgain);
+        }
+
+        if self.state() == SwapState::Activating {
+            return Ok(true);
+        }
+
+        let ret = self.comm.owner().is_some_and(|u| u.test_start_limit());
+        if !ret {
+            self.enter_dead(SwapResult::FailureStartLimitHit, true);
+            return Err(Error::UnitActionECanceled);
+        }
+
+        Ok(false)
+    }
+
+    pub(super) fn start_action(&self) {
+        self.set_result(SwapResult::Success);
+
+        // the swap without config file is only monitored, it is started when it appears
+        // in /proc/swaps. And there is nothing to do if it's active already.
+        if !self.config.configured() || swap_is_active(&self.config.swap_what()) {
+            self.enter_active(SwapResult::Success, true);
+            return;
+        }
+
+        self.enter_activating();
+    }
+
+    pub(super) fn stop_action(&self) {
+        match self.state() {
+            SwapState::Deactivating
+            | SwapState::DeactivatingSigterm
+            | SwapState::DeactivatingSigkill => {}
+            SwapState::Activating => {
+                self.enter_signal(SwapState::DeactivatingSigterm, SwapResult::Success)
+            }
+            SwapState::Active => self.enter_deactivating(),
+            SwapState::Dead | SwapState::Failed => {}
+        }
+    }
+
+    fn enter_activating(&self) {
+        if self.spawn_control(&self.config.swapon_command()).is_err() {
+            self.enter_dead(SwapResult::FailureResources, true);
+            return;
+        }
+        self.set_state(SwapState::Activating, true);
+    }
+
+    fn enter_deactivating(&self) {
+        if !self.config.configured() || !swap_is_active(&self.config.swap_what()) {
+            self.enter_dead(SwapResult::Success, true);
+            return;
+        }
+
+        if self.spawn_control(&self.config.swapoff_command()).is_err() {
+            self.enter_active(SwapResult::FailureResources, true);
+            return;
+        }
+        self.set_state(SwapState::Deactivating, true);
+    }
+
+    fn spawn_control(&self, cmd: &ExecCommand) -> Result<()> {
+        self.unwatch_control();
+        let pid = self.spawn.spawn_cmd(cmd)?;
+        self.control_pid.replace(Some(pid));
+        if let Err(e) = self.enable_timer(self.timeout_usec()) {
+            log::error!(
+                "Failed to enable the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn enter_signal(&self, state: SwapState, res: SwapResult) {
+        log::debug!("enter signal {:?}, res: {:?}", state, res);
+        if self.result() == SwapResult::Success {
+            self.set_result(res);
+        }
+
+        let op = state.to_kill_operation();
+        if let Some(u) = self.comm.owner() {
+            match u.kill_context(
+                self.config.kill_context(),
+                None,
+                self.control_pid(),
+                op,
+                false,
+            ) {
+                Ok(true) => {
+                    // wait for the control process to exit
+                    if let Err(e) = self.enable_timer(self.timeout_usec()) {
+                        log::error!("Failed to enable the timer of {}: {}", u.id(), e);
+                    }
+                    self.set_state(state, true);
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    log::error!("Failed to kill the control process of {}: {}", u.id(), e);
+                    self.enter_dead_or_active(SwapResult::FailureResources);
+                    return;
+                }
+            }
+        }
+
+        match state {
+            SwapState::DeactivatingSigterm => {
+                self.enter_signal(SwapState::DeactivatingSigkill, SwapResult::Success)
+            }
+            _ => self.enter_dead_or_active(SwapResult::Success),
+        }
+    }
+
+    pub(super) fn enter_dead(&self, res: SwapResult, notify: bool) {
+        log::debug!("enter dead state, res {:?}", res);
+        if self.result() == SwapResult::Success {
+            self.set_result(res);
+        }
+
+        let state = if self.result() == SwapResult::Success {
+            SwapState::Dead
+        } else {
+            SwapState::Failed
+        };
+        self.set_state(state, notify);
+    }
+
+    pub(super) fn enter_active(&self, res: SwapResult, notify: bool) {
+        if self.result() == SwapResult::Success {
+            self.set_result(res);
+        }
+        self.set_state(SwapState::Active, notify);
+    }
+
+    fn enter_dead_or_active(&self, res: SwapResult) {
+        if self.config.configured() && swap_is_active(&self.config.swap_what()) {
+            self.enter_active(res, true);
+        } else {
+            self.enter_dead(res, true);
+        }
+    }
+
+    pub(super) fn sigchld_event(&self, wait_status: WaitStatus) {
+        self.unwatch_control();
+        self.disable_timer();
+
+        let res = sigchld_result(wait_status);
+        match self.state() {
+            SwapState::Activating => {
+                if res == SwapResult::Success || swap_is_active(&self.config.swap_what()) {
+                    self.enter_active(res, true);
+                } else {
+                    self.enter_dead(res, true);
+                }
+            }
+            SwapState::Deactivating
+            | SwapState::DeactivatingSigterm
+            | SwapState::DeactivatingSigkill => self.enter_dead_or_active(res),
+            _ => {
+                log::error!(
+                    "control process should not exit, current state is : {:?}",
+                    self.state()
+                );
+            }
+        }
+
+        self.db_update();
+    }
+
+    pub(super) fn timer_event(&self) {
+        log::warn!(
+            "{} {} operation time out",
+            self.comm.get_owner_id(),
+            self.state()
+        );
+        match self.state() {
+            SwapState::Activating | SwapState::Deactivating => {
+                self.enter_signal(SwapState::DeactivatingSigterm, SwapResult::FailureTimeout)
+            }
+            SwapState::DeactivatingSigterm => {
+                self.enter_signal(SwapState::DeactivatingSigkill, SwapResult::FailureTimeout)
+            }
+            SwapState::DeactivatingSigkill => self.enter_dead_or_active(SwapResult::FailureTimeout),
+            _ => {}
+        }
+        self.db_update();
+    }
+
+    pub fn get_state(&self) -> String {
+        let state = *self.state.borrow();
+        state.to_string()
+    }
+
+    fn set_state(&self, new_state: SwapState, notify: bool) {
+        let old_state = self.state();
+        self.change_state(new_state);
+
+        if !IN_SET!(
+            new_state,
+            SwapState::Activating,
+            SwapState::Deactivating,
+            SwapState::DeactivatingSigterm,
+            SwapState::DeactivatingSigkill
+        ) {
+            self.disable_timer();
+            self.unwatch_control();
+        }
+
+        if notify {
+            self.state_notify(new_state, old_state);
+        }
+    }
+
+    fn state_notify(&self, new_state: SwapState, old_state: SwapState) {
+        if new_state != old_state {
+            log::debug!(
+                "{} original state[{:?}] -> new state[{:?}]",
+                self.comm.get_owner_id(),
+                old_state,
+                new_state,
+            );
+        }
+
+        let old_unit_state = old_state.swap_state_to_unit_state();
+        let new_unit_state = new_state.swap_state_to_unit_state();
+        if let Some(u) = self.comm.owner() {
+            u.notify(
+                old_unit_state,
+                new_unit_state,
+                UnitNotifyFlags::UNIT_NOTIFY_RELOAD_FAILURE,
+            )
+        }
+
+        self.db_update();
+    }
+
+    fn change_state(&self, new_state: SwapState) {
+        self.state.replace(new_state);
+    }
+
+    fn state(&self) -> SwapState {
+        *self.state.borrow()
+    }
+
+    fn result(&self) -> SwapResult {
+        *self.result.borrow()
+    }
+
+    fn set_result(&self, res: SwapResult) {
+        self.result.replace(res);
+    }
+
+    fn control_pid(&self) -> Option<Pid> {
+        *self.control_pid.borrow()
+    }
+
+    fn unwatch_control(&self) {
+        if let Some(pid) = self.control_pid.take() {
+            if let Some(u) = self.comm.owner() {
+                self.comm.um().child_unwatch_pid(u.id(), pid)
+            }
+        }
+    }
+
+    fn timeout_usec(&self) -> u64 {
+        self.config.config_data().borrow().Swap.TimeoutSec
+    }
+
+    fn enable_timer(&self, usec: u64) -> Result<()> {
+        if usec == u64::MAX {
+            self.disable_timer();
+            return Ok(());
+        }
+
+        let events = self.comm.um().events();
+        let timer = self.timer.clone();
+        if timer.armed() {
+            events.set_enabled(timer.clone(), EventState::Off)?;
+        } else {
+            events.add_source(timer.clone())?;
+            timer.set_armed(true);
+        }
+        timer.set_time(usec);
+        events.set_enabled(timer, EventState::OneShot)?;
+        Ok(())
+    }
+
+    fn disable_timer(&self) {
+        if !self.timer.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.set_enabled(self.timer.clone(), EventState::Off) {
+            log::error!(
+                "Failed to disable the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+    }
+
+    fn delete_timer(&self) {
+        if !self.timer.armed() {
+            return;
+        }
+        let events = self.comm.um().events();
+        if let Err(e) = events.del_source(self.timer.clone()) {
+            log::error!(
+                "Failed to delete the timer of {}: {}",
+                self.comm.get_owner_id(),
+                e
+            );
+        }
+        self.timer.set_armed(false);
+    }
+
+    pub(super) fn swap_state_to_unit_state(&self) -> UnitActiveState {
+        self.state().swap_state_to_unit_state()
+    }
+}
+
+fn sigchld_result(wait_status: WaitStatus) -> SwapResult {
+    match wait_status {
+        WaitStatus::Exited(_, 0) => SwapResult::Success,
+        WaitStatus::Exited(_, _) => SwapResult::FailureExitCode,
+        WaitStatus::Signaled(_, _, true) => SwapResult::FailureCoreDump,
+        WaitStatus::Signaled(_, _, false) => SwapResult::FailureSignal,
+        _ => SwapResult::FailureResources,
+    }
+}
+
+pub(super) struct SwapTimer {
+    time: RefCell<u64>,
+    armed: RefCell<bool>,
+    mng: RefCell<Weak<SwapMng>>,
+}
+
+impl SwapTimer {
+    fn new(usec: u64) -> Self {
+        SwapTimer {
+            time: RefCell::new(usec),
+            armed: RefCell::new(false),
+            mng: RefCell::new(Weak::new()),
+        }
+    }
+
+    fn attach_mng(&self, mng: Weak<SwapMng>) {
+        *self.mng.borrow_mut() = mng;
+    }
+
+    fn set_time(&self, usec: u64) {
+        *self.time.borrow_mut() = usec
+    }
+
+    fn armed(&self) -> bool {
+        *self.armed.borrow()
+    }
+
+    fn set_armed(&self, armed: bool) {
+        *self.armed.borrow_mut() = armed
+    }
+}
+
+impl Source for SwapTimer {
+    fn fd(&self) -> RawFd {
+        0
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::TimerMonotonic
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn time_relative(&self) -> u64 {
+        *self.time.borrow()
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        if let Some(mng) = self.mng.borrow().upgrade() {
+            mng.timer_event();
+        }
+        0
+    }
+
+    fn priority(&self) -> i8 {
+        0
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SwapMng;
+    use super::SwapUnitComm;
+    use super::{SwapResult, SwapState};
+    use crate::config::SwapConfig;
+    use nix::sys::wait::WaitStatus;
+    use nix::unistd::Pid;
+    use std::rc::Rc;
+    use sysmaster::exec::ExecContext;
+    use sysmaster::unit::UnitActiveState;
+
+    fn new_mng() -> SwapMng {
+        let _comm = Rc::new(SwapUnitComm::new());
+        let _config = Rc::new(SwapConfig::new(&_comm));
+        SwapMng::new(&_comm, &_config, &Rc::new(ExecContext::new()))
+    }
+
+    #[test]
+    fn test_swap_enter_dead() {
+        let tm = new_mng();
+        tm.enter_dead(SwapResult::Success, false);
+        assert_eq!(tm.state(), SwapState::Dead);
+        tm.enter_dead(SwapResult::FailureExitCode, false);
+        assert_eq!(tm.state(), SwapState::Failed)
+    }
+
+    #[test]
+    fn test_swap_enter_active() {
+        let tm = new_mng();
+        tm.enter_active(SwapResult::Success, false);
+        assert_eq!(tm.state(), SwapState::Active)
+    }
+
+    #[test]
+    fn test_swap_state_to_unit_state() {
+        assert_eq!(
+            SwapState::Activating.swap_state_to_unit_state(),
+            UnitActiveState::UnitActivating
+        );
+        assert_eq!(
+            SwapState::DeactivatingSigkill.swap_state_to_unit_state(),
+            UnitActiveState::UnitDeActivating
+        );
+        assert_eq!(
+            SwapState::Failed.swap_state_to_unit_state(),
+            UnitActiveState::UnitFailed
+        );
+    }
+
+    #[test]
+    fn test_swap_sigchld_result() {
+        let pid = Pid::from_raw(1);
+        assert_eq!(
+            super::sigchld_result(WaitStatus::Exited(pid, 0)),
+            SwapResult::Success
+        );
+        assert_eq!(
+            super::sigchld_result(WaitStatus::Exited(pid, 255)),
+            SwapResult::FailureExitCode
+        );
+    }
+}
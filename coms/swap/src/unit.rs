@@ -0,0 +1,159 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! swap unit is entry of swap type of unit，need impl
+//! UnitObj,UnitMngUtil, UnitSubClass trait
+
+use super::base::PLUGIN_NAME;
+use super::comm::SwapUnitComm;
+use super::config::SwapConfig;
+use super::load::SwapLoad;
+use super::mng::SwapMng;
+use basic::logger;
+use nix::sys::wait::WaitStatus;
+use std::path::PathBuf;
+use std::rc::Rc;
+use sysmaster::error::*;
+use sysmaster::exec::ExecContext;
+use sysmaster::rel::{ReStation, Reliability};
+use sysmaster::unit::{SubUnit, UmIf, UnitActiveState, UnitBase, UnitMngUtil};
+
+struct SwapUnit {
+    comm: Rc<SwapUnitComm>,
+    config: Rc<SwapConfig>,
+    mng: Rc<SwapMng>,
+    load: SwapLoad,
+}
+
+impl ReStation for SwapUnit {
+    // no input, no compensate
+
+    // data
+    fn db_map(&self, reload: bool) {
+        self.config.db_map(reload);
+        self.mng.db_map(reload);
+    }
+
+    fn db_insert(&self) {
+        self.config.db_insert();
+        self.mng.db_insert();
+    }
+
+    // reload: no external connections, entry-only
+    fn entry_coldplug(&self) {
+        // rebuild external connections, like: timer, ...
+        self.mng.entry_coldplug();
+    }
+
+    fn entry_clear(&self) {
+        // release external connection, like: timer, ...
+        self.mng.entry_clear();
+    }
+}
+
+impl SwapUnit {
+    fn new(_um: Rc<dyn UmIf>) -> SwapUnit {
+        let context = Rc::new(ExecContext::new());
+        let _comm = Rc::new(SwapUnitComm::new());
+        let _config = Rc::new(SwapConfig::new(&_comm));
+        let _mng = Rc::new(SwapMng::new(&_comm, &_config, &context));
+        SwapMng::attach_timer(&_mng);
+        SwapUnit {
+            comm: Rc::clone(&_comm),
+            config: Rc::clone(&_config),
+            mng: _mng,
+            load: SwapLoad::new(&_config, &_comm),
+        }
+    }
+}
+
+impl SubUnit for SwapUnit {
+    fn load(&self, paths: Vec<PathBuf>) -> Result<()> {
+        if let Some(u) = self.comm.owner() {
+            u.set_ignore_on_isolate(true)
+        }
+
+        // the swap without config file is only monitored
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        self.config.load(paths, true)?;
+        self.load.verify()?;
+        self.load.swap_add_default_dependencies()
+    }
+
+    fn current_active_state(&self) -> UnitActiveState {
+        self.mng.swap_state_to_unit_state()
+    }
+
+    fn get_subunit_state(&self) -> String {
+        self.mng.get_state()
+    }
+
+    fn attach_unit(&self, unit: Rc<dyn UnitBase>) {
+        self.comm.attach_unit(unit);
+        self.db_insert();
+    }
+
+    fn init(&self) {}
+
+    fn done(&self) {}
+
+    fn dump(&self) {}
+
+    fn start(&self) -> Result<()> {
+        let started = self.mng.start_check()?;
+        if started {
+            log::debug!("swap already in starting, just return immediately");
+            return Ok(());
+        }
+
+        self.mng.start_action();
+
+        Ok(())
+    }
+
+    fn stop(&self, _force: bool) -> Result<()> {
+        self.mng.stop_action();
+        Ok(())
+    }
+
+    fn kill(&self) {}
+
+    fn release_resources(&self) {}
+
+    fn sigchld_events(&self, wait_status: WaitStatus) {
+        self.mng.sigchld_event(wait_status)
+    }
+
+    fn reset_failed(&self) {}
+}
+
+impl UnitMngUtil for SwapUnit {
+    fn attach_um(&self, um: Rc<dyn UmIf>) {
+        self.comm.attach_um(um);
+    }
+
+    fn attach_reli(&self, reli: Rc<Reliability>) {
+        self.comm.attach_reli(reli);
+    }
+}
+
+/*impl Default for SwapUnit {
+    fn default() -> Self {
+        SwapUnit::new()
+    }
+}*/
+
+use sysmaster::declure_unitobj_plugin_with_param;
+declure_unitobj_plugin_with_param!(SwapUnit, SwapUnit::new, PLUGIN_NAME);
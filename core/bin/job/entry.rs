@@ -14,6 +14,7 @@ use super::rentry::{self, JobAttr, JobKind, JobRe};
 use crate::unit::DataManager;
 use crate::unit::JobMode;
 use crate::unit::UnitRelationAtom;
+use crate::unit::UnitType;
 use crate::unit::UnitX;
 use event::{EventState, EventType, Events, Source};
 use std::cell::RefCell;
@@ -24,6 +25,9 @@ use sysmaster::error::*;
 use sysmaster::rel::Reliability;
 use sysmaster::unit::{UnitActiveState, UnitNotifyFlags};
 
+/// the default timeout of the jobs of device units, in seconds
+const DEVICE_JOB_TIMEOUT_SEC: u64 = 90;
+
 #[allow(dead_code)]
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub(crate) enum JobResult {
@@ -269,13 +273,17 @@ impl Job {
     }
 
     pub(super) fn set_timer(&self) {
-        let sec = self
+        let mut sec = self
             .unit()
             .get_config()
             .config_data()
             .borrow()
             .Unit
             .JobTimeoutSec;
+        // Do not wait for a device which never shows up forever.
+        if sec == 0 && self.unit().unit_type() == UnitType::UnitDevice {
+            sec = DEVICE_JOB_TIMEOUT_SEC;
+        }
         // No need to enable timer if JobTimeoutSec is set to 0
         if sec == 0 {
             return;
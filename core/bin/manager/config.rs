@@ -13,6 +13,8 @@
 #![allow(non_snake_case)]
 
 use confique::Config;
+use sysmaster::exec::Rlimit;
+use sysmaster::serialize::DeserializeWith;
 
 pub const SYSTEM_CONFIG: &str = "/etc/sysmaster/system.toml";
 
@@ -29,6 +31,46 @@ pub struct ManagerConfig {
     pub LogTarget: String,
     #[config(default = "")]
     pub LogFile: String,
+
+    #[config(default = 0)]
+    pub RuntimeWatchdogSec: u64,
+    #[config(default = 600)]
+    pub RebootWatchdogSec: u64,
+    #[config(default = "/dev/watchdog0")]
+    pub WatchdogDevice: String,
+
+    #[config(deserialize_with = Rlimit::deserialize_sec)]
+    pub DefaultLimitCPU: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitFSIZE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitDATA: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitSTACK: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitCORE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitRSS: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub DefaultLimitNOFILE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitAS: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub DefaultLimitNPROC: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitMEMLOCK: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub DefaultLimitLOCKS: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub DefaultLimitSIGPENDING: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_bytes)]
+    pub DefaultLimitMSGQUEUE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_nice)]
+    pub DefaultLimitNICE: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_with)]
+    pub DefaultLimitRTPRIO: Option<Rlimit>,
+    #[config(deserialize_with = Rlimit::deserialize_usec)]
+    pub DefaultLimitRTTIME: Option<Rlimit>,
 }
 
 impl ManagerConfig {
@@ -41,6 +83,31 @@ impl ManagerConfig {
             Err(_) => ManagerConfig::default(),
         }
     }
+
+    /// the configured DefaultLimit* with their resources
+    pub fn default_rlimits(&self) -> Vec<(u8, Rlimit)> {
+        [
+            (libc::RLIMIT_CPU, self.DefaultLimitCPU),
+            (libc::RLIMIT_FSIZE, self.DefaultLimitFSIZE),
+            (libc::RLIMIT_DATA, self.DefaultLimitDATA),
+            (libc::RLIMIT_STACK, self.DefaultLimitSTACK),
+            (libc::RLIMIT_CORE, self.DefaultLimitCORE),
+            (libc::RLIMIT_RSS, self.DefaultLimitRSS),
+            (libc::RLIMIT_NOFILE, self.DefaultLimitNOFILE),
+            (libc::RLIMIT_AS, self.DefaultLimitAS),
+            (libc::RLIMIT_NPROC, self.DefaultLimitNPROC),
+            (libc::RLIMIT_MEMLOCK, self.DefaultLimitMEMLOCK),
+            (libc::RLIMIT_LOCKS, self.DefaultLimitLOCKS),
+            (libc::RLIMIT_SIGPENDING, self.DefaultLimitSIGPENDING),
+            (libc::RLIMIT_MSGQUEUE, self.DefaultLimitMSGQUEUE),
+            (libc::RLIMIT_NICE, self.DefaultLimitNICE),
+            (libc::RLIMIT_RTPRIO, self.DefaultLimitRTPRIO),
+            (libc::RLIMIT_RTTIME, self.DefaultLimitRTTIME),
+        ]
+        .into_iter()
+        .filter_map(|(resource, rlimit)| rlimit.map(|r| (resource as u8, r)))
+        .collect()
+    }
 }
 
 impl Default for ManagerConfig {
@@ -51,6 +118,25 @@ impl Default for ManagerConfig {
             LogLevel: log::LevelFilter::Debug,
             LogTarget: "console".to_string(),
             LogFile: String::new(),
+            RuntimeWatchdogSec: 0,
+            RebootWatchdogSec: 600,
+            WatchdogDevice: "/dev/watchdog0".to_string(),
+            DefaultLimitCPU: None,
+            DefaultLimitFSIZE: None,
+            DefaultLimitDATA: None,
+            DefaultLimitSTACK: None,
+            DefaultLimitCORE: None,
+            DefaultLimitRSS: None,
+            DefaultLimitNOFILE: None,
+            DefaultLimitAS: None,
+            DefaultLimitNPROC: None,
+            DefaultLimitMEMLOCK: None,
+            DefaultLimitLOCKS: None,
+            DefaultLimitSIGPENDING: None,
+            DefaultLimitMSGQUEUE: None,
+            DefaultLimitNICE: None,
+            DefaultLimitRTPRIO: None,
+            DefaultLimitRTTIME: None,
         }
     }
 }
@@ -68,5 +154,9 @@ mod test {
         let config = ManagerConfig::new(file.to_str());
         println!("{config:?}");
         assert_eq!(config.DefaultRestartSec, 100);
+        assert_eq!(config.RuntimeWatchdogSec, 0);
+        assert_eq!(config.RebootWatchdogSec, 600);
+        assert_eq!(config.WatchdogDevice, "/dev/watchdog0");
+        assert!(config.default_rlimits().is_empty());
     }
 }
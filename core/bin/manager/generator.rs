@@ -0,0 +1,217 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! run the generators, which convert other configurations like /etc/fstab to units at startup and
+//! daemon-reload. A generator is called with the normal, early and late output directories as arguments.
+
+use basic::path_lookup::LookupPaths;
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use sysmaster::error::*;
+
+/// the directories of the generators, a generator overrides the same named ones in the later directories
+const SYSTEM_GENERATOR_PATHS: [&str; 3] = [
+    "/etc/sysmaster/system-generators",
+    "/run/sysmaster/system-generators",
+    "/usr/lib/sysmaster/system-generators",
+];
+
+/// the generator running longer than this is killed
+const GENERATOR_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// clear the output directories and run all generators one by one
+pub(super) fn run_generators(lookup_path: &LookupPaths) {
+    let outputs = [
+        lookup_path.generator.as_str(),
+        lookup_path.generator_early.as_str(),
+        lookup_path.generator_late.as_str(),
+    ];
+    for dir in outputs {
+        if let Err(e) = reset_dir(dir) {
+            log::error!("Failed to prepare the generator directory {}: {}", dir, e);
+            return;
+        }
+    }
+
+    for (name, path) in find_generators(&SYSTEM_GENERATOR_PATHS) {
+        log::debug!("Running generator {}", name);
+        if let Err(e) = run_one(&path, &outputs) {
+            log::error!("Generator {} failed: {}", name, e);
+        }
+    }
+}
+
+fn reset_dir(dir: &str) -> Result<()> {
+    if Path::new(dir).exists() {
+        fs::remove_dir_all(dir).context(IoSnafu)?;
+    }
+    fs::create_dir_all(dir).context(IoSnafu)
+}
+
+fn find_generators(dirs: &[&str]) -> BTreeMap<String, PathBuf> {
+    let mut res = BTreeMap::new();
+    // the earlier directory is inserted later, to override the same named generator
+    for dir in dirs.iter().rev() {
+        let entries = match fs::read_dir(dir) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let executable = match fs::metadata(&path) {
+                Ok(m) => m.is_file() && m.permissions().mode() & 0o111 != 0,
+                Err(_) => false,
+            };
+            if executable {
+                res.insert(entry.file_name().to_string_lossy().to_string(), path);
+            }
+        }
+    }
+    res
+}
+
+fn run_one(path: &Path, outputs: &[&str]) -> Result<()> {
+    let mut child = Command::new(path)
+        .args(outputs)
+        .stdin(Stdio::null())
+        .spawn()
+        .context(IoSnafu)?;
+
+    match wait_timeout(&mut child, GENERATOR_TIMEOUT) {
+        Ok(Some(status)) if status.success() => Ok(()),
+        Ok(Some(status)) => Err(Error::Other {
+            msg: format!("exited with {status}"),
+        }),
+        Ok(None) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(Error::Other {
+                msg: "timed out".to_string(),
+            })
+        }
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(e)
+        }
+    }
+}
+
+// wait for the child until it exits or the timeout expires, the child is only reaped
+// if it exits, so it's safe to kill it by pid after the timeout
+fn wait_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let pidfd = unsafe { libc::syscall(libc::SYS_pidfd_open, child.id(), 0) } as RawFd;
+    if pidfd < 0 {
+        return Err(Error::Nix {
+            source: Errno::last(),
+        });
+    }
+
+    let deadline = Instant::now() + timeout;
+    let ret = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut fds = [PollFd::new(pidfd, PollFlags::POLLIN)];
+        match poll(&mut fds, left.as_millis().min(i32::MAX as u128) as i32) {
+            Err(Errno::EINTR) => continue,
+            Err(e) => break Err(Error::Nix { source: e }),
+            Ok(0) => break Ok(None),
+            Ok(_) => break child.wait().map(Some).context(IoSnafu),
+        }
+    };
+    let _ = nix::unistd::close(pidfd);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_run_generators() {
+        let root = std::env::temp_dir().join(format!("sysmaster-generator-{}", std::process::id()));
+        let (high, low) = (root.join("etc"), root.join("lib"));
+        fs::create_dir_all(&high).unwrap();
+        fs::create_dir_all(&low).unwrap();
+
+        let write_generator = |dir: &Path, name: &str, unit: &str| {
+            let path = dir.join(name);
+            let mut f = fs::File::create(&path).unwrap();
+            writeln!(f, "#!/bin/sh\ntouch \"$1/{unit}\"").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        write_generator(&low, "foo", "low.mount");
+        write_generator(&high, "foo", "high.mount");
+        write_generator(&low, "bar", "bar.mount");
+        fs::write(low.join("not-executable"), "").unwrap();
+
+        let dirs = [high.to_str().unwrap(), low.to_str().unwrap()];
+        let generators = find_generators(&dirs);
+        assert_eq!(generators.len(), 2);
+        assert_eq!(generators["foo"], high.join("foo"));
+
+        let mut lookup_path = LookupPaths::new();
+        lookup_path.generator = root.join("out").to_string_lossy().to_string();
+        lookup_path.generator_early = root.join("out.early").to_string_lossy().to_string();
+        lookup_path.generator_late = root.join("out.late").to_string_lossy().to_string();
+        fs::create_dir_all(&lookup_path.generator).unwrap();
+        fs::write(root.join("out").join("stale.mount"), "").unwrap();
+        let outputs = [
+            lookup_path.generator.as_str(),
+            lookup_path.generator_early.as_str(),
+            lookup_path.generator_late.as_str(),
+        ];
+        for dir in outputs {
+            reset_dir(dir).unwrap();
+        }
+        for (_, path) in generators {
+            run_one(&path, &outputs).unwrap();
+        }
+
+        let out = root.join("out");
+        assert!(!out.join("stale.mount").exists());
+        assert!(out.join("high.mount").exists());
+        assert!(out.join("bar.mount").exists());
+        assert!(!out.join("low.mount").exists());
+        assert!(Path::new(&lookup_path.generator_late).is_dir());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let mut child = Command::new("/bin/sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        let status = wait_timeout(&mut child, Duration::from_secs(10)).unwrap();
+        assert_eq!(status.unwrap().code(), Some(3));
+
+        let mut child = Command::new("/bin/sleep").arg("10").spawn().unwrap();
+        let start = Instant::now();
+        assert!(wait_timeout(&mut child, Duration::from_millis(100))
+            .unwrap()
+            .is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // not reaped yet
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -17,13 +17,15 @@ use sysmaster::unit::UnitStatus;
 pub(crate) mod alive_timer;
 pub(crate) mod commands;
 pub(crate) mod config;
+mod generator;
 pub(crate) mod pre_install;
 pub(crate) mod rentry;
 pub(crate) mod signals;
+mod watchdog;
 use crate::keep_alive::KeepAlive;
+use crate::mount::finalize;
 use crate::unit::UnitManagerX;
 use basic::path_lookup::LookupPaths;
-use basic::process_util::{self};
 use basic::special::{BASIC_TARGET, CGROUP_SYSMASTER};
 use cgroup::CgController;
 use cgroup::{cg_create_and_attach, CgFlags};
@@ -39,10 +41,13 @@ use std::cell::RefCell;
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::str::FromStr;
 use sysmaster::error::*;
+use sysmaster::exec::ExecDirectoryType;
 use sysmaster::rel::{ReliLastFrame, Reliability};
 
 use alive_timer::AliveTimer;
+use watchdog::ManagerWatchdog;
 
 use self::config::ManagerConfig;
 
@@ -109,6 +114,26 @@ impl ExecuterAction for CommandActionMgr {
         self.um.reload(unit_name)
     }
 
+    fn clean(&self, unit_name: &str, what: &[String]) -> Result<(), Self::Error> {
+        // remove the cache and runtime directories by default, like systemctl
+        let mut kinds = Vec::new();
+        if what.is_empty() {
+            kinds.push(ExecDirectoryType::Cache);
+            kinds.push(ExecDirectoryType::Runtime);
+        }
+        for w in what {
+            if w == "all" {
+                kinds.extend(ExecDirectoryType::ALL);
+                continue;
+            }
+            match ExecDirectoryType::from_str(w) {
+                Ok(kind) => kinds.push(kind),
+                Err(_) => return Err(Error::UnitActionEInval),
+            }
+        }
+        self.um.clean_unit(unit_name, &kinds)
+    }
+
     fn status(&self, unit_name: &str) -> Result<UnitStatus, Self::Error> {
         self.um.get_unit_status(unit_name)
     }
@@ -174,6 +199,7 @@ pub struct Manager {
     um: Rc<UnitManagerX>,
     lookup_path: Rc<LookupPaths>,
     alive_timer: Rc<AliveTimer>,
+    watchdog: Rc<ManagerWatchdog>,
     #[allow(dead_code)]
     config: Rc<ManagerConfig>,
 }
@@ -206,6 +232,7 @@ impl Manager {
 
         let kp = KeepAlive::get_instance();
         let alive_timer = Rc::new(AliveTimer::new(&event, kp.get_fd()));
+        let watchdog = Rc::new(ManagerWatchdog::new(&event, &manager_config));
 
         Manager {
             event,
@@ -221,6 +248,7 @@ impl Manager {
             um,
             lookup_path,
             alive_timer,
+            watchdog,
             config: manager_config,
         }
     }
@@ -271,6 +299,9 @@ impl Manager {
             // Do nothing during the initialization phase or when interrupted by process one before restore is true.
         }
 
+        // generate units before loading any of them
+        self.run_generators();
+
         // preset file before add default job
         self.preset_all().unwrap();
 
@@ -340,6 +371,9 @@ impl Manager {
     }
 
     fn reload(&self) {
+        // regenerate units, which are loaded again below
+        self.run_generators();
+
         // clear data
         self.um.entry_clear();
 
@@ -379,6 +413,9 @@ impl Manager {
 
     fn reboot(&self, reboot_mode: RebootMode) {
         log::debug!("RebootMode: {:?}", reboot_mode);
+        // keep the hardware watchdog running, in case the shutdown hangs
+        self.watchdog.arm_reboot();
+
         // self.start_unit("shutdown.target");
         if let Ok(mut cg_ctrl) = CgController::new("sysmaster", Pid::from_raw(0)) {
             if let Err(e) = cg_ctrl.trim(false) {
@@ -386,12 +423,7 @@ impl Manager {
             }
         }
 
-        let mut pids = process_util::kill_all_pids(15);
-        pids = process_util::wait_pids(pids, 10000000);
-        if !pids.is_empty() {
-            pids = process_util::kill_all_pids(9);
-            process_util::wait_pids(pids, 10000000);
-        }
+        finalize::shutdown_finalize();
 
         log::info!("Rebooting...");
         let _ = reboot::reboot(reboot_mode); // make lint happy
@@ -416,6 +448,9 @@ impl Manager {
         // time
         let timer = Rc::clone(&self.alive_timer);
         timer.enable(true);
+
+        // hardware watchdog
+        self.watchdog.enable();
     }
 }
 
@@ -532,6 +567,14 @@ impl Manager {
         *self.state.borrow()
     }
 
+    fn run_generators(&self) {
+        if self.mode != Mode::System {
+            return;
+        }
+
+        generator::run_generators(&self.lookup_path);
+    }
+
     pub(crate) fn preset_all(&self) -> Result<()> {
         if self.mode != Mode::System {
             return Ok(());
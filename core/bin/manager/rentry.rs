@@ -75,14 +75,33 @@ const RELI_DB_HSOCKET_MNG: &str = "sockmng";
 const RELI_DB_HSOCKETM_FRAME: &str = "sockm-frame";
 /* mount */
 #[allow(dead_code)]
+const RELI_DB_HMOUNT_CONF: &str = "mntconf";
+#[allow(dead_code)]
 const RELI_DB_HMOUNT_MNG: &str = "mntmng";
 #[allow(dead_code)]
 const RELI_DB_HMOUNTM_FRAME: &str = "mntm-frame";
+/* swap */
+#[allow(dead_code)]
+const RELI_DB_HSWAP_CONF: &str = "swapconf";
+#[allow(dead_code)]
+const RELI_DB_HSWAP_MNG: &str = "swapmng";
+#[allow(dead_code)]
+const RELI_DB_HSWAPM_FRAME: &str = "swapm-frame";
+/* automount */
+#[allow(dead_code)]
+const RELI_DB_HAUTOMOUNT_CONF: &str = "amntconf";
+#[allow(dead_code)]
+const RELI_DB_HAUTOMOUNT_MNG: &str = "amntmng";
+/* device */
+#[allow(dead_code)]
+const RELI_DB_HDEVICE_MNG: &str = "devmng";
+#[allow(dead_code)]
+const RELI_DB_HDEVICEM_FRAME: &str = "devm-frame";
 #[allow(dead_code)]
 /* target */
 const RELI_DB_HTARGET_MNG: &str = "tarmng";
 
-pub const RELI_HISTORY_MAX_DBS: u32 = 18;
+pub const RELI_HISTORY_MAX_DBS: u32 = 26;
 #[allow(dead_code)]
 static RELI_HISTORY_DB_NAME: [&str; RELI_HISTORY_MAX_DBS as usize] = [
     RELI_DB_HJOB_TRIGGER,
@@ -100,7 +119,15 @@ static RELI_HISTORY_DB_NAME: [&str; RELI_HISTORY_MAX_DBS as usize] = [
     RELI_DB_HSOCKET_CONF,
     RELI_DB_HSOCKET_MNG,
     RELI_DB_HSOCKETM_FRAME,
+    RELI_DB_HMOUNT_CONF,
     RELI_DB_HMOUNT_MNG,
     RELI_DB_HMOUNTM_FRAME,
+    RELI_DB_HSWAP_CONF,
+    RELI_DB_HSWAP_MNG,
+    RELI_DB_HSWAPM_FRAME,
+    RELI_DB_HAUTOMOUNT_CONF,
+    RELI_DB_HAUTOMOUNT_MNG,
+    RELI_DB_HDEVICE_MNG,
+    RELI_DB_HDEVICEM_FRAME,
     RELI_DB_HTARGET_MNG,
 ];
@@ -0,0 +1,358 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+use super::config::ManagerConfig;
+use event::{EventState, EventType, Events, Source};
+use std::cell::RefCell;
+use std::os::unix::prelude::RawFd;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+use watchdog::{HardwareWatchdog, Watchdog};
+
+/// feed the hardware watchdog from the event loop
+pub(super) struct ManagerWatchdog {
+    sub: Rc<ManagerWatchdogSub>,
+}
+
+impl ManagerWatchdog {
+    pub(super) fn new(eventr: &Rc<Events>, config: &ManagerConfig) -> ManagerWatchdog {
+        let mut device = HardwareWatchdog::default();
+        device.set_device(config.WatchdogDevice.clone());
+        ManagerWatchdog {
+            sub: ManagerWatchdogSub::new(
+                eventr,
+                Box::new(device),
+                &config.WatchdogDevice,
+                Duration::from_secs(config.RuntimeWatchdogSec),
+                Duration::from_secs(config.RebootWatchdogSec),
+            ),
+        }
+    }
+
+    /// configure the device with RuntimeWatchdogSec and start feeding it
+    pub(super) fn enable(&self) {
+        self.sub.enable_runtime();
+    }
+
+    /// switch the device to RebootWatchdogSec, and keep it armed across the shutdown
+    pub(super) fn arm_reboot(&self) {
+        self.sub.arm_reboot();
+    }
+}
+
+struct ManagerWatchdogSub {
+    // associated objects
+    event: Rc<Events>,
+
+    // owned objects
+    data: Rc<ManagerWatchdogData>,
+    device_path: String,
+    runtime: Duration,
+    reboot: Duration,
+}
+
+impl ManagerWatchdogSub {
+    fn new(
+        eventr: &Rc<Events>,
+        device: Box<dyn Watchdog>,
+        device_path: &str,
+        runtime: Duration,
+        reboot: Duration,
+    ) -> Rc<ManagerWatchdogSub> {
+        let sub = Rc::new(ManagerWatchdogSub {
+            event: Rc::clone(eventr),
+            data: Rc::new(ManagerWatchdogData::new(device)),
+            device_path: device_path.to_string(),
+            runtime,
+            reboot,
+        });
+        sub.data.set_sub(&sub);
+        sub
+    }
+
+    fn enable_runtime(&self) {
+        if self.runtime.is_zero() {
+            return;
+        }
+
+        if let Err(e) = self.data.config(self.runtime) {
+            log::error!(
+                "Failed to configure the hardware watchdog {}: {e}",
+                self.device_path
+            );
+            return;
+        }
+
+        let timeout = self.data.timeout().unwrap_or(self.runtime);
+        log::info!(
+            "Hardware watchdog {} armed with timeout {}s",
+            self.device_path,
+            timeout.as_secs()
+        );
+
+        // feed the watchdog at half of its timeout, so one delayed loop is tolerable.
+        self.data.set_interval(timeout / 2);
+        let source = Rc::clone(&self.data);
+        if let Err(e) = self.event.add_source(source) {
+            log::error!("Failed to add the hardware watchdog timer: {e}");
+            return;
+        }
+        self.enable_timer(true);
+    }
+
+    fn enable_timer(&self, enable: bool) -> i32 {
+        let source = Rc::clone(&self.data);
+        let state = match enable {
+            true => EventState::OneShot,
+            false => EventState::Off,
+        };
+        self.event.set_enabled(source, state).unwrap_or(-1)
+    }
+
+    fn arm_reboot(&self) {
+        if self.data.interval() != 0 {
+            self.enable_timer(false);
+        }
+
+        if self.reboot.is_zero() {
+            // RebootWatchdogSec=0 means no watchdog across the shutdown
+            if let Err(e) = self.data.close() {
+                log::warn!("Failed to disable the hardware watchdog: {e}");
+            }
+            return;
+        }
+
+        // Do not arm a watchdog that was never used on a machine without one.
+        if self.runtime.is_zero() && !Path::new(&self.device_path).exists() {
+            return;
+        }
+
+        if let Err(e) = self.data.config(self.reboot) {
+            log::warn!(
+                "Failed to set the hardware watchdog {} to the reboot timeout: {e}",
+                self.device_path
+            );
+            return;
+        }
+        log::info!(
+            "Hardware watchdog {} armed with reboot timeout {}s",
+            self.device_path,
+            self.reboot.as_secs()
+        );
+        self.data.release();
+    }
+}
+
+struct ManagerWatchdogData {
+    // associated objects
+    sub: RefCell<Weak<ManagerWatchdogSub>>,
+
+    // owned objects
+    device: RefCell<Box<dyn Watchdog>>,
+    interval: RefCell<u64>,
+}
+
+impl ManagerWatchdogData {
+    pub(self) fn new(device: Box<dyn Watchdog>) -> ManagerWatchdogData {
+        ManagerWatchdogData {
+            sub: RefCell::new(Weak::new()),
+            device: RefCell::new(device),
+            interval: RefCell::new(0),
+        }
+    }
+
+    pub(self) fn set_sub(&self, sub: &Rc<ManagerWatchdogSub>) {
+        self.sub.replace(Rc::downgrade(sub));
+    }
+
+    pub(self) fn sub(&self) -> Rc<ManagerWatchdogSub> {
+        self.sub.clone().into_inner().upgrade().unwrap()
+    }
+
+    pub(self) fn config(&self, timeout: Duration) -> std::io::Result<()> {
+        self.device.borrow_mut().config(Some(timeout))
+    }
+
+    pub(self) fn timeout(&self) -> Option<Duration> {
+        self.device.borrow().timeout()
+    }
+
+    pub(self) fn close(&self) -> std::io::Result<i32> {
+        self.device.borrow_mut().close()
+    }
+
+    pub(self) fn release(&self) {
+        self.device.borrow_mut().release();
+    }
+
+    pub(self) fn set_interval(&self, interval: Duration) {
+        *self.interval.borrow_mut() = interval.as_micros() as u64;
+    }
+
+    pub(self) fn interval(&self) -> u64 {
+        *self.interval.borrow()
+    }
+
+    fn feed(&self) {
+        if let Err(e) = self.device.borrow_mut().feed() {
+            log::error!("Failed to feed the hardware watchdog: {e}");
+        }
+    }
+}
+
+impl Source for ManagerWatchdogData {
+    fn fd(&self) -> RawFd {
+        0
+    }
+
+    fn event_type(&self) -> EventType {
+        EventType::TimerMonotonic
+    }
+
+    fn epoll_event(&self) -> u32 {
+        (libc::EPOLLIN) as u32
+    }
+
+    fn priority(&self) -> i8 {
+        0i8
+    }
+
+    fn time_relative(&self) -> u64 {
+        self.interval()
+    }
+
+    fn dispatch(&self, _: &Events) -> i32 {
+        self.feed();
+        self.sub().enable_timer(true)
+    }
+
+    fn token(&self) -> u64 {
+        let data: u64 = unsafe { std::mem::transmute(self) };
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[derive(Default)]
+    struct FakeWatchdogState {
+        timeout: Option<Duration>,
+        feeds: u32,
+        released: bool,
+        closed: bool,
+    }
+
+    // behaves like a watchdog character device, and records what was done to it
+    struct FakeWatchdog {
+        state: Rc<RefCell<FakeWatchdogState>>,
+    }
+
+    impl Watchdog for FakeWatchdog {
+        fn config(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+            let mut state = self.state.borrow_mut();
+            state.timeout = timeout;
+            state.feeds += 1;
+            Ok(())
+        }
+
+        fn close(&mut self) -> io::Result<i32> {
+            let mut state = self.state.borrow_mut();
+            state.timeout = None;
+            state.closed = true;
+            Ok(0)
+        }
+
+        fn feed(&mut self) -> io::Result<()> {
+            self.state.borrow_mut().feeds += 1;
+            Ok(())
+        }
+
+        fn timeout(&self) -> Option<Duration> {
+            self.state.borrow().timeout
+        }
+
+        fn release(&mut self) {
+            self.state.borrow_mut().released = true;
+        }
+    }
+
+    fn create_watchdog(
+        runtime: u64,
+        reboot: u64,
+    ) -> (
+        Rc<Events>,
+        Rc<ManagerWatchdogSub>,
+        Rc<RefCell<FakeWatchdogState>>,
+    ) {
+        let event = Rc::new(Events::new().unwrap());
+        let state = Rc::new(RefCell::new(FakeWatchdogState::default()));
+        let device = Box::new(FakeWatchdog {
+            state: Rc::clone(&state),
+        });
+        let sub = ManagerWatchdogSub::new(
+            &event,
+            device,
+            "/dev/null",
+            Duration::from_millis(runtime),
+            Duration::from_millis(reboot),
+        );
+        (event, sub, state)
+    }
+
+    #[test]
+    fn test_runtime_watchdog_feed() {
+        let (event, sub, state) = create_watchdog(20, 600);
+        sub.enable_runtime();
+        assert_eq!(state.borrow().timeout, Some(Duration::from_millis(20)));
+        assert_eq!(sub.data.interval(), 10000);
+
+        // configuring the device counts as the first feed
+        assert_eq!(state.borrow().feeds, 1);
+
+        // each dispatch of the timer feeds the device and rearms the timer,
+        // the source is dispatched directly so the test does not depend on timing
+        for feeds in 2..5 {
+            assert!(sub.data.dispatch(&event) >= 0);
+            assert_eq!(state.borrow().feeds, feeds);
+        }
+        assert!(!state.borrow().released);
+    }
+
+    #[test]
+    fn test_runtime_watchdog_disabled() {
+        let (_event, sub, state) = create_watchdog(0, 600);
+        sub.enable_runtime();
+        assert_eq!(state.borrow().timeout, None);
+        assert_eq!(state.borrow().feeds, 0);
+        assert_eq!(sub.data.interval(), 0);
+    }
+
+    #[test]
+    fn test_reboot_watchdog() {
+        let (_event, sub, state) = create_watchdog(20, 600);
+        sub.enable_runtime();
+        sub.arm_reboot();
+        assert_eq!(state.borrow().timeout, Some(Duration::from_millis(600)));
+        assert!(state.borrow().released);
+        assert!(!state.borrow().closed);
+
+        let (_event, sub, state) = create_watchdog(20, 0);
+        sub.enable_runtime();
+        sub.arm_reboot();
+        assert!(state.borrow().closed);
+        assert!(!state.borrow().released);
+    }
+}
@@ -0,0 +1,346 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! the final stage of the shutdown, run after all units are stopped and just before reboot(2):
+//! kill the remaining processes, then unmount or remount read-only the file systems, turn off the
+//! swaps, detach the loop devices and remove the device-mapper devices until nothing changes,
+//! and finally sync the file systems.
+use super::setup::mount_point_is_api;
+use basic::mount_util::{parse_mountinfo, parse_proc_swaps};
+use basic::parse_util::parse_devnum;
+use basic::process_util;
+use basic::virtualize::{self, Virtualization};
+use nix::fcntl::{self, OFlag};
+use nix::mount::{self, MntFlags, MsFlags};
+use nix::sys::stat::{self, Mode};
+use nix::unistd;
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use sysmaster::error::*;
+
+/// give up after so many rounds, in case some of the resources are never released
+const FINALIZE_ATTEMPTS: u32 = 50;
+
+/// how long to wait for the processes to exit after the signal, in microseconds
+const KILL_WAIT_USEC: u64 = 10000000;
+
+const PROC_MOUNTINFO: &str = "/proc/self/mountinfo";
+const PROC_SWAPS: &str = "/proc/swaps";
+const SYS_BLOCK: &str = "/sys/block";
+const DM_CONTROL: &str = "/dev/mapper/control";
+
+const DM_IOCTL: u8 = 0xfd;
+const DM_DEV_REMOVE_CMD: u8 = 4;
+const DM_VERSION_MAJOR: u32 = 4;
+const DM_NAME_LEN: usize = 128;
+const DM_UUID_LEN: usize = 129;
+
+nix::ioctl_none!(loop_clr_fd, 0x4c, 0x01);
+nix::ioctl_readwrite!(dm_dev_remove, DM_IOCTL, DM_DEV_REMOVE_CMD, DmIoctl);
+
+/// struct dm_ioctl of linux/dm-ioctl.h
+#[repr(C)]
+struct DmIoctl {
+    version: [u32; 3],
+    data_size: u32,
+    data_start: u32,
+    target_count: u32,
+    open_count: i32,
+    flags: u32,
+    event_nr: u32,
+    padding: u32,
+    dev: u64,
+    name: [u8; DM_NAME_LEN],
+    uuid: [u8; DM_UUID_LEN],
+    data: [u8; 7],
+}
+
+/// a resource which is released in the final stage
+#[derive(Clone, Copy, Debug)]
+enum Resource {
+    Mount,
+    Swap,
+    Loop,
+    DeviceMapper,
+}
+
+/// the result of one round of releasing a kind of resource
+struct Progress {
+    changed: bool,
+    left: usize,
+}
+
+/// kill the remaining processes, and release everything which may keep the disks busy
+pub fn shutdown_finalize() {
+    kill_remaining_processes();
+
+    // nothing but the mount points are ours in the container
+    let mut resources = vec![Resource::Mount];
+    if virtualize::detect_container() == Virtualization::None {
+        resources.extend([Resource::Swap, Resource::Loop, Resource::DeviceMapper]);
+    }
+
+    for _ in 0..FINALIZE_ATTEMPTS {
+        let mut changed = false;
+        let mut left = Vec::new();
+        for resource in resources {
+            match release(resource) {
+                Ok(p) => {
+                    changed |= p.changed;
+                    if p.left > 0 {
+                        log::debug!("{} {:?} resources are still busy", p.left, resource);
+                        left.push(resource);
+                    }
+                }
+                Err(e) => log::error!("Failed to release {:?} resources: {}", resource, e),
+            }
+        }
+
+        resources = left;
+        // releasing one kind of resource may release the others, try again if anything changed
+        if resources.is_empty() || !changed {
+            break;
+        }
+    }
+
+    if !resources.is_empty() {
+        log::warn!("Failed to release {:?}, ignoring", resources);
+    }
+
+    log::info!("Syncing filesystems and block devices.");
+    unistd::sync();
+}
+
+/// send SIGTERM to all processes, then SIGKILL to the ones still alive
+fn kill_remaining_processes() {
+    let mut pids = process_util::kill_all_pids(libc::SIGTERM);
+    pids = process_util::wait_pids(pids, KILL_WAIT_USEC);
+    if !pids.is_empty() {
+        pids = process_util::kill_all_pids(libc::SIGKILL);
+        process_util::wait_pids(pids, KILL_WAIT_USEC);
+    }
+}
+
+fn release(resource: Resource) -> Result<Progress> {
+    match resource {
+        Resource::Mount => umount_all(),
+        Resource::Swap => swapoff_all(),
+        Resource::Loop => loop_detach_all(),
+        Resource::DeviceMapper => dm_detach_all(),
+    }
+}
+
+/// unmount the mount points from the deepest one, remount them read-only if they are busy
+fn umount_all() -> Result<Progress> {
+    let content = fs::read_to_string(PROC_MOUNTINFO).context(IoSnafu)?;
+    let mut progress = Progress {
+        changed: false,
+        left: 0,
+    };
+
+    // the mount points are listed after the ones they are mounted on
+    for (path, flags) in parse_mountinfo(&content).iter().rev() {
+        if mount_point_is_api(path) {
+            continue;
+        }
+
+        // the root file system can't be unmounted by us
+        if path != Path::new("/") {
+            log::info!("Unmounting {:?}.", path);
+            match mount::umount2(path, MntFlags::MNT_FORCE) {
+                Ok(_) => {
+                    progress.changed = true;
+                    continue;
+                }
+                Err(e) => log::debug!("Failed to unmount {:?}: {}", path, e),
+            }
+        }
+
+        if flags.contains(MsFlags::MS_RDONLY) {
+            continue;
+        }
+
+        log::info!("Remounting {:?} read-only.", path);
+        match mount::mount::<str, Path, str, str>(
+            None,
+            path,
+            None,
+            MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+            None,
+        ) {
+            Ok(_) => progress.changed = true,
+            Err(e) => {
+                log::warn!("Failed to remount {:?} read-only: {}", path, e);
+                progress.left += 1;
+            }
+        }
+    }
+
+    Ok(progress)
+}
+
+/// turn off the swaps listed in /proc/swaps
+fn swapoff_all() -> Result<Progress> {
+    let content = fs::read_to_string(PROC_SWAPS).context(IoSnafu)?;
+    let mut progress = Progress {
+        changed: false,
+        left: 0,
+    };
+
+    for what in parse_proc_swaps(&content) {
+        log::info!("Deactivating swap {}.", what);
+        let path = CString::new(what.as_str()).map_err(|e| Error::Other {
+            msg: format!("invalid swap path {what}: {e}"),
+        })?;
+        if unsafe { libc::swapoff(path.as_ptr()) } == 0 {
+            progress.changed = true;
+        } else {
+            log::warn!(
+                "Failed to deactivate swap {}: {}",
+                what,
+                nix::errno::Errno::last()
+            );
+            progress.left += 1;
+        }
+    }
+
+    Ok(progress)
+}
+
+/// detach the loop devices with a backing file, except the one the root file system is on
+fn loop_detach_all() -> Result<Progress> {
+    let mut progress = Progress {
+        changed: false,
+        left: 0,
+    };
+
+    for (name, devnum) in block_devices("loop")? {
+        if !Path::new(SYS_BLOCK)
+            .join(&name)
+            .join("loop/backing_file")
+            .exists()
+        {
+            continue;
+        }
+        if is_root_device(devnum) {
+            log::debug!("Skipping the root loop device {}", name);
+            continue;
+        }
+
+        log::info!("Detaching loop device /dev/{}.", name);
+        let ret = with_device(&format!("/dev/{name}"), OFlag::O_RDONLY, |fd| {
+            unsafe { loop_clr_fd(fd) }.context(NixSnafu)
+        });
+        match ret {
+            Ok(_) => progress.changed = true,
+            Err(e) => {
+                log::warn!("Failed to detach loop device /dev/{}: {}", name, e);
+                progress.left += 1;
+            }
+        }
+    }
+
+    Ok(progress)
+}
+
+/// remove the device-mapper devices, except the one the root file system is on
+fn dm_detach_all() -> Result<Progress> {
+    let mut progress = Progress {
+        changed: false,
+        left: 0,
+    };
+
+    for (name, devnum) in block_devices("dm-")? {
+        if is_root_device(devnum) {
+            log::debug!("Skipping the root device-mapper device {}", name);
+            continue;
+        }
+
+        log::info!("Removing device-mapper device /dev/{}.", name);
+        let ret = with_device(DM_CONTROL, OFlag::O_RDWR, |fd| {
+            let mut dm = dm_ioctl_new(devnum);
+            unsafe { dm_dev_remove(fd, &mut dm) }.context(NixSnafu)
+        });
+        match ret {
+            Ok(_) => progress.changed = true,
+            Err(e) => {
+                log::warn!("Failed to remove device-mapper device /dev/{}: {}", name, e);
+                progress.left += 1;
+            }
+        }
+    }
+
+    Ok(progress)
+}
+
+fn dm_ioctl_new(devnum: u64) -> DmIoctl {
+    DmIoctl {
+        version: [DM_VERSION_MAJOR, 0, 0],
+        data_size: std::mem::size_of::<DmIoctl>() as u32,
+        data_start: 0,
+        target_count: 0,
+        open_count: 0,
+        flags: 0,
+        event_nr: 0,
+        padding: 0,
+        dev: devnum,
+        name: [0; DM_NAME_LEN],
+        uuid: [0; DM_UUID_LEN],
+        data: [0; 7],
+    }
+}
+
+/// open the device, run the ioctl and close it
+fn with_device<F>(path: &str, flags: OFlag, f: F) -> Result<i32>
+where
+    F: FnOnce(RawFd) -> Result<i32>,
+{
+    let fd = fcntl::open(path, flags | OFlag::O_CLOEXEC, Mode::empty()).context(NixSnafu)?;
+    let ret = f(fd);
+    let _ = unistd::close(fd);
+    ret
+}
+
+/// the names and the device numbers of the block devices with the prefix in /sys/block
+fn block_devices(prefix: &str) -> Result<Vec<(String, u64)>> {
+    let mut devices = Vec::new();
+    for entry in fs::read_dir(SYS_BLOCK).context(IoSnafu)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) {
+            continue;
+        }
+        let dev = match fs::read_to_string(entry.path().join("dev")) {
+            Ok(dev) => dev,
+            Err(_) => continue,
+        };
+        if let Ok(devnum) = parse_devnum(dev.trim().to_string()) {
+            devices.push((name, devnum));
+        }
+    }
+    Ok(devices)
+}
+
+fn is_root_device(devnum: u64) -> bool {
+    stat::stat("/").is_ok_and(|st| st.st_dev == devnum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dm_ioctl_size() {
+        assert_eq!(std::mem::size_of::<DmIoctl>(), 312);
+    }
+}
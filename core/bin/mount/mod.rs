@@ -26,5 +26,11 @@
 //! Create a directory sysmaster in the /sys/fs/cgroup/ directory that does not belong to any cgroup subsystem, and mount it as a file system type cgroup.
 //!
 //! Read the /proc/cgroups directory, query the subsystems supported by the current system, and mount the corresponding subsystem type in the /sys/fs/cgroup directory.
+//!
+//! # Release the file systems before reboot
+//!
+//! After all units are stopped, kill the remaining processes, unmount or remount read-only the file systems except the ones mounted above,
+//! turn off the swaps, detach the loop devices, remove the device-mapper devices, and sync.
 
+pub mod finalize;
 pub mod setup;
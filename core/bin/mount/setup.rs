@@ -395,6 +395,14 @@ pub fn mount_setup() -> Result<()> {
     Ok(())
 }
 
+/// the mount points set up by mount_setup and the ones under them are the api file systems,
+/// they are kept until reboot.
+pub fn mount_point_is_api(path: &Path) -> bool {
+    MOUNT_TABLE
+        .iter()
+        .any(|m| path.starts_with(&m.target) && m.target != "/")
+}
+
 /// mount all the cgroup controller subsystem
 pub fn mount_cgroup_controllers() -> Result<()> {
     if !cg_legacy_wanted() {
@@ -10,7 +10,9 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
-use crate::unit::rentry::{UeConfigInstall, UeConfigUnit, UnitLoadState, UnitRe, UnitRePps};
+use crate::unit::rentry::{
+    UeConfigInstall, UeConfigUnit, UnitLoadState, UnitRe, UnitReFirewall, UnitRePps,
+};
 use nix::unistd::Pid;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -81,11 +83,11 @@ impl UeBase {
         self.rentry.conf_get(&self.id)
     }
 
-    pub(super) fn rentry_cgroup_insert(&self, cg_path: &Path) {
-        self.rentry.cgroup_insert(&self.id, cg_path);
+    pub(super) fn rentry_cgroup_insert(&self, cg_path: &Path, firewall: Option<&UnitReFirewall>) {
+        self.rentry.cgroup_insert(&self.id, cg_path, firewall);
     }
 
-    pub(super) fn rentry_cgroup_get(&self) -> Option<PathBuf> {
+    pub(super) fn rentry_cgroup_get(&self) -> Option<(PathBuf, Option<UnitReFirewall>)> {
         self.rentry.cgroup_get(&self.id)
     }
 
@@ -11,6 +11,8 @@
 // See the Mulan PSL v2 for more details.
 
 use super::base::UeBase;
+use crate::unit::rentry::UnitReFirewall;
+use cgroup::{IpCounters, IpFirewall, IpFirewallIds, IpPrefix};
 use nix::NixPath;
 use std::rc::Rc;
 use std::{cell::RefCell, path::PathBuf};
@@ -51,7 +53,9 @@ impl UeCgroup {
     }
 
     pub(super) fn prepare_cg_exec(&self) -> Result<()> {
-        self.data.borrow_mut().prepare_cg_exec()
+        let ret = self.data.borrow_mut().prepare_cg_exec();
+        self.db_update();
+        ret
     }
 
     pub(super) fn cg_path(&self) -> PathBuf {
@@ -59,6 +63,44 @@ impl UeCgroup {
 
         cg_path
     }
+
+    pub(super) fn set_ip_access(&self, access: IpAccess) {
+        self.data.borrow_mut().ip_access = access;
+    }
+
+    pub(super) fn ip_counters(&self) -> Option<IpCounters> {
+        self.data.borrow().ip_counters()
+    }
+}
+
+/// IPAddressAllow, IPAddressDeny and IPAccounting of the unit
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct IpAccess {
+    pub(super) allow: Vec<IpPrefix>,
+    pub(super) deny: Vec<IpPrefix>,
+    pub(super) accounting: bool,
+}
+
+impl IpAccess {
+    fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty() && !self.accounting
+    }
+}
+
+/// format the bytes like "1.5K"
+pub(super) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1}{}", UNITS[unit])
 }
 
 struct UnitCgroupData {
@@ -67,6 +109,9 @@ struct UnitCgroupData {
 
     // owned objects
     cg_path: PathBuf,
+    ip_access: IpAccess,
+    // the programs are kept while the configuration is not changed, so are the counters
+    firewall: Option<(IpAccess, IpFirewall)>,
 }
 
 impl UnitCgroupData {
@@ -74,12 +119,45 @@ impl UnitCgroupData {
         UnitCgroupData {
             base: Rc::clone(baser),
             cg_path: PathBuf::from(""),
+            ip_access: IpAccess::default(),
+            firewall: None,
         }
     }
 
     pub(self) fn db_map(&mut self) {
-        if let Some(cg_path) = self.base.rentry_cgroup_get() {
+        if let Some((cg_path, firewall)) = self.base.rentry_cgroup_get() {
             self.cg_path = cg_path;
+            if self.firewall.is_none() {
+                if let Some(re) = firewall {
+                    self.adopt_firewall(&re);
+                }
+            }
+        }
+    }
+
+    // take over the programs attached before the reexecution, they are not attached again
+    fn adopt_firewall(&mut self, re: &UnitReFirewall) {
+        let parse = |prefixes: &[String]| {
+            prefixes
+                .iter()
+                .filter_map(|p| p.parse::<IpPrefix>().ok())
+                .collect::<Vec<_>>()
+        };
+        let access = IpAccess {
+            allow: parse(&re.allow),
+            deny: parse(&re.deny),
+            accounting: re.accounting,
+        };
+        let ids = IpFirewallIds {
+            progs: re.progs.clone(),
+            accounting: re.accounting_map,
+        };
+        match IpFirewall::adopt(&self.cg_path, &ids) {
+            Ok(firewall) => self.firewall = Some((access, firewall)),
+            Err(e) => log::warn!(
+                "Failed to take over the IP firewall of {}, it will be installed again: {e}",
+                self.base.id()
+            ),
         }
     }
 
@@ -100,8 +178,43 @@ impl UnitCgroupData {
     pub(self) fn prepare_cg_exec(&mut self) -> Result<()> {
         log::debug!("cgroup: prepare cg exec");
         cgroup::cg_create(&self.cg_path).context(CgroupSnafu)?;
+        self.setup_firewall()
+    }
 
-        Ok(())
+    // the access control is not optional, the command must not run without it
+    fn setup_firewall(&mut self) -> Result<()> {
+        if matches!(&self.firewall, Some((access, _)) if *access == self.ip_access) {
+            return Ok(());
+        }
+        // detach the programs of the old configuration
+        self.firewall = None;
+        if self.ip_access.is_empty() {
+            return Ok(());
+        }
+
+        let access = &self.ip_access;
+        match IpFirewall::install(
+            &self.cg_path,
+            &access.allow,
+            &access.deny,
+            access.accounting,
+        ) {
+            Ok(firewall) => {
+                self.firewall = Some((access.clone(), firewall));
+                Ok(())
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to install the IP firewall of {}, the kernel may not support cgroup BPF: {e}",
+                    self.base.id()
+                );
+                Err(Error::Cgroup { source: e })
+            }
+        }
+    }
+
+    pub(self) fn ip_counters(&self) -> Option<IpCounters> {
+        self.firewall.as_ref()?.1.counters()
     }
 
     pub(self) fn cg_path(&self) -> PathBuf {
@@ -109,6 +222,32 @@ impl UnitCgroupData {
     }
 
     pub(self) fn db_insert(&self) {
-        self.base.rentry_cgroup_insert(&self.cg_path);
+        let firewall = self.firewall.as_ref().and_then(|(access, firewall)| {
+            let ids = firewall.ids().ok()?;
+            Some(UnitReFirewall {
+                allow: access.allow.iter().map(|p| p.to_string()).collect(),
+                deny: access.deny.iter().map(|p| p.to_string()).collect(),
+                accounting: access.accounting,
+                progs: ids.progs,
+                accounting_map: ids.accounting,
+            })
+        });
+        self.base
+            .rentry_cgroup_insert(&self.cg_path, firewall.as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_bytes;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0B");
+        assert_eq!(format_bytes(1023), "1023B");
+        assert_eq!(format_bytes(1536), "1.5K");
+        assert_eq!(format_bytes(3 << 20), "3.0M");
+        assert_eq!(format_bytes(1 << 40), "1.0T");
+        assert_eq!(format_bytes(u64::MAX), "16777216.0T");
     }
 }
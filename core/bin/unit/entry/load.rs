@@ -134,6 +134,15 @@ impl UeLoad {
         Ok(())
     }
 
+    /// whether the unit has a fragment file in the lookup paths
+    pub(super) fn has_fragment(&self) -> bool {
+        self.file.build_name_map(
+            self.base.id().clone(),
+            self.load_state() == UnitLoadState::Loaded,
+        );
+        !self.get_unit_id_fragment_pathbuf().is_empty()
+    }
+
     pub(super) fn set_in_target_dep_queue(&self, t: bool) {
         self.in_target_dep_queue.replace(t);
         if t {
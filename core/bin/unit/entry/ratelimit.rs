@@ -10,7 +10,9 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
-use std::{cell::RefCell, time::Instant};
+use basic::ratelimit::RateLimit;
+use basic::time_util::USEC_PER_SEC;
+use std::cell::RefCell;
 
 pub(super) struct StartLimit {
     hit: RefCell<bool>,
@@ -47,73 +49,6 @@ impl StartLimit {
     pub(super) fn init_from_config(&self, interval: u64, burst: u32) {
         self.start_limit
             .borrow_mut()
-            .init_from_config(interval, burst);
-    }
-}
-
-struct RateLimit {
-    interval: u64,
-    burst: u32,
-    begin: Option<Instant>,
-    nums: u32,
-}
-
-impl RateLimit {
-    fn new(interval: u64, burst: u32) -> Self {
-        RateLimit {
-            interval,
-            burst,
-            begin: None,
-            nums: 0,
-        }
-    }
-
-    fn ratelimit_below(&mut self) -> bool {
-        if !self.ratelimit_enabled() {
-            return true;
-        }
-
-        let now = Instant::now();
-        if self.begin.is_none() || now.duration_since(self.begin.unwrap()).as_secs() > self.interval
-        {
-            self.begin = Some(now);
-            self.nums = 1;
-            return true;
-        }
-
-        if self.nums < self.burst {
-            self.nums += 1;
-            return true;
-        }
-
-        false
-    }
-
-    fn ratelimit_enabled(&self) -> bool {
-        if self.interval > 0 && self.burst > 0 {
-            return true;
-        }
-
-        false
-    }
-
-    pub(super) fn init_from_config(&mut self, interval: u64, burst: u32) {
-        self.interval = interval;
-        self.burst = burst;
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::RateLimit;
-    #[test]
-    fn test_ratelimit() {
-        let mut tmp = RateLimit::new(0, 0);
-        assert!(tmp.ratelimit_below());
-
-        let mut tmp2 = RateLimit::new(3, 2);
-        assert!(tmp2.ratelimit_below());
-        assert!(tmp2.ratelimit_below());
-        assert!(!tmp2.ratelimit_below());
+            .init_from_config(interval.saturating_mul(USEC_PER_SEC), burst);
     }
 }
@@ -11,7 +11,7 @@
 // See the Mulan PSL v2 for more details.
 
 use super::base::UeBase;
-use super::cgroup::UeCgroup;
+use super::cgroup::{format_bytes, IpAccess, UeCgroup};
 use super::child::UeChild;
 use super::condition::{assert_keys::*, condition_keys::*, UeCondition};
 use super::config::UeConfig;
@@ -22,7 +22,7 @@ use crate::unit::data::{DataManager, UnitState};
 use crate::unit::rentry::{UnitLoadState, UnitRe};
 use crate::unit::util::UnitFile;
 use basic::process_util::{self, my_child};
-use cgroup::{self, CgFlags};
+use cgroup::{self, CgFlags, IpPrefix};
 use nix::sys::socket::UnixCredentials;
 use nix::sys::wait::WaitStatus;
 use nix::unistd::Pid;
@@ -33,6 +33,7 @@ use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 use std::rc::Rc;
 use sysmaster::error::*;
+use sysmaster::exec::ExecDirectoryType;
 use sysmaster::rel::ReStation;
 use sysmaster::unit::{KillContext, KillMode, KillOperation, UnitNotifyFlags};
 use sysmaster::unit::{SubUnit, UnitActiveState, UnitBase, UnitType};
@@ -156,6 +157,10 @@ impl UnitBase for Unit {
         self.cg_path()
     }
 
+    fn set_ip_access(&self, allow: Vec<IpPrefix>, deny: Vec<IpPrefix>, accounting: bool) {
+        self.set_ip_access(allow, deny, accounting)
+    }
+
     fn ignore_on_isolate(&self) -> bool {
         self.ignore_on_isolate()
     }
@@ -495,6 +500,33 @@ impl Unit {
         self.sub.get_subunit_state()
     }
 
+    /// return the extra status lines of the sub unit
+    pub fn get_status_extra(&self) -> Vec<(String, String)> {
+        let mut extra = self.sub.get_status_extra();
+        if let Some(c) = self.cgroup.ip_counters() {
+            extra.push((
+                "IP:".to_string(),
+                format!(
+                    "{} in ({} packets), {} out ({} packets)",
+                    format_bytes(c.ingress_bytes),
+                    c.ingress_packets,
+                    format_bytes(c.egress_bytes),
+                    c.egress_packets
+                ),
+            ));
+        }
+        extra
+    }
+
+    /// set IPAddressAllow, IPAddressDeny and IPAccounting, applied when preparing the cgroup
+    pub fn set_ip_access(&self, allow: Vec<IpPrefix>, deny: Vec<IpPrefix>, accounting: bool) {
+        self.cgroup.set_ip_access(IpAccess {
+            allow,
+            deny,
+            accounting,
+        });
+    }
+
     /// test start rate, if start more than burst times in interval time, return error
     fn test_start_limit(&self) -> bool {
         if self.config.config_data().borrow().Unit.StartLimitInterval > 0
@@ -544,9 +576,14 @@ impl Unit {
 
     pub(super) fn load_unit(&self) -> Result<()> {
         self.set_in_load_queue(false);
-        // Mount unit doesn't have config file, set its loadstate to
-        // UnitLoaded directly.
-        if self.unit_type() == UnitType::UnitMount {
+        // Mount and swap units without config file are only monitored, and device
+        // units never have one, set their loadstate to UnitLoaded directly.
+        if matches!(
+            self.unit_type(),
+            UnitType::UnitMount | UnitType::UnitSwap | UnitType::UnitDevice
+        ) && !self.load.has_fragment()
+        {
+            self.sub.load(Vec::new())?;
             self.load.set_load_state(UnitLoadState::Loaded);
             return Ok(());
         }
@@ -687,6 +724,20 @@ impl Unit {
         }
     }
 
+    /// remove the directories of the given types, the unit must not be running
+    pub fn clean(&self, what: &[ExecDirectoryType]) -> Result<()> {
+        let active_state = self.current_active_state();
+        if !matches!(
+            active_state,
+            UnitActiveState::UnitInActive | UnitActiveState::UnitFailed
+        ) {
+            log::warn!("{} unit is {}, can not clean it", self.id(), active_state);
+            return Err(Error::UnitActionEBusy);
+        }
+
+        self.sub.clean(what)
+    }
+
     pub(super) fn sigchld_events(&self, wait_status: WaitStatus) {
         self.sub.sigchld_events(wait_status)
     }
@@ -711,6 +762,36 @@ impl Unit {
         self.sub.collect_fds()
     }
 
+    /// pass the connection fd accepted by a socket, only possible before the unit is started
+    pub fn set_socket_fd(&self, fd: i32) -> Result<()> {
+        let active_state = self.current_active_state();
+        if !matches!(
+            active_state,
+            UnitActiveState::UnitInActive | UnitActiveState::UnitFailed
+        ) {
+            log::warn!(
+                "{} unit is {}, can not set socket fd",
+                self.id(),
+                active_state
+            );
+            return Err(Error::UnitActionEBusy);
+        }
+
+        self.sub.set_socket_fd(fd)
+    }
+
+    /// close the connection fd if the unit failed to start with it
+    pub fn release_socket_fd(&self) {
+        if !self.current_active_state().is_inactive_or_failed() {
+            return;
+        }
+        self.sub.release_socket_fd()
+    }
+
+    pub(super) fn trigger_notify(&self, other: &str) {
+        self.sub.trigger_notify(other)
+    }
+
     pub(crate) fn notify_message(
         &self,
         ucred: &UnixCredentials,
@@ -24,6 +24,7 @@ use std::ops::Deref;
 use std::path::PathBuf;
 use std::rc::Rc;
 use sysmaster::error::*;
+use sysmaster::exec::ExecDirectoryType;
 use sysmaster::rel::ReStation;
 use sysmaster::unit::{SubUnit, UnitActiveState, UnitRelations, UnitType};
 
@@ -94,6 +95,10 @@ impl UnitX {
         self.0.reload()
     }
 
+    pub(crate) fn clean(&self, what: &[ExecDirectoryType]) -> Result<()> {
+        self.0.clean(what)
+    }
+
     #[allow(dead_code)]
     pub(crate) fn kill(&self) {}
     #[allow(dead_code)]
@@ -103,7 +108,9 @@ impl UnitX {
     }
     #[allow(dead_code)]
     pub(crate) fn reset_failed(&self) {}
-    pub(crate) fn trigger(&self, _other: &Self) {}
+    pub(crate) fn trigger(&self, other: &Self) {
+        self.0.trigger_notify(other.id())
+    }
     pub(crate) fn in_load_queue(&self) -> bool {
         self.0.in_load_queue()
     }
@@ -221,6 +228,14 @@ impl UnitX {
         self.0.collect_fds()
     }
 
+    pub(crate) fn set_socket_fd(&self, fd: i32) -> Result<()> {
+        self.0.set_socket_fd(fd)
+    }
+
+    pub(crate) fn release_socket_fd(&self) {
+        self.0.release_socket_fd()
+    }
+
     pub fn get_config(&self) -> Rc<UeConfig> {
         self.0.get_config()
     }
@@ -0,0 +1,229 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! expand the environment variables and the specifiers in the command line
+//!
+//! "$VAR" as a whole argument is split at whitespace and may result in zero or
+//! more arguments, "${VAR}" is replaced by the exact value and always stays in
+//! one argument, "$$" is a literal "$".
+//!
+//! the specifiers:
+//! %n the full unit name, %N the unit name without the suffix,
+//! %t the runtime directory, %S the state directory,
+//! %h the home directory of the user, %u the user name,
+//! %H the host name, %m the machine id, %% a literal "%".
+
+use nix::unistd::{Uid, User};
+use std::collections::HashMap;
+
+const MACHINE_ID: &str = "/etc/machine-id";
+
+/// the values that the specifiers are replaced by
+pub(super) struct Specifiers {
+    unit_id: String,
+    user: Option<User>,
+}
+
+impl Specifiers {
+    /// the user is the one that the command runs as, root if None
+    pub(super) fn new(unit_id: &str, user: Option<User>) -> Specifiers {
+        Specifiers {
+            unit_id: unit_id.to_string(),
+            user,
+        }
+    }
+
+    fn user(&self) -> Option<User> {
+        match &self.user {
+            Some(u) => Some(u.clone()),
+            None => User::from_uid(Uid::from_raw(0)).ok().flatten(),
+        }
+    }
+
+    fn value(&self, c: char) -> Option<String> {
+        let v = match c {
+            'n' => self.unit_id.clone(),
+            'N' => match self.unit_id.rsplit_once('.') {
+                Some((name, _)) => name.to_string(),
+                None => self.unit_id.clone(),
+            },
+            't' => "/run".to_string(),
+            'S' => "/var/lib".to_string(),
+            'h' => match self.user() {
+                Some(u) => u.dir.to_string_lossy().to_string(),
+                None => "/root".to_string(),
+            },
+            'u' => match self.user() {
+                Some(u) => u.name,
+                None => "root".to_string(),
+            },
+            'H' => match nix::unistd::gethostname(&mut [0u8; 256]) {
+                Ok(h) => h.to_string_lossy().to_string(),
+                Err(_) => String::new(),
+            },
+            'm' => match std::fs::read_to_string(MACHINE_ID) {
+                Ok(id) => id.trim().to_string(),
+                Err(_) => String::new(),
+            },
+            '%' => "%".to_string(),
+            _ => return None,
+        };
+        Some(v)
+    }
+
+    /// replace the specifiers in the string, unknown specifiers are kept as they are
+    pub(super) fn expand(&self, s: &str) -> String {
+        let mut res = String::with_capacity(s.len());
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                res.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some(n) => match self.value(n) {
+                    Some(v) => res.push_str(&v),
+                    None => {
+                        log::warn!("Unknown specifier %{n} in \"{s}\", ignoring.");
+                        res.push('%');
+                        res.push(n);
+                    }
+                },
+                None => res.push('%'),
+            }
+        }
+        res
+    }
+}
+
+fn is_name_char(c: char, first: bool) -> bool {
+    c == '_' || c.is_ascii_alphabetic() || (!first && c.is_ascii_digit())
+}
+
+/// return the variable name if the argument is "$VAR"
+fn whole_variable(arg: &str) -> Option<&str> {
+    let name = arg.strip_prefix('$')?;
+    let mut chars = name.chars();
+    if !matches!(chars.next(), Some(c) if is_name_char(c, true)) {
+        return None;
+    }
+    if !chars.all(|c| is_name_char(c, false)) {
+        return None;
+    }
+    Some(name)
+}
+
+/// replace "${VAR}" and "$$" in the argument
+fn expand_braced(arg: &str, envs: &HashMap<String, String>) -> String {
+    let mut res = String::with_capacity(arg.len());
+    let mut rest = arg;
+    while let Some(pos) = rest.find('$') {
+        res.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if let Some(r) = rest.strip_prefix("$$") {
+            res.push('$');
+            rest = r;
+            continue;
+        }
+        let name = rest
+            .strip_prefix("${")
+            .and_then(|r| r.find('}').map(|end| &r[..end]))
+            .filter(|name| {
+                let mut chars = name.chars();
+                matches!(chars.next(), Some(c) if is_name_char(c, true))
+                    && chars.all(|c| is_name_char(c, false))
+            });
+        match name {
+            Some(name) => {
+                if let Some(v) = envs.get(name) {
+                    res.push_str(v);
+                }
+                rest = &rest[name.len() + 3..];
+            }
+            None => {
+                res.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+/// expand the environment variables in the arguments, unset variables are empty
+pub(super) fn expand_environment(argv: &[String], envs: &HashMap<String, String>) -> Vec<String> {
+    let mut args = Vec::new();
+    for arg in argv {
+        match whole_variable(arg) {
+            Some(name) => {
+                if let Some(v) = envs.get(name) {
+                    args.extend(v.split_whitespace().map(|s| s.to_string()));
+                }
+            }
+            None => args.push(expand_braced(arg, envs)),
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_environment() {
+        let envs = HashMap::from([
+            ("FOO".to_string(), "a b  c".to_string()),
+            ("BAR_1".to_string(), "x".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ]);
+        let argv = [
+            "$FOO",
+            "${FOO}",
+            "--bar=${BAR_1}",
+            "$BAR_1-suffix",
+            "$EMPTY",
+            "$UNSET",
+            "${UNSET}",
+            "$$FOO",
+            "${1x}",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            expand_environment(&argv, &envs),
+            vec![
+                "a",
+                "b",
+                "c",
+                "a b  c",
+                "--bar=x",
+                "$BAR_1-suffix",
+                "",
+                "$FOO",
+                "${1x}"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_specifiers() {
+        let specifiers = Specifiers::new("foo.service", None);
+        assert_eq!(
+            specifiers.expand("%n %N %t/%N %S 100%% %x %"),
+            "foo.service foo /run/foo /var/lib 100% %x %"
+        );
+        assert_eq!(specifiers.expand("%u"), "root");
+    }
+}
@@ -0,0 +1,140 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! The system call filters and the capability bounding set of the kernel hardening
+//! switches, the file system part is done in the mount namespace.
+
+use basic::seccomp_util::{SeccompArg, SeccompFilter};
+use sysmaster::error::*;
+use sysmaster::exec::{ExecContext, ExecHardening};
+
+// query the current execution domain without changing it
+const PERSONALITY_QUERY: u32 = 0xffff_ffff;
+
+// see capability.h
+const CAP_SYS_MODULE: libc::c_int = 16;
+
+fn drop_bounding_capability(cap: libc::c_int) -> Result<()> {
+    let ret = unsafe { libc::prctl(libc::PR_CAPBSET_DROP, cap, 0, 0, 0) };
+    if ret < 0 {
+        return Err(Error::Nix {
+            source: nix::Error::last(),
+        });
+    }
+    Ok(())
+}
+
+fn add_kernel_modules_rules(filter: &mut SeccompFilter) {
+    for nr in [
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+    ] {
+        filter.add_rule(nr, SeccompArg::Any, libc::EPERM);
+    }
+}
+
+fn add_personality_rules(filter: &mut SeccompFilter) {
+    let current = unsafe { libc::personality(PERSONALITY_QUERY as libc::c_ulong) };
+    let mut allowed = vec![PERSONALITY_QUERY];
+    if current >= 0 {
+        allowed.push(current as u32);
+    }
+    filter.add_rule(
+        libc::SYS_personality,
+        SeccompArg::NotIn(0, allowed),
+        libc::EPERM,
+    );
+}
+
+fn add_write_execute_rules(filter: &mut SeccompFilter) {
+    let wx = (libc::PROT_WRITE | libc::PROT_EXEC) as u32;
+    let x = libc::PROT_EXEC as u32;
+    filter.add_rule(libc::SYS_mmap, SeccompArg::MaskedEq(2, wx, wx), libc::EPERM);
+    for nr in [libc::SYS_mprotect, libc::SYS_pkey_mprotect] {
+        filter.add_rule(nr, SeccompArg::MaskedEq(2, x, x), libc::EPERM);
+    }
+    let shm_exec = libc::SHM_EXEC as u32;
+    filter.add_rule(
+        libc::SYS_shmat,
+        SeccompArg::MaskedEq(2, shm_exec, shm_exec),
+        libc::EPERM,
+    );
+}
+
+fn add_namespace_rules(filter: &mut SeccompFilter, denied: u32) {
+    filter.add_rule(
+        libc::SYS_unshare,
+        SeccompArg::MaskedNe(0, denied, 0),
+        libc::EPERM,
+    );
+    filter.add_rule(
+        libc::SYS_clone,
+        SeccompArg::MaskedNe(0, denied, 0),
+        libc::EPERM,
+    );
+    // setns(fd, 0) may join any kind of namespace
+    filter.add_rule(libc::SYS_setns, SeccompArg::In(1, vec![0]), libc::EPERM);
+    filter.add_rule(
+        libc::SYS_setns,
+        SeccompArg::MaskedNe(1, denied, 0),
+        libc::EPERM,
+    );
+    // the flags of clone3 are in memory and can't be checked, let the callers fall back to clone
+    filter.add_rule(libc::SYS_clone3, SeccompArg::Any, libc::ENOSYS);
+}
+
+/// apply the kernel hardening switches, must be called before dropping the privileges
+pub(super) fn apply_hardening(ctx: &ExecContext) -> Result<()> {
+    let hardening = ctx.hardening();
+    let mut filter = SeccompFilter::new();
+
+    if hardening.contains(ExecHardening::PROTECT_KERNEL_MODULES) {
+        drop_bounding_capability(CAP_SYS_MODULE)?;
+        add_kernel_modules_rules(&mut filter);
+    }
+
+    if hardening.contains(ExecHardening::PROTECT_KERNEL_LOGS) {
+        filter.add_rule(libc::SYS_syslog, SeccompArg::Any, libc::EPERM);
+    }
+
+    if hardening.contains(ExecHardening::LOCK_PERSONALITY) {
+        add_personality_rules(&mut filter);
+    }
+
+    if hardening.contains(ExecHardening::MEMORY_DENY_WRITE_EXECUTE) {
+        add_write_execute_rules(&mut filter);
+    }
+
+    // the host name of the UTS namespace is locked
+    if hardening.contains(ExecHardening::PROTECT_HOSTNAME) {
+        for nr in [libc::SYS_sethostname, libc::SYS_setdomainname] {
+            filter.add_rule(nr, SeccompArg::Any, libc::EPERM);
+        }
+    }
+
+    if let Some((allow, families)) = ctx.restrict_address_families().families() {
+        let families = families.into_iter().map(|f| f as u32).collect();
+        let arg = match allow {
+            true => SeccompArg::NotIn(0, families),
+            false => SeccompArg::In(0, families),
+        };
+        filter.add_rule(libc::SYS_socket, arg, libc::EAFNOSUPPORT);
+    }
+
+    let denied = ctx.restrict_namespaces().denied();
+    if !denied.is_empty() {
+        add_namespace_rules(&mut filter, denied.bits() as u32);
+    }
+
+    filter.load().context(UtilSnafu)
+}
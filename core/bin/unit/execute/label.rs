@@ -0,0 +1,154 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! The security labels of the commands: the SELinux context, the AppArmor profile
+//! and the SMACK label. The labels of the disabled security modules are ignored.
+
+use basic::security;
+use std::os::unix::prelude::RawFd;
+use std::path::Path;
+use sysmaster::error::*;
+use sysmaster::exec::ExecContext;
+
+// the label starting with "-" is optional, the failures are only logged
+fn split_optional(label: &str) -> (bool, &str) {
+    match label.strip_prefix('-') {
+        Some(l) => (true, l),
+        None => (false, label),
+    }
+}
+
+fn check_optional(optional: bool, what: &str, ret: basic::Result<()>) -> Result<()> {
+    match ret {
+        Err(e) if optional => {
+            log::debug!("Failed to set the {what}, ignoring: {e}");
+            Ok(())
+        }
+        ret => ret.context(UtilSnafu),
+    }
+}
+
+// the MLS range of the peer replaces the one of the context, the context defaults to
+// the one that the kernel transitions to when executing the command
+fn selinux_exec_context(
+    context: Option<&str>,
+    from_net: bool,
+    path: &Path,
+    socket_fd: Option<RawFd>,
+) -> basic::Result<Option<String>> {
+    let fd = match socket_fd {
+        Some(fd) if from_net => fd,
+        _ => return Ok(context.map(|c| c.to_string())),
+    };
+    let peer = security::selinux_peer_context(fd)?;
+    let context = match context {
+        Some(c) => c.to_string(),
+        None => {
+            let current = security::selinux_current_context()?;
+            let file = security::selinux_file_context(path)?;
+            security::selinux_compute_create(&current, &file, "process")?
+        }
+    };
+    Ok(Some(security::selinux_with_range_of(&context, &peer)))
+}
+
+/// set the smack label of the process, must be called before dropping the privileges
+pub(super) fn apply_process_label(ctx: &ExecContext) -> Result<()> {
+    let label = match ctx.smack_process_label() {
+        Some(l) if security::smack_enabled() => l,
+        _ => return Ok(()),
+    };
+    let (optional, label) = split_optional(&label);
+    check_optional(
+        optional,
+        "smack process label",
+        security::smack_set_process_label(label),
+    )
+}
+
+/// set the selinux context and the apparmor profile that the command is executed in, the
+/// socket is the connection that the command is spawned for, a listening socket has no peer
+pub(super) fn apply_exec_labels(
+    ctx: &ExecContext,
+    path: &Path,
+    socket_fd: Option<RawFd>,
+) -> Result<()> {
+    if security::selinux_enabled() {
+        let context = ctx.selinux_context();
+        let (optional, context) = match &context {
+            Some(c) => {
+                let (optional, c) = split_optional(c);
+                (optional, Some(c))
+            }
+            None => (false, None),
+        };
+        let ret = selinux_exec_context(context, ctx.selinux_context_from_net(), path, socket_fd)
+            .and_then(|c| match c {
+                Some(c) => security::selinux_set_exec_context(&c),
+                None => Ok(()),
+            });
+        check_optional(optional, "selinux context", ret)?;
+    }
+
+    if let Some(profile) = ctx.apparmor_profile() {
+        if security::apparmor_enabled() {
+            let (optional, profile) = split_optional(&profile);
+            check_optional(
+                optional,
+                "apparmor profile",
+                security::apparmor_change_onexec(profile),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::socket::{socketpair, AddressFamily, SockFlag, SockType};
+
+    #[test]
+    fn test_selinux_exec_context_without_connection() {
+        let path = Path::new("/bin/true");
+        // the listening sockets are not passed, the configured context is used
+        assert_eq!(
+            selinux_exec_context(Some("system_u:system_r:foo_t:s0"), true, path, None).unwrap(),
+            Some("system_u:system_r:foo_t:s0".to_string())
+        );
+        // the kernel transitions the context as usual
+        assert_eq!(selinux_exec_context(None, true, path, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_selinux_exec_context_not_from_net() {
+        let (fd, peer) = socketpair(
+            AddressFamily::Unix,
+            SockType::Stream,
+            None,
+            SockFlag::SOCK_CLOEXEC,
+        )
+        .unwrap();
+        let path = Path::new("/bin/true");
+        assert_eq!(
+            selinux_exec_context(Some("system_u:system_r:foo_t:s0"), false, path, Some(fd))
+                .unwrap(),
+            Some("system_u:system_r:foo_t:s0".to_string())
+        );
+        assert_eq!(
+            selinux_exec_context(None, false, path, Some(fd)).unwrap(),
+            None
+        );
+        basic::fd_util::close(fd);
+        basic::fd_util::close(peer);
+    }
+}
@@ -11,4 +11,8 @@
 // See the Mulan PSL v2 for more details.
 
 pub(super) use spawn::ExecSpawn;
+mod expand;
+mod hardening;
+mod label;
+mod namespace;
 mod spawn;
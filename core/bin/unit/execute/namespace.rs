@@ -0,0 +1,603 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! The namespaces of the commands. The mount namespace carries ProtectSystem,
+//! PrivateTmp, the private directories of the dynamic user and the kernel hardening
+//! switches, the others are PrivatePIDs, PrivateIPC, ProtectHostname and PrivateUsers.
+
+use basic::mount_util::parse_mountinfo;
+use nix::fcntl::{open, openat, OFlag};
+use nix::mount::{mount, MsFlags};
+use nix::sched::{unshare, CloneFlags};
+use nix::sys::signal::{self, SigHandler, SigSet, Signal};
+use nix::sys::stat::Mode;
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{self, ForkResult, Gid, Pid, Uid};
+use std::fs::Permissions;
+use std::io::Write;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::os::unix::prelude::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use sysmaster::error::*;
+use sysmaster::exec::{
+    private_tmp_dirs, ExecContext, ExecDirectoryType, ExecHardening, ExecParameters,
+};
+
+// the api file systems stay writable even if the whole file system is read-only
+const API_FILE_SYSTEMS: [&str; 3] = ["/dev", "/proc", "/sys"];
+
+// the kernel tunables made read-only by ProtectKernelTunables
+const KERNEL_TUNABLES: [&str; 12] = [
+    "/proc/acpi",
+    "/proc/apm",
+    "/proc/asound",
+    "/proc/bus",
+    "/proc/fs",
+    "/proc/irq",
+    "/proc/latency_stats",
+    "/proc/mtrr",
+    "/proc/sys",
+    "/proc/sysrq-trigger",
+    "/proc/timer_stats",
+    "/sys",
+];
+
+const KERNEL_MODULES: [&str; 2] = ["/lib/modules", "/usr/lib/modules"];
+
+const KERNEL_LOGS: [&str; 2] = ["/proc/kmsg", "/dev/kmsg"];
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+// the empty nodes without any permission, bound over the inaccessible paths
+const INACCESSIBLE_DIR: &str = "/run/sysmaster/inaccessible";
+
+/// return true if the directories of the kind are private, only the State, Cache
+/// and Logs directories of the dynamic user are private.
+pub(super) fn directory_is_private(params: &ExecParameters, kind: ExecDirectoryType) -> bool {
+    params.dynamic_user()
+        && matches!(
+            kind,
+            ExecDirectoryType::State | ExecDirectoryType::Cache | ExecDirectoryType::Logs
+        )
+}
+
+fn bind_mount(source: &Path, target: &Path) -> Result<()> {
+    mount::<Path, Path, str, str>(
+        Some(source),
+        target,
+        None,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None,
+    )
+    .context(NixSnafu)
+}
+
+fn setup_private_tmp(unit_id: &str) -> Result<()> {
+    for (host, target) in private_tmp_dirs(unit_id)
+        .iter()
+        .zip([Path::new("/tmp"), Path::new("/var/tmp")])
+    {
+        // the outer directory is only accessible by root, so others can't reach the inner one
+        let inner = host.join("tmp");
+        if !inner.exists() {
+            std::fs::create_dir_all(&inner).context(IoSnafu)?;
+            std::fs::set_permissions(host, Permissions::from_mode(0o700)).context(IoSnafu)?;
+            std::fs::set_permissions(&inner, Permissions::from_mode(0o1777)).context(IoSnafu)?;
+        }
+        bind_mount(&inner, target)?;
+    }
+    Ok(())
+}
+
+// hide the other private directories by mounting a tmpfs on "private", and bind the
+// directories of this unit back
+fn setup_private_directories(params: &ExecParameters) -> Result<()> {
+    for kind in [
+        ExecDirectoryType::State,
+        ExecDirectoryType::Cache,
+        ExecDirectoryType::Logs,
+    ] {
+        let directories = match &params.get_exec_directory()[kind as usize] {
+            Some(v) if directory_is_private(params, kind) => v,
+            _ => continue,
+        };
+
+        // keep the directories reachable before they are covered by the tmpfs
+        let mut fds = Vec::new();
+        for d in directories {
+            let private = kind.private_path(d);
+            let fd = open(&private, OFlag::O_PATH | OFlag::O_CLOEXEC, Mode::empty())
+                .context(NixSnafu)?;
+            fds.push((private, fd));
+        }
+
+        let root = kind.prefix().join("private");
+        let ret = mount::<str, Path, str, str>(
+            Some("tmpfs"),
+            &root,
+            Some("tmpfs"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+            Some("mode=0755"),
+        )
+        .context(NixSnafu);
+
+        let ret = ret.and_then(|_| {
+            for (private, fd) in &fds {
+                std::fs::create_dir_all(private).context(IoSnafu)?;
+                bind_mount(&PathBuf::from(format!("/proc/self/fd/{fd}")), private)?;
+            }
+            Ok(())
+        });
+        for (_, fd) in fds {
+            basic::fd_util::close(fd);
+        }
+        ret?;
+    }
+    Ok(())
+}
+
+fn writable_paths(params: &ExecParameters, ctx: &ExecContext) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for kind in ExecDirectoryType::ALL {
+        if kind == ExecDirectoryType::Config {
+            continue;
+        }
+        if let Some(directories) = &params.get_exec_directory()[kind as usize] {
+            for d in directories {
+                if directory_is_private(params, kind) {
+                    paths.push(kind.private_path(d));
+                } else {
+                    paths.push(d.clone());
+                }
+            }
+        }
+    }
+    if ctx.private_tmp() {
+        paths.push(PathBuf::from("/tmp"));
+        paths.push(PathBuf::from("/var/tmp"));
+    }
+    paths
+}
+
+fn read_only_paths(ctx: &ExecContext) -> Vec<&'static Path> {
+    let mut paths: Vec<&str> = ctx.protect_system().read_only_paths().to_vec();
+    let hardening = ctx.hardening();
+    if hardening.contains(ExecHardening::PROTECT_KERNEL_TUNABLES) {
+        paths.extend(KERNEL_TUNABLES);
+    }
+    if hardening.contains(ExecHardening::PROTECT_CONTROL_GROUPS) {
+        paths.push(CGROUP_ROOT);
+    }
+    paths.into_iter().map(Path::new).collect()
+}
+
+fn inaccessible_paths(ctx: &ExecContext) -> Vec<&'static Path> {
+    let mut paths = Vec::new();
+    let hardening = ctx.hardening();
+    if hardening.contains(ExecHardening::PROTECT_KERNEL_MODULES) {
+        paths.extend(KERNEL_MODULES);
+    }
+    if hardening.contains(ExecHardening::PROTECT_KERNEL_LOGS) {
+        paths.extend(KERNEL_LOGS);
+    }
+    paths.into_iter().map(Path::new).collect()
+}
+
+fn setup_inaccessible_paths(ctx: &ExecContext) -> Result<()> {
+    let paths: Vec<&Path> = inaccessible_paths(ctx)
+        .into_iter()
+        .filter(|p| p.exists())
+        .collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let root = Path::new(INACCESSIBLE_DIR);
+    let (dir, reg) = (root.join("dir"), root.join("reg"));
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir).context(IoSnafu)?;
+    }
+    if !reg.exists() {
+        std::fs::File::create(&reg).context(IoSnafu)?;
+    }
+    for node in [&dir, &reg] {
+        std::fs::set_permissions(node, Permissions::from_mode(0o000)).context(IoSnafu)?;
+    }
+
+    for p in paths {
+        let source = match p.is_dir() {
+            true => &dir,
+            false => &reg,
+        };
+        bind_mount(source, p)?;
+    }
+    Ok(())
+}
+
+fn setup_read_only_paths(params: &ExecParameters, ctx: &ExecContext) -> Result<()> {
+    let read_only: Vec<&Path> = read_only_paths(ctx)
+        .into_iter()
+        .chain(inaccessible_paths(ctx))
+        .filter(|p| p.exists())
+        .collect();
+    if read_only.is_empty() {
+        return Ok(());
+    }
+
+    // make the paths mount points, so they can be remounted separately
+    let writable = writable_paths(params, ctx);
+    for p in writable
+        .iter()
+        .map(|p| p.as_path())
+        .chain(read_only.clone())
+    {
+        if p != Path::new("/") && p.exists() {
+            bind_mount(p, p)?;
+        }
+    }
+
+    // the api file systems are only read-only if the paths in them are listed explicitly
+    let is_api = |p: &Path| API_FILE_SYSTEMS.iter().any(|a| p.starts_with(a));
+    let content = std::fs::read_to_string("/proc/self/mountinfo").context(IoSnafu)?;
+    for (path, flags) in parse_mountinfo(&content) {
+        if !read_only
+            .iter()
+            .any(|p| path.starts_with(p) && (is_api(p) || !is_api(&path)))
+            || writable.iter().any(|p| path.starts_with(p))
+        {
+            continue;
+        }
+
+        if let Err(e) = mount::<str, Path, str, str>(
+            None,
+            &path,
+            None,
+            flags | MsFlags::MS_REMOUNT | MsFlags::MS_BIND | MsFlags::MS_RDONLY,
+            None,
+        ) {
+            // the mount point may be covered by the other mounts
+            log::debug!("Failed to remount {:?} read-only: {e}", path);
+        }
+    }
+    Ok(())
+}
+
+fn setup_mount_namespace(unit_id: &str, params: &ExecParameters, ctx: &ExecContext) -> Result<()> {
+    if !params.dynamic_user()
+        && !ctx.private_tmp()
+        && !ctx.private_pids()
+        && read_only_paths(ctx).is_empty()
+        && inaccessible_paths(ctx).is_empty()
+    {
+        return Ok(());
+    }
+
+    unshare(CloneFlags::CLONE_NEWNS).context(NixSnafu)?;
+    // don't propagate the mounts of the namespace back to the host
+    mount::<str, str, str, str>(None, "/", None, MsFlags::MS_REC | MsFlags::MS_SLAVE, None)
+        .context(NixSnafu)?;
+
+    // the processes of the PID namespace are only visible in its own proc
+    if ctx.private_pids() {
+        mount::<str, str, str, str>(
+            Some("proc"),
+            "/proc",
+            Some("proc"),
+            MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+            None,
+        )
+        .context(NixSnafu)?;
+    }
+
+    if ctx.private_tmp() {
+        setup_private_tmp(unit_id)?;
+    }
+
+    if params.dynamic_user() {
+        setup_private_directories(params)?;
+    }
+
+    setup_inaccessible_paths(ctx)?;
+
+    setup_read_only_paths(params, ctx)
+}
+
+// the processes of the namespace and the host see the root and the user of the
+// command as themselves, the others are unmapped and shown as nobody
+fn id_map(id: u32) -> String {
+    match id {
+        0 => "0 0 1\n".to_string(),
+        _ => format!("0 0 1\n{id} {id} 1\n"),
+    }
+}
+
+fn write_id_maps(proc_fd: RawFd, uid: Uid, gid: Gid) -> Result<()> {
+    for (file, id) in [("uid_map", uid.as_raw()), ("gid_map", gid.as_raw())] {
+        let fd = openat(
+            proc_fd,
+            file,
+            OFlag::O_WRONLY | OFlag::O_CLOEXEC,
+            Mode::empty(),
+        )
+        .context(NixSnafu)?;
+        let mut map = unsafe { std::fs::File::from_raw_fd(fd) };
+        map.write_all(id_map(id).as_bytes()).context(IoSnafu)?;
+    }
+    Ok(())
+}
+
+// only the id of the creator can be mapped from inside the user namespace, so a helper
+// left in the parent namespace writes the maps. The proc directory of this process is
+// opened beforehand, the PID namespace of the helper may differ from the one of /proc.
+fn setup_private_users(uid: Uid, gid: Gid) -> Result<()> {
+    let proc_fd = open(
+        "/proc/self",
+        OFlag::O_PATH | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .context(NixSnafu)?;
+    let (ready_r, ready_w) = unistd::pipe().context(NixSnafu)?;
+    let (ret_r, ret_w) = unistd::pipe().context(NixSnafu)?;
+
+    match unsafe { unistd::fork() }.context(NixSnafu)? {
+        ForkResult::Child => {
+            let mut ready = [0u8; 1];
+            let errno = match unistd::read(ready_r, &mut ready) {
+                Ok(1) if ready[0] == 1 => match write_id_maps(proc_fd, uid, gid) {
+                    Ok(_) => 0,
+                    Err(Error::Nix { source }) => source as i32,
+                    Err(Error::Io { source }) => source.raw_os_error().unwrap_or(libc::EIO),
+                    Err(_) => libc::EIO,
+                },
+                _ => libc::ECANCELED,
+            };
+            let _ = unistd::write(ret_w, &errno.to_ne_bytes());
+            process::exit(0);
+        }
+        ForkResult::Parent { child } => {
+            basic::fd_util::close(ready_r);
+            basic::fd_util::close(ret_w);
+
+            let ret = unshare(CloneFlags::CLONE_NEWUSER).context(NixSnafu);
+            let _ = unistd::write(ready_w, &[ret.is_ok() as u8]);
+            let mut errno = [0u8; 4];
+            let n = unistd::read(ret_r, &mut errno);
+            basic::fd_util::close(ready_w);
+            basic::fd_util::close(ret_r);
+            basic::fd_util::close(proc_fd);
+            let _ = waitpid(child, None);
+
+            ret?;
+            match (n, i32::from_ne_bytes(errno)) {
+                (Ok(4), 0) => Ok(()),
+                (Ok(4), e) => Err(Error::Nix {
+                    source: nix::Error::from_i32(e),
+                }),
+                _ => Err(Error::Nix {
+                    source: nix::Error::EIO,
+                }),
+            }
+        }
+    }
+}
+
+// the signals that the reaper passes on to its child
+const FORWARD_SIGNALS: [Signal; 7] = [
+    Signal::SIGTERM,
+    Signal::SIGINT,
+    Signal::SIGHUP,
+    Signal::SIGQUIT,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+    Signal::SIGCONT,
+];
+
+fn exit_code(status: WaitStatus) -> i32 {
+    match status {
+        WaitStatus::Exited(_, code) => code,
+        WaitStatus::Signaled(_, sig, _) => 128 + sig as i32,
+        _ => 1,
+    }
+}
+
+// the init process of the namespace can't be killed by the signals raised by itself,
+// so the signal that killed the command is reported to the outer process through the
+// pipe, then it exits with the code of the shell
+fn exit_init(status: WaitStatus, report: RawFd) -> ! {
+    if let WaitStatus::Signaled(_, sig, _) = status {
+        let _ = unistd::write(report, &(sig as i32).to_ne_bytes());
+    }
+    process::exit(exit_code(status));
+}
+
+// exit like the command, the outer process is out of the namespace and is killed by the
+// signal reported by the init process like any other process
+fn exit_outer(status: WaitStatus, report: RawFd) -> ! {
+    let mut buf = [0u8; 4];
+    let reported = match unistd::read(report, &mut buf) {
+        Ok(4) => Signal::try_from(i32::from_ne_bytes(buf)).ok(),
+        _ => None,
+    };
+
+    if let Some(sig) = reported {
+        let mut set = SigSet::empty();
+        set.add(sig);
+        unsafe {
+            let _ = signal::signal(sig, SigHandler::SigDfl);
+        }
+        let _ = set.thread_unblock();
+        let _ = signal::raise(sig);
+    }
+    process::exit(exit_code(status));
+}
+
+// pass the signals on to the child and reap all the processes that are left to us,
+// exit like the child once it exits
+fn run_reaper(child: Pid, exit: fn(WaitStatus, RawFd) -> !, report: RawFd) -> ! {
+    let mut set = SigSet::empty();
+    set.add(Signal::SIGCHLD);
+    for sig in FORWARD_SIGNALS {
+        set.add(sig);
+    }
+    if let Err(e) = set.thread_block() {
+        log::error!("Failed to block the signals of the reaper: {e}");
+    }
+
+    loop {
+        // reap before waiting, the child may have exited before the signals are blocked
+        loop {
+            match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) => break,
+                Ok(status) if status.pid() == Some(child) => exit(status, report),
+                Ok(_) => continue,
+                Err(_) => break,
+            }
+        }
+
+        match set.wait() {
+            Ok(Signal::SIGCHLD) | Err(_) => continue,
+            Ok(sig) => {
+                let _ = signal::kill(child, sig);
+            }
+        }
+    }
+}
+
+// the process forked by the manager stays outside and waits for the init process of the
+// namespace, which in turn reaps the namespace and waits for the command. Only the
+// command returns from here.
+fn setup_private_pids() -> Result<()> {
+    unshare(CloneFlags::CLONE_NEWPID).context(NixSnafu)?;
+    // the init process reports the signal that killed the command to the outer process
+    let (read_fd, write_fd) = unistd::pipe2(OFlag::O_CLOEXEC).context(NixSnafu)?;
+
+    match unsafe { unistd::fork() }.context(NixSnafu)? {
+        ForkResult::Parent { child } => {
+            let _ = unistd::close(write_fd);
+            run_reaper(child, exit_outer, read_fd)
+        }
+        ForkResult::Child => {
+            let _ = unistd::close(read_fd);
+        }
+    }
+
+    // the whole namespace goes down with the outer process
+    unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL, 0, 0, 0) };
+    match unsafe { unistd::fork() }.context(NixSnafu)? {
+        ForkResult::Parent { child } => run_reaper(child, exit_init, write_fd),
+        ForkResult::Child => {
+            let _ = unistd::close(write_fd);
+            Ok(())
+        }
+    }
+}
+
+/// set up the namespaces of the command, nothing is done if no option needs them. The
+/// user namespace is the last one, so the others are still owned by the host.
+pub(super) fn setup_namespace(
+    unit_id: &str,
+    params: &ExecParameters,
+    ctx: &ExecContext,
+) -> Result<()> {
+    if ctx.private_pids() {
+        setup_private_pids()?;
+    }
+
+    setup_mount_namespace(unit_id, params, ctx)?;
+
+    let mut flags = CloneFlags::empty();
+    if ctx.private_ipc() {
+        flags |= CloneFlags::CLONE_NEWIPC;
+    }
+    if ctx.hardening().contains(ExecHardening::PROTECT_HOSTNAME) {
+        flags |= CloneFlags::CLONE_NEWUTS;
+    }
+    if !flags.is_empty() {
+        unshare(flags).context(NixSnafu)?;
+    }
+
+    if ctx.private_users() {
+        let uid = params.get_user().map_or(Uid::from_raw(0), |u| u.uid);
+        let gid = params.get_group().map_or(Gid::from_raw(0), |g| g.gid);
+        setup_private_users(uid, gid)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // run the command in a private PID namespace like the manager does, return how
+    // the process forked by the manager ends after the signal is sent to it
+    fn run_private_pids(signal: Option<Signal>, code: i32) -> Option<WaitStatus> {
+        let (read_fd, write_fd) = unistd::pipe2(OFlag::O_CLOEXEC).unwrap();
+        match unsafe { unistd::fork() }.unwrap() {
+            ForkResult::Child => {
+                let _ = unistd::close(read_fd);
+                if setup_private_pids().is_err() {
+                    process::exit(100);
+                }
+                // the command
+                let _ = unistd::write(write_fd, b"1");
+                if signal.is_some() {
+                    loop {
+                        unistd::pause();
+                    }
+                }
+                process::exit(code);
+            }
+            ForkResult::Parent { child } => {
+                let _ = unistd::close(write_fd);
+                let started = unistd::read(read_fd, &mut [0u8; 1]).unwrap_or(0) == 1;
+                let _ = unistd::close(read_fd);
+                if let (true, Some(sig)) = (started, signal) {
+                    signal::kill(child, sig).unwrap();
+                }
+                match waitpid(child, None).unwrap() {
+                    WaitStatus::Exited(_, 100) => None,
+                    status => Some(status),
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the privilege to create the PID namespace"]
+    fn test_private_pids_status() {
+        let status =
+            run_private_pids(Some(Signal::SIGTERM), 0).expect("the PID namespace can't be created");
+        // the service stopped by SIGTERM ends as it does without the namespace
+        assert!(matches!(
+            status,
+            WaitStatus::Signaled(_, Signal::SIGTERM, _)
+        ));
+
+        let status = run_private_pids(None, 3).unwrap();
+        assert!(matches!(status, WaitStatus::Exited(_, 3)));
+    }
+
+    #[test]
+    fn test_id_map() {
+        assert_eq!(id_map(0), "0 0 1\n");
+        assert_eq!(id_map(1000), "0 0 1\n1000 1000 1\n");
+    }
+
+    #[test]
+    fn test_private_path() {
+        assert_eq!(
+            ExecDirectoryType::State.private_path(Path::new("/var/lib/foo/bar")),
+            PathBuf::from("/var/lib/private/foo/bar")
+        );
+    }
+}
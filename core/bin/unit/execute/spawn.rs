@@ -11,23 +11,36 @@
 // See the Mulan PSL v2 for more details.
 
 use super::super::entry::Unit;
+use super::expand::{self, Specifiers};
+use super::hardening;
+use super::label;
+use super::namespace;
 use basic::fd_util;
+use nix::errno::Errno;
 use nix::fcntl::FcntlArg;
+use nix::mount::MsFlags;
+use nix::sched;
 use nix::sys::signal::{pthread_sigmask, SigmaskHow};
 use nix::sys::signalfd::SigSet;
 use nix::sys::stat::Mode;
 use nix::unistd::{self, chroot, setresgid, setresuid, ForkResult, Gid, Group, Pid, Uid, User};
-use regex::Regex;
+use std::collections::HashMap;
 use std::fs::Permissions;
-use std::os::unix::prelude::PermissionsExt;
-use std::path::PathBuf;
+use std::os::unix::prelude::{OsStrExt, PermissionsExt};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::rc::Rc;
 use sysmaster::error::*;
-use sysmaster::exec::{ExecCommand, ExecContext, ExecDirectoryType, ExecFlags, ExecParameters};
+use sysmaster::exec::{
+    credentials_dir, CpuSchedulingPolicy, CpuSet, ExecCommand, ExecContext, ExecDirectoryType,
+    ExecFlag, ExecFlags, ExecParameters, IoSchedulingClass, NumaPolicy, Rlimit,
+};
 use walkdir::DirEntry;
 use walkdir::WalkDir;
 
+const IOPRIO_CLASS_SHIFT: u32 = 13;
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
 pub(in crate::unit) struct ExecSpawn;
 
 impl ExecSpawn {
@@ -41,6 +54,7 @@ impl ExecSpawn {
         cmdline: &ExecCommand,
         params: &ExecParameters,
         ctx: Rc<ExecContext>,
+        default_rlimits: &[(u8, Rlimit)],
     ) -> Result<Pid> {
         let ret = unsafe { unistd::fork() };
 
@@ -55,7 +69,7 @@ impl ExecSpawn {
                 if pthread_sigmask(SigmaskHow::SIG_SETMASK, Some(&set), None).is_err() {
                     log::info!("Failed to reset the sigmask of child process, ignoring.");
                 }
-                exec_child(unit, cmdline, params, ctx);
+                exec_child(unit, cmdline, params, ctx, default_rlimits);
                 process::exit(0);
             }
             Err(_e) => Err(Error::SpawnError),
@@ -112,11 +126,115 @@ fn apply_working_directory(working_directory: Option<PathBuf>) -> Result<()> {
     std::env::set_current_dir(working_directory).context(IoSnafu)
 }
 
+// the directory of the dynamic user is created in "private", and linked to the
+// configured path, e.g. "/var/lib/foo" -> "/var/lib/private/foo"
+fn setup_private_directory(kind: ExecDirectoryType, d: &Path) -> Result<PathBuf> {
+    let private = kind.private_path(d);
+    let root = kind.prefix().join("private");
+    if !root.exists() {
+        std::fs::create_dir_all(&root).context(IoSnafu)?;
+    }
+    // only root can access the private directories from the host
+    std::fs::set_permissions(&root, Permissions::from_mode(0o700)).context(IoSnafu)?;
+
+    let is_symlink = matches!(d.symlink_metadata(), Ok(m) if m.is_symlink());
+    if !is_symlink {
+        if d.is_dir() && !private.exists() {
+            // migrate the directory used by the service before
+            if let Some(parent) = private.parent() {
+                std::fs::create_dir_all(parent).context(IoSnafu)?;
+            }
+            std::fs::rename(d, &private).context(IoSnafu)?;
+        }
+        if !d.exists() {
+            if let Some(parent) = d.parent() {
+                std::fs::create_dir_all(parent).context(IoSnafu)?;
+            }
+            std::os::unix::fs::symlink(&private, d).context(IoSnafu)?;
+        }
+    }
+    Ok(private)
+}
+
+fn is_mount_point(d: &Path) -> bool {
+    let parent = match d.parent() {
+        Some(v) => v,
+        None => return true,
+    };
+    match (nix::sys::stat::stat(d), nix::sys::stat::stat(parent)) {
+        (Ok(a), Ok(b)) => a.st_dev != b.st_dev,
+        _ => false,
+    }
+}
+
+fn mount_credentials_dir(d: &Path) -> Result<()> {
+    if is_mount_point(d) {
+        return Ok(());
+    }
+    // ramfs is never swapped out, tmpfs is the second choice, the credentials
+    // are never placed on the disk
+    let mut ret = Ok(());
+    for fstype in ["ramfs", "tmpfs"] {
+        match nix::mount::mount(
+            Some(fstype),
+            d,
+            Some(fstype),
+            MsFlags::MS_NODEV | MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID,
+            Some("mode=0700"),
+        ) {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                log::warn!("Failed to mount {fstype} on {:?}: {e}", d);
+                ret = Err(Error::Nix { source: e });
+            }
+        }
+    }
+    ret
+}
+
+fn setup_credentials(unit_id: &str, params: &ExecParameters, ctx: &ExecContext) -> Result<()> {
+    let credentials = ctx.credentials();
+    if credentials.is_empty() {
+        return Ok(());
+    }
+
+    let d = credentials_dir(unit_id);
+    std::fs::create_dir_all(&d).context(IoSnafu)?;
+    mount_credentials_dir(&d)?;
+    std::fs::set_permissions(&d, Permissions::from_mode(0o700)).context(IoSnafu)?;
+
+    let uid = params.get_user().map(|u| u.uid);
+    let gid = params.get_group().map(|g| g.gid);
+    for (id, credential) in credentials {
+        let data = match credential.load(&id) {
+            Ok(v) => v,
+            Err(e) => {
+                log::error!("Failed to load the credential {id}: {e}");
+                return Err(e);
+            }
+        };
+        let path = d.join(&id);
+        if path.exists() {
+            std::fs::set_permissions(&path, Permissions::from_mode(0o600)).context(IoSnafu)?;
+        }
+        std::fs::write(&path, data).context(IoSnafu)?;
+        std::fs::set_permissions(&path, Permissions::from_mode(0o400)).context(IoSnafu)?;
+        nix::unistd::chown(&path, uid, gid).context(NixSnafu)?;
+    }
+
+    nix::unistd::chown(&d, uid, gid).context(NixSnafu)?;
+    std::fs::set_permissions(&d, Permissions::from_mode(0o500)).context(IoSnafu)?;
+    params.add_env("CREDENTIALS_DIRECTORY", d.to_string_lossy().to_string());
+    Ok(())
+}
+
 fn setup_exec_directory(
     exec_directory: &[Option<Vec<PathBuf>>],
     user: Option<User>,
     group: Option<Group>,
     kind: ExecDirectoryType,
+    mode: u32,
+    private: bool,
 ) -> Result<()> {
     /* Always change the directory's owner, because sysmaster only
      * runs under system mode. */
@@ -130,6 +248,10 @@ fn setup_exec_directory(
     };
     if let Some(directories) = &exec_directory[kind as usize] {
         for d in directories {
+            let d = &match private {
+                true => setup_private_directory(kind, d)?,
+                false => d.clone(),
+            };
             /* d should be prefixed already, create it if it doesn't exist */
             if !d.exists() {
                 if let Err(e) = std::fs::create_dir_all(d) {
@@ -138,13 +260,16 @@ fn setup_exec_directory(
                 }
             }
 
-            if let Err(e) = nix::unistd::chown(d, Some(uid), Some(gid)) {
-                log::error!("Failed to set the owner of {:?}: {e}", d);
-                return Err(Error::Nix { source: e });
+            /* The configuration directory is owned by root, the service
+             * is only expected to read it. */
+            if kind != ExecDirectoryType::Config {
+                if let Err(e) = nix::unistd::chown(d, Some(uid), Some(gid)) {
+                    log::error!("Failed to set the owner of {:?}: {e}", d);
+                    return Err(Error::Nix { source: e });
+                }
             }
 
-            /* Hard-coding 755, change this when we implementing XXXDirectoryMode. */
-            if let Err(e) = std::fs::set_permissions(d, Permissions::from_mode(0o755)) {
+            if let Err(e) = std::fs::set_permissions(d, Permissions::from_mode(mode)) {
                 log::error!("Failed to set the permissions of {:?}: {e}", d);
                 return Err(Error::Io { source: e });
             }
@@ -164,7 +289,132 @@ fn apply_umask(umask: Option<Mode>) -> Result<()> {
     Ok(())
 }
 
-fn exec_child(unit: &Unit, cmdline: &ExecCommand, params: &ExecParameters, ctx: Rc<ExecContext>) {
+fn apply_oom_score_adjust(adjust: Option<i32>) -> Result<()> {
+    let adjust = match adjust {
+        None => return Ok(()),
+        Some(v) => v,
+    };
+    std::fs::write("/proc/self/oom_score_adj", adjust.to_string()).context(IoSnafu)
+}
+
+fn apply_nice(nice: Option<i32>) -> Result<()> {
+    let nice = match nice {
+        None => return Ok(()),
+        Some(v) => v,
+    };
+    let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    Errno::result(ret).context(NixSnafu)?;
+    Ok(())
+}
+
+fn apply_cpu_sched(policy: Option<CpuSchedulingPolicy>, priority: Option<i32>) -> Result<()> {
+    if policy.is_none() && priority.is_none() {
+        return Ok(());
+    }
+
+    // only the priority is configured, keep the inherited policy
+    let policy = match policy {
+        Some(v) => v.policy(),
+        None => Errno::result(unsafe { libc::sched_getscheduler(0) }).context(NixSnafu)?,
+    };
+
+    // the valid priority range depends on the policy, i.e. 1-99 for fifo and rr, 0 for others.
+    // the configured policy is checked on loading, the inherited one is refused here.
+    let min = unsafe { libc::sched_get_priority_min(policy) };
+    let max = unsafe { libc::sched_get_priority_max(policy) };
+    let sched_priority = priority.unwrap_or(min);
+    if !(min..=max).contains(&sched_priority) {
+        return Err(Error::Nix {
+            source: Errno::EINVAL,
+        });
+    }
+    let param = libc::sched_param { sched_priority };
+    let ret = unsafe { libc::sched_setscheduler(0, policy, &param) };
+    Errno::result(ret).context(NixSnafu)?;
+    Ok(())
+}
+
+fn apply_cpu_affinity(cpus: Option<CpuSet>) -> Result<()> {
+    let cpus = match cpus {
+        None => return Ok(()),
+        Some(v) => v,
+    };
+    let mut cpu_set = sched::CpuSet::new();
+    for index in cpus.indexes() {
+        cpu_set.set(*index).context(NixSnafu)?;
+    }
+    sched::sched_setaffinity(Pid::from_raw(0), &cpu_set).context(NixSnafu)
+}
+
+fn apply_numa(policy: Option<NumaPolicy>, mask: Option<CpuSet>) -> Result<()> {
+    let policy = match policy {
+        None => return Ok(()),
+        Some(v) => v,
+    };
+
+    let bits = libc::c_ulong::BITS as usize;
+    let mut nodes: Vec<libc::c_ulong> = Vec::new();
+    if let Some(mask) = mask {
+        for index in mask.indexes() {
+            if nodes.len() <= index / bits {
+                nodes.resize(index / bits + 1, 0);
+            }
+            nodes[index / bits] |= 1 << (index % bits);
+        }
+    }
+
+    // the kernel ignores the last bit of maxnode, see set_mempolicy(2)
+    let (nodemask, maxnode) = match nodes.is_empty() {
+        true => (std::ptr::null(), 0),
+        false => (nodes.as_ptr(), nodes.len() * bits + 1),
+    };
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_set_mempolicy,
+            policy.mode(),
+            nodemask,
+            maxnode as libc::c_ulong,
+        )
+    };
+    Errno::result(ret).context(NixSnafu)?;
+    Ok(())
+}
+
+fn apply_io_sched(class: Option<IoSchedulingClass>, priority: Option<u32>) -> Result<()> {
+    if class.is_none() && priority.is_none() {
+        return Ok(());
+    }
+
+    let class = class.unwrap_or(IoSchedulingClass::BestEffort);
+    let ioprio = (class.class() << IOPRIO_CLASS_SHIFT) | priority.unwrap_or(4);
+    let ret = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) };
+    Errno::result(ret).context(NixSnafu)?;
+    Ok(())
+}
+
+fn apply_sched_context(ctx: &ExecContext) -> Result<()> {
+    apply_oom_score_adjust(ctx.oom_score_adjust())?;
+    apply_nice(ctx.nice())?;
+    apply_cpu_sched(ctx.cpu_sched_policy(), ctx.cpu_sched_priority())?;
+    apply_numa(ctx.numa_policy(), ctx.numa_mask())?;
+    apply_cpu_affinity(ctx.cpu_affinity())?;
+    apply_io_sched(ctx.io_sched_class(), ctx.io_sched_priority())
+}
+
+fn apply_stdio_fd(fd: i32) -> Result<()> {
+    for target in [libc::STDIN_FILENO, libc::STDOUT_FILENO] {
+        unistd::dup2(fd, target).context(NixSnafu)?;
+    }
+    Ok(())
+}
+
+fn exec_child(
+    unit: &Unit,
+    cmdline: &ExecCommand,
+    params: &ExecParameters,
+    ctx: Rc<ExecContext>,
+    default_rlimits: &[(u8, Rlimit)],
+) {
     log::debug!("exec context params: {:?}", ctx.envs());
 
     if let Err(e) = apply_root_directory(params.get_root_directory()) {
@@ -172,13 +422,51 @@ fn exec_child(unit: &Unit, cmdline: &ExecCommand, params: &ExecParameters, ctx:
         return;
     }
 
-    if let Err(e) = setup_exec_directory(
-        params.get_exec_directory(),
-        params.get_user(),
-        params.get_group(),
-        ExecDirectoryType::Runtime,
-    ) {
-        log::error!("Failed to apply exec directory: {e}");
+    for kind in ExecDirectoryType::ALL {
+        if let Err(e) = setup_exec_directory(
+            params.get_exec_directory(),
+            params.get_user(),
+            params.get_group(),
+            kind,
+            params.get_exec_directory_mode(kind),
+            namespace::directory_is_private(params, kind),
+        ) {
+            log::error!("Failed to apply exec directory: {e}");
+            return;
+        }
+    }
+
+    if let Err(e) = setup_credentials(unit.id(), params, &ctx) {
+        log::error!("Failed to set up the credentials: {e}");
+        return;
+    }
+
+    // must be done before dropping the privileges and entering the user namespace,
+    // raising the priorities or the limits, or lowering the oom score needs
+    // CAP_SYS_NICE or CAP_SYS_RESOURCE of the host.
+    if let Err(e) = apply_sched_context(&ctx) {
+        log::error!("Failed to apply scheduling parameters: {e}");
+        return;
+    }
+
+    if let Err(e) = set_all_rlimits(ctx.clone(), default_rlimits) {
+        log::error!("failed to set rlimit: {}", e.to_string());
+        return;
+    }
+
+    if let Err(e) = namespace::setup_namespace(unit.id(), params, &ctx) {
+        log::error!("Failed to set up the namespaces: {e}");
+        return;
+    }
+
+    // the system call filters are installed while CAP_SYS_ADMIN is still held
+    if let Err(e) = hardening::apply_hardening(&ctx) {
+        log::error!("Failed to apply the kernel hardening: {e}");
+        return;
+    }
+
+    if let Err(e) = label::apply_process_label(&ctx) {
+        log::error!("Failed to apply the security label: {e}");
         return;
     }
 
@@ -206,7 +494,12 @@ fn exec_child(unit: &Unit, cmdline: &ExecCommand, params: &ExecParameters, ctx:
         params.add_env(&key, value.to_string());
     }
 
-    let (cmd, args) = build_run_args(unit, cmdline, params);
+    let mut envs = build_environment(unit, params);
+    envs.append(&mut params.envs());
+
+    log::debug!("exec child env env is: {:?}", envs);
+
+    let (cmd, args) = build_run_args(unit, cmdline, params, &envs);
     let cstr_args = args
         .iter()
         .map(|cstring| cstring.as_c_str())
@@ -218,17 +511,21 @@ fn exec_child(unit: &Unit, cmdline: &ExecCommand, params: &ExecParameters, ctx:
         args
     );
 
-    let mut envs = build_environment(unit, params);
-    envs.append(&mut params.envs());
-
-    log::debug!("exec child env env is: {:?}", envs);
+    let envs_cstr = envs.iter().map(|v| v.as_c_str()).collect::<Vec<_>>();
+    if let Some(fd) = params.stdio_fd() {
+        if let Err(e) = apply_stdio_fd(fd) {
+            log::error!("Failed to apply standard input and output: {e}");
+            return;
+        }
+    }
 
-    if let Err(e) = set_all_rlimits(ctx) {
-        log::error!("failed to set rlimit: {}", e.to_string());
+    // the labels only take effect at execve(2), the socket is read before the fds are shifted
+    let path = Path::new(std::ffi::OsStr::from_bytes(cmd.as_bytes()));
+    if let Err(e) = label::apply_exec_labels(&ctx, path, params.socket_fd()) {
+        log::error!("Failed to apply the security labels: {e}");
         return;
     }
 
-    let envs_cstr = envs.iter().map(|v| v.as_c_str()).collect::<Vec<_>>();
     let mut keep_fds = params.fds();
 
     let ret = close_all_fds(params.fds());
@@ -261,40 +558,48 @@ fn exec_child(unit: &Unit, cmdline: &ExecCommand, params: &ExecParameters, ctx:
 
 // contrast: build_environment
 fn build_run_args(
-    _unit: &Unit,
+    unit: &Unit,
     cmdline: &ExecCommand,
-    env: &ExecParameters,
+    params: &ExecParameters,
+    envs: &[std::ffi::CString],
 ) -> (std::ffi::CString, Vec<std::ffi::CString>) {
-    let cmd = std::ffi::CString::new(cmdline.path().clone()).unwrap();
-    let exec_name = std::ffi::CString::new(cmdline.path().clone()).unwrap();
-
-    let mut args = Vec::new();
-    args.push(exec_name);
-
-    let var_regex = Regex::new(r"(\$[A-Z_]+)|(\$\{[A-Z_]+\})").unwrap();
-    for arg in cmdline.argv() {
-        let cap = var_regex.captures(arg);
-        if let Some(cap) = cap {
-            let match_result = {
-                if let Some(mat) = cap.get(1) {
-                    Some(mat.as_str())
-                } else {
-                    cap.get(2).map(|mat| mat.as_str())
-                }
-            };
+    // the ':' prefix disables both the specifiers and the environment variables
+    let no_expand = cmdline
+        .get_exec_flag()
+        .contains(ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND);
+    let specifiers = Specifiers::new(unit.id(), params.get_user());
+
+    let (path, mut argv) = match no_expand {
+        true => (
+            cmdline.path().clone(),
+            cmdline.argv().into_iter().cloned().collect::<Vec<_>>(),
+        ),
+        false => (
+            specifiers.expand(cmdline.path()),
+            cmdline
+                .argv()
+                .into_iter()
+                .map(|arg| specifiers.expand(arg))
+                .collect::<Vec<_>>(),
+        ),
+    };
 
-            if let Some(val) = match_result {
-                let v = val.trim_matches('$').trim_matches('{').trim_matches('}');
-                if let Some(target) = env.get_env(v) {
-                    args.push(
-                        std::ffi::CString::new(var_regex.replace(arg, target).to_string()).unwrap(),
-                    );
-                };
-            }
-            continue;
-        }
+    if !no_expand {
+        let envs = envs
+            .iter()
+            .filter_map(|e| e.to_str().ok()?.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        argv = expand::expand_environment(&argv, &envs);
+    }
 
-        args.push(std::ffi::CString::new(arg.as_str()).unwrap())
+    let cmd = std::ffi::CString::new(path.clone()).unwrap();
+    let mut args = vec![std::ffi::CString::new(path).unwrap()];
+    for arg in argv {
+        match std::ffi::CString::new(arg) {
+            Ok(v) => args.push(v),
+            Err(e) => log::warn!("Invalid argument {:?}, ignoring.", e.into_vec()),
+        }
     }
 
     (cmd, args)
@@ -418,6 +723,6 @@ fn flags_fds(fds: &mut Vec<i32>, nonblock: bool) -> bool {
     true
 }
 
-fn set_all_rlimits(ctx: Rc<ExecContext>) -> Result<()> {
-    ctx.set_all_rlimits()
+fn set_all_rlimits(ctx: Rc<ExecContext>, default_rlimits: &[(u8, Rlimit)]) -> Result<()> {
+    ctx.set_all_rlimits(default_rlimits)
 }
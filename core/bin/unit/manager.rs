@@ -48,7 +48,7 @@ use std::path::PathBuf;
 use std::rc::Rc;
 use sysmaster::error::*;
 use sysmaster::exec::ExecParameters;
-use sysmaster::exec::{ExecCommand, ExecContext};
+use sysmaster::exec::{ExecCommand, ExecContext, ExecDirectoryType};
 use sysmaster::rel::{ReStation, ReStationKind, ReliLastFrame, Reliability};
 use sysmaster::unit::{UmIf, UnitActiveState, UnitDependencyMask, UnitStatus, UnitType};
 use unit_submanager::UnitSubManagers;
@@ -139,6 +139,10 @@ impl UnitManagerX {
         self.data.restart_unit(name, is_manual)
     }
 
+    pub(crate) fn clean_unit(&self, name: &str, what: &[ExecDirectoryType]) -> Result<()> {
+        self.data.clean_unit(name, what)
+    }
+
     pub(crate) fn get_unit_status(&self, name: &str) -> Result<UnitStatus> {
         self.data.get_unit_status(name)
     }
@@ -374,7 +378,9 @@ impl UmIf for UnitManager {
     ) -> Result<Pid> {
         let unit = self.units_get(unit);
         if let Some(u) = unit {
-            self.exec.spawn(&u, cmdline, params, ctx)
+            // DefaultLimit* only take effect when the unit does not configure its own Limit*
+            let default_rlimits = self.manager_config.default_rlimits();
+            self.exec.spawn(&u, cmdline, params, ctx, &default_rlimits)
         } else {
             Err(Error::SpawnError)
         }
@@ -400,6 +406,20 @@ impl UmIf for UnitManager {
         self.collect_socket_fds(name)
     }
 
+    fn unit_set_socket_fd(&self, name: &str, fd: i32) -> Result<()> {
+        if let Some(unit) = self.db.units_get(name) {
+            unit.set_socket_fd(fd)
+        } else {
+            Err(Error::UnitActionENoent)
+        }
+    }
+
+    fn unit_release_socket_fd(&self, name: &str) {
+        if let Some(unit) = self.db.units_get(name) {
+            unit.release_socket_fd();
+        }
+    }
+
     fn get_dependency_list(&self, _unit_name: &str, _atom: UnitRelationAtom) -> Vec<String> {
         self.get_dependency_list(_unit_name, _atom)
     }
@@ -811,6 +831,13 @@ impl UnitManager {
         }
     }
 
+    pub(self) fn clean_unit(&self, name: &str, what: &[ExecDirectoryType]) -> Result<()> {
+        match self.load_unitx(name) {
+            None => Err(Error::UnitActionENoent),
+            Some(unit) => unit.clean(what),
+        }
+    }
+
     pub(self) fn restart_unit(&self, name: &str, is_manual: bool) -> Result<()> {
         let unit = match self.load_unitx(name) {
             None => {
@@ -888,7 +915,7 @@ impl UnitManager {
             UnitActiveState::UnitFailed | UnitActiveState::UnitInActive => 3,
             _ => 0,
         };
-        Ok(UnitStatus::new(
+        let mut status = UnitStatus::new(
             name.to_string(),
             unit.get_description(),
             unit.get_documentation(),
@@ -898,7 +925,9 @@ impl UnitManager {
             self.get_unit_cgroup_path(unit.clone()),
             self.get_unit_status_pids(unit.clone()),
             error_code,
-        ))
+        );
+        status.add_extra(unit.get_status_extra());
+        Ok(status)
     }
 
     pub(self) fn get_all_units(&self) -> Result<String> {
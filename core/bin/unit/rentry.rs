@@ -252,16 +252,30 @@ impl UnitReConfig {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 struct UnitReCgroup {
     cg_path: PathBuf,
+    #[serde(default)]
+    firewall: Option<UnitReFirewall>,
 }
 
 impl UnitReCgroup {
-    fn new(cg_pathr: &Path) -> UnitReCgroup {
+    fn new(cg_pathr: &Path, firewallr: Option<&UnitReFirewall>) -> UnitReCgroup {
         UnitReCgroup {
             cg_path: cg_pathr.to_path_buf(),
+            firewall: firewallr.cloned(),
         }
     }
 }
 
+/// the IP firewall attached to the cgroup, the programs stay attached across the reexecution
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct UnitReFirewall {
+    pub(super) allow: Vec<String>,
+    pub(super) deny: Vec<String>,
+    pub(super) accounting: bool,
+    // attach type and program id
+    pub(super) progs: Vec<(u32, u32)>,
+    pub(super) accounting_map: Option<u32>,
+}
+
 bitflags! {
     #[derive(Serialize, Deserialize)]
     pub(super) struct UnitRePps: u32 {
@@ -414,10 +428,15 @@ impl UnitRe {
         u_conf.map(|c| (c.unit, c.install))
     }
 
-    pub(super) fn cgroup_insert(&self, unit_id: &String, cg_path: &Path) {
+    pub(super) fn cgroup_insert(
+        &self,
+        unit_id: &String,
+        cg_path: &Path,
+        firewall: Option<&UnitReFirewall>,
+    ) {
         assert!(self.base_contains(unit_id));
 
-        let u_cgroup = UnitReCgroup::new(cg_path);
+        let u_cgroup = UnitReCgroup::new(cg_path, firewall);
         self.cgroup.insert(unit_id.clone(), u_cgroup);
     }
 
@@ -426,9 +445,9 @@ impl UnitRe {
         self.cgroup.remove(unit_id);
     }
 
-    pub(super) fn cgroup_get(&self, unit_id: &String) -> Option<PathBuf> {
+    pub(super) fn cgroup_get(&self, unit_id: &String) -> Option<(PathBuf, Option<UnitReFirewall>)> {
         let u_cgroup = self.cgroup.get(unit_id);
-        u_cgroup.map(|c| c.cg_path)
+        u_cgroup.map(|c| (c.cg_path, c.firewall))
     }
 
     pub(super) fn child_insert(&self, unit_id: &String, pids: &[Pid]) {
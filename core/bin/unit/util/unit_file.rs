@@ -12,6 +12,7 @@
 
 use basic::path_lookup::LookupPaths;
 use basic::time_util;
+use basic::unit_name::unit_name_template;
 use siphasher::sip::SipHasher24;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -102,6 +103,19 @@ impl UnitFileData {
     }
 
     fn build_id_fragment(&mut self, name: &String) {
+        let mut pathbuf_fragment = self.search_fragment(name);
+        // an instance without its own unit file is loaded from the template, foo@bar.service -> foo@.service
+        if pathbuf_fragment.is_empty() {
+            if let Some(template) = unit_name_template(name) {
+                pathbuf_fragment = self.search_fragment(&template);
+            }
+        }
+
+        self.unit_id_fragment
+            .insert(name.to_string(), pathbuf_fragment);
+    }
+
+    fn search_fragment(&self, name: &str) -> Vec<PathBuf> {
         let mut pathbuf_fragment = Vec::new();
         for v in &self.lookup_path.search_path {
             if let Err(_e) = fs::metadata(v) {
@@ -143,8 +157,7 @@ impl UnitFileData {
             }
         }
 
-        self.unit_id_fragment
-            .insert(name.to_string(), pathbuf_fragment);
+        pathbuf_fragment
     }
 
     fn build_id_dropin(&mut self, name: &String, suffix: String) {
@@ -12,9 +12,11 @@
 
 use crate::error::*;
 use crate::serialize::DeserializeWith;
-use basic::rlimit_util;
+use basic::time_util::{self, USEC_PER_SEC};
+use basic::{credential_util, parse_util, rlimit_util};
 use bitflags::bitflags;
 use libc::EPERM;
+use nix::sched::CloneFlags;
 use nix::sys::stat::Mode;
 use nix::unistd::{Group, Uid, User};
 use serde::{de, Deserialize, Deserializer, Serialize};
@@ -66,7 +68,22 @@ impl Rlimit {
     }
 }
 
-fn parse_rlimit(limit: &str) -> Result<u64, Error> {
+/// how the value of a resource limit is written in the unit file
+#[derive(Clone, Copy)]
+enum RlimitUnit {
+    /// a plain number
+    Count,
+    /// bytes, with an optional K/M/G.. suffix
+    Bytes,
+    /// seconds, with an optional time unit
+    Sec,
+    /// microseconds, with an optional time unit
+    Usec,
+    /// a nice level like "-5", or the raw value in [0, 40]
+    Nice,
+}
+
+fn parse_rlimit(limit: &str, unit: RlimitUnit) -> Result<u64, Error> {
     if limit.is_empty() {
         return Err(Error::ConfigureError {
             msg: "empty configure for Limit".to_string(),
@@ -76,22 +93,52 @@ fn parse_rlimit(limit: &str) -> Result<u64, Error> {
         return Ok(rlimit_util::INFINITY);
     }
 
+    let ret = match unit {
+        RlimitUnit::Count => limit.parse::<u64>()?,
+        RlimitUnit::Bytes => parse_util::parse_size(limit)?,
+        RlimitUnit::Sec => {
+            let usec = time_util::parse_timespan(limit, USEC_PER_SEC)?;
+            // round up, a non-zero time span should never become an unlimited 0
+            usec.div_ceil(USEC_PER_SEC)
+        }
+        RlimitUnit::Usec => time_util::parse_timespan(limit, 1)?,
+        RlimitUnit::Nice => parse_rlimit_nice(limit)?,
+    };
+    Ok(ret)
+}
+
+fn parse_rlimit_nice(limit: &str) -> Result<u64, Error> {
+    // the rlimit of nice is 20 - nice, so the nice level [-20, 19] maps to [40, 1]
+    if limit.starts_with('+') || limit.starts_with('-') {
+        let nice = limit.parse::<i64>()?;
+        if !(-20..=19).contains(&nice) {
+            return Err(Error::ConfigureError {
+                msg: format!("nice level {nice} is out of range [-20, 19]"),
+            });
+        }
+        return Ok((20 - nice) as u64);
+    }
+
     let ret = limit.parse::<u64>()?;
+    if ret > 40 {
+        return Err(Error::ConfigureError {
+            msg: format!("nice limit {ret} is out of range [0, 40]"),
+        });
+    }
     Ok(ret)
 }
 
-impl FromStr for Rlimit {
-    type Err = Error;
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+impl Rlimit {
+    fn parse(s: &str, unit: RlimitUnit) -> Result<Rlimit> {
         let value: Vec<_> = s.trim().split_terminator(':').collect();
         let soft: u64;
         let hard: u64;
         if value.len() == 1 {
-            soft = parse_rlimit(value[0])?;
+            soft = parse_rlimit(value[0], unit)?;
             hard = soft;
         } else if value.len() == 2 {
-            soft = parse_rlimit(value[0])?;
-            hard = parse_rlimit(value[1])?;
+            soft = parse_rlimit(value[0], unit)?;
+            hard = parse_rlimit(value[1], unit)?;
         } else {
             return Err(Error::ConfigureError {
                 msg: "invalid configure for Limit".to_string(),
@@ -106,18 +153,682 @@ impl FromStr for Rlimit {
 
         Ok(Rlimit { soft, hard })
     }
+
+    fn deserialize_unit<'de, D>(de: D, unit: RlimitUnit) -> Result<Rlimit, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        Rlimit::parse(&s, unit).map_err(de::Error::custom)
+    }
+
+    /// deserialize the rlimit in bytes, like LimitFSIZE="4K:1G"
+    pub fn deserialize_bytes<'de, D>(de: D) -> Result<Rlimit, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Rlimit::deserialize_unit(de, RlimitUnit::Bytes)
+    }
+
+    /// deserialize the rlimit in seconds, like LimitCPU="1h"
+    pub fn deserialize_sec<'de, D>(de: D) -> Result<Rlimit, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Rlimit::deserialize_unit(de, RlimitUnit::Sec)
+    }
+
+    /// deserialize the rlimit in microseconds, like LimitRTTIME="500ms"
+    pub fn deserialize_usec<'de, D>(de: D) -> Result<Rlimit, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Rlimit::deserialize_unit(de, RlimitUnit::Usec)
+    }
+
+    /// deserialize the rlimit of nice, like LimitNICE="-5"
+    pub fn deserialize_nice<'de, D>(de: D) -> Result<Rlimit, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Rlimit::deserialize_unit(de, RlimitUnit::Nice)
+    }
+}
+
+impl FromStr for Rlimit {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Rlimit::parse(s, RlimitUnit::Count)
+    }
 }
 
 impl DeserializeWith for Rlimit {
     type Item = Self;
 
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Rlimit::deserialize_unit(de, RlimitUnit::Count)
+    }
+}
+
+/// the cpu scheduling policy, see sched(7)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CpuSchedulingPolicy {
+    /// SCHED_OTHER
+    Other,
+    /// SCHED_BATCH
+    Batch,
+    /// SCHED_IDLE
+    Idle,
+    /// SCHED_FIFO
+    Fifo,
+    /// SCHED_RR
+    Rr,
+}
+
+impl CpuSchedulingPolicy {
+    /// return the policy value used by sched_setscheduler
+    pub fn policy(&self) -> i32 {
+        match self {
+            CpuSchedulingPolicy::Other => libc::SCHED_OTHER,
+            CpuSchedulingPolicy::Batch => libc::SCHED_BATCH,
+            CpuSchedulingPolicy::Idle => libc::SCHED_IDLE,
+            CpuSchedulingPolicy::Fifo => libc::SCHED_FIFO,
+            CpuSchedulingPolicy::Rr => libc::SCHED_RR,
+        }
+    }
+
+    /// return the priority range allowed by the policy
+    pub fn priority_range(&self) -> std::ops::RangeInclusive<i32> {
+        match self {
+            CpuSchedulingPolicy::Fifo | CpuSchedulingPolicy::Rr => 1..=99,
+            _ => 0..=0,
+        }
+    }
+}
+
+impl FromStr for CpuSchedulingPolicy {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let policy = match s.trim() {
+            "other" => CpuSchedulingPolicy::Other,
+            "batch" => CpuSchedulingPolicy::Batch,
+            "idle" => CpuSchedulingPolicy::Idle,
+            "fifo" => CpuSchedulingPolicy::Fifo,
+            "rr" => CpuSchedulingPolicy::Rr,
+            _ => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid configure for CPUSchedulingPolicy: {s}"),
+                })
+            }
+        };
+        Ok(policy)
+    }
+}
+
+impl DeserializeWith for CpuSchedulingPolicy {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        CpuSchedulingPolicy::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// the io scheduling class, see ioprio_set(2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IoSchedulingClass {
+    /// IOPRIO_CLASS_RT
+    Realtime,
+    /// IOPRIO_CLASS_BE
+    BestEffort,
+    /// IOPRIO_CLASS_IDLE
+    Idle,
+}
+
+impl IoSchedulingClass {
+    /// return the class value used by ioprio_set
+    pub fn class(&self) -> u32 {
+        match self {
+            IoSchedulingClass::Realtime => 1,
+            IoSchedulingClass::BestEffort => 2,
+            IoSchedulingClass::Idle => 3,
+        }
+    }
+}
+
+impl FromStr for IoSchedulingClass {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let class = match s.trim() {
+            "realtime" => IoSchedulingClass::Realtime,
+            "best-effort" => IoSchedulingClass::BestEffort,
+            "idle" => IoSchedulingClass::Idle,
+            _ => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid configure for IOSchedulingClass: {s}"),
+                })
+            }
+        };
+        Ok(class)
+    }
+}
+
+impl DeserializeWith for IoSchedulingClass {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        IoSchedulingClass::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// which parts of the file system are read-only for the commands
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ProtectSystem {
+    /// nothing is read-only
+    #[default]
+    No,
+    /// /usr, /boot and /efi are read-only
+    Yes,
+    /// /etc is read-only too
+    Full,
+    /// the whole file system is read-only except /dev, /proc, /sys and the exec directories
+    Strict,
+}
+
+impl ProtectSystem {
+    /// the read-only directories, "/" means the whole file system
+    pub fn read_only_paths(&self) -> &'static [&'static str] {
+        match self {
+            ProtectSystem::No => &[],
+            ProtectSystem::Yes => &["/usr", "/boot", "/efi"],
+            ProtectSystem::Full => &["/usr", "/boot", "/efi", "/etc"],
+            ProtectSystem::Strict => &["/"],
+        }
+    }
+}
+
+impl FromStr for ProtectSystem {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let protect = match s.trim() {
+            "no" | "false" => ProtectSystem::No,
+            "yes" | "true" => ProtectSystem::Yes,
+            "full" => ProtectSystem::Full,
+            "strict" => ProtectSystem::Strict,
+            _ => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid configure for ProtectSystem: {s}"),
+                })
+            }
+        };
+        Ok(protect)
+    }
+}
+
+impl DeserializeWith for ProtectSystem {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        ProtectSystem::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// the namespaces that RestrictNamespaces knows
+pub const NAMESPACE_FLAGS: [(&str, CloneFlags); 7] = [
+    ("cgroup", CloneFlags::CLONE_NEWCGROUP),
+    ("ipc", CloneFlags::CLONE_NEWIPC),
+    ("net", CloneFlags::CLONE_NEWNET),
+    ("mnt", CloneFlags::CLONE_NEWNS),
+    ("pid", CloneFlags::CLONE_NEWPID),
+    ("user", CloneFlags::CLONE_NEWUSER),
+    ("uts", CloneFlags::CLONE_NEWUTS),
+];
+
+/// the namespace flags that the commands are allowed to use, None if not restricted
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RestrictNamespaces(Option<i32>);
+
+impl RestrictNamespaces {
+    /// return the namespace flags that unshare(2), clone(2) and setns(2) must not use
+    pub fn denied(&self) -> CloneFlags {
+        let all = NAMESPACE_FLAGS
+            .iter()
+            .fold(CloneFlags::empty(), |all, (_, flag)| all | *flag);
+        match self.0 {
+            None => CloneFlags::empty(),
+            Some(allowed) => all - CloneFlags::from_bits_truncate(allowed),
+        }
+    }
+}
+
+impl FromStr for RestrictNamespaces {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "" | "no" | "false" => return Ok(RestrictNamespaces(None)),
+            "yes" | "true" => return Ok(RestrictNamespaces(Some(0))),
+            _ => {}
+        }
+
+        // "~" inverts the list, the listed namespaces are denied
+        let (invert, list) = match s.strip_prefix('~') {
+            Some(list) => (true, list),
+            None => (false, s),
+        };
+        let mut flags = CloneFlags::empty();
+        for name in list.split_whitespace() {
+            match NAMESPACE_FLAGS.iter().find(|(n, _)| *n == name) {
+                Some((_, flag)) => flags |= *flag,
+                None => {
+                    return Err(Error::ConfigureError {
+                        msg: format!("invalid namespace in RestrictNamespaces: {name}"),
+                    })
+                }
+            }
+        }
+        if invert {
+            let all = NAMESPACE_FLAGS
+                .iter()
+                .fold(CloneFlags::empty(), |all, (_, flag)| all | *flag);
+            flags = all - flags;
+        }
+        Ok(RestrictNamespaces(Some(flags.bits())))
+    }
+}
+
+impl DeserializeWith for RestrictNamespaces {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        RestrictNamespaces::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// the address families that RestrictAddressFamilies knows
+pub const ADDRESS_FAMILIES: [(&str, i32); 16] = [
+    ("AF_UNIX", libc::AF_UNIX),
+    ("AF_LOCAL", libc::AF_LOCAL),
+    ("AF_INET", libc::AF_INET),
+    ("AF_INET6", libc::AF_INET6),
+    ("AF_NETLINK", libc::AF_NETLINK),
+    ("AF_PACKET", libc::AF_PACKET),
+    ("AF_KEY", libc::AF_KEY),
+    ("AF_BLUETOOTH", libc::AF_BLUETOOTH),
+    ("AF_CAN", libc::AF_CAN),
+    ("AF_TIPC", libc::AF_TIPC),
+    ("AF_ALG", libc::AF_ALG),
+    ("AF_NFC", libc::AF_NFC),
+    ("AF_VSOCK", libc::AF_VSOCK),
+    ("AF_XDP", libc::AF_XDP),
+    ("AF_APPLETALK", libc::AF_APPLETALK),
+    ("AF_X25", libc::AF_X25),
+];
+
+/// the address families that socket(2) accepts, None if not restricted
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RestrictAddressFamilies(Option<(bool, Vec<i32>)>);
+
+impl RestrictAddressFamilies {
+    /// return whether the families are allowed or denied, and the families
+    pub fn families(&self) -> Option<(bool, Vec<i32>)> {
+        self.0.clone()
+    }
+}
+
+impl FromStr for RestrictAddressFamilies {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        match s {
+            "" => return Ok(RestrictAddressFamilies(None)),
+            "none" => return Ok(RestrictAddressFamilies(Some((true, Vec::new())))),
+            _ => {}
+        }
+
+        // "~" inverts the list, the listed families are denied
+        let (allow, list) = match s.strip_prefix('~') {
+            Some(list) => (false, list),
+            None => (true, s),
+        };
+        let mut families = Vec::new();
+        for name in list.split_whitespace() {
+            match ADDRESS_FAMILIES.iter().find(|(n, _)| *n == name) {
+                Some((_, family)) => families.push(*family),
+                None => {
+                    return Err(Error::ConfigureError {
+                        msg: format!("invalid address family in RestrictAddressFamilies: {name}"),
+                    })
+                }
+            }
+        }
+        families.sort_unstable();
+        families.dedup();
+        Ok(RestrictAddressFamilies(Some((allow, families))))
+    }
+}
+
+impl DeserializeWith for RestrictAddressFamilies {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        RestrictAddressFamilies::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+bitflags! {
+    /// the kernel hardening switches of the commands
+    pub struct ExecHardening: u16 {
+        /// /proc/sys, /sys and the other kernel tunables are read-only
+        const PROTECT_KERNEL_TUNABLES = 1 << 0;
+        /// CAP_SYS_MODULE is dropped and the kernel modules are inaccessible
+        const PROTECT_KERNEL_MODULES = 1 << 1;
+        /// syslog(2), /dev/kmsg and /proc/kmsg are denied
+        const PROTECT_KERNEL_LOGS = 1 << 2;
+        /// the cgroup file system is read-only
+        const PROTECT_CONTROL_GROUPS = 1 << 3;
+        /// the execution domain can't be changed by personality(2)
+        const LOCK_PERSONALITY = 1 << 4;
+        /// the memory mappings can't be writable and executable at the same time
+        const MEMORY_DENY_WRITE_EXECUTE = 1 << 5;
+        /// the commands get their own UTS namespace and can't change the host name
+        const PROTECT_HOSTNAME = 1 << 6;
+    }
+}
+
+/// the config names of the hardening switches
+pub const HARDENING_NAMES: [(&str, ExecHardening); 7] = [
+    (
+        "ProtectKernelTunables",
+        ExecHardening::PROTECT_KERNEL_TUNABLES,
+    ),
+    (
+        "ProtectKernelModules",
+        ExecHardening::PROTECT_KERNEL_MODULES,
+    ),
+    ("ProtectKernelLogs", ExecHardening::PROTECT_KERNEL_LOGS),
+    (
+        "ProtectControlGroups",
+        ExecHardening::PROTECT_CONTROL_GROUPS,
+    ),
+    ("LockPersonality", ExecHardening::LOCK_PERSONALITY),
+    (
+        "MemoryDenyWriteExecute",
+        ExecHardening::MEMORY_DENY_WRITE_EXECUTE,
+    ),
+    ("ProtectHostname", ExecHardening::PROTECT_HOSTNAME),
+];
+
+/// summarize how exposed the commands are, the value ranges from 0.0 (all the switches
+/// are on) to 10.0 (none is on), followed by the switches that are off
+pub fn exposure_summary(switches: &[(&str, bool)]) -> String {
+    if switches.is_empty() {
+        return "0.0 OK".to_string();
+    }
+    let off: Vec<&str> = switches
+        .iter()
+        .filter(|(_, on)| !on)
+        .map(|(name, _)| *name)
+        .collect();
+    let exposure = off.len() as f64 * 10.0 / switches.len() as f64;
+    let level = match exposure {
+        e if e < 2.0 => "OK",
+        e if e < 5.0 => "MEDIUM",
+        e if e < 8.0 => "EXPOSED",
+        _ => "UNSAFE",
+    };
+    match off.is_empty() {
+        true => format!("{exposure:.1} {level}"),
+        false => format!("{exposure:.1} {level}; unprotected: {}", off.join(", ")),
+    }
+}
+
+/// the private /tmp and /var/tmp of the unit, they are shared by all the commands of the unit
+/// and should be removed when the unit stops
+pub fn private_tmp_dirs(unit_id: &str) -> [PathBuf; 2] {
+    let name = format!("sysmaster-private-{unit_id}");
+    [
+        Path::new("/tmp").join(&name),
+        Path::new("/var/tmp").join(&name),
+    ]
+}
+
+/// the directory that the credentials of the unit are placed in
+pub fn credentials_dir(unit_id: &str) -> PathBuf {
+    Path::new(credential_util::CREDENTIALS_DIR).join(unit_id)
+}
+
+/// where the content of a credential comes from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExecCredential {
+    /// LoadCredential, read from the file
+    Load(PathBuf),
+    /// SetCredential, the configured value
+    Set(String),
+    /// LoadCredentialEncrypted, read from the file and decrypted with the host key
+    LoadEncrypted(PathBuf),
+}
+
+/// the kinds of the credential settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecCredentialType {
+    /// LoadCredential
+    Load,
+    /// SetCredential
+    Set,
+    /// LoadCredentialEncrypted
+    LoadEncrypted,
+}
+
+impl ExecCredential {
+    /// parse "id:value" to the credential id and the credential
+    pub fn parse(s: &str, kind: ExecCredentialType) -> Result<(String, ExecCredential)> {
+        let (id, value) = match s.split_once(':') {
+            Some(v) => v,
+            None => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid credential, \"id:value\" is expected: {s}"),
+                })
+            }
+        };
+        if !credential_util::credential_name_valid(id) {
+            return Err(Error::ConfigureError {
+                msg: format!("invalid credential id: {id}"),
+            });
+        }
+        if kind == ExecCredentialType::Set {
+            return Ok((id.to_string(), ExecCredential::Set(value.to_string())));
+        }
+
+        let path = PathBuf::from(value);
+        if !path.is_absolute() {
+            return Err(Error::ConfigureError {
+                msg: format!("the credential path must be absolute: {value}"),
+            });
+        }
+        let credential = match kind {
+            ExecCredentialType::LoadEncrypted => ExecCredential::LoadEncrypted(path),
+            _ => ExecCredential::Load(path),
+        };
+        Ok((id.to_string(), credential))
+    }
+
+    /// return the content of the credential
+    pub fn load(&self, id: &str) -> Result<Vec<u8>> {
+        match self {
+            ExecCredential::Load(path) => std::fs::read(path).context(IoSnafu),
+            ExecCredential::Set(value) => Ok(value.as_bytes().to_vec()),
+            ExecCredential::LoadEncrypted(path) => {
+                let data = std::fs::read(path).context(IoSnafu)?;
+                credential_util::decrypt_credential(
+                    Path::new(credential_util::CREDENTIAL_HOST_KEY),
+                    id,
+                    &data,
+                )
+                .context(UtilSnafu)
+            }
+        }
+    }
+}
+
+/// the numa memory policy, see set_mempolicy(2)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NumaPolicy {
+    /// MPOL_DEFAULT
+    Default,
+    /// MPOL_PREFERRED
+    Preferred,
+    /// MPOL_BIND
+    Bind,
+    /// MPOL_INTERLEAVE
+    Interleave,
+    /// MPOL_LOCAL
+    Local,
+}
+
+impl NumaPolicy {
+    /// return the mode value used by set_mempolicy
+    pub fn mode(&self) -> i32 {
+        match self {
+            NumaPolicy::Default => 0,
+            NumaPolicy::Preferred => 1,
+            NumaPolicy::Bind => 2,
+            NumaPolicy::Interleave => 3,
+            NumaPolicy::Local => 4,
+        }
+    }
+
+    /// whether the policy can not work without a node mask
+    pub fn need_nodes(&self) -> bool {
+        matches!(self, NumaPolicy::Bind | NumaPolicy::Interleave)
+    }
+}
+
+impl FromStr for NumaPolicy {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let policy = match s.trim() {
+            "default" => NumaPolicy::Default,
+            "preferred" => NumaPolicy::Preferred,
+            "bind" => NumaPolicy::Bind,
+            "interleave" => NumaPolicy::Interleave,
+            "local" => NumaPolicy::Local,
+            _ => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid configure for NUMAPolicy: {s}"),
+                })
+            }
+        };
+        Ok(policy)
+    }
+}
+
+impl DeserializeWith for NumaPolicy {
+    type Item = Self;
+
     fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
     where
         D: Deserializer<'de>,
     {
         let s = String::deserialize(de)?;
-        let rlimit = Rlimit::from_str(&s).map_err(de::Error::custom)?;
-        Ok(rlimit)
+        NumaPolicy::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+/// the max index of cpu or numa node that can be configured
+const CPU_SET_MAX: usize = 8192;
+
+/// a set of cpu or numa node indexes, like "0-3 5,7"
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuSet {
+    indexes: Vec<usize>,
+}
+
+impl CpuSet {
+    /// return the sorted indexes in the set
+    pub fn indexes(&self) -> &Vec<usize> {
+        &self.indexes
+    }
+
+    /// whether no index is in the set
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+}
+
+fn parse_cpu_index(s: &str) -> Result<usize> {
+    let index = s.parse::<usize>().map_err(|_| Error::ConfigureError {
+        msg: format!("invalid cpu index: {s}"),
+    })?;
+    if index >= CPU_SET_MAX {
+        return Err(Error::ConfigureError {
+            msg: format!("cpu index {index} is too large"),
+        });
+    }
+    Ok(index)
+}
+
+impl FromStr for CpuSet {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut indexes = Vec::new();
+        for item in s.split(|c: char| c.is_whitespace() || c == ',') {
+            if item.is_empty() {
+                continue;
+            }
+
+            let (start, end) = match item.split_once('-') {
+                None => {
+                    let index = parse_cpu_index(item)?;
+                    (index, index)
+                }
+                Some((start, end)) => (parse_cpu_index(start)?, parse_cpu_index(end)?),
+            };
+            if start > end {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid cpu range: {item}"),
+                });
+            }
+            indexes.extend(start..=end);
+        }
+
+        indexes.sort_unstable();
+        indexes.dedup();
+        Ok(CpuSet { indexes })
+    }
+}
+
+impl DeserializeWith for CpuSet {
+    type Item = Self;
+
+    fn deserialize_with<'de, D>(de: D) -> Result<Self::Item, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(de)?;
+        CpuSet::from_str(&s).map_err(de::Error::custom)
     }
 }
 
@@ -127,6 +838,28 @@ pub struct ExecContext {
     envs: RefCell<HashMap<String, String>>,
     env_files: RefCell<Vec<PathBuf>>,
     rlimits: RefCell<HashMap<u8, Rlimit>>,
+    nice: RefCell<Option<i32>>,
+    cpu_sched_policy: RefCell<Option<CpuSchedulingPolicy>>,
+    cpu_sched_priority: RefCell<Option<i32>>,
+    cpu_affinity: RefCell<Option<CpuSet>>,
+    numa_policy: RefCell<Option<NumaPolicy>>,
+    numa_mask: RefCell<Option<CpuSet>>,
+    io_sched_class: RefCell<Option<IoSchedulingClass>>,
+    io_sched_priority: RefCell<Option<u32>>,
+    oom_score_adjust: RefCell<Option<i32>>,
+    protect_system: RefCell<ProtectSystem>,
+    private_tmp: RefCell<bool>,
+    private_users: RefCell<bool>,
+    private_ipc: RefCell<bool>,
+    private_pids: RefCell<bool>,
+    credentials: RefCell<Vec<(String, ExecCredential)>>,
+    hardening: RefCell<ExecHardening>,
+    restrict_namespaces: RefCell<RestrictNamespaces>,
+    restrict_address_families: RefCell<RestrictAddressFamilies>,
+    selinux_context: RefCell<Option<String>>,
+    selinux_context_from_net: RefCell<bool>,
+    apparmor_profile: RefCell<Option<String>>,
+    smack_process_label: RefCell<Option<String>>,
 }
 
 impl Default for ExecContext {
@@ -142,6 +875,28 @@ impl ExecContext {
             envs: RefCell::new(HashMap::new()),
             env_files: RefCell::new(vec![]),
             rlimits: RefCell::new(HashMap::new()),
+            nice: RefCell::new(None),
+            cpu_sched_policy: RefCell::new(None),
+            cpu_sched_priority: RefCell::new(None),
+            cpu_affinity: RefCell::new(None),
+            numa_policy: RefCell::new(None),
+            numa_mask: RefCell::new(None),
+            io_sched_class: RefCell::new(None),
+            io_sched_priority: RefCell::new(None),
+            oom_score_adjust: RefCell::new(None),
+            protect_system: RefCell::new(ProtectSystem::No),
+            private_tmp: RefCell::new(false),
+            private_users: RefCell::new(false),
+            private_ipc: RefCell::new(false),
+            private_pids: RefCell::new(false),
+            credentials: RefCell::new(Vec::new()),
+            hardening: RefCell::new(ExecHardening::empty()),
+            restrict_namespaces: RefCell::new(RestrictNamespaces::default()),
+            restrict_address_families: RefCell::new(RestrictAddressFamilies::default()),
+            selinux_context: RefCell::new(None),
+            selinux_context_from_net: RefCell::new(false),
+            apparmor_profile: RefCell::new(None),
+            smack_process_label: RefCell::new(None),
         }
     }
 
@@ -205,17 +960,259 @@ impl ExecContext {
         self.rlimits.borrow_mut().insert(resource, rlimit);
     }
 
-    /// set the configured rlimit
-    pub fn set_all_rlimits(&self) -> Result<()> {
-        for (resource, limit) in &*self.rlimits.borrow() {
+    /// return the configured rlimits, completed by the defaults for the resources
+    /// not configured, the configured rlimits themselves are left untouched
+    pub fn rlimits_with_defaults(&self, defaults: &[(u8, Rlimit)]) -> HashMap<u8, Rlimit> {
+        let mut rlimits = self.rlimits.borrow().clone();
+        for (resource, rlimit) in defaults {
+            rlimits.entry(*resource).or_insert(*rlimit);
+        }
+        rlimits
+    }
+
+    /// set the configured rlimit, and the defaults for the resources not configured
+    pub fn set_all_rlimits(&self, defaults: &[(u8, Rlimit)]) -> Result<()> {
+        for (resource, limit) in &self.rlimits_with_defaults(defaults) {
             limit.setrlimit(*resource)?;
         }
 
         Ok(())
     }
+
+    /// set the configured nice level
+    pub fn set_nice(&self, nice: Option<i32>) {
+        *self.nice.borrow_mut() = nice;
+    }
+
+    /// return the configured nice level
+    pub fn nice(&self) -> Option<i32> {
+        *self.nice.borrow()
+    }
+
+    /// set the configured cpu scheduling policy and priority
+    pub fn set_cpu_sched(&self, policy: Option<CpuSchedulingPolicy>, priority: Option<i32>) {
+        *self.cpu_sched_policy.borrow_mut() = policy;
+        *self.cpu_sched_priority.borrow_mut() = priority;
+    }
+
+    /// return the configured cpu scheduling policy
+    pub fn cpu_sched_policy(&self) -> Option<CpuSchedulingPolicy> {
+        *self.cpu_sched_policy.borrow()
+    }
+
+    /// return the configured cpu scheduling priority
+    pub fn cpu_sched_priority(&self) -> Option<i32> {
+        *self.cpu_sched_priority.borrow()
+    }
+
+    /// set the configured cpu affinity
+    pub fn set_cpu_affinity(&self, cpus: Option<CpuSet>) {
+        *self.cpu_affinity.borrow_mut() = cpus;
+    }
+
+    /// return the configured cpu affinity
+    pub fn cpu_affinity(&self) -> Option<CpuSet> {
+        self.cpu_affinity.borrow().clone()
+    }
+
+    /// set the configured numa policy and node mask
+    pub fn set_numa(&self, policy: Option<NumaPolicy>, mask: Option<CpuSet>) {
+        *self.numa_policy.borrow_mut() = policy;
+        *self.numa_mask.borrow_mut() = mask;
+    }
+
+    /// return the configured numa policy
+    pub fn numa_policy(&self) -> Option<NumaPolicy> {
+        *self.numa_policy.borrow()
+    }
+
+    /// return the configured numa node mask
+    pub fn numa_mask(&self) -> Option<CpuSet> {
+        self.numa_mask.borrow().clone()
+    }
+
+    /// set the configured io scheduling class and priority
+    pub fn set_io_sched(&self, class: Option<IoSchedulingClass>, priority: Option<u32>) {
+        *self.io_sched_class.borrow_mut() = class;
+        *self.io_sched_priority.borrow_mut() = priority;
+    }
+
+    /// return the configured io scheduling class
+    pub fn io_sched_class(&self) -> Option<IoSchedulingClass> {
+        *self.io_sched_class.borrow()
+    }
+
+    /// return the configured io scheduling priority
+    pub fn io_sched_priority(&self) -> Option<u32> {
+        *self.io_sched_priority.borrow()
+    }
+
+    /// set the configured oom score adjust
+    pub fn set_oom_score_adjust(&self, adjust: Option<i32>) {
+        *self.oom_score_adjust.borrow_mut() = adjust;
+    }
+
+    /// return the configured oom score adjust
+    pub fn oom_score_adjust(&self) -> Option<i32> {
+        *self.oom_score_adjust.borrow()
+    }
+
+    /// set which parts of the file system are read-only
+    pub fn set_protect_system(&self, protect: ProtectSystem) {
+        *self.protect_system.borrow_mut() = protect;
+    }
+
+    /// return which parts of the file system are read-only
+    pub fn protect_system(&self) -> ProtectSystem {
+        *self.protect_system.borrow()
+    }
+
+    /// set whether the commands get private /tmp and /var/tmp
+    pub fn set_private_tmp(&self, private_tmp: bool) {
+        *self.private_tmp.borrow_mut() = private_tmp;
+    }
+
+    /// return whether the commands get private /tmp and /var/tmp
+    pub fn private_tmp(&self) -> bool {
+        *self.private_tmp.borrow()
+    }
+
+    /// set whether the commands run in their own user namespace
+    pub fn set_private_users(&self, private_users: bool) {
+        *self.private_users.borrow_mut() = private_users;
+    }
+
+    /// return whether the commands run in their own user namespace
+    pub fn private_users(&self) -> bool {
+        *self.private_users.borrow()
+    }
+
+    /// set whether the commands run in their own IPC namespace
+    pub fn set_private_ipc(&self, private_ipc: bool) {
+        *self.private_ipc.borrow_mut() = private_ipc;
+    }
+
+    /// return whether the commands run in their own IPC namespace
+    pub fn private_ipc(&self) -> bool {
+        *self.private_ipc.borrow()
+    }
+
+    /// set whether the commands run in their own PID namespace
+    pub fn set_private_pids(&self, private_pids: bool) {
+        *self.private_pids.borrow_mut() = private_pids;
+    }
+
+    /// return whether the commands run in their own PID namespace
+    pub fn private_pids(&self) -> bool {
+        *self.private_pids.borrow()
+    }
+
+    /// add a credential, the later one overrides the former one with the same id
+    pub fn insert_credential(&self, id: String, credential: ExecCredential) {
+        let mut credentials = self.credentials.borrow_mut();
+        credentials.retain(|(i, _)| *i != id);
+        credentials.push((id, credential));
+    }
+
+    /// return all the configured credentials
+    pub fn credentials(&self) -> Vec<(String, ExecCredential)> {
+        self.credentials.borrow().clone()
+    }
+
+    /// set the kernel hardening switches
+    pub fn set_hardening(&self, hardening: ExecHardening) {
+        *self.hardening.borrow_mut() = hardening;
+    }
+
+    /// return the kernel hardening switches
+    pub fn hardening(&self) -> ExecHardening {
+        *self.hardening.borrow()
+    }
+
+    /// set the namespaces that the commands are allowed to create
+    pub fn set_restrict_namespaces(&self, restrict: RestrictNamespaces) {
+        *self.restrict_namespaces.borrow_mut() = restrict;
+    }
+
+    /// return the namespaces that the commands are allowed to create
+    pub fn restrict_namespaces(&self) -> RestrictNamespaces {
+        *self.restrict_namespaces.borrow()
+    }
+
+    /// set the address families that socket(2) accepts
+    pub fn set_restrict_address_families(&self, restrict: RestrictAddressFamilies) {
+        *self.restrict_address_families.borrow_mut() = restrict;
+    }
+
+    /// return the address families that socket(2) accepts
+    pub fn restrict_address_families(&self) -> RestrictAddressFamilies {
+        self.restrict_address_families.borrow().clone()
+    }
+
+    /// set the selinux context that the commands run in, "-" in front ignores the failures
+    pub fn set_selinux_context(&self, context: Option<String>) {
+        *self.selinux_context.borrow_mut() = context;
+    }
+
+    /// return the selinux context that the commands run in
+    pub fn selinux_context(&self) -> Option<String> {
+        self.selinux_context.borrow().clone()
+    }
+
+    /// set whether the MLS range of the selinux context comes from the peer of the socket
+    pub fn set_selinux_context_from_net(&self, from_net: bool) {
+        *self.selinux_context_from_net.borrow_mut() = from_net;
+    }
+
+    /// return whether the MLS range of the selinux context comes from the peer of the socket
+    pub fn selinux_context_from_net(&self) -> bool {
+        *self.selinux_context_from_net.borrow()
+    }
+
+    /// set the apparmor profile that the commands run in, "-" in front ignores the failures
+    pub fn set_apparmor_profile(&self, profile: Option<String>) {
+        *self.apparmor_profile.borrow_mut() = profile;
+    }
+
+    /// return the apparmor profile that the commands run in
+    pub fn apparmor_profile(&self) -> Option<String> {
+        self.apparmor_profile.borrow().clone()
+    }
+
+    /// set the smack label that the commands run with, "-" in front ignores the failures
+    pub fn set_smack_process_label(&self, label: Option<String>) {
+        *self.smack_process_label.borrow_mut() = label;
+    }
+
+    /// return the smack label that the commands run with
+    pub fn smack_process_label(&self) -> Option<String> {
+        self.smack_process_label.borrow().clone()
+    }
+
+    /// return the sandboxing switches and whether they are on, used by the exposure summary
+    pub fn exposure(&self) -> Vec<(&'static str, bool)> {
+        let mut switches = vec![
+            ("ProtectSystem", self.protect_system() != ProtectSystem::No),
+            ("PrivateTmp", self.private_tmp()),
+            ("PrivateUsers", self.private_users()),
+        ];
+        let hardening = self.hardening();
+        for (name, flag) in HARDENING_NAMES {
+            switches.push((name, hardening.contains(flag)));
+        }
+        switches.push((
+            "RestrictNamespaces",
+            self.restrict_namespaces() != RestrictNamespaces::default(),
+        ));
+        switches.push((
+            "RestrictAddressFamilies",
+            self.restrict_address_families() != RestrictAddressFamilies::default(),
+        ));
+        switches
+    }
 }
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 ///
 pub enum ExecDirectoryType {
     ///
@@ -230,16 +1227,90 @@ pub enum ExecDirectoryType {
     Config = 4,
 }
 
+impl ExecDirectoryType {
+    /// all the exec directory types
+    pub const ALL: [ExecDirectoryType; 5] = [
+        ExecDirectoryType::Runtime,
+        ExecDirectoryType::State,
+        ExecDirectoryType::Cache,
+        ExecDirectoryType::Logs,
+        ExecDirectoryType::Config,
+    ];
+
+    /// the directory that the configured relative paths are created in
+    pub fn prefix(&self) -> &'static Path {
+        match self {
+            ExecDirectoryType::Runtime => Path::new("/run"),
+            ExecDirectoryType::State => Path::new("/var/lib"),
+            ExecDirectoryType::Cache => Path::new("/var/cache"),
+            ExecDirectoryType::Logs => Path::new("/var/log"),
+            ExecDirectoryType::Config => Path::new("/etc"),
+        }
+    }
+
+    /// join the configured directory to the prefix, only relative paths that
+    /// do not escape the prefix are allowed
+    pub fn path(&self, directory: &str) -> Result<PathBuf> {
+        let path = Path::new(directory);
+        if path.is_absolute() || path.components().any(|c| c.as_os_str() == "..") {
+            return Err(Error::InvalidData);
+        }
+        Ok(self.prefix().join(path))
+    }
+
+    /// the private directory of the dynamic user, "/var/lib/foo" -> "/var/lib/private/foo"
+    pub fn private_path(&self, directory: &Path) -> PathBuf {
+        match directory.strip_prefix(self.prefix()) {
+            Ok(relative) => self.prefix().join("private").join(relative),
+            Err(_) => directory.to_path_buf(),
+        }
+    }
+
+    /// the environment variable that tells the command where the directories are
+    pub fn env_name(&self) -> &'static str {
+        match self {
+            ExecDirectoryType::Runtime => "RUNTIME_DIRECTORY",
+            ExecDirectoryType::State => "STATE_DIRECTORY",
+            ExecDirectoryType::Cache => "CACHE_DIRECTORY",
+            ExecDirectoryType::Logs => "LOGS_DIRECTORY",
+            ExecDirectoryType::Config => "CONFIGURATION_DIRECTORY",
+        }
+    }
+}
+
+impl FromStr for ExecDirectoryType {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let kind = match s {
+            "runtime" => ExecDirectoryType::Runtime,
+            "state" => ExecDirectoryType::State,
+            "cache" => ExecDirectoryType::Cache,
+            "logs" => ExecDirectoryType::Logs,
+            "configuration" => ExecDirectoryType::Config,
+            _ => {
+                return Err(Error::ConfigureError {
+                    msg: format!("invalid exec directory type: {s}"),
+                })
+            }
+        };
+        Ok(kind)
+    }
+}
+
 /// the environment that will be set when start a new command
 pub struct ExecParameters {
     environment: Rc<EnvData>,
     fds: Vec<i32>,
+    stdio_fd: Option<i32>,
+    socket_fd: Option<i32>,
     notify_sock: Option<PathBuf>,
     root_directory: Option<PathBuf>,
     working_directory: Option<PathBuf>,
     exec_directory: Vec<Option<Vec<PathBuf>>>,
+    exec_directory_mode: Vec<u32>,
     user: Option<User>,
     group: Option<Group>,
+    dynamic_user: bool,
     umask: Option<Mode>,
     watchdog_usec: u64,
     flags: ExecFlags,
@@ -289,12 +1360,16 @@ impl ExecParameters {
         ExecParameters {
             environment: Rc::new(EnvData::new()),
             fds: Vec::new(),
+            stdio_fd: None,
+            socket_fd: None,
             notify_sock: None,
             root_directory: None,
             working_directory: None,
             exec_directory,
+            exec_directory_mode: vec![0o755; ExecDirectoryType::ALL.len()],
             user: None,
             group: None,
+            dynamic_user: false,
             umask: None,
             watchdog_usec: 0,
             flags: ExecFlags::CONTROL,
@@ -337,6 +1412,27 @@ impl ExecParameters {
         self.fds.to_vec()
     }
 
+    /// set the fd that will be the standard input and output of child
+    pub fn set_stdio_fd(&mut self, fd: i32) {
+        self.stdio_fd = Some(fd)
+    }
+
+    /// return the fd that will be the standard input and output of child
+    pub fn stdio_fd(&self) -> Option<i32> {
+        self.stdio_fd
+    }
+
+    /// set the connection socket that the child is spawned for
+    pub fn set_socket_fd(&mut self, fd: i32) {
+        self.socket_fd = Some(fd)
+    }
+
+    /// return the connection socket that the child is spawned for, the listening
+    /// sockets are not
+    pub fn socket_fd(&self) -> Option<i32> {
+        self.socket_fd
+    }
+
     /// set the NOTIFY_SOCKET value
     pub fn set_notify_sock(&mut self, notify_sock: PathBuf) {
         self.notify_sock = Some(notify_sock)
@@ -362,20 +1458,45 @@ impl ExecParameters {
 
     /// add RuntimeDirectory
     pub fn add_runtime_directory(&mut self, runtime_directories: &Vec<String>) -> Result<()> {
-        if runtime_directories.is_empty() {
+        self.add_exec_directory(ExecDirectoryType::Runtime, runtime_directories)
+    }
+
+    /// add the directories of RuntimeDirectory, StateDirectory, CacheDirectory,
+    /// LogsDirectory or ConfigurationDirectory, and export them to the environment
+    pub fn add_exec_directory(
+        &mut self,
+        kind: ExecDirectoryType,
+        exec_directories: &Vec<String>,
+    ) -> Result<()> {
+        if exec_directories.is_empty() {
             return Ok(());
         }
         let mut directories: Vec<PathBuf> = Vec::new();
-        for d in runtime_directories {
+        for d in exec_directories {
             /* Different from RootDirectory or WorkingDirectory, we don't
              * check if the specified directory exists. We will create it
              * later if it doesn't exist. */
-            directories.push(Path::new("/run").join(d));
+            directories.push(kind.path(d)?);
         }
-        self.exec_directory[ExecDirectoryType::Runtime as usize] = Some(directories);
+        let env: Vec<String> = directories
+            .iter()
+            .map(|d| d.to_string_lossy().to_string())
+            .collect();
+        self.add_env(kind.env_name(), env.join(":"));
+        self.exec_directory[kind as usize] = Some(directories);
         Ok(())
     }
 
+    /// set the mode of the exec directories, configured by *DirectoryMode
+    pub fn set_exec_directory_mode(&mut self, kind: ExecDirectoryType, mode: u32) {
+        self.exec_directory_mode[kind as usize] = mode;
+    }
+
+    /// get the mode of the exec directories
+    pub fn get_exec_directory_mode(&self, kind: ExecDirectoryType) -> u32 {
+        self.exec_directory_mode[kind as usize]
+    }
+
     /// get RootDirectory
     pub fn get_root_directory(&self) -> Option<PathBuf> {
         self.root_directory.clone()
@@ -453,6 +1574,20 @@ impl ExecParameters {
         Err(Error::InvalidData)
     }
 
+    /// add the dynamic user allocated by the unit, the uid is used as the gid too
+    pub fn add_dynamic_user(&mut self, name: &str, uid: Uid) {
+        let (user, group) = basic::user_group_util::dynamic_user_creds(name, uid);
+        self.user = Some(user);
+        self.group = Some(group);
+        self.dynamic_user = true;
+    }
+
+    /// return true if the user is a dynamic user, the State, Cache and Logs
+    /// directories of the dynamic user are private
+    pub fn dynamic_user(&self) -> bool {
+        self.dynamic_user
+    }
+
     /// get User
     pub fn get_user(&self) -> Option<User> {
         self.user.clone()
@@ -556,9 +1691,14 @@ mod tests {
         unistd::{Gid, Uid},
     };
 
-    use crate::exec::base::Rlimit;
+    use crate::exec::base::{
+        exposure_summary, CpuSchedulingPolicy, CpuSet, ExecContext, ExecCredential,
+        ExecCredentialType, IoSchedulingClass, NumaPolicy, ProtectSystem, RestrictAddressFamilies,
+        RestrictNamespaces, Rlimit, RlimitUnit,
+    };
 
-    use super::ExecParameters;
+    use super::{ExecDirectoryType, ExecParameters};
+    use std::path::PathBuf;
 
     #[test]
     fn test_add_working_directory() {
@@ -613,6 +1753,15 @@ mod tests {
         assert!(params.add_user("wwwwyyyyyffffff".to_string()).is_err());
     }
 
+    #[test]
+    fn test_add_dynamic_user() {
+        let mut params = ExecParameters::new();
+        params.add_dynamic_user("foo", Uid::from_raw(61184));
+        assert!(params.dynamic_user());
+        assert_eq!(params.get_user().unwrap().name, "foo");
+        assert_eq!(params.get_group().unwrap().gid, Gid::from_raw(61184));
+    }
+
     #[test]
     fn test_add_group() {
         let mut params = ExecParameters::new();
@@ -632,6 +1781,45 @@ mod tests {
         assert!(params.add_group("wwwwyyyyyffffff".to_string()).is_err());
     }
 
+    #[test]
+    fn test_add_exec_directory() {
+        let mut params = ExecParameters::new();
+        assert!(params
+            .add_exec_directory(
+                ExecDirectoryType::State,
+                &vec!["foo".to_string(), "bar/baz".to_string()]
+            )
+            .is_ok());
+        assert_eq!(
+            params.get_exec_directory()[ExecDirectoryType::State as usize],
+            Some(vec![
+                PathBuf::from("/var/lib/foo"),
+                PathBuf::from("/var/lib/bar/baz")
+            ])
+        );
+        assert_eq!(
+            params.get_env("STATE_DIRECTORY"),
+            Some("/var/lib/foo:/var/lib/bar/baz".to_string())
+        );
+        assert_eq!(params.get_env("CACHE_DIRECTORY"), None);
+        assert_eq!(
+            params.get_exec_directory_mode(ExecDirectoryType::State),
+            0o755
+        );
+
+        let mut params = ExecParameters::new();
+        assert!(params
+            .add_exec_directory(ExecDirectoryType::Cache, &vec!["/foo".to_string()])
+            .is_err());
+        assert!(params
+            .add_exec_directory(ExecDirectoryType::Cache, &vec!["foo/../..".to_string()])
+            .is_err());
+        assert_eq!(
+            params.get_exec_directory()[ExecDirectoryType::Cache as usize],
+            None
+        );
+    }
+
     #[test]
     fn test_add_umask() {
         let mut params = ExecParameters::new();
@@ -689,5 +1877,176 @@ mod tests {
         let source5 = "infinity:100";
         let rlimit = Rlimit::from_str(source5);
         assert!(rlimit.is_err());
+
+        assert!(Rlimit::from_str("4K").is_err());
+    }
+
+    #[test]
+    fn test_rlimit_parse_unit() {
+        let rlimit = Rlimit::parse("4K:1G", RlimitUnit::Bytes).unwrap();
+        assert_eq!(rlimit.soft, 4096);
+        assert_eq!(rlimit.hard, 1 << 30);
+
+        let rlimit = Rlimit::parse("1h", RlimitUnit::Sec).unwrap();
+        assert_eq!(rlimit.soft, 3600);
+        let rlimit = Rlimit::parse("1ms:30", RlimitUnit::Sec).unwrap();
+        assert_eq!(rlimit.soft, 1);
+        assert_eq!(rlimit.hard, 30);
+
+        let rlimit = Rlimit::parse("500ms:infinity", RlimitUnit::Usec).unwrap();
+        assert_eq!(rlimit.soft, 500000);
+        assert_eq!(rlimit.hard, rlimit_util::INFINITY);
+
+        let rlimit = Rlimit::parse("-5", RlimitUnit::Nice).unwrap();
+        assert_eq!(rlimit.soft, 25);
+        let rlimit = Rlimit::parse("+19:40", RlimitUnit::Nice).unwrap();
+        assert_eq!(rlimit.soft, 1);
+        assert_eq!(rlimit.hard, 40);
+        assert!(Rlimit::parse("41", RlimitUnit::Nice).is_err());
+        assert!(Rlimit::parse("-21", RlimitUnit::Nice).is_err());
+    }
+
+    #[test]
+    fn test_rlimits_with_defaults() {
+        let ctx = ExecContext::new();
+        ctx.insert_rlimit(libc::RLIMIT_NOFILE as u8, Rlimit::from_str("1024").unwrap());
+        let defaults = vec![
+            (libc::RLIMIT_NOFILE as u8, Rlimit::from_str("4096").unwrap()),
+            (libc::RLIMIT_CORE as u8, Rlimit::from_str("0").unwrap()),
+        ];
+
+        let rlimits = ctx.rlimits_with_defaults(&defaults);
+        assert_eq!(rlimits.len(), 2);
+        assert_eq!(rlimits[&(libc::RLIMIT_NOFILE as u8)].soft, 1024);
+        assert_eq!(rlimits[&(libc::RLIMIT_CORE as u8)].hard, 0);
+
+        // the defaults are applied to each spawn, and never become part of the unit config
+        let rlimits = ctx.rlimits_with_defaults(&[]);
+        assert_eq!(rlimits.len(), 1);
+        assert!(!rlimits.contains_key(&(libc::RLIMIT_CORE as u8)));
+    }
+
+    #[test]
+    fn test_cpu_set_from_str() {
+        let cpus = CpuSet::from_str("0-3 5,7").unwrap();
+        assert_eq!(cpus.indexes(), &vec![0, 1, 2, 3, 5, 7]);
+
+        let cpus = CpuSet::from_str("3 1, 2-3").unwrap();
+        assert_eq!(cpus.indexes(), &vec![1, 2, 3]);
+
+        assert!(CpuSet::from_str("").unwrap().is_empty());
+        assert!(CpuSet::from_str("3-1").is_err());
+        assert!(CpuSet::from_str("a").is_err());
+        assert!(CpuSet::from_str("0-100000").is_err());
+    }
+
+    #[test]
+    fn test_sched_from_str() {
+        assert_eq!(
+            CpuSchedulingPolicy::from_str("fifo").unwrap(),
+            CpuSchedulingPolicy::Fifo
+        );
+        assert!(CpuSchedulingPolicy::from_str("deadline").is_err());
+        assert!(CpuSchedulingPolicy::Rr.priority_range().contains(&99));
+        assert!(!CpuSchedulingPolicy::Fifo.priority_range().contains(&0));
+        assert!(!CpuSchedulingPolicy::Batch.priority_range().contains(&1));
+        assert_eq!(
+            IoSchedulingClass::from_str("best-effort").unwrap().class(),
+            2
+        );
+        assert!(IoSchedulingClass::from_str("none").is_err());
+        assert!(NumaPolicy::from_str("bind").unwrap().need_nodes());
+        assert!(!NumaPolicy::from_str("local").unwrap().need_nodes());
+        assert!(NumaPolicy::from_str("weighted").is_err());
+        assert_eq!(
+            ProtectSystem::from_str("strict").unwrap().read_only_paths(),
+            &["/"]
+        );
+        assert!(ProtectSystem::from_str("no")
+            .unwrap()
+            .read_only_paths()
+            .is_empty());
+        assert!(ProtectSystem::from_str("ro").is_err());
+    }
+
+    #[test]
+    fn test_credential_parse() {
+        assert_eq!(
+            ExecCredential::parse("token:/etc/token", ExecCredentialType::Load).unwrap(),
+            (
+                "token".to_string(),
+                ExecCredential::Load(PathBuf::from("/etc/token"))
+            )
+        );
+        assert_eq!(
+            ExecCredential::parse("key:a:b", ExecCredentialType::Set).unwrap(),
+            ("key".to_string(), ExecCredential::Set("a:b".to_string()))
+        );
+        assert_eq!(
+            ExecCredential::parse("key:/etc/key", ExecCredentialType::LoadEncrypted).unwrap(),
+            (
+                "key".to_string(),
+                ExecCredential::LoadEncrypted(PathBuf::from("/etc/key"))
+            )
+        );
+        assert!(ExecCredential::parse("token", ExecCredentialType::Set).is_err());
+        assert!(ExecCredential::parse("../x:/etc/token", ExecCredentialType::Load).is_err());
+        assert!(ExecCredential::parse("a b:/etc/token", ExecCredentialType::Load).is_err());
+        assert!(
+            ExecCredential::parse("token:etc/token", ExecCredentialType::LoadEncrypted).is_err()
+        );
+
+        let credential = ExecCredential::Set("secret".to_string());
+        assert_eq!(credential.load("key").unwrap(), b"secret".to_vec());
+    }
+
+    #[test]
+    fn test_restrict_namespaces() {
+        use nix::sched::CloneFlags;
+
+        assert!(RestrictNamespaces::from_str("no")
+            .unwrap()
+            .denied()
+            .is_empty());
+        let all = RestrictNamespaces::from_str("yes").unwrap().denied();
+        assert!(all.contains(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS));
+
+        let denied = RestrictNamespaces::from_str("net ipc").unwrap().denied();
+        assert!(!denied.contains(CloneFlags::CLONE_NEWNET));
+        assert!(denied.contains(CloneFlags::CLONE_NEWUSER));
+
+        let denied = RestrictNamespaces::from_str("~user").unwrap().denied();
+        assert_eq!(denied, CloneFlags::CLONE_NEWUSER);
+        assert!(RestrictNamespaces::from_str("time").is_err());
+
+        assert_eq!(
+            RestrictAddressFamilies::from_str("AF_INET6 AF_UNIX AF_INET")
+                .unwrap()
+                .families(),
+            Some((true, vec![libc::AF_UNIX, libc::AF_INET, libc::AF_INET6]))
+        );
+        assert_eq!(
+            RestrictAddressFamilies::from_str("~AF_PACKET")
+                .unwrap()
+                .families(),
+            Some((false, vec![libc::AF_PACKET]))
+        );
+        assert_eq!(
+            RestrictAddressFamilies::from_str("none")
+                .unwrap()
+                .families(),
+            Some((true, vec![]))
+        );
+        assert!(RestrictAddressFamilies::from_str("")
+            .unwrap()
+            .families()
+            .is_none());
+        assert!(RestrictAddressFamilies::from_str("AF_FOO").is_err());
+
+        assert_eq!(exposure_summary(&[("A", true), ("B", true)]), "0.0 OK");
+        assert_eq!(
+            exposure_summary(&[("A", true), ("B", false)]),
+            "5.0 EXPOSED; unprotected: B"
+        );
     }
 }
@@ -91,23 +91,29 @@ impl DeserializeWith for ExecCommand {
     {
         let mut s = String::deserialize(de)?;
 
-        let first = match s.as_bytes().first() {
-            None => {
-                return Err(de::Error::invalid_value(
-                    Unexpected::Str(&s),
-                    &"The configured value is empty.",
-                ));
-            }
-            Some(v) => *v,
-        };
+        if s.is_empty() {
+            return Err(de::Error::invalid_value(
+                Unexpected::Str(&s),
+                &"The configured value is empty.",
+            ));
+        }
 
-        let exec_flag = match first as char {
-            '-' => {
-                s = s.trim_start_matches('-').to_string();
-                ExecFlag::EXEC_COMMAND_IGNORE_FAILURE
+        // '-' ignores the failure, ':' disables the expansion of the environment variables and specifiers
+        let mut exec_flag = ExecFlag::EXEC_COMMAND_EMPTY;
+        let prefix_len = s.find(|c| c != '-' && c != ':').unwrap_or(s.len());
+        for c in s[..prefix_len].chars() {
+            match c {
+                '-' => exec_flag |= ExecFlag::EXEC_COMMAND_IGNORE_FAILURE,
+                _ => exec_flag |= ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND,
             }
-            _ => ExecFlag::EXEC_COMMAND_EMPTY,
-        };
+        }
+        s = s[prefix_len..].to_string();
+        if s.trim().is_empty() {
+            return Err(de::Error::invalid_value(
+                Unexpected::Str(&s),
+                &"The configured command is empty.",
+            ));
+        }
 
         let mut commands = VecDeque::new();
 
@@ -150,3 +156,59 @@ impl DeserializeWith for ExecCommand {
         Ok(commands)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ExecCommand, ExecFlag};
+    use crate::serialize::DeserializeWith;
+    use serde::de::{value, IntoDeserializer};
+    use std::collections::VecDeque;
+
+    fn parse(s: &str) -> Result<VecDeque<ExecCommand>, value::Error> {
+        ExecCommand::deserialize_with(s.into_deserializer())
+    }
+
+    #[test]
+    fn test_exec_command_prefix() {
+        let commands = parse("/bin/echo $HOME").unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].path(), "/bin/echo");
+        assert_eq!(commands[0].get_exec_flag(), ExecFlag::EXEC_COMMAND_EMPTY);
+
+        let commands = parse("-/bin/false").unwrap();
+        assert_eq!(commands[0].path(), "/bin/false");
+        assert_eq!(
+            commands[0].get_exec_flag(),
+            ExecFlag::EXEC_COMMAND_IGNORE_FAILURE
+        );
+
+        // ':' keeps "$HOME" as it is
+        let commands = parse(":/bin/echo $HOME").unwrap();
+        assert_eq!(commands[0].path(), "/bin/echo");
+        assert_eq!(commands[0].argv(), vec!["$HOME"]);
+        assert_eq!(
+            commands[0].get_exec_flag(),
+            ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND
+        );
+
+        // the prefixes can be combined in any order
+        for s in ["-:/bin/echo $HOME", ":-/bin/echo $HOME"] {
+            let commands = parse(s).unwrap();
+            assert_eq!(commands[0].path(), "/bin/echo");
+            assert_eq!(
+                commands[0].get_exec_flag(),
+                ExecFlag::EXEC_COMMAND_IGNORE_FAILURE | ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND
+            );
+        }
+
+        // the prefix applies to all the commands of the value
+        let commands = parse("-:/bin/true;/bin/false").unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(commands[1]
+            .get_exec_flag()
+            .contains(ExecFlag::EXEC_COMMAND_NO_ENV_EXPAND));
+
+        assert!(parse(":").is_err());
+        assert!(parse("-:bin/echo").is_err());
+    }
+}
@@ -13,6 +13,11 @@
 //! execute module
 mod base;
 mod cmd;
-pub use base::{ExecContext, ExecDirectoryType, ExecFlags, ExecParameters, Rlimit};
+pub use base::{
+    credentials_dir, exposure_summary, private_tmp_dirs, CpuSchedulingPolicy, CpuSet, ExecContext,
+    ExecCredential, ExecCredentialType, ExecDirectoryType, ExecFlags, ExecHardening,
+    ExecParameters, IoSchedulingClass, NumaPolicy, ProtectSystem, RestrictAddressFamilies,
+    RestrictNamespaces, Rlimit, HARDENING_NAMES,
+};
 pub use cmd::ExecCommand;
 pub use cmd::ExecFlag;
@@ -11,7 +11,7 @@
 // See the Mulan PSL v2 for more details.
 
 //!
-use serde::{Deserialize, Deserializer};
+use serde::{de, Deserialize, Deserializer};
 
 ///
 pub trait DeserializeWith: Sized {
@@ -39,3 +39,16 @@ impl DeserializeWith for Vec<String> {
         Ok(vec)
     }
 }
+
+/// deserialize the octal mode of the directories, like RuntimeDirectoryMode="0755"
+pub fn deserialize_directory_mode<'de, D>(de: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(de)?;
+    basic::parse_util::parse_mode(&s).map_err(|_| {
+        de::Error::custom(format!(
+            "invalid directory mode: {s}, should be an octal number like \"0755\""
+        ))
+    })
+}
@@ -15,6 +15,8 @@ use super::kill::{KillContext, KillOperation};
 use super::state::{UnitActiveState, UnitNotifyFlags};
 use super::umif::UnitMngUtil;
 use crate::error::*;
+use crate::exec::ExecDirectoryType;
+use cgroup::IpPrefix;
 use nix::sys::wait::WaitStatus;
 use nix::{sys::socket::UnixCredentials, unistd::Pid};
 use std::{collections::HashMap, path::PathBuf, rc::Rc};
@@ -55,6 +57,9 @@ pub trait UnitBase {
     ///
     fn cg_path(&self) -> PathBuf;
 
+    /// set IPAddressAllow, IPAddressDeny and IPAccounting of the cgroup
+    fn set_ip_access(&self, allow: Vec<IpPrefix>, deny: Vec<IpPrefix>, accounting: bool);
+
     ///
     fn ignore_on_isolate(&self) -> bool;
 
@@ -117,11 +122,29 @@ pub trait SubUnit: ReStation + UnitMngUtil {
     ///
     fn reset_failed(&self) {}
 
+    /// remove the directories of the given types, like StateDirectory
+    /// return UnitActionEOpNotSupp for default, if the sub unit has no such directories
+    fn clean(&self, _what: &[ExecDirectoryType]) -> Result<()> {
+        Err(Error::UnitActionEOpNotSupp)
+    }
+
     ///
     fn collect_fds(&self) -> Vec<i32> {
         Vec::new()
     }
 
+    /// hand over a connection fd accepted by a socket with Accept=true
+    /// return UnitActionEOpNotSupp for default, if the sub unit can not be spawned for a connection
+    fn set_socket_fd(&self, _fd: i32) -> Result<()> {
+        Err(Error::UnitActionEOpNotSupp)
+    }
+
+    /// close the connection fd handed over by set_socket_fd, the unit is not started with it
+    fn release_socket_fd(&self) {}
+
+    /// the state of the unit {_other} triggered by this unit has changed
+    fn trigger_notify(&self, _other: &str) {}
+
     ///Get the the unit state
     ///
     /// Every sub unit  can define self states and map to [`UnitActiveState`]
@@ -131,6 +154,11 @@ pub trait SubUnit: ReStation + UnitMngUtil {
     /// Return the state of subunit, i.e. (mounted for .mount, running for .service, listening for .socket)
     fn get_subunit_state(&self) -> String;
 
+    /// Return the extra lines shown by the status of the unit, like the connection counters of .socket
+    fn get_status_extra(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+
     ///
     fn attach_unit(&self, unit: Rc<dyn UnitBase>);
 
@@ -114,6 +114,9 @@ pub enum UnitType {
     UnitTarget,
     UnitSocket,
     UnitMount,
+    UnitSwap,
+    UnitAutomount,
+    UnitDevice,
     UnitTypeMax,
     UnitTypeInvalid,
     UnitTypeErrnoMax,
@@ -127,6 +130,9 @@ impl UnitType {
             UnitType::UnitTarget,
             UnitType::UnitSocket,
             UnitType::UnitMount,
+            UnitType::UnitSwap,
+            UnitType::UnitAutomount,
+            UnitType::UnitDevice,
         ]
         .iter()
         .copied()
@@ -142,6 +148,9 @@ impl FromStr for UnitType {
             "target" => UnitType::UnitTarget,
             "socket" => UnitType::UnitSocket,
             "mount" => UnitType::UnitMount,
+            "swap" => UnitType::UnitSwap,
+            "automount" => UnitType::UnitAutomount,
+            "device" => UnitType::UnitDevice,
             _ => UnitType::UnitTypeInvalid,
         };
         Ok(ret)
@@ -155,6 +164,9 @@ impl From<UnitType> for String {
             UnitType::UnitTarget => "target".into(),
             UnitType::UnitSocket => "socket".into(),
             UnitType::UnitMount => "mount".into(),
+            UnitType::UnitSwap => "swap".into(),
+            UnitType::UnitAutomount => "automount".into(),
+            UnitType::UnitDevice => "device".into(),
             UnitType::UnitTypeMax => null_str!(""),
             UnitType::UnitTypeInvalid => null_str!(""),
             UnitType::UnitTypeErrnoMax => null_str!(""),
@@ -170,6 +182,9 @@ impl TryFrom<u32> for UnitType {
             1 => Ok(UnitType::UnitTarget),
             2 => Ok(UnitType::UnitSocket),
             3 => Ok(UnitType::UnitMount),
+            4 => Ok(UnitType::UnitSwap),
+            5 => Ok(UnitType::UnitAutomount),
+            6 => Ok(UnitType::UnitDevice),
             v => Err(format!("input {v} is invalid")),
         }
     }
@@ -121,6 +121,7 @@ pub struct UnitStatus {
     active_state: String,
     cgroup_path: String,
     pid: String,
+    extra: Vec<(String, String)>,
     error_code: i32,
 }
 
@@ -147,9 +148,15 @@ impl UnitStatus {
             active_state,
             cgroup_path,
             pid,
+            extra: Vec::new(),
             error_code,
         }
     }
+
+    /// add extra lines of the sub unit, shown before the pids
+    pub fn add_extra(&mut self, extra: Vec<(String, String)>) {
+        self.extra.extend(extra);
+    }
 }
 
 impl std::fmt::Display for UnitStatus {
@@ -170,6 +177,9 @@ impl std::fmt::Display for UnitStatus {
         if let Some(doc) = &self.documentation {
             status_table.add_line(vec!["Docs:", doc]);
         }
+        for (key, value) in &self.extra {
+            status_table.add_line(vec![key, value]);
+        }
         status_table.add_line(vec!["PID:", &self.pid]);
         status_table.set_one_col_align(0, CellAlign::Right);
         /* The first column: keep the left space, delete the right space. */
@@ -165,6 +165,14 @@ pub trait UmIf {
         Vec::new()
     }
 
+    /// pass the connection fd accepted by a socket to the unit {name}
+    fn unit_set_socket_fd(&self, _name: &str, _fd: i32) -> Result<()> {
+        Err(Error::UnitActionEOpNotSupp)
+    }
+
+    /// close the connection fd passed to the unit {name} which failed to start with it
+    fn unit_release_socket_fd(&self, _name: &str) {}
+
     /// get all unit in sysmaster
     fn units_get_all(&self, _unit_type: Option<UnitType>) -> Vec<String> {
         Vec::new()
@@ -12,27 +12,839 @@
 
 //! blkid builtin
 //!
+//! probe the filesystem superblock and the partition table of block devices,
+//! the values are named as libblkid and exported in udev's format, e.g., 'ID_FS_UUID'
+//!
 
-use crate::builtin::Builtin;
 use crate::builtin::Netlink;
-use crate::error::Result;
+use crate::builtin::{add_property, Builtin};
+use crate::error::{Error, Result};
+use crate::utils::{encode_string, replace_chars, replace_whitespace_chars, ALLOWED_CHARS_INPUT};
 use device::Device;
+use nix::sys::stat::{major, minor};
 use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom};
+use std::os::unix::fs::{FileExt, FileTypeExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
 use std::sync::{Arc, Mutex};
 
+/// the unit of partition offsets and sizes
+const SECTOR_SIZE: u64 = 512;
+
+/// the value named as libblkid, e.g., ("TYPE", "ext4")
+type ProbeValue = (&'static str, String);
+
+/// the prober of superblock, None if the magic does not match
+type Prober = fn(&Probe) -> Option<Vec<ProbeValue>>;
+
+/// the superblock probers, the first matched one wins
+const SUPERBLOCK_PROBERS: [Prober; 8] = [
+    probe_luks,
+    probe_lvm2,
+    probe_xfs,
+    probe_ext,
+    probe_btrfs,
+    probe_swap,
+    probe_iso9660,
+    probe_vfat,
+];
+
+/// ext features that decide the filesystem type
+const EXT3_FEATURE_COMPAT_HAS_JOURNAL: u32 = 0x0004;
+const EXT3_FEATURE_INCOMPAT_JOURNAL_DEV: u32 = 0x0008;
+const EXT3_FEATURE_INCOMPAT_SUPP: u32 = 0x0002 | 0x0004 | 0x0010;
+const EXT3_FEATURE_RO_COMPAT_SUPP: u32 = 0x0001 | 0x0002 | 0x0004;
+
+// get the logical sector size of block device
+nix::ioctl_read_bad!(blksszget, 0x1268, libc::c_int);
+
 /// blkid builtin command
 pub struct Blkid;
 
+/// the reader of the device or image file, the offsets are relative to the probing area
+struct Probe<'a> {
+    file: &'a File,
+    offset: u64,
+    size: u64,
+    sector_size: u64,
+}
+
+impl<'a> Probe<'a> {
+    fn new(file: &'a File, offset: u64, sector_size: u64) -> Option<Probe<'a>> {
+        let mut f = file;
+        let total = f.seek(SeekFrom::End(0)).ok()?;
+        if offset >= total {
+            return None;
+        }
+
+        Some(Probe {
+            file,
+            offset,
+            size: total - offset,
+            sector_size,
+        })
+    }
+
+    fn read(&self, off: u64, len: usize) -> Option<Vec<u8>> {
+        if off.checked_add(len as u64)? > self.size {
+            return None;
+        }
+
+        let mut buf = vec![0; len];
+        self.file.read_exact_at(&mut buf, self.offset + off).ok()?;
+        Some(buf)
+    }
+
+    /// probe the filesystem, the raid members are skipped if 'noraid' is set
+    fn probe_superblock(&self, noraid: bool) -> Option<Vec<ProbeValue>> {
+        SUPERBLOCK_PROBERS
+            .iter()
+            .filter_map(|prober| prober(self))
+            .find(|values| !(noraid && values.contains(&("USAGE", "raid".to_string()))))
+    }
+
+    fn probe_partition_table(&self) -> Option<PartTable> {
+        probe_gpt(self).or_else(|| probe_dos(self))
+    }
+}
+
+/// the partition entry, the offset and size are in 512-byte sectors
+#[derive(Debug, Default, PartialEq, Eq)]
+struct PartEntry {
+    number: u32,
+    offset: u64,
+    size: u64,
+    part_type: String,
+    uuid: Option<String>,
+    name: Option<String>,
+    flags: u64,
+}
+
+#[derive(Debug)]
+struct PartTable {
+    pttype: &'static str,
+    ptuuid: Option<String>,
+    entries: Vec<PartEntry>,
+}
+
+impl PartTable {
+    fn values(&self) -> Vec<ProbeValue> {
+        let mut values = vec![("PTTYPE", self.pttype.to_string())];
+        push_value(&mut values, "PTUUID", self.ptuuid.clone());
+        values
+    }
+
+    /// the values of the partition entry, the disk is in the form of 'major:minor'
+    fn entry_values(&self, number: u32, disk: &str) -> Option<Vec<ProbeValue>> {
+        let entry = self.entries.iter().find(|e| e.number == number)?;
+
+        let mut values = vec![("PART_ENTRY_SCHEME", self.pttype.to_string())];
+        push_value(&mut values, "PART_ENTRY_NAME", entry.name.clone());
+        push_value(&mut values, "PART_ENTRY_UUID", entry.uuid.clone());
+        values.push(("PART_ENTRY_TYPE", entry.part_type.clone()));
+        if entry.flags != 0 {
+            values.push(("PART_ENTRY_FLAGS", format!("0x{:x}", entry.flags)));
+        }
+        values.push(("PART_ENTRY_NUMBER", entry.number.to_string()));
+        values.push(("PART_ENTRY_OFFSET", entry.offset.to_string()));
+        values.push(("PART_ENTRY_SIZE", entry.size.to_string()));
+        values.push(("PART_ENTRY_DISK", disk.to_string()));
+
+        Some(values)
+    }
+}
+
+fn le16(b: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes([b[off], b[off + 1]])
+}
+
+fn le32(b: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(b[off..off + 4].try_into().unwrap())
+}
+
+fn le64(b: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(b[off..off + 8].try_into().unwrap())
+}
+
+fn be16(b: &[u8], off: usize) -> u16 {
+    u16::from_be_bytes([b[off], b[off + 1]])
+}
+
+fn be32(b: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes(b[off..off + 4].try_into().unwrap())
+}
+
+/// push the value if it is not empty
+fn push_value(values: &mut Vec<ProbeValue>, name: &'static str, value: Option<String>) {
+    if let Some(value) = value.filter(|v| !v.is_empty()) {
+        values.push((name, value));
+    }
+}
+
+/// decode the string padded by NUL or spaces
+fn decode_str(b: &[u8]) -> String {
+    let end = b.iter().position(|c| *c == 0).unwrap_or(b.len());
+    String::from_utf8_lossy(&b[..end]).trim_end().to_string()
+}
+
+/// format the UUID stored in big endian, the UUID of all zeros is treated as empty
+fn format_uuid(b: &[u8]) -> Option<String> {
+    if b.iter().all(|c| *c == 0) {
+        return None;
+    }
+
+    let h: String = b.iter().map(|c| format!("{:02x}", c)).collect();
+    Some(format!(
+        "{}-{}-{}-{}-{}",
+        &h[0..8],
+        &h[8..12],
+        &h[12..16],
+        &h[16..20],
+        &h[20..32]
+    ))
+}
+
+/// format the GUID, the first three fields are stored in little endian
+fn format_guid(b: &[u8]) -> Option<String> {
+    let mut b = b.to_vec();
+    b[0..4].reverse();
+    b[4..6].reverse();
+    b[6..8].reverse();
+    format_uuid(&b)
+}
+
+/// the CRC32 used by GPT
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for b in data {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+/// ext2, ext3 and ext4, the superblock locates at 1024
+fn probe_ext(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let sb = p.read(1024, 1024)?;
+    if le16(&sb, 0x38) != 0xef53 {
+        return None;
+    }
+
+    let compat = le32(&sb, 0x5c);
+    let incompat = le32(&sb, 0x60);
+    let ro_compat = le32(&sb, 0x64);
+
+    let (fs_type, usage) = if incompat & EXT3_FEATURE_INCOMPAT_JOURNAL_DEV != 0 {
+        ("jbd", "other")
+    } else if incompat & !EXT3_FEATURE_INCOMPAT_SUPP != 0
+        || ro_compat & !EXT3_FEATURE_RO_COMPAT_SUPP != 0
+    {
+        ("ext4", "filesystem")
+    } else if compat & EXT3_FEATURE_COMPAT_HAS_JOURNAL != 0 {
+        ("ext3", "filesystem")
+    } else {
+        ("ext2", "filesystem")
+    };
+
+    let mut values = vec![
+        ("TYPE", fs_type.to_string()),
+        ("USAGE", usage.to_string()),
+        (
+            "VERSION",
+            format!("{}.{}", le32(&sb, 0x4c), le16(&sb, 0x3e)),
+        ),
+    ];
+    push_value(&mut values, "UUID", format_uuid(&sb[0x68..0x78]));
+    push_value(&mut values, "LABEL", Some(decode_str(&sb[0x78..0x88])));
+
+    Some(values)
+}
+
+fn probe_xfs(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let sb = p.read(0, 512)?;
+    if &sb[0..4] != b"XFSB" {
+        return None;
+    }
+
+    let blocksize = be32(&sb, 4);
+    let sectsize = be16(&sb, 102);
+    if !(512..=65536).contains(&blocksize) || !blocksize.is_power_of_two() || sectsize < 512 {
+        return None;
+    }
+
+    let mut values = vec![
+        ("TYPE", "xfs".to_string()),
+        ("USAGE", "filesystem".to_string()),
+    ];
+    push_value(&mut values, "UUID", format_uuid(&sb[32..48]));
+    push_value(&mut values, "LABEL", Some(decode_str(&sb[108..120])));
+
+    Some(values)
+}
+
+/// btrfs, the superblock locates at 64K
+fn probe_btrfs(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let sb = p.read(0x10000, 0x1000)?;
+    if &sb[0x40..0x48] != b"_BHRfS_M" {
+        return None;
+    }
+
+    let mut values = vec![
+        ("TYPE", "btrfs".to_string()),
+        ("USAGE", "filesystem".to_string()),
+    ];
+    push_value(&mut values, "UUID", format_uuid(&sb[0x20..0x30]));
+    // the uuid of the device item
+    push_value(&mut values, "UUID_SUB", format_uuid(&sb[0x10b..0x11b]));
+    push_value(&mut values, "LABEL", Some(decode_str(&sb[0x12b..0x22b])));
+
+    Some(values)
+}
+
+/// check the BIOS parameter block of FAT boot sector
+fn probe_vfat(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let bs = p.read(0, 512)?;
+    if le16(&bs, 510) != 0xaa55 {
+        return None;
+    }
+
+    let sector_size = le16(&bs, 11) as u64;
+    let cluster_size = bs[13] as u64;
+    let reserved = le16(&bs, 14) as u64;
+    let fats = bs[16] as u64;
+    let root_entries = le16(&bs, 17) as u64;
+    let media = bs[21];
+
+    if !matches!(sector_size, 512 | 1024 | 2048 | 4096)
+        || !cluster_size.is_power_of_two()
+        || reserved == 0
+        || fats == 0
+        || (media != 0xf0 && media < 0xf8)
+    {
+        return None;
+    }
+
+    let fat_length = le16(&bs, 22) as u64;
+    let (version, ext) = if fat_length == 0 {
+        if le32(&bs, 36) == 0 {
+            return None;
+        }
+        ("FAT32", 66)
+    } else {
+        let sectors = match le16(&bs, 19) {
+            0 => le32(&bs, 32) as u64,
+            n => n as u64,
+        };
+        let root_sectors = (root_entries * 32).div_ceil(sector_size);
+        let clusters =
+            sectors.checked_sub(reserved + fats * fat_length + root_sectors)? / cluster_size;
+
+        (if clusters < 4085 { "FAT12" } else { "FAT16" }, 38)
+    };
+
+    let mut values = vec![
+        ("TYPE", "vfat".to_string()),
+        ("USAGE", "filesystem".to_string()),
+        ("VERSION", version.to_string()),
+    ];
+
+    // the serial number and label are valid with the extended boot signature
+    if matches!(bs[ext], 0x28 | 0x29) {
+        let serial = le32(&bs, ext + 1);
+        values.push((
+            "UUID",
+            format!("{:04X}-{:04X}", serial >> 16, serial & 0xffff),
+        ));
+
+        let label = decode_str(&bs[ext + 5..ext + 16]);
+        if label != "NO NAME" {
+            push_value(&mut values, "LABEL", Some(label));
+        }
+    }
+
+    Some(values)
+}
+
+/// the swap signature locates at the end of the first page
+fn probe_swap(p: &Probe) -> Option<Vec<ProbeValue>> {
+    for pagesize in [4096, 8192, 16384, 32768, 65536] {
+        let magic = match p.read(pagesize - 10, 10) {
+            Some(m) => m,
+            None => continue,
+        };
+
+        match &magic[..] {
+            b"SWAPSPACE2" => {
+                let header = p.read(1024, 44)?;
+                let mut values = vec![
+                    ("TYPE", "swap".to_string()),
+                    ("USAGE", "other".to_string()),
+                    ("VERSION", le32(&header, 0).to_string()),
+                ];
+                push_value(&mut values, "UUID", format_uuid(&header[12..28]));
+                push_value(&mut values, "LABEL", Some(decode_str(&header[28..44])));
+
+                return Some(values);
+            }
+            b"SWAP-SPACE" => {
+                return Some(vec![
+                    ("TYPE", "swap".to_string()),
+                    ("USAGE", "other".to_string()),
+                    ("VERSION", "0".to_string()),
+                ]);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// LUKS1 and LUKS2 share the same layout of magic, version and uuid
+fn probe_luks(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let hdr = p.read(0, 256)?;
+    if &hdr[0..6] != b"LUKS\xba\xbe" {
+        return None;
+    }
+
+    let version = be16(&hdr, 6);
+    let mut values = vec![
+        ("TYPE", "crypto_LUKS".to_string()),
+        ("USAGE", "crypto".to_string()),
+        ("VERSION", version.to_string()),
+    ];
+    push_value(&mut values, "UUID", Some(decode_str(&hdr[168..208])));
+    if version == 2 {
+        push_value(&mut values, "LABEL", Some(decode_str(&hdr[24..72])));
+    }
+
+    Some(values)
+}
+
+/// the LVM2 label locates at one of the first four sectors
+fn probe_lvm2(p: &Probe) -> Option<Vec<ProbeValue>> {
+    for sector in 0..4 {
+        let label = match p.read(sector * SECTOR_SIZE, SECTOR_SIZE as usize) {
+            Some(l) => l,
+            None => break,
+        };
+
+        // the sector number recorded in the label must match its location
+        if &label[0..8] != b"LABELONE" || &label[24..32] != b"LVM2 001" || le64(&label, 8) != sector
+        {
+            continue;
+        }
+
+        let offset = le32(&label, 20) as usize;
+        if offset + 32 > label.len() {
+            continue;
+        }
+
+        let id = String::from_utf8_lossy(&label[offset..offset + 32]).to_string();
+        let uuid = [
+            (0, 6),
+            (6, 10),
+            (10, 14),
+            (14, 18),
+            (18, 22),
+            (22, 26),
+            (26, 32),
+        ]
+        .iter()
+        .map(|(s, e)| &id[*s..*e])
+        .collect::<Vec<&str>>()
+        .join("-");
+
+        return Some(vec![
+            ("TYPE", "LVM2_member".to_string()),
+            ("USAGE", "raid".to_string()),
+            ("VERSION", "LVM2 001".to_string()),
+            ("UUID", uuid),
+        ]);
+    }
+
+    None
+}
+
+/// the primary volume descriptor locates at 32K
+fn probe_iso9660(p: &Probe) -> Option<Vec<ProbeValue>> {
+    let pvd = p.read(0x8000, 2048)?;
+    if pvd[0] != 1 || &pvd[1..6] != b"CD001" || pvd[6] != 1 {
+        return None;
+    }
+
+    let mut values = vec![
+        ("TYPE", "iso9660".to_string()),
+        ("USAGE", "filesystem".to_string()),
+    ];
+
+    // the uuid is composed from the modification time, or the creation time
+    let date = [&pvd[830..846], &pvd[813..829]]
+        .into_iter()
+        .find(|d| d.iter().all(u8::is_ascii_digit) && d.iter().any(|c| *c != b'0'))
+        .map(|d| {
+            let d = String::from_utf8_lossy(d);
+            format!(
+                "{}-{}-{}-{}-{}-{}-{}",
+                &d[0..4],
+                &d[4..6],
+                &d[6..8],
+                &d[8..10],
+                &d[10..12],
+                &d[12..14],
+                &d[14..16]
+            )
+        });
+    push_value(&mut values, "UUID", date);
+
+    let label = decode_str(&pvd[40..72]);
+    push_value(&mut values, "LABEL", Some(label.clone()));
+    push_value(&mut values, "VOLUME_ID", Some(label));
+    push_value(&mut values, "SYSTEM_ID", Some(decode_str(&pvd[8..40])));
+    push_value(
+        &mut values,
+        "VOLUME_SET_ID",
+        Some(decode_str(&pvd[190..318])),
+    );
+    push_value(
+        &mut values,
+        "PUBLISHER_ID",
+        Some(decode_str(&pvd[318..446])),
+    );
+    push_value(
+        &mut values,
+        "DATA_PREPARER_ID",
+        Some(decode_str(&pvd[446..574])),
+    );
+    push_value(
+        &mut values,
+        "APPLICATION_ID",
+        Some(decode_str(&pvd[574..702])),
+    );
+
+    Some(values)
+}
+
+/// GPT requires the protective MBR and valid checksums
+fn probe_gpt(p: &Probe) -> Option<PartTable> {
+    let mbr = p.read(0, 512)?;
+    if le16(&mbr, 510) != 0xaa55 || !(0..4).any(|i| mbr[446 + i * 16 + 4] == 0xee) {
+        return None;
+    }
+
+    let lba_size = p.sector_size;
+    let hdr = p.read(lba_size, 512)?;
+    if &hdr[0..8] != b"EFI PART" {
+        return None;
+    }
+
+    let header_size = le32(&hdr, 12) as usize;
+    if !(92..=512).contains(&header_size) || le64(&hdr, 24) != 1 {
+        return None;
+    }
+
+    let mut check = hdr[..header_size].to_vec();
+    check[16..20].fill(0);
+    if crc32(&check) != le32(&hdr, 16) {
+        log::debug!("GPT header checksum mismatch");
+        return None;
+    }
+
+    let entries_lba = le64(&hdr, 72);
+    let num = le32(&hdr, 80) as usize;
+    let entry_size = le32(&hdr, 84) as usize;
+    if entry_size < 128 || entry_size & 7 != 0 || num.checked_mul(entry_size)? > 1 << 20 {
+        return None;
+    }
+
+    let entries = p.read(entries_lba.checked_mul(lba_size)?, num * entry_size)?;
+    if crc32(&entries) != le32(&hdr, 88) {
+        log::debug!("GPT entries checksum mismatch");
+        return None;
+    }
+
+    let factor = lba_size / SECTOR_SIZE;
+    let entries = entries
+        .chunks(entry_size)
+        .enumerate()
+        .filter(|(_, e)| e[0..16].iter().any(|c| *c != 0))
+        .filter_map(|(i, e)| {
+            let first = le64(e, 32);
+            let last = le64(e, 40);
+            // the LBAs come from the disk, skip the entry if it is out of range
+            let offset = first.checked_mul(factor)?;
+            let size = last
+                .checked_sub(first)?
+                .checked_add(1)?
+                .checked_mul(factor)?;
+
+            let name: Vec<u16> = e[56..128]
+                .chunks(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .take_while(|c| *c != 0)
+                .collect();
+
+            Some(PartEntry {
+                number: i as u32 + 1,
+                offset,
+                size,
+                part_type: format_guid(&e[0..16])?,
+                uuid: format_guid(&e[16..32]),
+                name: Some(String::from_utf16_lossy(&name)),
+                flags: le64(e, 48),
+            })
+        })
+        .collect();
+
+    Some(PartTable {
+        pttype: "gpt",
+        ptuuid: format_guid(&hdr[56..72]),
+        entries,
+    })
+}
+
+/// whether the MBR partition type is extended partition
+fn is_extended(part_type: u8) -> bool {
+    matches!(part_type, 0x05 | 0x0f | 0x85)
+}
+
+/// DOS partition table, the logical partitions are numbered from 5
+fn probe_dos(p: &Probe) -> Option<PartTable> {
+    let mbr = p.read(0, 512)?;
+    if le16(&mbr, 510) != 0xaa55 {
+        return None;
+    }
+
+    // the boot indicator must be valid, and the protective MBR is left to GPT
+    let records: Vec<&[u8]> = mbr[446..510].chunks(16).collect();
+    if records
+        .iter()
+        .any(|r| (r[0] != 0 && r[0] != 0x80) || r[4] == 0xee)
+    {
+        return None;
+    }
+
+    // exclude FAT filesystem that has a valid partition-table-like sector
+    if probe_vfat(p).is_some() {
+        return None;
+    }
+
+    let ptuuid = format!("{:08x}", le32(&mbr, 440));
+    let factor = p.sector_size / SECTOR_SIZE;
+    let mut entries = vec![];
+
+    for (i, r) in records.iter().enumerate() {
+        let (start, size) = (le32(r, 8) as u64, le32(r, 12) as u64);
+        if r[4] == 0 || size == 0 {
+            continue;
+        }
+
+        entries.push(PartEntry {
+            number: i as u32 + 1,
+            offset: start * factor,
+            size: size * factor,
+            part_type: format!("0x{:x}", r[4]),
+            uuid: Some(format!("{}-{:02x}", ptuuid, i + 1)),
+            name: None,
+            flags: r[0] as u64,
+        });
+
+        if is_extended(r[4]) {
+            probe_dos_logical(p, start, &ptuuid, &mut entries);
+        }
+    }
+
+    Some(PartTable {
+        pttype: "dos",
+        ptuuid: Some(ptuuid),
+        entries,
+    })
+}
+
+/// walk the chain of extended boot records, the next record is relative to the extended partition
+fn probe_dos_logical(p: &Probe, ext_start: u64, ptuuid: &str, entries: &mut Vec<PartEntry>) {
+    let factor = p.sector_size / SECTOR_SIZE;
+    let mut ebr = ext_start;
+    let mut number = 5;
+
+    // limit the chain length in case of loops
+    for _ in 0..128 {
+        let sector = match p.read(ebr * p.sector_size, 512) {
+            Some(s) if le16(&s, 510) == 0xaa55 => s,
+            _ => break,
+        };
+
+        let logical = &sector[446..462];
+        if logical[4] != 0 && le32(logical, 12) != 0 {
+            entries.push(PartEntry {
+                number,
+                offset: (ebr + le32(logical, 8) as u64) * factor,
+                size: le32(logical, 12) as u64 * factor,
+                part_type: format!("0x{:x}", logical[4]),
+                uuid: Some(format!("{}-{:02x}", ptuuid, number)),
+                name: None,
+                flags: logical[0] as u64,
+            });
+            number += 1;
+        }
+
+        let next = &sector[462..478];
+        if !is_extended(next[4]) || le32(next, 8) == 0 {
+            break;
+        }
+        ebr = ext_start + le32(next, 8) as u64;
+    }
+}
+
+/// translate the value named as libblkid into udev properties
+fn blkid_properties(name: &str, value: &str) -> Vec<(String, String)> {
+    let safe = |v: &str| replace_chars(&replace_whitespace_chars(v), ALLOWED_CHARS_INPUT);
+
+    match name {
+        "TYPE" | "USAGE" | "VERSION" => vec![(format!("ID_FS_{}", name), value.to_string())],
+        "UUID" | "UUID_SUB" | "LABEL" => vec![
+            (format!("ID_FS_{}", name), safe(value)),
+            (format!("ID_FS_{}_ENC", name), encode_string(value)),
+        ],
+        "PTTYPE" => vec![("ID_PART_TABLE_TYPE".to_string(), value.to_string())],
+        "PTUUID" => vec![("ID_PART_TABLE_UUID".to_string(), value.to_string())],
+        "PART_ENTRY_NAME" | "PART_ENTRY_TYPE" => {
+            vec![(format!("ID_{}", name), encode_string(value))]
+        }
+        _ if name.starts_with("PART_ENTRY_") => vec![(format!("ID_{}", name), value.to_string())],
+        "SYSTEM_ID" | "PUBLISHER_ID" | "APPLICATION_ID" | "BOOT_SYSTEM_ID" | "VOLUME_ID"
+        | "LOGICAL_VOLUME_ID" | "VOLUME_SET_ID" | "DATA_PREPARER_ID" => {
+            vec![(format!("ID_FS_{}", name), encode_string(value))]
+        }
+        _ => vec![],
+    }
+}
+
+/// open the device node for probing, the media change is not triggered
+fn open_device(devname: &str) -> std::io::Result<(File, u64)> {
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+        .open(devname)?;
+
+    let mut sector_size: libc::c_int = SECTOR_SIZE as libc::c_int;
+    if file.metadata()?.file_type().is_block_device() {
+        unsafe { blksszget(file.as_raw_fd(), &mut sector_size) }?;
+    }
+
+    Ok((file, sector_size as u64))
+}
+
+/// probe the partition entry of the partition device on its parent disk
+fn probe_partition_entry(device: &Arc<Mutex<Device>>) -> Option<Vec<ProbeValue>> {
+    let number = device
+        .lock()
+        .unwrap()
+        .get_sysattr_value("partition".to_string())
+        .ok()?
+        .trim()
+        .parse::<u32>()
+        .ok()?;
+
+    let disk = device.lock().unwrap().get_parent().ok()?;
+    let mut disk = disk.lock().unwrap();
+    let devname = disk.get_devname().ok()?;
+    let devnum = disk.get_devnum().ok()?;
+
+    let (file, sector_size) = open_device(&devname).ok()?;
+    let table = Probe::new(&file, 0, sector_size)?.probe_partition_table()?;
+
+    table.entry_values(number, &format!("{}:{}", major(devnum), minor(devnum)))
+}
+
 impl Builtin for Blkid {
     /// builtin command
     fn cmd(
         &self,
-        _device: Arc<Mutex<Device>>,
+        device: Arc<Mutex<Device>>,
         _ret_rtnl: &mut RefCell<Option<Netlink>>,
         _argc: i32,
-        _argv: Vec<String>,
-        _test: bool,
+        argv: Vec<String>,
+        test: bool,
     ) -> Result<bool> {
+        let mut offset = 0;
+        let mut noraid = false;
+
+        let mut args = argv.iter().skip(1);
+        while let Some(arg) = args.next() {
+            let value = match arg.as_str() {
+                "-o" | "--offset" => args.next().map(|s| s.as_str()),
+                "-R" | "--noraid" => {
+                    noraid = true;
+                    continue;
+                }
+                _ => arg.strip_prefix("--offset="),
+            };
+
+            match value {
+                Some(v) => {
+                    offset = v.parse::<u64>().map_err(|_| Error::BuiltinCommandError {
+                        msg: "invalid offset",
+                    })?
+                }
+                None => log::debug!("Ignoring unsupported blkid argument '{}'", arg),
+            }
+        }
+
+        let (devname, is_partition) = {
+            let mut device = device.lock().unwrap();
+
+            // never open the character devices, e.g., opening watchdog starts it
+            if device.get_subsystem().unwrap_or_default() != "block" {
+                return Ok(false);
+            }
+
+            match device.get_devname() {
+                Ok(devname) => (
+                    devname,
+                    device.get_devtype().unwrap_or_default() == "partition",
+                ),
+                Err(_) => return Ok(false),
+            }
+        };
+
+        let (file, sector_size) = match open_device(&devname) {
+            Ok(ret) => ret,
+            Err(e) => {
+                log::debug!("Failed to open '{}' for probing: {}", devname, e);
+                return Ok(false);
+            }
+        };
+
+        let mut values = vec![];
+        if let Some(probe) = Probe::new(&file, offset, sector_size) {
+            if let Some(table) = probe.probe_partition_table() {
+                values.extend(table.values());
+            }
+            if let Some(sb) = probe.probe_superblock(noraid) {
+                values.extend(sb);
+            }
+        }
+
+        if is_partition {
+            values.extend(probe_partition_entry(&device).unwrap_or_default());
+        }
+
+        for (name, value) in values {
+            for (key, value) in blkid_properties(name, &value) {
+                add_property(&device, test, &key, &value)?;
+            }
+        }
+
         Ok(true)
     }
 
@@ -57,3 +869,397 @@ impl Builtin for Blkid {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    /// the image file built by writing the bytes of on-disk structures
+    struct Image {
+        path: PathBuf,
+        file: File,
+    }
+
+    impl Image {
+        fn new(name: &str, size: u64) -> Image {
+            let path = std::env::current_dir()
+                .unwrap()
+                .join(format!("test_blkid_{}.img", name));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+                .unwrap();
+            file.set_len(size).unwrap();
+
+            Image { path, file }
+        }
+
+        fn write(&self, offset: u64, data: &[u8]) -> &Self {
+            self.file.write_all_at(data, offset).unwrap();
+            self
+        }
+
+        fn probe(&self) -> Probe<'_> {
+            Probe::new(&self.file, 0, SECTOR_SIZE).unwrap()
+        }
+
+        fn superblock(&self) -> HashMap<&'static str, String> {
+            self.probe()
+                .probe_superblock(false)
+                .unwrap_or_default()
+                .into_iter()
+                .collect()
+        }
+    }
+
+    impl Drop for Image {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    const UUID: [u8; 16] = [
+        0x3e, 0x6b, 0xe9, 0xde, 0x81, 0x39, 0x11, 0xd1, 0x9d, 0x19, 0x00, 0x00, 0xf8, 0x0a, 0x1e,
+        0x4d,
+    ];
+    const UUID_STR: &str = "3e6be9de-8139-11d1-9d19-0000f80a1e4d";
+
+    /// the partition record of MBR or EBR
+    fn mbr_record(boot: u8, part_type: u8, start: u32, size: u32) -> Vec<u8> {
+        let mut r = vec![boot, 0, 0, 0, part_type, 0, 0, 0];
+        r.extend(start.to_le_bytes());
+        r.extend(size.to_le_bytes());
+        r
+    }
+
+    #[test]
+    fn test_probe_ext() {
+        let img = Image::new("ext", 8192);
+        img.write(1024 + 0x38, &[0x53, 0xef])
+            .write(1024 + 0x4c, &1u32.to_le_bytes())
+            .write(1024 + 0x68, &UUID)
+            .write(1024 + 0x78, b"rootfs");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "ext2");
+        assert_eq!(sb["USAGE"], "filesystem");
+        assert_eq!(sb["VERSION"], "1.0");
+        assert_eq!(sb["UUID"], UUID_STR);
+        assert_eq!(sb["LABEL"], "rootfs");
+
+        img.write(1024 + 0x5c, &EXT3_FEATURE_COMPAT_HAS_JOURNAL.to_le_bytes());
+        assert_eq!(img.superblock()["TYPE"], "ext3");
+
+        // extents
+        img.write(1024 + 0x60, &0x40u32.to_le_bytes());
+        assert_eq!(img.superblock()["TYPE"], "ext4");
+
+        img.write(
+            1024 + 0x60,
+            &EXT3_FEATURE_INCOMPAT_JOURNAL_DEV.to_le_bytes(),
+        );
+        assert_eq!(img.superblock()["TYPE"], "jbd");
+    }
+
+    #[test]
+    fn test_probe_xfs_and_btrfs() {
+        let img = Image::new("xfs", 4096);
+        img.write(0, b"XFSB")
+            .write(4, &4096u32.to_be_bytes())
+            .write(32, &UUID)
+            .write(102, &512u16.to_be_bytes())
+            .write(108, b"data");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "xfs");
+        assert_eq!(sb["UUID"], UUID_STR);
+        assert_eq!(sb["LABEL"], "data");
+
+        let img = Image::new("btrfs", 0x11000);
+        let mut sub = UUID;
+        sub[15] = 0x4e;
+        img.write(0x10000 + 0x20, &UUID)
+            .write(0x10000 + 0x40, b"_BHRfS_M")
+            .write(0x10000 + 0x10b, &sub)
+            .write(0x10000 + 0x12b, b"pool");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "btrfs");
+        assert_eq!(sb["UUID"], UUID_STR);
+        assert_eq!(sb["UUID_SUB"], "3e6be9de-8139-11d1-9d19-0000f80a1e4e");
+        assert_eq!(sb["LABEL"], "pool");
+    }
+
+    #[test]
+    fn test_probe_vfat() {
+        let img = Image::new("vfat32", 4096);
+        img.write(11, &512u16.to_le_bytes())
+            .write(13, &[8])
+            .write(14, &32u16.to_le_bytes())
+            .write(16, &[2])
+            .write(21, &[0xf8])
+            .write(32, &262144u32.to_le_bytes())
+            .write(36, &1000u32.to_le_bytes())
+            .write(66, &[0x29])
+            .write(67, &0x1234abcdu32.to_le_bytes())
+            .write(71, b"EFI SYSTEM ")
+            .write(510, &[0x55, 0xaa]);
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "vfat");
+        assert_eq!(sb["VERSION"], "FAT32");
+        assert_eq!(sb["UUID"], "1234-ABCD");
+        assert_eq!(sb["LABEL"], "EFI SYSTEM");
+
+        // the boot sector of FAT is not a partition table
+        assert!(probe_dos(&img.probe()).is_none());
+
+        let img = Image::new("vfat16", 4096);
+        img.write(11, &512u16.to_le_bytes())
+            .write(13, &[4])
+            .write(14, &1u16.to_le_bytes())
+            .write(16, &[2])
+            .write(17, &512u16.to_le_bytes())
+            .write(19, &65535u16.to_le_bytes())
+            .write(21, &[0xf8])
+            .write(22, &64u16.to_le_bytes())
+            .write(38, &[0x29])
+            .write(39, &0x0000beefu32.to_le_bytes())
+            .write(43, b"NO NAME    ")
+            .write(510, &[0x55, 0xaa]);
+
+        let sb = img.superblock();
+        assert_eq!(sb["VERSION"], "FAT16");
+        assert_eq!(sb["UUID"], "0000-BEEF");
+        assert!(!sb.contains_key("LABEL"));
+
+        // the invalid media byte
+        img.write(21, &[0x12]);
+        assert!(img.superblock().is_empty());
+    }
+
+    #[test]
+    fn test_probe_swap_luks_lvm2() {
+        let img = Image::new("swap", 8192);
+        img.write(1024, &1u32.to_le_bytes())
+            .write(1024 + 12, &UUID)
+            .write(1024 + 28, b"swap0")
+            .write(4096 - 10, b"SWAPSPACE2");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "swap");
+        assert_eq!(sb["USAGE"], "other");
+        assert_eq!(sb["VERSION"], "1");
+        assert_eq!(sb["UUID"], UUID_STR);
+        assert_eq!(sb["LABEL"], "swap0");
+
+        let img = Image::new("luks", 4096);
+        img.write(0, b"LUKS\xba\xbe")
+            .write(6, &2u16.to_be_bytes())
+            .write(24, b"secret")
+            .write(168, UUID_STR.as_bytes());
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "crypto_LUKS");
+        assert_eq!(sb["USAGE"], "crypto");
+        assert_eq!(sb["VERSION"], "2");
+        assert_eq!(sb["UUID"], UUID_STR);
+        assert_eq!(sb["LABEL"], "secret");
+
+        let img = Image::new("lvm2", 4096);
+        img.write(512, b"LABELONE")
+            .write(512 + 8, &1u64.to_le_bytes())
+            .write(512 + 20, &32u32.to_le_bytes())
+            .write(512 + 24, b"LVM2 001")
+            .write(512 + 32, b"abcdefghijklmnopqrstuvwxyz012345");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "LVM2_member");
+        assert_eq!(sb["USAGE"], "raid");
+        assert_eq!(sb["UUID"], "abcdef-ghij-klmn-opqr-stuv-wxyz-012345");
+        assert!(img.probe().probe_superblock(true).is_none());
+    }
+
+    #[test]
+    fn test_probe_iso9660() {
+        let img = Image::new("iso9660", 0x9000);
+        img.write(0x8000, b"\x01CD001\x01")
+            .write(0x8000 + 8, b"LINUX                           ")
+            .write(0x8000 + 40, b"Install Media                   ")
+            .write(0x8000 + 318, b"PUBLISHER")
+            .write(0x8000 + 813, b"2023010112000000")
+            .write(0x8000 + 830, b"0000000000000000");
+
+        let sb = img.superblock();
+        assert_eq!(sb["TYPE"], "iso9660");
+        assert_eq!(sb["UUID"], "2023-01-01-12-00-00-00");
+        assert_eq!(sb["LABEL"], "Install Media");
+        assert_eq!(sb["SYSTEM_ID"], "LINUX");
+        assert_eq!(sb["PUBLISHER_ID"], "PUBLISHER");
+        assert!(!sb.contains_key("APPLICATION_ID"));
+    }
+
+    #[test]
+    fn test_probe_gpt() {
+        let img = Image::new("gpt", 1 << 20);
+        img.write(446, &mbr_record(0, 0xee, 1, 2047))
+            .write(510, &[0x55, 0xaa]);
+
+        // the EFI system partition in the first slot, and the linux filesystem in the third slot
+        let mut entries = vec![0u8; 128 * 128];
+        let esp: [u8; 16] = [
+            0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e,
+            0xc9, 0x3b,
+        ];
+        let linux: [u8; 16] = [
+            0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47,
+            0x7d, 0xe4,
+        ];
+        entries[0..16].copy_from_slice(&esp);
+        entries[16..32].copy_from_slice(&UUID);
+        entries[32..40].copy_from_slice(&34u64.to_le_bytes());
+        entries[40..48].copy_from_slice(&1000u64.to_le_bytes());
+        entries[48..56].copy_from_slice(&1u64.to_le_bytes());
+        for (i, c) in "EFI System".encode_utf16().enumerate() {
+            entries[56 + i * 2..58 + i * 2].copy_from_slice(&c.to_le_bytes());
+        }
+        entries[256..272].copy_from_slice(&linux);
+        entries[288..296].copy_from_slice(&1001u64.to_le_bytes());
+        entries[296..304].copy_from_slice(&2014u64.to_le_bytes());
+        // the size of the entry in the fourth slot overflows, it is skipped
+        entries[384..400].copy_from_slice(&linux);
+        entries[424..432].copy_from_slice(&u64::MAX.to_le_bytes());
+
+        let mut hdr = vec![0u8; 92];
+        hdr[0..8].copy_from_slice(b"EFI PART");
+        hdr[8..12].copy_from_slice(&0x10000u32.to_le_bytes());
+        hdr[12..16].copy_from_slice(&92u32.to_le_bytes());
+        hdr[24..32].copy_from_slice(&1u64.to_le_bytes());
+        hdr[32..40].copy_from_slice(&2047u64.to_le_bytes());
+        hdr[40..48].copy_from_slice(&34u64.to_le_bytes());
+        hdr[48..56].copy_from_slice(&2014u64.to_le_bytes());
+        hdr[56..72].copy_from_slice(&UUID);
+        hdr[72..80].copy_from_slice(&2u64.to_le_bytes());
+        hdr[80..84].copy_from_slice(&128u32.to_le_bytes());
+        hdr[84..88].copy_from_slice(&128u32.to_le_bytes());
+        hdr[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+        let crc = crc32(&hdr);
+        hdr[16..20].copy_from_slice(&crc.to_le_bytes());
+        img.write(512, &hdr).write(1024, &entries);
+
+        let table = img.probe().probe_partition_table().unwrap();
+        assert_eq!(table.pttype, "gpt");
+        assert_eq!(
+            table.ptuuid.as_deref(),
+            Some("dee96b3e-3981-d111-9d19-0000f80a1e4d")
+        );
+        assert_eq!(table.entries.len(), 2);
+        assert_eq!(
+            table.entries[0],
+            PartEntry {
+                number: 1,
+                offset: 34,
+                size: 967,
+                part_type: "c12a7328-f81f-11d2-ba4b-00a0c93ec93b".to_string(),
+                uuid: Some("dee96b3e-3981-d111-9d19-0000f80a1e4d".to_string()),
+                name: Some("EFI System".to_string()),
+                flags: 1,
+            }
+        );
+        assert_eq!(table.entries[1].number, 3);
+        assert_eq!(
+            table.entries[1].part_type,
+            "0fc63daf-8483-4772-8e79-3d69d8477de4"
+        );
+
+        let values: HashMap<&str, String> =
+            table.entry_values(1, "8:0").unwrap().into_iter().collect();
+        assert_eq!(values["PART_ENTRY_SCHEME"], "gpt");
+        assert_eq!(values["PART_ENTRY_NAME"], "EFI System");
+        assert_eq!(values["PART_ENTRY_FLAGS"], "0x1");
+        assert_eq!(values["PART_ENTRY_DISK"], "8:0");
+        assert!(table.entry_values(2, "8:0").is_none());
+
+        // the corrupted header is refused, and the protective MBR is not a DOS table
+        img.write(512 + 16, &[0, 0, 0, 0]);
+        assert!(img.probe().probe_partition_table().is_none());
+    }
+
+    #[test]
+    fn test_probe_dos() {
+        let img = Image::new("dos", 12 << 20);
+        img.write(440, &0xdeadbeefu32.to_le_bytes())
+            .write(446, &mbr_record(0x80, 0x83, 2048, 4096))
+            .write(462, &mbr_record(0, 0x05, 8192, 8192))
+            .write(510, &[0x55, 0xaa]);
+
+        // the chain of two logical partitions
+        img.write(8192 * 512 + 446, &mbr_record(0, 0x83, 63, 1000))
+            .write(8192 * 512 + 462, &mbr_record(0, 0x05, 2048, 1000))
+            .write(8192 * 512 + 510, &[0x55, 0xaa])
+            .write(10240 * 512 + 446, &mbr_record(0, 0x82, 63, 500))
+            .write(10240 * 512 + 510, &[0x55, 0xaa]);
+
+        let table = img.probe().probe_partition_table().unwrap();
+        assert_eq!(table.pttype, "dos");
+        assert_eq!(table.ptuuid.as_deref(), Some("deadbeef"));
+
+        let entries: Vec<(u32, u64, u64, &str)> = table
+            .entries
+            .iter()
+            .map(|e| (e.number, e.offset, e.size, e.part_type.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (1, 2048, 4096, "0x83"),
+                (2, 8192, 8192, "0x5"),
+                (5, 8255, 1000, "0x83"),
+                (6, 10303, 500, "0x82"),
+            ]
+        );
+        assert_eq!(table.entries[0].uuid.as_deref(), Some("deadbeef-01"));
+        assert_eq!(table.entries[0].flags, 0x80);
+        assert_eq!(table.entries[3].uuid.as_deref(), Some("deadbeef-06"));
+
+        // the invalid boot indicator
+        img.write(446, &[0x12]);
+        assert!(img.probe().probe_partition_table().is_none());
+    }
+
+    #[test]
+    fn test_blkid_properties() {
+        assert_eq!(
+            blkid_properties("LABEL", "My Disk"),
+            vec![
+                ("ID_FS_LABEL".to_string(), "My_Disk".to_string()),
+                ("ID_FS_LABEL_ENC".to_string(), "My\\x20Disk".to_string()),
+            ]
+        );
+        assert_eq!(
+            blkid_properties("UUID", UUID_STR)[0],
+            ("ID_FS_UUID".to_string(), UUID_STR.to_string())
+        );
+        assert_eq!(
+            blkid_properties("PTTYPE", "gpt"),
+            vec![("ID_PART_TABLE_TYPE".to_string(), "gpt".to_string())]
+        );
+        assert_eq!(
+            blkid_properties("PART_ENTRY_NAME", "EFI System"),
+            vec![(
+                "ID_PART_ENTRY_NAME".to_string(),
+                "EFI\\x20System".to_string()
+            )]
+        );
+        assert_eq!(
+            blkid_properties("PART_ENTRY_NUMBER", "1"),
+            vec![("ID_PART_ENTRY_NUMBER".to_string(), "1".to_string())]
+        );
+        assert!(blkid_properties("UNKNOWN", "1").is_empty());
+    }
+}
@@ -69,8 +69,23 @@ pub trait Builtin {
     }
 }
 
+/// add the property to the device and print it in the test mode, like udev_builtin_add_property
+pub fn add_property(device: &Arc<Mutex<Device>>, test: bool, key: &str, value: &str) -> Result<()> {
+    if test {
+        println!("{}={}", key, value);
+    }
+
+    device
+        .lock()
+        .unwrap()
+        .add_property(key.to_string(), value.to_string())
+        .map_err(|_| Error::BuiltinCommandError {
+            msg: "add property failed",
+        })
+}
+
 /// enumerator of builtin commands
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
 #[allow(missing_docs)]
 pub enum BuiltinCommand {
     Blkid,
@@ -141,13 +156,12 @@ impl Display for BuiltinCommand {
 }
 
 /// manage builtin commands
-struct BuiltinManager {
+pub(crate) struct BuiltinManager {
     builtins: HashMap<BuiltinCommand, Box<dyn Builtin>>,
 }
 
 impl BuiltinManager {
     /// create builtin manager
-    #[allow(dead_code)]
     pub fn new() -> Self {
         let mut builtins = HashMap::<BuiltinCommand, Box<dyn Builtin>>::with_capacity(
             BuiltinCommand::Max as usize,
@@ -209,7 +223,6 @@ impl BuiltinManager {
     }
 
     /// check whether the builtin command run once
-    #[allow(dead_code)]
     pub fn run_once(&self, cmd: BuiltinCommand) -> bool {
         match self.builtins.get(&cmd) {
             Some(builtin) => builtin.run_once(),
@@ -218,7 +231,6 @@ impl BuiltinManager {
     }
 
     /// run builtin command
-    #[allow(dead_code)]
     pub fn run(
         &self,
         device: Arc<Mutex<Device>>,
@@ -239,8 +251,28 @@ impl BuiltinManager {
 mod tests {
     use super::BuiltinManager;
     use super::Netlink;
+    use super::{add_property, Device};
     use device::device_enumerator::DeviceEnumerator;
     use std::cell::RefCell;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_add_property() {
+        let device = Device::from_nulstr(
+            "ACTION=add\0DEVPATH=/devices/virtual/net/lo\0SUBSYSTEM=net\0".as_bytes(),
+        )
+        .unwrap();
+        let device = Arc::new(Mutex::new(device));
+        add_property(&device, true, "ID_PATH", "pci-0000:00:01.0").unwrap();
+        assert_eq!(
+            device
+                .lock()
+                .unwrap()
+                .get_property_value("ID_PATH".to_string())
+                .unwrap(),
+            "pci-0000:00:01.0"
+        );
+    }
 
     #[test]
     fn test_builtin_manager() {
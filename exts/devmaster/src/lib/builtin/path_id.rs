@@ -12,27 +12,500 @@
 
 //! path_id builtin
 //!
+//! compose the persistent device path by walking up the parent chain,
+//! the format is compatible with udev, e.g., 'pci-0000:00:1f.2-ata-1.0'
+//!
 
-use crate::builtin::Builtin;
 use crate::builtin::Netlink;
+use crate::builtin::{add_property, Builtin};
 use crate::error::Result;
 use device::Device;
 use std::cell::RefCell;
+use std::fs;
 use std::sync::{Arc, Mutex};
 
 /// path_id builtin command
 pub struct PathId;
 
+/// the sysfs device that path_id walks through
+trait SysDevice: Sized + Clone {
+    /// the absolute path in sysfs
+    fn syspath(&self) -> String;
+
+    /// the last component of syspath
+    fn sysname(&self) -> String;
+
+    /// the subsystem, e.g., 'pci' and 'scsi'
+    fn subsystem(&self) -> Option<String>;
+
+    /// the device type, e.g., 'scsi_device' and 'usb_interface'
+    fn devtype(&self) -> Option<String>;
+
+    /// the sysattr value without trailing newline
+    fn sysattr(&self, attr: &str) -> Option<String>;
+
+    /// the parent device
+    fn parent(&self) -> Option<Self>;
+
+    /// find the device by its subsystem and sysname, e.g., the 'fc_transport' of a scsi target
+    fn lookup(&self, subsystem: &str, sysname: &str) -> Option<Self>;
+
+    /// the trailing digits of sysname
+    fn sysnum(&self) -> Option<String> {
+        let sysname = self.sysname();
+        let prefix = sysname.trim_end_matches(|c: char| c.is_ascii_digit());
+        if prefix.len() == sysname.len() {
+            return None;
+        }
+
+        Some(sysname[prefix.len()..].to_string())
+    }
+
+    /// find the closest ancestor with the subsystem and device type
+    fn parent_with_subsystem_devtype(&self, subsystem: &str, devtype: &str) -> Option<Self> {
+        let mut parent = self.parent();
+
+        while let Some(dev) = parent {
+            if dev.subsystem().as_deref() == Some(subsystem)
+                && dev.devtype().as_deref() == Some(devtype)
+            {
+                return Some(dev);
+            }
+            parent = dev.parent();
+        }
+
+        None
+    }
+}
+
+impl SysDevice for Arc<Mutex<Device>> {
+    fn syspath(&self) -> String {
+        self.lock()
+            .unwrap()
+            .get_syspath()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn sysname(&self) -> String {
+        self.lock()
+            .unwrap()
+            .get_sysname()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn subsystem(&self) -> Option<String> {
+        self.lock().unwrap().get_subsystem().ok()
+    }
+
+    fn devtype(&self) -> Option<String> {
+        self.lock().unwrap().get_devtype().ok()
+    }
+
+    fn sysattr(&self, attr: &str) -> Option<String> {
+        self.lock()
+            .unwrap()
+            .get_sysattr_value(attr.to_string())
+            .ok()
+            .map(|v| v.trim_end().to_string())
+    }
+
+    fn parent(&self) -> Option<Self> {
+        self.lock().unwrap().get_parent().ok()
+    }
+
+    fn lookup(&self, subsystem: &str, sysname: &str) -> Option<Self> {
+        Device::from_subsystem_sysname(subsystem.to_string(), sysname.to_string())
+            .ok()
+            .map(|d| Arc::new(Mutex::new(d)))
+    }
+}
+
+/// prepend the element to the path, elements are joined by '-'
+fn path_prepend(path: &mut Option<String>, element: String) {
+    *path = Some(match path.take() {
+        Some(old) => format!("{}-{}", element, old),
+        None => element,
+    });
+}
+
+/// prepend the element to the path only if the path is already composed
+fn compat_prepend(compat_path: &mut Option<String>, element: String) {
+    if compat_path.is_some() {
+        path_prepend(compat_path, element);
+    }
+}
+
+/// format the scsi lun of the device, e.g., 'lun-0'
+fn format_lun_number<D: SysDevice>(dev: &D) -> Option<String> {
+    let lun = dev.sysnum()?.parse::<u64>().ok()?;
+
+    if lun < 256 {
+        // address method 0, peripheral device addressing with bus id of zero
+        Some(format!("lun-{}", lun))
+    } else {
+        // handle all other lun addressing methods by using a variant of the original lun format
+        Some(format!(
+            "lun-0x{:04x}{:04x}00000000",
+            lun & 0xffff,
+            (lun >> 16) & 0xffff
+        ))
+    }
+}
+
+/// skip the consecutive ancestors in the same subsystem, return the topmost one
+fn skip_subsystem<D: SysDevice>(dev: &D, subsystem: &str) -> D {
+    let mut ret = dev.clone();
+    let mut parent = Some(dev.clone());
+
+    while let Some(p) = parent {
+        if p.subsystem().as_deref() != Some(subsystem) {
+            break;
+        }
+        parent = p.parent();
+        ret = p;
+    }
+
+    ret
+}
+
+/// parse the scsi address 'host:bus:target:lun' in sysname
+fn scsi_address<D: SysDevice>(dev: &D) -> Option<(u32, u32, u32, u32)> {
+    let sysname = dev.sysname();
+    let addr = sysname
+        .split(':')
+        .map(|s| s.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    match addr[..] {
+        [host, bus, target, lun] => Some((host, bus, target, lun)),
+        _ => None,
+    }
+}
+
+fn handle_scsi_fibre_channel<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    let target = parent.parent_with_subsystem_devtype("scsi", "scsi_target")?;
+    let fc = parent.lookup("fc_transport", &target.sysname())?;
+    let port = fc.sysattr("port_name")?;
+    let lun = format_lun_number(parent)?;
+
+    path_prepend(path, format!("fc-{}-{}", port, lun));
+    Some(parent.clone())
+}
+
+fn handle_scsi_sas_wide_port<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    let target = parent.parent_with_subsystem_devtype("scsi", "scsi_target")?;
+    let end_device = target.parent()?;
+    let sas = parent.lookup("sas_device", &end_device.sysname())?;
+    let address = sas.sysattr("sas_address")?;
+    let lun = format_lun_number(parent)?;
+
+    path_prepend(path, format!("sas-{}-{}", address, lun));
+    Some(parent.clone())
+}
+
+fn handle_scsi_sas<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    let target = parent.parent_with_subsystem_devtype("scsi", "scsi_target")?;
+    let end_device = target.parent()?;
+    let sas = parent.lookup("sas_device", &end_device.sysname())?;
+
+    // the next parent is sas port
+    let port = end_device.parent()?;
+    let num_phys = parent
+        .lookup("sas_port", &port.sysname())?
+        .sysattr("num_phys")?;
+
+    // the port with multiple phys is a wide port
+    if num_phys != "1" {
+        return handle_scsi_sas_wide_port(parent, path);
+    }
+
+    let phy_id = sas.sysattr("phy_identifier")?;
+
+    // the parent of port is either hba or expander
+    let expander = port.parent()?;
+    let expander_address = match parent.lookup("sas_device", &expander.sysname()) {
+        Some(dev) => Some(dev.sysattr("sas_address")?),
+        None => None,
+    };
+
+    let lun = format_lun_number(parent)?;
+    match expander_address {
+        Some(address) => path_prepend(path, format!("sas-exp{}-phy{}-{}", address, phy_id, lun)),
+        None => path_prepend(path, format!("sas-phy{}-{}", phy_id, lun)),
+    }
+
+    Some(parent.clone())
+}
+
+fn handle_scsi_iscsi<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    let mut transport = parent.parent_with_subsystem_devtype("scsi", "scsi_target")?;
+
+    // find iscsi session
+    let session = loop {
+        transport = transport.parent()?;
+        let sysname = transport.sysname();
+        if sysname.starts_with("session") {
+            break sysname;
+        }
+    };
+
+    let target = parent
+        .lookup("iscsi_session", &session)?
+        .sysattr("targetname")?;
+
+    let conn = parent.lookup(
+        "iscsi_connection",
+        &format!("connection{}:0", session.trim_start_matches("session")),
+    )?;
+    let addr = conn.sysattr("persistent_address")?;
+    let port = conn.sysattr("persistent_port")?;
+    let lun = format_lun_number(parent)?;
+
+    path_prepend(
+        path,
+        format!("ip-{}:{}-iscsi-{}-{}", addr, port, target, lun),
+    );
+    Some(parent.clone())
+}
+
+fn handle_scsi_ata<D: SysDevice>(
+    parent: &D,
+    path: &mut Option<String>,
+    compat_path: &mut Option<String>,
+) -> Option<D> {
+    let (_, bus, target, _) = scsi_address(parent)?;
+    let host = parent.parent_with_subsystem_devtype("scsi", "scsi_host")?;
+    let port = host.parent()?;
+    let port_no = parent
+        .lookup("ata_port", &port.sysname())?
+        .sysattr("port_no")?;
+
+    if bus != 0 {
+        // devices behind port multiplier have a bus != 0
+        path_prepend(path, format!("ata-{}.{}.0", port_no, bus));
+    } else {
+        // master/slave are distinguished by target id
+        path_prepend(path, format!("ata-{}.{}", port_no, target));
+    }
+
+    // old compatible persistent link for ATA devices
+    path_prepend(compat_path, format!("ata-{}", port_no));
+
+    Some(parent.clone())
+}
+
+fn handle_scsi_default<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    let (host, bus, target, lun) = scsi_address(parent)?;
+    let hostdev = parent.parent_with_subsystem_devtype("scsi", "scsi_host")?;
+
+    // rebase host offset to get the local relative number, the smallest
+    // host number under the same parent is taken as the base
+    let syspath = hostdev.syspath();
+    let base = syspath.rsplit_once('/')?.0;
+    let basenum = fs::read_dir(base)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_prefix("host")?.parse::<u32>().ok()
+        })
+        .min();
+
+    let basenum = match basenum {
+        Some(n) => n,
+        None => return Some(hostdev),
+    };
+
+    path_prepend(
+        path,
+        format!("scsi-{}:{}:{}:{}", host - basenum, bus, target, lun),
+    );
+    Some(hostdev)
+}
+
+fn handle_scsi<D: SysDevice>(
+    parent: &D,
+    path: &mut Option<String>,
+    compat_path: &mut Option<String>,
+    supported_parent: &mut bool,
+) -> Option<D> {
+    if parent.devtype().as_deref() != Some("scsi_device") {
+        return Some(parent.clone());
+    }
+
+    // firewire
+    if let Some(id) = parent.sysattr("ieee1394_id") {
+        path_prepend(path, format!("ieee1394-0x{}", id));
+        *supported_parent = true;
+        return Some(skip_subsystem(parent, "scsi"));
+    }
+
+    // scsi sysfs does not have a "subsystem" for the transport
+    let syspath = parent.syspath();
+
+    if syspath.contains("/rport-") {
+        *supported_parent = true;
+        return handle_scsi_fibre_channel(parent, path);
+    }
+
+    if syspath.contains("/end_device-") {
+        *supported_parent = true;
+        return handle_scsi_sas(parent, path);
+    }
+
+    if syspath.contains("/session") {
+        *supported_parent = true;
+        return handle_scsi_iscsi(parent, path);
+    }
+
+    if syspath.contains("/ata") {
+        return handle_scsi_ata(parent, path, compat_path);
+    }
+
+    handle_scsi_default(parent, path)
+}
+
+fn handle_usb<D: SysDevice>(parent: &D, path: &mut Option<String>) -> Option<D> {
+    match parent.devtype().as_deref() {
+        Some("usb_interface") | Some("usb_device") => {}
+        _ => return Some(parent.clone()),
+    }
+
+    // e.g., '1-1.2:1.0' is composed to 'usb-0:1.2:1.0'
+    let sysname = parent.sysname();
+    let port = match sysname.split_once('-') {
+        Some((_, port)) => port,
+        None => return Some(parent.clone()),
+    };
+
+    path_prepend(path, format!("usb-0:{}", port));
+    Some(skip_subsystem(parent, "usb"))
+}
+
+/// compose the persistent path and the ATA compatible path of the device
+fn compose_path<D: SysDevice>(dev: &D) -> Option<(String, Option<String>)> {
+    let mut path: Option<String> = None;
+    let mut compat_path: Option<String> = None;
+    let mut supported_transport = false;
+    let mut supported_parent = false;
+
+    // walk up the chain of devices and compose path
+    let mut parent = dev.clone();
+    loop {
+        let subsystem = parent.subsystem().unwrap_or_default();
+        let sysname = parent.sysname();
+
+        let next = match subsystem.as_str() {
+            "scsi" => {
+                supported_transport = true;
+                handle_scsi(&parent, &mut path, &mut compat_path, &mut supported_parent)
+            }
+            "usb" => {
+                supported_transport = true;
+                handle_usb(&parent, &mut path)
+            }
+            "pci" | "acpi" => {
+                path_prepend(&mut path, format!("{}-{}", subsystem, sysname));
+                compat_prepend(&mut compat_path, format!("{}-{}", subsystem, sysname));
+                supported_parent = true;
+                Some(skip_subsystem(&parent, &subsystem))
+            }
+            "platform" => {
+                path_prepend(&mut path, format!("platform-{}", sysname));
+                compat_prepend(&mut compat_path, format!("platform-{}", sysname));
+                supported_transport = true;
+                supported_parent = true;
+                Some(skip_subsystem(&parent, "platform"))
+            }
+            "virtio" => {
+                supported_transport = true;
+                Some(skip_subsystem(&parent, "virtio"))
+            }
+            "nvme" | "nvme-subsystem" => match dev.sysattr("nsid") {
+                Some(nsid) => {
+                    path_prepend(&mut path, format!("nvme-{}", nsid));
+                    compat_prepend(&mut compat_path, format!("nvme-{}", nsid));
+                    supported_parent = true;
+                    supported_transport = true;
+                    Some(skip_subsystem(&parent, "nvme"))
+                }
+                None => Some(parent),
+            },
+            _ => Some(parent),
+        };
+
+        parent = match next.and_then(|d| d.parent()) {
+            Some(p) => p,
+            None => break,
+        };
+    }
+
+    let path = path?;
+
+    // do not return devices with an unknown parent device type, they might
+    // produce conflicting IDs if the parent does not provide a unique and
+    // predictable name
+    if !supported_parent {
+        return None;
+    }
+
+    // do not return block devices without a well-known transport, some devices
+    // do not expose their buses and do not provide a unique and predictable name
+    if dev.subsystem().as_deref() == Some("block") && !supported_transport {
+        return None;
+    }
+
+    Some((path, compat_path))
+}
+
+/// compose valid tag name from the path, the characters except for
+/// alphanumerics and '-' are replaced by single '_'
+fn path_tag(path: &str) -> String {
+    let mut tag = String::new();
+
+    for c in path.chars() {
+        if c.is_ascii_alphanumeric() || c == '-' {
+            tag.push(c);
+            continue;
+        }
+
+        // skip all leading '_' and avoid second '_'
+        if tag.is_empty() || tag.ends_with('_') {
+            continue;
+        }
+
+        tag.push('_');
+    }
+
+    tag.trim_end_matches('_').to_string()
+}
+
 impl Builtin for PathId {
     /// builtin command
     fn cmd(
         &self,
-        _device: Arc<Mutex<Device>>,
+        device: Arc<Mutex<Device>>,
         _ret_rtnl: &mut RefCell<Option<Netlink>>,
         _argc: i32,
         _argv: Vec<String>,
-        _test: bool,
+        test: bool,
     ) -> Result<bool> {
+        let (path, compat_path) = match compose_path(&device) {
+            Some(ret) => ret,
+            None => return Ok(false),
+        };
+
+        let mut properties = vec![("ID_PATH", path.clone()), ("ID_PATH_TAG", path_tag(&path))];
+        if let Some(compat_path) = compat_path {
+            properties.push(("ID_PATH_ATA_COMPAT", compat_path));
+        }
+
+        for (key, value) in properties {
+            add_property(&device, test, key, &value)?;
+        }
+
         Ok(true)
     }
 
@@ -57,3 +530,527 @@ impl Builtin for PathId {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+
+    /// the device in the fake sysfs tree, the tree is laid out as
+    /// '<root>/devices/...' with '<root>/class/<subsystem>/<sysname>' links
+    #[derive(Clone)]
+    struct FakeDevice {
+        root: PathBuf,
+        syspath: PathBuf,
+    }
+
+    impl SysDevice for FakeDevice {
+        fn syspath(&self) -> String {
+            self.syspath.to_string_lossy().to_string()
+        }
+
+        fn sysname(&self) -> String {
+            self.syspath
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        }
+
+        fn subsystem(&self) -> Option<String> {
+            let link = fs::read_link(self.syspath.join("subsystem")).ok()?;
+            Some(link.file_name()?.to_string_lossy().to_string())
+        }
+
+        fn devtype(&self) -> Option<String> {
+            let uevent = fs::read_to_string(self.syspath.join("uevent")).ok()?;
+            uevent
+                .lines()
+                .find_map(|l| l.strip_prefix("DEVTYPE=").map(|s| s.to_string()))
+        }
+
+        fn sysattr(&self, attr: &str) -> Option<String> {
+            fs::read_to_string(self.syspath.join(attr))
+                .ok()
+                .map(|v| v.trim_end().to_string())
+        }
+
+        fn parent(&self) -> Option<Self> {
+            let mut dir = self.syspath.parent();
+
+            while let Some(d) = dir {
+                if !d.starts_with(self.root.join("devices")) {
+                    return None;
+                }
+
+                if d.join("uevent").exists() {
+                    return Some(FakeDevice {
+                        root: self.root.clone(),
+                        syspath: d.to_path_buf(),
+                    });
+                }
+                dir = d.parent();
+            }
+
+            None
+        }
+
+        fn lookup(&self, subsystem: &str, sysname: &str) -> Option<Self> {
+            let syspath = fs::canonicalize(self.root.join("class").join(subsystem).join(sysname));
+
+            Some(FakeDevice {
+                root: self.root.clone(),
+                syspath: syspath.ok()?,
+            })
+        }
+    }
+
+    /// create the fake sysfs tree
+    struct FakeSysfs {
+        root: PathBuf,
+    }
+
+    impl FakeSysfs {
+        fn new(name: &str) -> FakeSysfs {
+            let root = std::env::current_dir().unwrap().join(name);
+            if root.exists() {
+                fs::remove_dir_all(&root).unwrap();
+            }
+            fs::create_dir_all(root.join("devices")).unwrap();
+
+            FakeSysfs { root }
+        }
+
+        /// add the device, the directories without uevent are not treated as devices
+        fn add(
+            &self,
+            devpath: &str,
+            subsystem: Option<&str>,
+            devtype: Option<&str>,
+            attrs: &[(&str, &str)],
+        ) -> FakeDevice {
+            let syspath = self.root.join("devices").join(devpath);
+            fs::create_dir_all(&syspath).unwrap();
+
+            let uevent = devtype
+                .map(|t| format!("DEVTYPE={}\n", t))
+                .unwrap_or_default();
+            fs::write(syspath.join("uevent"), uevent).unwrap();
+
+            if let Some(subsystem) = subsystem {
+                let class = self.root.join("class").join(subsystem);
+                fs::create_dir_all(&class).unwrap();
+                symlink(&class, syspath.join("subsystem")).unwrap();
+                symlink(&syspath, class.join(syspath.file_name().unwrap())).unwrap();
+            }
+
+            for (attr, value) in attrs {
+                fs::write(syspath.join(attr), format!("{}\n", value)).unwrap();
+            }
+
+            FakeDevice {
+                root: self.root.clone(),
+                syspath,
+            }
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn compose(dev: &FakeDevice) -> Option<(String, String, Option<String>)> {
+        compose_path(dev).map(|(path, compat)| (path.clone(), path_tag(&path), compat))
+    }
+
+    #[test]
+    fn test_path_tag() {
+        assert_eq!(
+            path_tag("pci-0000:00:1f.2-ata-1.0"),
+            "pci-0000_00_1f_2-ata-1_0"
+        );
+        assert_eq!(path_tag("::usb-0:1.2:1.0::"), "usb-0_1_2_1_0");
+    }
+
+    #[test]
+    fn test_path_id_pci_ata() {
+        let sysfs = FakeSysfs::new("test_path_id_pci_ata");
+        let pci = "pci0000:00/0000:00:1f.2";
+        sysfs.add(pci, Some("pci"), None, &[]);
+        sysfs.add(
+            &format!("{}/ata1", pci),
+            Some("ata_port"),
+            None,
+            &[("port_no", "1")],
+        );
+        sysfs.add(
+            &format!("{}/ata1/host0", pci),
+            Some("scsi"),
+            Some("scsi_host"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/ata1/host0/target0:0:0", pci),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/ata1/host0/target0:0:0/0:0:0:0", pci),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/ata1/host0/target0:0:0/0:0:0:0/block/sda", pci),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+
+        assert_eq!(
+            compose(&disk).unwrap(),
+            (
+                "pci-0000:00:1f.2-ata-1.0".to_string(),
+                "pci-0000_00_1f_2-ata-1_0".to_string(),
+                Some("pci-0000:00:1f.2-ata-1".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_path_id_scsi() {
+        let sysfs = FakeSysfs::new("test_path_id_scsi");
+        let pci = "pci0000:00/0000:00:10.0";
+        sysfs.add(pci, Some("pci"), None, &[]);
+
+        // the host number is rebased on the smallest one under the same parent
+        sysfs.add(
+            &format!("{}/host2", pci),
+            Some("scsi"),
+            Some("scsi_host"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/host3", pci),
+            Some("scsi"),
+            Some("scsi_host"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/host3/target3:0:1", pci),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/host3/target3:0:1/3:0:1:0", pci),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/host3/target3:0:1/3:0:1:0/block/sdb", pci),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+
+        assert_eq!(compose(&disk).unwrap().0, "pci-0000:00:10.0-scsi-1:0:1:0");
+    }
+
+    #[test]
+    fn test_path_id_sas() {
+        let sysfs = FakeSysfs::new("test_path_id_sas");
+        let host = "pci0000:00/0000:00:02.0/0000:01:00.0/host0";
+        sysfs.add("pci0000:00/0000:00:02.0", Some("pci"), None, &[]);
+        sysfs.add(
+            "pci0000:00/0000:00:02.0/0000:01:00.0",
+            Some("pci"),
+            None,
+            &[],
+        );
+        sysfs.add(host, Some("scsi"), Some("scsi_host"), &[]);
+        sysfs.add(
+            &format!("{}/port-0:0", host),
+            Some("sas_port"),
+            None,
+            &[("num_phys", "1")],
+        );
+        sysfs.add(
+            &format!("{}/port-0:0/end_device-0:0", host),
+            Some("sas_device"),
+            None,
+            &[
+                ("sas_address", "0x5000c500a1b2c3d4"),
+                ("phy_identifier", "4"),
+            ],
+        );
+        let end_device = format!("{}/port-0:0/end_device-0:0", host);
+        sysfs.add(
+            &format!("{}/target0:0:0", end_device),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/target0:0:0/0:0:0:0", end_device),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/target0:0:0/0:0:0:0/block/sda", end_device),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+
+        // the disk connected to the hba directly
+        assert_eq!(compose(&disk).unwrap().0, "pci-0000:01:00.0-sas-phy4-lun-0");
+
+        // the disk connected to the wide port is identified by its sas address
+        fs::write(sysfs.root.join("class/sas_port/port-0:0/num_phys"), "4\n").unwrap();
+        assert_eq!(
+            compose(&disk).unwrap().0,
+            "pci-0000:01:00.0-sas-0x5000c500a1b2c3d4-lun-0"
+        );
+    }
+
+    #[test]
+    fn test_path_id_iscsi_and_fc() {
+        let sysfs = FakeSysfs::new("test_path_id_iscsi_and_fc");
+
+        // iscsi devices are attached to the virtual platform device
+        let session = "platform/host5/session1";
+        sysfs.add("platform", None, None, &[]);
+        sysfs.add("platform/host5", Some("scsi"), Some("scsi_host"), &[]);
+        sysfs.add(session, None, None, &[]);
+        sysfs.add(
+            &format!("{}/iscsi_session/session1", session),
+            Some("iscsi_session"),
+            None,
+            &[("targetname", "iqn.2023-01.org.example:disk")],
+        );
+        sysfs.add(
+            &format!("{}/connection1:0/iscsi_connection/connection1:0", session),
+            Some("iscsi_connection"),
+            None,
+            &[
+                ("persistent_address", "192.168.1.2"),
+                ("persistent_port", "3260"),
+            ],
+        );
+        sysfs.add(
+            &format!("{}/target5:0:0", session),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/target5:0:0/5:0:0:1", session),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/target5:0:0/5:0:0:1/block/sdc", session),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+
+        assert_eq!(
+            compose(&disk).unwrap().0,
+            "ip-192.168.1.2:3260-iscsi-iqn.2023-01.org.example:disk-lun-1"
+        );
+
+        // fibre channel target is identified by the port name of its transport
+        let rport = "pci0000:00/0000:00:03.0/host6/rport-6:0-2";
+        sysfs.add("pci0000:00/0000:00:03.0", Some("pci"), None, &[]);
+        sysfs.add(
+            "pci0000:00/0000:00:03.0/host6",
+            Some("scsi"),
+            Some("scsi_host"),
+            &[],
+        );
+        sysfs.add(rport, None, None, &[]);
+        sysfs.add(
+            &format!("{}/target6:0:0", rport),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/target6:0:0/fc_transport/target6:0:0", rport),
+            Some("fc_transport"),
+            None,
+            &[("port_name", "0x500601663ee0025f")],
+        );
+        sysfs.add(
+            &format!("{}/target6:0:0/6:0:0:300", rport),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/target6:0:0/6:0:0:300/block/sdd", rport),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+
+        assert_eq!(
+            compose(&disk).unwrap().0,
+            "pci-0000:00:03.0-fc-0x500601663ee0025f-lun-0x012c000000000000"
+        );
+    }
+
+    #[test]
+    fn test_path_id_usb_nvme_virtio() {
+        let sysfs = FakeSysfs::new("test_path_id_usb_nvme_virtio");
+
+        let usb = "pci0000:00/0000:00:14.0/usb1/1-1/1-1.2/1-1.2:1.0";
+        sysfs.add("pci0000:00/0000:00:14.0", Some("pci"), None, &[]);
+        sysfs.add(
+            "pci0000:00/0000:00:14.0/usb1",
+            Some("usb"),
+            Some("usb_device"),
+            &[],
+        );
+        sysfs.add(
+            "pci0000:00/0000:00:14.0/usb1/1-1",
+            Some("usb"),
+            Some("usb_device"),
+            &[],
+        );
+        sysfs.add(
+            "pci0000:00/0000:00:14.0/usb1/1-1/1-1.2",
+            Some("usb"),
+            Some("usb_device"),
+            &[],
+        );
+        sysfs.add(usb, Some("usb"), Some("usb_interface"), &[]);
+        sysfs.add(
+            &format!("{}/host7", usb),
+            Some("scsi"),
+            Some("scsi_host"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/host7/target7:0:0", usb),
+            Some("scsi"),
+            Some("scsi_target"),
+            &[],
+        );
+        sysfs.add(
+            &format!("{}/host7/target7:0:0/7:0:0:0", usb),
+            Some("scsi"),
+            Some("scsi_device"),
+            &[],
+        );
+        let disk = sysfs.add(
+            &format!("{}/host7/target7:0:0/7:0:0:0/block/sde", usb),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+        assert_eq!(
+            compose(&disk).unwrap().0,
+            "pci-0000:00:14.0-usb-0:1.2:1.0-scsi-0:0:0:0"
+        );
+
+        let nvme = "pci0000:00/0000:00:1d.0/0000:3d:00.0/nvme/nvme0";
+        sysfs.add("pci0000:00/0000:00:1d.0", Some("pci"), None, &[]);
+        sysfs.add(
+            "pci0000:00/0000:00:1d.0/0000:3d:00.0",
+            Some("pci"),
+            None,
+            &[],
+        );
+        sysfs.add(nvme, Some("nvme"), None, &[]);
+        let disk = sysfs.add(
+            &format!("{}/nvme0n1", nvme),
+            Some("block"),
+            Some("disk"),
+            &[("nsid", "1")],
+        );
+        assert_eq!(compose(&disk).unwrap().0, "pci-0000:3d:00.0-nvme-1");
+
+        let virtio = "pci0000:00/0000:00:04.0/virtio1";
+        sysfs.add("pci0000:00/0000:00:04.0", Some("pci"), None, &[]);
+        sysfs.add(virtio, Some("virtio"), None, &[]);
+        let disk = sysfs.add(
+            &format!("{}/block/vda", virtio),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+        assert_eq!(
+            compose(&disk).unwrap(),
+            (
+                "pci-0000:00:04.0".to_string(),
+                "pci-0000_00_04_0".to_string(),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn test_path_id_platform_acpi_mmc() {
+        let sysfs = FakeSysfs::new("test_path_id_platform_acpi_mmc");
+
+        // mmc cards are identified by their host controllers
+        let mmc = "platform/fe320000.mmc/mmc_host/mmc1/mmc1:0001";
+        sysfs.add("platform", None, None, &[]);
+        sysfs.add("platform/fe320000.mmc", Some("platform"), None, &[]);
+        sysfs.add(
+            "platform/fe320000.mmc/mmc_host/mmc1",
+            Some("mmc_host"),
+            None,
+            &[],
+        );
+        sysfs.add(mmc, Some("mmc"), Some("MMC"), &[]);
+        let disk = sysfs.add(
+            &format!("{}/block/mmcblk1", mmc),
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+        assert_eq!(compose(&disk).unwrap().0, "platform-fe320000.mmc");
+
+        sysfs.add("LNXSYSTM:00", Some("acpi"), None, &[]);
+        sysfs.add("LNXSYSTM:00/LNXPWRBN:00", Some("acpi"), None, &[]);
+        let input = sysfs.add(
+            "LNXSYSTM:00/LNXPWRBN:00/input/input3",
+            Some("input"),
+            None,
+            &[],
+        );
+        assert_eq!(
+            compose(&input).unwrap(),
+            (
+                "acpi-LNXPWRBN:00".to_string(),
+                "acpi-LNXPWRBN_00".to_string(),
+                None
+            )
+        );
+
+        // block devices without well-known transport are refused
+        let disk = sysfs.add(
+            "LNXSYSTM:00/LNXPWRBN:00/block/ram0",
+            Some("block"),
+            Some("disk"),
+            &[],
+        );
+        assert!(compose(&disk).is_none());
+
+        // devices with unknown parents are refused
+        let virt = sysfs.add("virtual/block/loop0", Some("block"), Some("disk"), &[]);
+        assert!(compose(&virt).is_none());
+        assert!(Path::new(&virt.syspath()).exists());
+    }
+}
@@ -24,6 +24,7 @@ use std::{
     sync::{Arc, RwLock, Weak},
 };
 
+pub mod node;
 pub mod rule_execute;
 pub mod rule_load;
 
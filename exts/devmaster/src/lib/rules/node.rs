@@ -0,0 +1,499 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+//! manage the device node and its symlinks after rules are applied
+//! the implementation has referred to udev for compatibility.
+//!
+
+use crate::error::{Error, Result};
+use device::Device;
+use libc::{gid_t, mode_t, uid_t, S_IFBLK, S_IFCHR, S_IFMT};
+use nix::{
+    errno::Errno,
+    sys::stat::{lstat, major, minor},
+    unistd::{chown, Gid, Uid},
+};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs,
+    os::unix::fs::{symlink, PermissionsExt},
+    path::{Component, Path, PathBuf},
+};
+
+/// the directory to record the devices claiming the same symlink,
+/// each symlink owns a subdirectory named by its escaped path
+pub const LINKS_DIRECTORY_PATH: &str = "/run/udev/links/";
+
+/// translate io error into devmaster error
+fn io_err(e: std::io::Error, msg: String) -> Error {
+    Error::RulesExecuteError {
+        msg: format!("{} ({})", msg, e),
+        errno: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+    }
+}
+
+/// apply the mode, owner, group and security labels on the device node,
+/// the node is skipped if it does not match the device number
+pub(crate) fn node_apply_permissions(
+    device: &mut Device,
+    apply_mac: bool,
+    mode: mode_t,
+    uid: Option<uid_t>,
+    gid: Option<gid_t>,
+    seclabel_list: &HashMap<String, String>,
+) -> Result<()> {
+    let devnode = match device.get_devname() {
+        Ok(n) => n,
+        Err(_) => return Ok(()),
+    };
+    let devnum = device.get_devnum().unwrap_or_default();
+    let file_type = if device.get_subsystem().unwrap_or_default() == "block" {
+        S_IFBLK
+    } else {
+        S_IFCHR
+    };
+
+    let stat = lstat(devnode.as_str()).map_err(|e| Error::RulesExecuteError {
+        msg: format!("Failed to stat '{}': {}", devnode, e),
+        errno: e,
+    })?;
+
+    if stat.st_mode & S_IFMT != file_type || stat.st_rdev != devnum {
+        log::debug!(
+            "Found node '{}' with mismatched type or devnum, skip applying permissions.",
+            devnode
+        );
+        return Ok(());
+    }
+
+    let apply_mode = stat.st_mode & 0o7777 != mode & 0o7777;
+    let apply_uid = uid.is_some_and(|uid| stat.st_uid != uid);
+    let apply_gid = gid.is_some_and(|gid| stat.st_gid != gid);
+
+    if apply_mode {
+        log::debug!("Setting permissions {}, {:#o}", devnode, mode & 0o7777);
+        fs::set_permissions(&devnode, fs::Permissions::from_mode(mode & 0o7777))
+            .map_err(|e| io_err(e, format!("Failed to set mode of '{}'", devnode)))?;
+    }
+
+    if apply_uid || apply_gid {
+        log::debug!("Setting owner {}, uid={:?}, gid={:?}", devnode, uid, gid);
+        chown(
+            devnode.as_str(),
+            uid.map(Uid::from_raw),
+            gid.map(Gid::from_raw),
+        )
+        .map_err(|e| Error::RulesExecuteError {
+            msg: format!("Failed to set owner of '{}': {}", devnode, e),
+            errno: e,
+        })?;
+    }
+
+    if apply_mac {
+        for (name, label) in seclabel_list {
+            let attr = match name.as_str() {
+                "selinux" => "security.selinux",
+                "smack" => "security.SMACK64",
+                _ => {
+                    log::error!("Unknown security label '{}', ignoring.", name);
+                    continue;
+                }
+            };
+
+            if let Err(e) = set_xattr(&devnode, attr, label) {
+                log::error!(
+                    "Failed to set {} label '{}' on '{}': {}",
+                    name,
+                    label,
+                    devnode,
+                    e
+                );
+            }
+        }
+    }
+
+    // always update the timestamp when the node is reused, like on media change events
+    let path = CString::new(devnode.as_str()).unwrap_or_default();
+    if unsafe { libc::utimensat(libc::AT_FDCWD, path.as_ptr(), std::ptr::null(), 0) } < 0 {
+        log::debug!(
+            "Failed to update timestamp of '{}': {}",
+            devnode,
+            Errno::last()
+        );
+    }
+
+    Ok(())
+}
+
+/// set the extended attribute on the file without following symlink
+fn set_xattr(path: &str, name: &str, value: &str) -> Result<()> {
+    let c_path = CString::new(path).unwrap_or_default();
+    let c_name = CString::new(name).unwrap_or_default();
+
+    let ret = unsafe {
+        libc::lsetxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr() as *const libc::c_void,
+            value.len(),
+            0,
+        )
+    };
+
+    if ret < 0 {
+        return Err(Error::RulesExecuteError {
+            msg: format!("lsetxattr '{}' failed", name),
+            errno: Errno::last(),
+        });
+    }
+
+    Ok(())
+}
+
+/// create or update the symlinks of the device node,
+/// the stale symlinks that only exist in the old database are removed
+pub(crate) fn node_update(device: &mut Device, device_db: Option<&Device>) -> Result<()> {
+    let devnode = match device.get_devname() {
+        Ok(n) => n,
+        Err(_) => return Ok(()),
+    };
+    let id = execute_device_err(device.get_device_id(), "get device id")?;
+    let priority = device.devlink_priority;
+
+    if let Some(old) = device_db {
+        for link in old.devlinks.difference(&device.devlinks) {
+            log::debug!("Removing stale symlink '{}' of '{}'", link, devnode);
+            if let Err(e) = link_update(LINKS_DIRECTORY_PATH, link, &id, &devnode, priority, false)
+            {
+                log::error!("{}", e);
+            }
+        }
+    }
+
+    for link in device.devlinks.iter() {
+        if let Err(e) = link_update(LINKS_DIRECTORY_PATH, link, &id, &devnode, priority, true) {
+            log::error!("{}", e);
+        }
+    }
+
+    // create '/dev/{block,char}/$major:$minor' symlink
+    if let Some(link) = devnum_link(device) {
+        node_symlink(&devnode, &link, &id)?;
+    }
+
+    Ok(())
+}
+
+/// remove the symlinks of the device node, the symlinks claimed by other devices are kept
+pub(crate) fn node_remove(device: &mut Device) -> Result<()> {
+    let devnode = match device.get_devname() {
+        Ok(n) => n,
+        Err(_) => return Ok(()),
+    };
+    let id = execute_device_err(device.get_device_id(), "get device id")?;
+    let priority = device.devlink_priority;
+
+    for link in device.devlinks.iter() {
+        if let Err(e) = link_update(LINKS_DIRECTORY_PATH, link, &id, &devnode, priority, false) {
+            log::error!("{}", e);
+        }
+    }
+
+    if let Some(link) = devnum_link(device) {
+        remove_symlink(&link)?;
+    }
+
+    Ok(())
+}
+
+/// translate device error into devmaster error
+fn execute_device_err<T>(
+    ret: std::result::Result<T, device::error::Error>,
+    what: &str,
+) -> Result<T> {
+    ret.map_err(|e| Error::RulesExecuteError {
+        msg: format!("Failed to {}: {}", what, e),
+        errno: e.get_errno(),
+    })
+}
+
+/// get the path of '/dev/{block,char}/$major:$minor'
+fn devnum_link(device: &mut Device) -> Option<String> {
+    let devnum = device.get_devnum().ok()?;
+    let dir = if device.get_subsystem().unwrap_or_default() == "block" {
+        "block"
+    } else {
+        "char"
+    };
+
+    Some(format!("/dev/{}/{}:{}", dir, major(devnum), minor(devnum)))
+}
+
+/// escape the symlink path into the name of its stack directory, e.g.,
+/// '/dev/disk/by-id/foo' is escaped to '\x2fdisk\x2fby-id\x2ffoo'
+fn escape_link(link: &str) -> String {
+    link.strip_prefix("/dev")
+        .unwrap_or(link)
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => format!("\\x{:02x}", c as u8),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+/// record the device in the stack directory of the symlink, and let the symlink
+/// point to the device node with the highest priority, the device being added
+/// wins among the devices with the same priority
+pub(crate) fn link_update(
+    links_dir: &str,
+    link: &str,
+    id: &str,
+    devnode: &str,
+    priority: i32,
+    add: bool,
+) -> Result<()> {
+    let stack_dir = Path::new(links_dir).join(escape_link(link));
+    let entry = stack_dir.join(id);
+
+    if add {
+        fs::create_dir_all(&stack_dir)
+            .and_then(|_| fs::write(&entry, format!("{}:{}", priority, devnode)))
+            .map_err(|e| io_err(e, format!("Failed to record '{}' in {:?}", id, stack_dir)))?;
+    } else if let Err(e) = fs::remove_file(&entry) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(io_err(e, format!("Failed to remove {:?}", entry)));
+        }
+    }
+
+    match link_find_prioritized(&stack_dir, if add { Some(id) } else { None }) {
+        Some((target_id, target)) => {
+            log::debug!("Symlink '{}' is owned by '{}'", link, target_id);
+            node_symlink(&target, link, &target_id)
+        }
+        None => {
+            log::debug!("No device claims symlink '{}', removing it", link);
+            let _ = fs::remove_dir(&stack_dir);
+            remove_symlink(link)
+        }
+    }
+}
+
+/// find the device node with the highest priority in the stack directory,
+/// the entries whose device node disappears are ignored
+fn link_find_prioritized(stack_dir: &Path, prefer_id: Option<&str>) -> Option<(String, String)> {
+    let mut best: Option<(i32, String, String)> = None;
+
+    for entry in fs::read_dir(stack_dir).ok()?.flatten() {
+        let id = entry.file_name().to_string_lossy().to_string();
+        let content = match fs::read_to_string(entry.path()) {
+            Ok(c) => c,
+            Err(_) => continue,
+        };
+
+        let (priority, devnode) = match content.split_once(':') {
+            Some((p, n)) => match p.parse::<i32>() {
+                Ok(p) => (p, n.to_string()),
+                Err(_) => continue,
+            },
+            None => continue,
+        };
+
+        if !Path::new(&devnode).exists() {
+            continue;
+        }
+
+        let better = match &best {
+            None => true,
+            Some((p, best_id, _)) => {
+                priority > *p
+                    || (priority == *p
+                        && prefer_id != Some(best_id.as_str())
+                        && (prefer_id == Some(id.as_str()) || id < *best_id))
+            }
+        };
+
+        if better {
+            best = Some((priority, id, devnode));
+        }
+    }
+
+    best.map(|(_, id, devnode)| (id, devnode))
+}
+
+/// create the relative symlink to the device node atomically,
+/// the existing file that is not a symlink is never replaced
+fn node_symlink(devnode: &str, link: &str, id: &str) -> Result<()> {
+    let link_path = Path::new(link);
+    let parent = link_path.parent().unwrap_or_else(|| Path::new("/"));
+    let target = relative_path(parent, Path::new(devnode));
+
+    if let Ok(metadata) = fs::symlink_metadata(link_path) {
+        if !metadata.file_type().is_symlink() {
+            log::error!(
+                "Conflicting file '{}' found, symlink to '{}' will not be created.",
+                link,
+                devnode
+            );
+            return Ok(());
+        }
+
+        if fs::read_link(link_path).ok().as_ref() == Some(&target) {
+            return Ok(());
+        }
+    }
+
+    log::debug!("Creating symlink '{}' to '{:?}'", link, target);
+
+    let tmp = format!("{}.tmp-{}", link, id);
+    let _ = fs::remove_file(&tmp);
+    fs::create_dir_all(parent)
+        .and_then(|_| symlink(&target, &tmp))
+        .and_then(|_| fs::rename(&tmp, link_path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            io_err(e, format!("Failed to create symlink '{}'", link))
+        })
+}
+
+/// remove the symlink, and remove its parent directories if they are empty
+fn remove_symlink(link: &str) -> Result<()> {
+    let link_path = Path::new(link);
+
+    match fs::symlink_metadata(link_path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::remove_file(link_path)
+                .map_err(|e| io_err(e, format!("Failed to remove symlink '{}'", link)))?;
+        }
+        _ => return Ok(()),
+    }
+
+    // stop at the first non-empty directory
+    let mut dir = link_path.parent();
+    while let Some(d) = dir {
+        if d == Path::new("/dev") || fs::remove_dir(d).is_err() {
+            break;
+        }
+        dir = d.parent();
+    }
+
+    Ok(())
+}
+
+/// get the relative path from the directory to the target, both of them are absolute
+fn relative_path(from_dir: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = to.components().collect();
+
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut ret = PathBuf::new();
+    for _ in common..from.len() {
+        ret.push("..");
+    }
+    for c in &to[common..] {
+        ret.push(c);
+    }
+
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear_test_dir(dir: &str) {
+        if Path::new(dir).exists() {
+            assert!(fs::remove_dir_all(dir).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_escape_and_relative_path() {
+        assert_eq!(
+            escape_link("/dev/disk/by-id/foo"),
+            "\\x2fdisk\\x2fby-id\\x2ffoo"
+        );
+        assert_eq!(
+            relative_path(Path::new("/dev/disk/by-id"), Path::new("/dev/sda")),
+            PathBuf::from("../../sda")
+        );
+        assert_eq!(
+            relative_path(Path::new("/dev"), Path::new("/dev/sda")),
+            PathBuf::from("sda")
+        );
+    }
+
+    #[test]
+    fn test_link_update() {
+        let base = std::env::current_dir()
+            .unwrap()
+            .join("test_link_update")
+            .to_string_lossy()
+            .to_string();
+        clear_test_dir(&base);
+
+        let links = format!("{}/links", base);
+        let link = format!("{}/dev/disk/by-label/test", base);
+        let node_a = format!("{}/dev/sda", base);
+        let node_b = format!("{}/dev/sdb", base);
+        fs::create_dir_all(format!("{}/dev", base)).unwrap();
+        fs::write(&node_a, "").unwrap();
+        fs::write(&node_b, "").unwrap();
+
+        // the device with higher priority owns the symlink
+        link_update(&links, &link, "b8:0", &node_a, 10, true).unwrap();
+        link_update(&links, &link, "b8:16", &node_b, 0, true).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("../../sda"));
+
+        // the symlink falls back to the remaining device
+        link_update(&links, &link, "b8:0", &node_a, 10, false).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("../../sdb"));
+
+        // the device being added wins among the same priority
+        link_update(&links, &link, "b8:0", &node_a, 0, true).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("../../sda"));
+
+        // the stale device whose node disappears is ignored
+        fs::remove_file(&node_a).unwrap();
+        link_update(&links, &link, "b8:16", &node_b, 0, true).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("../../sdb"));
+
+        // the symlink and its empty parent directories are removed with the last device
+        link_update(&links, &link, "b8:0", &node_a, 0, false).unwrap();
+        link_update(&links, &link, "b8:16", &node_b, 0, false).unwrap();
+        assert!(fs::symlink_metadata(&link).is_err());
+        assert!(!Path::new(&format!("{}/dev/disk", base)).exists());
+        assert!(Path::new(&node_b).exists());
+
+        // the conflicting regular file is never replaced
+        fs::write(format!("{}/dev/conflict", base), "").unwrap();
+        link_update(
+            &links,
+            &format!("{}/dev/conflict", base),
+            "b8:16",
+            &node_b,
+            0,
+            true,
+        )
+        .unwrap();
+        assert!(fs::symlink_metadata(format!("{}/dev/conflict", base))
+            .unwrap()
+            .is_file());
+
+        clear_test_dir(&base);
+    }
+}
@@ -9,81 +9,515 @@
 // KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
-
 //! the process unit to apply rules on device uevent in worker thread
 //!
 
-use super::{RuleFile, RuleLine, RuleToken, Rules, TokenType::*};
+use super::{
+    node::{node_apply_permissions, node_remove, node_update},
+    FormatSubstitutionType, MatchType, OperatorType, RuleFile, RuleLine, RuleToken, Rules,
+    SubstituteType,
+    TokenType::*,
+};
+use crate::builtin::{BuiltinCommand, BuiltinManager, Netlink};
 use crate::error::{Error, Result};
+use crate::utils::{replace_chars, replace_whitespace_chars, ALLOWED_CHARS_INPUT};
+use basic::parse_util::parse_mode;
+use basic::user_group_util::{get_group_creds, get_user_creds};
+use basic::virtualize::{detect_container, Virtualization};
 use device::{Device, DeviceAction};
+use libc::{gid_t, mode_t, uid_t};
+use nix::{
+    sys::stat::{major, minor},
+    time::{clock_gettime, ClockId},
+};
 use std::{
     cell::RefCell,
-    rc::Rc,
-    sync::{Arc, RwLock},
+    collections::HashMap,
+    fs,
+    io::Read,
+    process::{Command, Stdio},
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
 };
 
 use crate::device_trace;
 use crate::{execute_err, execute_none};
 use nix::errno::Errno;
 
+/// the directory to find the programs that are not specified by absolute path
+const PROGRAM_DIR: &str = "/usr/lib/udev";
+
+/// the time to wait for the programs spawned by 'PROGRAM', 'IMPORT{program}' and 'RUN'
+const PROGRAM_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// the long names of formatters, the longer one should be ahead of its prefix
+const FORMATTERS: [&str; 18] = [
+    "devnode", "tempnode", "attr", "sysfs", "env", "kernel", "number", "driver", "devpath", "id",
+    "major", "minor", "result", "parent", "name", "links", "root", "sys",
+];
+
+/// how to escape the unsafe characters in assigned strings, set by 'OPTIONS+="string_escape=..."'
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EscapeType {
+    Unset,
+    None,
+    Replace,
+}
+
 /// the process unit on device uevent
-#[allow(missing_docs)]
+#[allow(missing_docs, dead_code)]
 struct ExecuteUnit {
-    device: Rc<RefCell<Device>>,
-    // parent: Option<Arc<Mutex<Device>>>,
-    // device_db_clone: Option<Device>,
-    // name: String,
-    // program_result: String,
-    // mode: mode_t,
-    // uid: uid_t,
-    // gid: gid_t,
-    // seclabel_list: HashMap<String, String>,
-    // run_list: HashMap<String, String>,
-    // exec_delay_usec: useconds_t,
-    // birth_sec: useconds_t,
-    // rtnl: Option<Rc<RefCell<Netlink>>>,
-    // builtin_run: u32,
-    // builtin_ret: u32,
-    // escape_type: RuleEscapeType,
-    // inotify_watch: bool,
-    // inotify_watch_final: bool,
-    // group_final: bool,
-    // owner_final: bool,
-    // mode_final: bool,
-    // name_final: bool,
-    // devlink_final: bool,
-    // run_final: bool,
+    device: Arc<Mutex<Device>>,
+    /// the device recovered from the database before applying rules
+    device_db_clone: Option<Device>,
+    /// the device matched by the parent tokens in the current line
+    parent: Option<Arc<Mutex<Device>>>,
+    name: String,
+    program_result: String,
+    mode: Option<mode_t>,
+    uid: Option<uid_t>,
+    gid: Option<gid_t>,
+    seclabel_list: HashMap<String, String>,
+    run_list: Vec<(String, Option<BuiltinCommand>)>,
+    rtnl: RefCell<Option<Netlink>>,
+    builtin_run: u32,
+    builtin_ret: u32,
+    escape_type: EscapeType,
+    inotify_watch: bool,
+    inotify_watch_final: bool,
+    group_final: bool,
+    owner_final: bool,
+    mode_final: bool,
+    name_final: bool,
+    devlink_final: bool,
+    run_final: bool,
 }
 
 impl ExecuteUnit {
-    pub fn new(device: Rc<RefCell<Device>>) -> ExecuteUnit {
-        // let mut unit = ProcessUnit::default();
-        // unit.device = device;
-        // unit
+    pub fn new(device: Arc<Mutex<Device>>) -> ExecuteUnit {
         ExecuteUnit {
             device,
-            // parent: None,
-            // device_db_clone: None,
-            // name: (),
-            // program_result: (),
-            // mode: (),
-            // uid: (),
-            // gid: (),
-            // seclabel_list: (),
-            // run_list: (),
-            // exec_delay_usec: (),
-            // birth_sec: (),
-            // rtnl: (),
-            // builtin_run: (),
-            // builtin_ret: (),
-            // inotify_watch: (),
-            // inotify_watch_final: (),
-            // group_final: (),
-            // owner_final: (),
-            // mode_final: (),
-            // name_final: (),
-            // devlink_final: (),
-            // run_final: (),
+            device_db_clone: None,
+            parent: None,
+            name: String::new(),
+            program_result: String::new(),
+            mode: None,
+            uid: None,
+            gid: None,
+            seclabel_list: HashMap::new(),
+            run_list: vec![],
+            rtnl: RefCell::new(None),
+            builtin_run: 0,
+            builtin_ret: 0,
+            escape_type: EscapeType::Unset,
+            inotify_watch: false,
+            inotify_watch_final: false,
+            group_final: false,
+            owner_final: false,
+            mode_final: false,
+            name_final: false,
+            devlink_final: false,
+            run_final: false,
+        }
+    }
+
+    /// substitute the formatters in the string, e.g., '$kernel', '%k' and '$attr{size}',
+    /// '$$' and '%%' are escaped to '$' and '%'
+    fn apply_format(&self, src: &str, replace_whitespace: bool) -> String {
+        let mut ret = String::new();
+        let mut rest = src;
+
+        while let Some(pos) = rest.find(['$', '%']) {
+            ret.push_str(&rest[..pos]);
+            let lead = &rest[pos..pos + 1];
+            rest = &rest[pos + 1..];
+
+            if let Some(s) = rest.strip_prefix(lead) {
+                ret.push_str(lead);
+                rest = s;
+                continue;
+            }
+
+            let name = if lead == "$" {
+                FORMATTERS
+                    .iter()
+                    .find(|n| rest.starts_with(*n))
+                    .map(|n| n.to_string())
+            } else {
+                rest.chars().next().map(|c| c.to_string())
+            };
+
+            let subst_type = match name
+                .as_ref()
+                .and_then(|n| n.parse::<FormatSubstitutionType>().ok())
+            {
+                Some(t) => t,
+                None => {
+                    log::debug!("Invalid formatter in '{}', keep it as it is.", src);
+                    ret.push_str(lead);
+                    continue;
+                }
+            };
+            rest = &rest[name.unwrap().len()..];
+
+            let mut attr = None;
+            if rest.starts_with('{') {
+                if let Some(end) = rest.find('}') {
+                    attr = Some(&rest[1..end]);
+                    rest = &rest[end + 1..];
+                }
+            }
+
+            let value = self.subst_format(subst_type, attr);
+            if replace_whitespace {
+                ret.push_str(&replace_whitespace_chars(&value));
+            } else {
+                ret.push_str(&value);
+            }
+        }
+
+        ret.push_str(rest);
+        ret
+    }
+
+    /// get the substitution of a formatter
+    fn subst_format(&self, subst_type: FormatSubstitutionType, attr: Option<&str>) -> String {
+        match subst_type {
+            FormatSubstitutionType::Devnode => self
+                .device
+                .lock()
+                .unwrap()
+                .get_devname()
+                .unwrap_or_default(),
+            FormatSubstitutionType::Attr => {
+                let attr = match attr {
+                    Some(a) => a,
+                    None => {
+                        log::error!("Formatter 'attr' requires an attribute.");
+                        return String::new();
+                    }
+                };
+
+                match self.read_attr(attr) {
+                    Ok(v) => replace_chars(v.trim_end(), ALLOWED_CHARS_INPUT),
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        String::new()
+                    }
+                }
+            }
+            FormatSubstitutionType::Env => match attr {
+                Some(key) => self
+                    .device
+                    .lock()
+                    .unwrap()
+                    .get_property_value(key.to_string())
+                    .unwrap_or_default(),
+                None => {
+                    log::error!("Formatter 'env' requires an attribute.");
+                    String::new()
+                }
+            },
+            FormatSubstitutionType::Kernel => self
+                .device
+                .lock()
+                .unwrap()
+                .get_sysname()
+                .unwrap_or_default()
+                .to_string(),
+            FormatSubstitutionType::KernelNumber => {
+                self.device.lock().unwrap().get_sysnum().unwrap_or_default()
+            }
+            FormatSubstitutionType::Driver => self
+                .parent
+                .as_ref()
+                .and_then(|p| p.lock().unwrap().get_driver().ok())
+                .unwrap_or_default(),
+            FormatSubstitutionType::Devpath => self
+                .device
+                .lock()
+                .unwrap()
+                .get_devpath()
+                .unwrap_or_default()
+                .to_string(),
+            FormatSubstitutionType::Id => self
+                .parent
+                .as_ref()
+                .and_then(|p| p.lock().unwrap().get_sysname().map(|s| s.to_string()))
+                .unwrap_or_default(),
+            FormatSubstitutionType::Major | FormatSubstitutionType::Minor => {
+                let devnum = self.device.lock().unwrap().get_devnum().unwrap_or_default();
+                if subst_type == FormatSubstitutionType::Major {
+                    major(devnum).to_string()
+                } else {
+                    minor(devnum).to_string()
+                }
+            }
+            FormatSubstitutionType::Result => self.format_result(attr),
+            FormatSubstitutionType::Parent => {
+                let parent = self.device.lock().unwrap().get_parent();
+                parent
+                    .ok()
+                    .and_then(|p| p.lock().unwrap().get_devname().ok())
+                    .map(|n| n.trim_start_matches("/dev/").to_string())
+                    .unwrap_or_default()
+            }
+            FormatSubstitutionType::Name => {
+                if !self.name.is_empty() {
+                    return self.name.clone();
+                }
+
+                let mut device = self.device.lock().unwrap();
+                match device.get_devname() {
+                    Ok(n) => n.trim_start_matches("/dev/").to_string(),
+                    Err(_) => device.get_sysname().unwrap_or_default().to_string(),
+                }
+            }
+            FormatSubstitutionType::Links => {
+                let mut links: Vec<String> = self
+                    .device
+                    .lock()
+                    .unwrap()
+                    .devlinks
+                    .iter()
+                    .map(|l| l.trim_start_matches("/dev/").to_string())
+                    .collect();
+                links.sort();
+                links.join(" ")
+            }
+            FormatSubstitutionType::Root => "/dev".to_string(),
+            FormatSubstitutionType::Sys => "/sys".to_string(),
+            FormatSubstitutionType::Invalid => String::new(),
+        }
+    }
+
+    /// get the program result, '$result{N}' takes the Nth word and '$result{N+}' takes the rest from the Nth word
+    fn format_result(&self, attr: Option<&str>) -> String {
+        let attr = match attr {
+            Some(a) => a,
+            None => return self.program_result.clone(),
+        };
+
+        let (index, rest) = match attr.strip_suffix('+') {
+            Some(i) => (i, true),
+            None => (attr, false),
+        };
+
+        let index = match index.parse::<usize>() {
+            Ok(i) => i,
+            Err(_) => {
+                log::error!("Invalid index '{}' of formatter 'result'.", attr);
+                return String::new();
+            }
+        };
+
+        let words: Vec<&str> = self.program_result.split_whitespace().collect();
+        if index >= words.len() {
+            return String::new();
+        }
+
+        if rest {
+            words[index..].join(" ")
+        } else {
+            words[index].to_string()
+        }
+    }
+
+    /// read the sysattr of the device, fall back to the matched parent device
+    fn read_attr(&self, attr: &str) -> Result<String> {
+        if attr.starts_with('[') {
+            let (mut device, attr) = resolve_subsystem_device(attr)?;
+            return execute_err!(device.get_sysattr_value(attr), "attr");
+        }
+
+        let ret = self
+            .device
+            .lock()
+            .unwrap()
+            .get_sysattr_value(attr.to_string());
+
+        match &self.parent {
+            Some(parent) if ret.is_err() && !Arc::ptr_eq(parent, &self.device) => {
+                execute_err!(
+                    parent.lock().unwrap().get_sysattr_value(attr.to_string()),
+                    "attr"
+                )
+            }
+            _ => execute_err!(ret, "attr"),
+        }
+    }
+
+    /// read the sysattr specified by the token attribute, which may contain formatters
+    /// or be in the form of '[<subsystem>/<sysname>]<attribute>'
+    fn read_token_attr(&self, device: &Arc<Mutex<Device>>, attr: &str) -> Result<String> {
+        match attr.parse::<SubstituteType>()? {
+            SubstituteType::Subsys => {
+                let (mut device, attr) = resolve_subsystem_device(attr)?;
+                execute_err!(device.get_sysattr_value(attr), "ATTR")
+            }
+            SubstituteType::Format => {
+                let attr = self.apply_format(attr, false);
+                execute_err!(device.lock().unwrap().get_sysattr_value(attr), "ATTR")
+            }
+            _ => execute_err!(
+                device.lock().unwrap().get_sysattr_value(attr.to_string()),
+                "ATTR"
+            ),
+        }
+    }
+
+    /// match the sysattr of the device with the token value
+    fn match_attr(&self, device: &Arc<Mutex<Device>>, token: &RuleToken) -> bool {
+        let attr = token.attr.clone().unwrap_or_default();
+        let value = self.read_token_attr(device, &attr).unwrap_or_default();
+
+        // remove trailing whitespace, if not asked to match for it
+        if token.value.ends_with(char::is_whitespace) {
+            token.pattern_match(&value)
+        } else {
+            token.pattern_match(value.trim_end())
+        }
+    }
+
+    /// match a parent token on the device
+    fn match_parent_token(&self, device: &Arc<Mutex<Device>>, token: &RuleToken) -> bool {
+        match token.r#type {
+            MatchParentsKernel => {
+                token.pattern_match(device.lock().unwrap().get_sysname().unwrap_or_default())
+            }
+            MatchParentsSubsystem => {
+                token.pattern_match(&device.lock().unwrap().get_subsystem().unwrap_or_default())
+            }
+            MatchParentsDriver => {
+                token.pattern_match(&device.lock().unwrap().get_driver().unwrap_or_default())
+            }
+            MatchParentsAttr => self.match_attr(device, token),
+            MatchParentsTag => {
+                let mut device = device.lock().unwrap();
+                if let Err(e) = device.read_db() {
+                    log::debug!("Failed to read db of {}: {}", device.devpath, e);
+                }
+                token.pattern_match_any(device.all_tags.iter())
+            }
+            _ => false,
+        }
+    }
+
+    /// spawn the program with the device properties as environment, and return its output
+    fn spawn_program(&self, cmd: &str) -> Result<String> {
+        let argv = split_args(cmd);
+        if argv.is_empty() {
+            return Err(Error::RulesExecuteError {
+                msg: "Empty program".to_string(),
+                errno: Errno::EINVAL,
+            });
+        }
+
+        let program = if argv[0].starts_with('/') {
+            argv[0].clone()
+        } else {
+            format!("{}/{}", PROGRAM_DIR, argv[0])
+        };
+
+        let envs = self.device.lock().unwrap().properties.clone();
+
+        let mut child = Command::new(&program)
+            .args(&argv[1..])
+            .env_clear()
+            .envs(envs)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| Error::RulesExecuteError {
+                msg: format!("Failed to spawn '{}': {}", cmd, e),
+                errno: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+            })?;
+
+        // read the output in another thread, in case the program blocks on a full pipe
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stdout.read_to_string(&mut output);
+            output
+        });
+
+        let begin = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {
+                    if begin.elapsed() > PROGRAM_TIMEOUT {
+                        let _ = child.kill();
+                        let _ = child.wait();
+                        return Err(Error::RulesExecuteError {
+                            msg: format!("Spawned program '{}' timed out", cmd),
+                            errno: Errno::ETIME,
+                        });
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => {
+                    return Err(Error::RulesExecuteError {
+                        msg: format!("Failed to wait '{}': {}", cmd, e),
+                        errno: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+                    });
+                }
+            }
+        };
+
+        let output = reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(Error::RulesExecuteError {
+                msg: format!("Spawned program '{}' failed: {}", cmd, status),
+                errno: Errno::EIO,
+            });
+        }
+
+        Ok(output)
+    }
+
+    /// run the builtin command, the result of run-once commands is cached
+    fn run_builtin(&mut self, builtin_mgr: &BuiltinManager, cmd_line: &str) -> Result<bool> {
+        let cmd = cmd_line.parse::<BuiltinCommand>()?;
+        let mask = 1 << cmd as u32;
+
+        if builtin_mgr.run_once(cmd) {
+            if self.builtin_run & mask != 0 {
+                log::debug!(
+                    "Skip running builtin command '{}' that runs only once.",
+                    cmd
+                );
+                return Ok(self.builtin_ret & mask == 0);
+            }
+            self.builtin_run |= mask;
+        }
+
+        let argv = split_args(cmd_line);
+        let ret = builtin_mgr.run(
+            self.device.clone(),
+            &mut self.rtnl,
+            cmd,
+            argv.len() as i32,
+            argv,
+            false,
+        );
+
+        if !matches!(ret, Ok(true)) {
+            self.builtin_ret |= mask;
+        }
+
+        ret
+    }
+
+    /// import the properties from the lines in the form of 'KEY=VALUE'
+    fn import_properties(&self, content: &str) {
+        let mut device = self.device.lock().unwrap();
+        for (key, value) in content.lines().filter_map(parse_key_value) {
+            if let Err(e) = device.add_property(key, value) {
+                log::error!("Failed to import property: {}", e);
+            }
         }
     }
 }
@@ -92,6 +526,8 @@ impl ExecuteUnit {
 pub struct ExecuteManager {
     rules: Arc<RwLock<Rules>>,
 
+    builtin_mgr: BuiltinManager,
+
     current_rule_file: Option<Arc<RwLock<RuleFile>>>,
     current_rule_line: Option<Arc<RwLock<RuleLine>>>,
     current_rule_token: Option<Arc<RwLock<RuleToken>>>,
@@ -104,6 +540,7 @@ impl ExecuteManager {
     pub fn new(rules: Arc<RwLock<Rules>>) -> ExecuteManager {
         ExecuteManager {
             rules,
+            builtin_mgr: BuiltinManager::new(),
             current_rule_file: None,
             current_rule_line: None,
             current_rule_token: None,
@@ -112,8 +549,8 @@ impl ExecuteManager {
     }
 
     /// process a device object
-    pub fn process_device(&mut self, device: Rc<RefCell<Device>>) -> Result<()> {
-        log::debug!("Processing device {}", device.as_ref().borrow_mut().devpath);
+    pub fn process_device(&mut self, device: Arc<Mutex<Device>>) -> Result<()> {
+        log::debug!("Processing device {}", device.lock().unwrap().devpath);
 
         self.current_unit = Some(ExecuteUnit::new(device));
         // lock whole disk: todo
@@ -137,9 +574,7 @@ impl ExecuteManager {
     pub(crate) fn execute_rules(&mut self) -> Result<()> {
         let unit = self.current_unit.as_mut().unwrap();
 
-        let action = unit.device.as_ref().borrow_mut().action;
-
-        println!("{}", action);
+        let action = unit.device.lock().unwrap().action;
 
         if action == DeviceAction::Remove {
             return self.execute_rules_on_remove();
@@ -147,51 +582,136 @@ impl ExecuteManager {
 
         // inotify watch end: todo
 
-        // clone device with db: todo
-
-        // copy all tags to device with db: todo
+        // clone device with db
+        unit.device_db_clone = {
+            let syspath = unit
+                .device
+                .lock()
+                .unwrap()
+                .get_syspath()
+                .unwrap_or_default()
+                .to_string();
 
-        // add property to device with db: todo
+            Device::from_syspath(syspath, true)
+                .and_then(|mut db| db.read_db().map(|_| db))
+                .ok()
+        };
 
         self.apply_rules()?;
 
         // rename netif: todo
 
-        // update devnode: todo
+        self.update_devnode()?;
+
+        let unit = self.current_unit.as_mut().unwrap();
+        let mut device = unit.device.lock().unwrap();
 
-        // preserve old, or get new initialization timestamp: todo
+        // preserve old, or get new initialization timestamp
+        let usec = unit
+            .device_db_clone
+            .as_mut()
+            .and_then(|db| db.get_usec_initialized().ok())
+            .filter(|usec| *usec > 0)
+            .unwrap_or_else(now_monotonic_usec);
+        execute_err!(
+            device.set_usec_initialized(usec),
+            "set initialization timestamp"
+        )?;
+        device.is_initialized = true;
 
-        // write database file: todo
+        // write database file
+        execute_err!(device.update_db(), "update database")?;
+        execute_err!(
+            device.update_tag_index(unit.device_db_clone.as_ref(), true),
+            "update tag index"
+        )?;
 
         Ok(())
     }
 
     /// execute rules on remove uevent
     pub(crate) fn execute_rules_on_remove(&mut self) -> Result<()> {
-        todo!();
+        {
+            let unit = self.current_unit.as_mut().unwrap();
+            let mut device = unit.device.lock().unwrap();
+
+            // the devlinks and tags to clean up are recorded in the database
+            if let Err(e) = device.read_db() {
+                log::debug!("Failed to read database on remove: {}", e);
+            }
+
+            execute_err!(device.update_tag_index(None, false), "remove tag index")?;
+            execute_err!(device.delete_db(), "delete database")?;
+        }
+
+        self.apply_rules()?;
+
+        let unit = self.current_unit.as_mut().unwrap();
+        let mut device = unit.device.lock().unwrap();
+        if device.get_devnum().is_ok() {
+            node_remove(&mut device)?;
+        }
+
+        Ok(())
+    }
+
+    /// apply the permissions on the device node, and create or update its symlinks
+    pub(crate) fn update_devnode(&mut self) -> Result<()> {
+        let unit = self.current_unit.as_mut().unwrap();
+        let mut device = unit.device.lock().unwrap();
+
+        if device.get_devnum().is_err() {
+            return Ok(());
+        }
+
+        let uid = unit.uid.or_else(|| {
+            device
+                .get_property_value("DEVUID".to_string())
+                .ok()
+                .and_then(|v| v.parse::<uid_t>().ok())
+        });
+        let gid = unit.gid.or_else(|| {
+            device
+                .get_property_value("DEVGID".to_string())
+                .ok()
+                .and_then(|v| v.parse::<gid_t>().ok())
+        });
+        let mode = unit
+            .mode
+            .or_else(|| {
+                device
+                    .get_property_value("DEVMODE".to_string())
+                    .ok()
+                    .and_then(|v| parse_mode(&v).ok())
+            })
+            .unwrap_or(if gid.unwrap_or(0) > 0 { 0o660 } else { 0o600 });
+
+        // the permissions are applied on add uevent, or if they are specified by rules
+        let apply = device.action == DeviceAction::Add
+            || unit.mode.is_some()
+            || unit.uid.is_some()
+            || unit.gid.is_some()
+            || !unit.seclabel_list.is_empty();
+
+        if apply {
+            if let Err(e) =
+                node_apply_permissions(&mut device, apply, mode, uid, gid, &unit.seclabel_list)
+            {
+                log::error!("{}", e);
+            }
+        }
+
+        node_update(&mut device, unit.device_db_clone.as_ref())
     }
 
     /// apply rules on device
     pub(crate) fn apply_rules(&mut self) -> Result<()> {
         self.current_rule_file = self.rules.as_ref().read().unwrap().files.clone();
 
-        loop {
-            let next_file = self
-                .current_rule_file
-                .clone()
-                .unwrap()
-                .as_ref()
-                .read()
-                .unwrap()
-                .next
-                .clone();
-
+        while let Some(file) = self.current_rule_file.clone() {
             self.apply_rule_file()?;
 
-            self.current_rule_file = next_file;
-            if self.current_rule_file.is_none() {
-                break;
-            }
+            self.current_rule_file = file.read().unwrap().next.clone();
         }
 
         Ok(())
@@ -209,14 +729,10 @@ impl ExecuteManager {
             .lines
             .clone();
 
-        loop {
-            let next_line = self.apply_rule_line()?;
-
-            self.current_rule_line = next_line;
-            if self.current_rule_line.is_none() {
-                break;
-            }
+        while self.current_rule_line.is_some() {
+            self.current_rule_line = self.apply_rule_line()?;
         }
+
         Ok(())
     }
 
@@ -224,158 +740,600 @@ impl ExecuteManager {
     /// normally return the next rule line after current line
     /// if current line has goto label, use the line with the target label as the next line
     pub(crate) fn apply_rule_line(&mut self) -> Result<Option<Arc<RwLock<RuleLine>>>> {
-        self.current_rule_token = match self.current_rule_line.clone() {
-            Some(line) => line.as_ref().read().unwrap().tokens.clone(),
+        let line = match self.current_rule_line.clone() {
+            Some(line) => line,
             None => return Ok(None),
         };
 
-        device_trace!(
-            "Apply Rule Line:",
-            self.current_unit
-                .as_ref()
-                .unwrap()
-                .device
-                .as_ref()
-                .borrow_mut(),
-            self.get_current_rule_file(),
-            self.get_current_line_number()
-        );
+        self.current_rule_token = line.read().unwrap().tokens.clone();
+
+        {
+            let device = self.current_unit.as_ref().unwrap().device.clone();
+            let mut device = device.lock().unwrap();
+            device_trace!(
+                "Apply Rule Line:",
+                device,
+                self.get_current_rule_file(),
+                self.get_current_line_number()
+            );
+        }
 
         // only apply rule token on parent device once
         // that means if some a parent device matches the token, do not match any parent tokens in the following
         let mut parents_done = false;
 
-        loop {
-            let next_token = self
-                .current_rule_token
-                .clone()
-                .unwrap()
-                .as_ref()
-                .read()
-                .unwrap()
-                .next
-                .clone();
-
-            if self
-                .current_rule_token
-                .clone()
-                .unwrap()
-                .as_ref()
-                .read()
-                .unwrap()
-                .is_for_parents()
-            {
-                if !parents_done {
-                    if !self.apply_rule_token_on_parent()? {
-                        return Ok(self
-                            .current_rule_line
-                            .clone()
-                            .unwrap()
-                            .as_ref()
-                            .read()
-                            .unwrap()
-                            .next
-                            .clone());
-                    }
+        while let Some(token) = self.current_rule_token.clone() {
+            let is_for_parents = token.read().unwrap().is_for_parents();
 
+            let matched = if is_for_parents {
+                if parents_done {
+                    true
+                } else {
                     parents_done = true;
+                    self.apply_rule_token_on_parent()?
                 }
-            } else if !self.apply_rule_token()? {
-                // if current rule token does not match, abort applying the rest tokens in this line
-                return Ok(self
-                    .current_rule_line
-                    .clone()
-                    .unwrap()
-                    .as_ref()
-                    .read()
-                    .unwrap()
-                    .next
-                    .clone());
-            }
+            } else {
+                self.apply_rule_token()?
+            };
 
-            self.current_rule_token = next_token;
-            if self.current_rule_token.is_none() {
-                break;
+            // if current rule token does not match, abort applying the rest tokens in this line
+            if !matched {
+                return Ok(line.read().unwrap().next.clone());
             }
-        }
 
-        let goto_line = self
-            .current_rule_line
-            .clone()
-            .unwrap()
-            .as_ref()
-            .read()
-            .unwrap()
-            .goto_line
-            .clone();
+            self.current_rule_token = token.read().unwrap().next.clone();
+        }
 
-        match goto_line {
-            Some(line) => Ok(Some(line)),
-            None => Ok(self
-                .current_rule_line
-                .clone()
-                .unwrap()
-                .as_ref()
-                .read()
-                .unwrap()
-                .next
-                .clone()),
+        let line = line.read().unwrap();
+        match line.goto_line.clone() {
+            Some(goto_line) => Ok(Some(goto_line)),
+            None => Ok(line.next.clone()),
         }
     }
 
     /// apply rule token on device
     pub(crate) fn apply_rule_token(&mut self) -> Result<bool> {
-        let token_type = self
-            .current_rule_token
-            .clone()
-            .unwrap()
-            .as_ref()
-            .read()
-            .unwrap()
-            .r#type;
-
-        let device = self
-            .current_unit
-            .as_ref()
-            .unwrap()
-            .device
-            .as_ref()
-            .borrow_mut();
-
-        let token = self
-            .current_rule_token
-            .as_ref()
-            .unwrap()
-            .as_ref()
-            .read()
-            .unwrap();
+        let token = self.current_rule_token.clone().unwrap();
+        let token = token.read().unwrap();
+        let unit = self.current_unit.as_mut().unwrap();
+        let device = unit.device.clone();
 
-        match token_type {
+        match token.r#type {
             MatchAction => {
-                let action = execute_err!(device.get_action(), "MatchAction")?;
+                let action = execute_err!(device.lock().unwrap().get_action(), "ACTION")?;
 
-                Ok(action.to_string() == token.value)
+                Ok(token.pattern_match(&action.to_string()))
             }
             MatchDevpath => {
-                let devpath = execute_none!(device.get_devpath(), "MatchDevpath", "DEVPATH")?;
-                Ok(*devpath == token.value)
+                let device = device.lock().unwrap();
+                let devpath = execute_none!(device.get_devpath(), "DEVPATH", "devpath")?;
+
+                Ok(token.pattern_match(devpath))
+            }
+            MatchKernel => {
+                let mut device = device.lock().unwrap();
+                let sysname = execute_none!(device.get_sysname(), "KERNEL", "sysname")?;
+
+                Ok(token.pattern_match(sysname))
+            }
+            MatchDevlink => {
+                let device = device.lock().unwrap();
+                let matched = device
+                    .devlinks
+                    .iter()
+                    .any(|l| token.pattern_match(l.trim_start_matches("/dev/")));
+
+                Ok(matched == (token.op == OperatorType::Match))
+            }
+            MatchName => Ok(token.pattern_match(&unit.name)),
+            MatchEnv => {
+                let key = token.attr.clone().unwrap_or_default();
+                let value = device
+                    .lock()
+                    .unwrap()
+                    .get_property_value(key)
+                    .unwrap_or_default();
+
+                Ok(token.pattern_match(&value))
+            }
+            MatchConst => {
+                let value = match token.attr.as_deref() {
+                    Some("arch") => architecture(),
+                    Some("virt") => virtualization(),
+                    _ => String::new(),
+                };
+
+                Ok(token.pattern_match(&value))
+            }
+            MatchTag => {
+                let mut device = device.lock().unwrap();
+                if let Err(e) = device.read_db() {
+                    log::debug!("Failed to read db of {}: {}", device.devpath, e);
+                }
+
+                Ok(token.pattern_match_any(device.current_tags.iter()))
+            }
+            MatchSubsystem => {
+                let subsystem = device.lock().unwrap().get_subsystem().unwrap_or_default();
+
+                Ok(token.pattern_match(&subsystem))
+            }
+            MatchDriver => {
+                let driver = device.lock().unwrap().get_driver().unwrap_or_default();
+
+                Ok(token.pattern_match(&driver))
+            }
+            MatchAttr => Ok(unit.match_attr(&device, &token)),
+            MatchSysctl => {
+                let key = unit.apply_format(&token.attr.clone().unwrap_or_default(), false);
+                let value = fs::read_to_string(sysctl_path(&key)).unwrap_or_default();
+
+                Ok(token.pattern_match(value.trim_end()))
+            }
+            MatchTest => {
+                let mut path = unit.apply_format(&token.value, false);
+                if !path.starts_with('/') {
+                    let syspath = device
+                        .lock()
+                        .unwrap()
+                        .get_syspath()
+                        .unwrap_or_default()
+                        .to_string();
+                    path = format!("{}/{}", syspath, path);
+                }
+
+                let exists = match fs::metadata(&path) {
+                    Ok(metadata) => match token.attr.as_deref() {
+                        Some(mode) => {
+                            use std::os::unix::fs::PermissionsExt;
+                            let mode = parse_mode(mode).unwrap_or_default();
+                            metadata.permissions().mode() & mode > 0
+                        }
+                        None => true,
+                    },
+                    Err(_) => false,
+                };
+
+                Ok(exists == (token.op == OperatorType::Match))
+            }
+            MatchProgram => {
+                let cmd = unit.apply_format(&token.value, false);
+                match unit.spawn_program(&cmd) {
+                    Ok(output) => {
+                        let output = output.trim_end_matches('\n');
+                        unit.program_result = if unit.escape_type == EscapeType::None {
+                            output.to_string()
+                        } else {
+                            replace_chars(output, ALLOWED_CHARS_INPUT)
+                        };
+
+                        Ok(token.op == OperatorType::Match)
+                    }
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        Ok(token.op == OperatorType::Nomatch)
+                    }
+                }
+            }
+            MatchImportFile => {
+                let path = unit.apply_format(&token.value, false);
+                match fs::read_to_string(&path) {
+                    Ok(content) => {
+                        unit.import_properties(&content);
+                        Ok(token.op == OperatorType::Match)
+                    }
+                    Err(e) => {
+                        log::debug!("Failed to import file '{}': {}", path, e);
+                        Ok(token.op == OperatorType::Nomatch)
+                    }
+                }
+            }
+            MatchImportProgram => {
+                let cmd = unit.apply_format(&token.value, false);
+                match unit.spawn_program(&cmd) {
+                    Ok(output) => {
+                        unit.import_properties(&output);
+                        Ok(token.op == OperatorType::Match)
+                    }
+                    Err(e) => {
+                        log::debug!("{}", e);
+                        Ok(token.op == OperatorType::Nomatch)
+                    }
+                }
+            }
+            MatchImportBuiltin => {
+                let cmd = unit.apply_format(&token.value, false);
+                match unit.run_builtin(&self.builtin_mgr, &cmd) {
+                    Ok(ret) => Ok(ret == (token.op == OperatorType::Match)),
+                    Err(e) => {
+                        log::debug!("Failed to run builtin command '{}': {}", cmd, e);
+                        Ok(token.op == OperatorType::Nomatch)
+                    }
+                }
+            }
+            MatchImportDb => {
+                let value = match unit.device_db_clone.as_mut() {
+                    Some(db) => db.get_property_value(token.value.clone()),
+                    None => return Ok(token.op == OperatorType::Nomatch),
+                };
+
+                match value {
+                    Ok(value) => {
+                        execute_err!(
+                            device
+                                .lock()
+                                .unwrap()
+                                .add_property(token.value.clone(), value),
+                            "IMPORT{db}"
+                        )?;
+                        Ok(token.op == OperatorType::Match)
+                    }
+                    Err(_) => Ok(token.op == OperatorType::Nomatch),
+                }
+            }
+            MatchImportCmdline => {
+                let cmdline = fs::read_to_string("/proc/cmdline").unwrap_or_default();
+                let value = cmdline.split_whitespace().find_map(|item| {
+                    if item == token.value {
+                        Some("1".to_string())
+                    } else {
+                        item.strip_prefix(&format!("{}=", token.value))
+                            .map(|v| v.to_string())
+                    }
+                });
+
+                match value {
+                    Some(value) => {
+                        execute_err!(
+                            device
+                                .lock()
+                                .unwrap()
+                                .add_property(token.value.clone(), value),
+                            "IMPORT{cmdline}"
+                        )?;
+                        Ok(token.op == OperatorType::Match)
+                    }
+                    None => Ok(token.op == OperatorType::Nomatch),
+                }
+            }
+            MatchImportParent => {
+                let parent = match device.lock().unwrap().get_parent() {
+                    Ok(p) => p,
+                    Err(_) => return Ok(token.op == OperatorType::Nomatch),
+                };
+
+                let patterns = unit.apply_format(&token.value, false);
+                let patterns: Vec<regex::Regex> = patterns
+                    .split('|')
+                    .filter_map(|p| fnmatch_regex::glob_to_regex(p).ok())
+                    .collect();
+
+                let properties = {
+                    let mut parent = parent.lock().unwrap();
+                    // load the properties from uevent file and database
+                    let _ = parent.get_property_value(String::new());
+                    parent.properties.clone()
+                };
+
+                let mut device = device.lock().unwrap();
+                for (key, value) in properties {
+                    if patterns.iter().any(|p| p.is_match(&key)) {
+                        execute_err!(device.add_property(key, value), "IMPORT{parent}")?;
+                    }
+                }
+
+                Ok(token.op == OperatorType::Match)
+            }
+            MatchResult => Ok(token.pattern_match(&unit.program_result)),
+            AssignOptionsStringEscapeNone => {
+                unit.escape_type = EscapeType::None;
+                Ok(true)
+            }
+            AssignOptionsStringEscapeReplace => {
+                unit.escape_type = EscapeType::Replace;
+                Ok(true)
+            }
+            AssignOptionsDbPersist => {
+                device.lock().unwrap().db_persist = true;
+                Ok(true)
+            }
+            AssignOptionsInotifyWatch => {
+                if unit.inotify_watch_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.inotify_watch_final = true;
+                }
+
+                unit.inotify_watch = token.value == "1";
+                Ok(true)
+            }
+            AssignOptionsDevlinkPriority => {
+                device.lock().unwrap().devlink_priority = token.value.parse().unwrap_or_default();
+                Ok(true)
+            }
+            AssignOptionsLogLevel => {
+                log::debug!("Ignore 'OPTIONS+=\"log_level={}\"'.", token.value);
+                Ok(true)
+            }
+            AssignOwner | AssignOwnerId => {
+                if unit.owner_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.owner_final = true;
+                }
+
+                let owner = if token.r#type == AssignOwner {
+                    unit.apply_format(&token.value, false)
+                } else {
+                    token.value.clone()
+                };
+
+                match get_user_creds(&owner) {
+                    Ok(user) => unit.uid = Some(user.uid.as_raw()),
+                    Err(e) => log::error!("Failed to resolve user '{}': {}", owner, e),
+                }
+                Ok(true)
+            }
+            AssignGroup | AssignGroupId => {
+                if unit.group_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.group_final = true;
+                }
+
+                let group = if token.r#type == AssignGroup {
+                    unit.apply_format(&token.value, false)
+                } else {
+                    token.value.clone()
+                };
+
+                match get_group_creds(&group) {
+                    Ok(group) => unit.gid = Some(group.gid.as_raw()),
+                    Err(e) => log::error!("Failed to resolve group '{}': {}", group, e),
+                }
+                Ok(true)
+            }
+            AssignMode | AssignModeId => {
+                if unit.mode_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.mode_final = true;
+                }
+
+                let mode = if token.r#type == AssignMode {
+                    unit.apply_format(&token.value, false)
+                } else {
+                    token.value.clone()
+                };
+
+                match parse_mode(&mode) {
+                    Ok(mode) => unit.mode = Some(mode),
+                    Err(e) => log::error!("Failed to parse mode '{}': {}", mode, e),
+                }
+                Ok(true)
+            }
+            AssignTag => {
+                let tag = unit.apply_format(&token.value, false);
+
+                let mut device = device.lock().unwrap();
+                if token.op == OperatorType::Assign {
+                    device.cleanup_tags();
+                }
+
+                if !tag
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+                {
+                    log::error!("Invalid tag name '{}', ignoring.", tag);
+                    return Ok(true);
+                }
+
+                if token.op == OperatorType::Remove {
+                    device.remove_tag(&tag);
+                } else {
+                    execute_err!(device.add_tag(tag, true), "TAG")?;
+                }
+                Ok(true)
+            }
+            AssignOptionsStaticNode => {
+                // static nodes are handled when devmaster starts up
+                Ok(true)
+            }
+            AssignSeclabel => {
+                let value = unit.apply_format(&token.value, false);
+
+                if token.op == OperatorType::Assign {
+                    unit.seclabel_list.clear();
+                }
+
+                unit.seclabel_list
+                    .insert(token.attr.clone().unwrap_or_default(), value);
+                Ok(true)
+            }
+            AssignEnv => {
+                let key = token.attr.clone().unwrap_or_default();
+                let mut value = unit.apply_format(&token.value, false);
+                if unit.escape_type == EscapeType::Replace {
+                    value = replace_chars(&value, "");
+                }
+
+                let mut device = device.lock().unwrap();
+                if token.op == OperatorType::Add && !value.is_empty() {
+                    if let Ok(old) = device.get_property_value(key.clone()) {
+                        value = format!("{} {}", old, value);
+                    }
+                }
+
+                execute_err!(device.add_property(key, value), "ENV")?;
+                Ok(true)
+            }
+            AssignName => {
+                if unit.name_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.name_final = true;
+                }
+
+                if device.lock().unwrap().get_ifindex().unwrap_or_default() == 0 {
+                    log::warn!(
+                        "Only network interfaces can be renamed, ignoring NAME=\"{}\".",
+                        token.value
+                    );
+                    return Ok(true);
+                }
+
+                let name = unit.apply_format(&token.value, unit.escape_type != EscapeType::None);
+                unit.name = if unit.escape_type == EscapeType::None {
+                    name
+                } else {
+                    replace_chars(&name, "/")
+                };
+                Ok(true)
             }
             AssignDevlink => {
-                todo!()
+                if unit.devlink_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.devlink_final = true;
+                }
+                if matches!(token.op, OperatorType::Assign | OperatorType::AssignFinal) {
+                    device.lock().unwrap().cleanup_devlinks();
+                }
+
+                // multiple symlinks are separated by whitespace
+                let links = unit.apply_format(&token.value, unit.escape_type != EscapeType::None);
+                let links = match unit.escape_type {
+                    EscapeType::Unset => replace_chars(&links, "/ "),
+                    EscapeType::Replace => replace_chars(&links, "/"),
+                    EscapeType::None => links,
+                };
+
+                let mut device = device.lock().unwrap();
+                for link in links.split_whitespace() {
+                    let link = format!("/dev/{}", link.trim_start_matches("/dev/"));
+                    execute_err!(device.add_devlink(link), "SYMLINK")?;
+                }
+                Ok(true)
+            }
+            AssignAttr => {
+                let attr = token.attr.clone().unwrap_or_default();
+                let value = unit.apply_format(&token.value, false);
+
+                let ret = match attr.parse::<SubstituteType>()? {
+                    SubstituteType::Subsys => {
+                        resolve_subsystem_device(&attr).and_then(|(mut device, attr)| {
+                            execute_err!(device.set_sysattr_value(attr, Some(value)), "ATTR")
+                        })
+                    }
+                    _ => {
+                        let attr = unit.apply_format(&attr, false);
+                        execute_err!(
+                            device.lock().unwrap().set_sysattr_value(attr, Some(value)),
+                            "ATTR"
+                        )
+                    }
+                };
+
+                if let Err(e) = ret {
+                    log::error!("{}", e);
+                }
+                Ok(true)
+            }
+            AssignSysctl => {
+                let key = unit.apply_format(&token.attr.clone().unwrap_or_default(), false);
+                let value = unit.apply_format(&token.value, false);
+
+                if let Err(e) = fs::write(sysctl_path(&key), value) {
+                    log::error!("Failed to write sysctl '{}': {}", key, e);
+                }
+                Ok(true)
             }
+            AssignRunBuiltin | AssignRunProgram => {
+                if unit.run_final {
+                    return Ok(true);
+                }
+                if token.op == OperatorType::AssignFinal {
+                    unit.run_final = true;
+                }
+                if matches!(token.op, OperatorType::Assign | OperatorType::AssignFinal) {
+                    unit.run_list.clear();
+                }
+
+                let cmd = unit.apply_format(&token.value, false);
+                let builtin = if token.r#type == AssignRunBuiltin {
+                    Some(cmd.parse::<BuiltinCommand>()?)
+                } else {
+                    None
+                };
+
+                if !unit.run_list.iter().any(|(c, _)| *c == cmd) {
+                    unit.run_list.push((cmd, builtin));
+                }
+                Ok(true)
+            }
+            Goto | Label => Ok(true),
             _ => {
-                todo!();
+                log::debug!("Ignore unsupported token type {:?}.", token.r#type);
+                Ok(true)
             }
         }
     }
 
     /// apply rule token on the parent device
+    /// the parent tokens in the line should be matched by the same device,
+    /// which is searched from the device itself up to the root
     pub(crate) fn apply_rule_token_on_parent(&mut self) -> Result<bool> {
-        todo!();
+        let tokens: Vec<Arc<RwLock<RuleToken>>> = self
+            .current_rule_line
+            .as_ref()
+            .unwrap()
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|t| t.read().unwrap().is_for_parents())
+            .collect();
+
+        let unit = self.current_unit.as_mut().unwrap();
+        let mut device = unit.device.clone();
+
+        loop {
+            if tokens
+                .iter()
+                .all(|t| unit.match_parent_token(&device, &t.read().unwrap()))
+            {
+                unit.parent = Some(device);
+                return Ok(true);
+            }
+
+            let parent = device.lock().unwrap().get_parent();
+            match parent {
+                Ok(parent) => device = parent,
+                Err(_) => return Ok(false),
+            }
+        }
     }
 
     /// execute run
     pub(crate) fn execute_run(&mut self) -> Result<()> {
+        let unit = self.current_unit.as_mut().unwrap();
+
+        for (cmd, builtin) in unit.run_list.clone() {
+            let ret = match builtin {
+                Some(_) => unit.run_builtin(&self.builtin_mgr, &cmd).map(|_| ()),
+                None => unit.spawn_program(&cmd).map(|_| ()),
+            };
+
+            if let Err(e) = ret {
+                log::error!("Failed to execute '{}': {}", cmd, e);
+            }
+        }
+
         Ok(())
     }
 
@@ -401,6 +1359,147 @@ impl ExecuteManager {
     }
 }
 
+impl RuleToken {
+    /// check whether the string matches the token value, considering the operator
+    pub(crate) fn pattern_match(&self, s: &str) -> bool {
+        let matched = match self.match_type {
+            MatchType::Subsystem => matches!(s, "subsystem" | "bus" | "class"),
+            _ => self.value_regex.iter().any(|r| r.is_match(s)),
+        };
+
+        matched == (self.op == OperatorType::Match)
+    }
+
+    /// check whether any of the strings matches the token value, considering the operator
+    pub(crate) fn pattern_match_any<'a>(&self, mut iter: impl Iterator<Item = &'a String>) -> bool {
+        let matched = iter.any(|s| self.value_regex.iter().any(|r| r.is_match(s)));
+
+        matched == (self.op == OperatorType::Match)
+    }
+}
+
+/// resolve the attribute in the form of '[<subsystem>/<sysname>]<attribute>'
+fn resolve_subsystem_device(s: &str) -> Result<(Device, String)> {
+    let invalid = || Error::RulesExecuteError {
+        msg: format!("Invalid attribute '{}'", s),
+        errno: Errno::EINVAL,
+    };
+
+    let (kernel, attr) = s
+        .strip_prefix('[')
+        .and_then(|s| s.split_once(']'))
+        .ok_or_else(invalid)?;
+    let (subsystem, sysname) = kernel.split_once('/').ok_or_else(invalid)?;
+
+    let device = execute_err!(
+        Device::from_subsystem_sysname(subsystem.to_string(), sysname.to_string()),
+        s
+    )?;
+
+    Ok((device, attr.trim_start_matches('/').to_string()))
+}
+
+/// get the path under /proc/sys of the sysctl key, e.g., 'net.ipv4.ip_forward'
+fn sysctl_path(key: &str) -> String {
+    if key.contains('/') {
+        format!("/proc/sys/{}", key.trim_start_matches('/'))
+    } else {
+        format!("/proc/sys/{}", key.replace('.', "/"))
+    }
+}
+
+/// split the command line into arguments, quotes are removed
+fn split_args(s: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+
+    for c in s.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => arg.push(c),
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            None => {
+                arg.push(c);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(arg);
+    }
+
+    args
+}
+
+/// parse the line in the form of 'KEY=VALUE', the value may be quoted
+fn parse_key_value(line: &str) -> Option<(String, String)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim_end();
+    let mut value = value.trim_start();
+    if key.is_empty() {
+        return None;
+    }
+
+    for q in ['"', '\''] {
+        if let Some(v) = value.strip_prefix(q) {
+            value = v.strip_suffix(q)?;
+            break;
+        }
+    }
+
+    Some((key.to_string(), value.to_string()))
+}
+
+/// get the current time of CLOCK_MONOTONIC in microseconds
+fn now_monotonic_usec() -> u64 {
+    match clock_gettime(ClockId::CLOCK_MONOTONIC) {
+        Ok(t) => t.tv_sec() as u64 * 1_000_000 + t.tv_nsec() as u64 / 1_000,
+        Err(_) => 0,
+    }
+}
+
+/// get the architecture in the same naming as 'ConditionArchitecture'
+fn architecture() -> String {
+    match std::env::consts::ARCH {
+        "x86_64" => "x86-64",
+        "aarch64" => "arm64",
+        "powerpc64" => "ppc64",
+        "riscv64" => "riscv64",
+        arch => arch,
+    }
+    .to_string()
+}
+
+/// get the container type, or 'none' if not running in container
+fn virtualization() -> String {
+    match detect_container() {
+        Virtualization::None => "none",
+        Virtualization::Docker => "docker",
+        Virtualization::Lxc => "lxc",
+        Virtualization::Podman => "podman",
+        Virtualization::Containerd => "containerd",
+        Virtualization::NotSupported => "container-other",
+    }
+    .to_string()
+}
+
 /// translate execution error from downside call chain
 #[macro_export]
 macro_rules! execute_err {
@@ -426,3 +1525,190 @@ macro_rules! execute_none {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::rule_load::tests::{clear_test_rules_dir, create_test_rules_dir};
+    use crate::rules::ResolveNameTime;
+
+    // the tests run the rules on the loopback interface, which is absent in some containers,
+    // run them by "cargo test -- --ignored" where /sys/devices/virtual/net/lo exists
+    fn execute_on_loopback(dir: &'static str, action: &str) -> ExecuteManager {
+        let rules = Rules::load_rules(&[dir], ResolveNameTime::Early);
+        let device = Device::from_nulstr(
+            format!(
+                "ACTION={}\0DEVPATH=/devices/virtual/net/lo\0SUBSYSTEM=net\0INTERFACE=lo\0IFINDEX=1\0",
+                action
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let mut mgr = ExecuteManager::new(rules);
+        mgr.current_unit = Some(ExecuteUnit::new(Arc::new(Mutex::new(device))));
+        mgr.apply_rules().unwrap();
+        mgr
+    }
+
+    fn property(mgr: &ExecuteManager, key: &str) -> Option<String> {
+        mgr.current_unit
+            .as_ref()
+            .unwrap()
+            .device
+            .lock()
+            .unwrap()
+            .get_property_value(key.to_string())
+            .ok()
+    }
+
+    #[test]
+    #[ignore = "needs /sys/devices/virtual/net/lo"]
+    fn test_apply_match_tokens() {
+        clear_test_rules_dir("test_apply_match_tokens");
+        create_test_rules_dir(
+            "test_apply_match_tokens",
+            "ACTION==\"add\", SUBSYSTEM==\"net\", KERNEL==\"lo\", ENV{MATCHED}=\"1\"
+KERNEL==\"eth*|wlan*\", ENV{WRONG}=\"1\"
+KERNEL!=\"eth*|wlan*\", DEVPATH==\"*/net/lo\", ENV{NOMATCH}=\"1\"
+ENV{MATCHED}==\"1\", ENV{MATCHED}+=\"2\"
+ATTR{ifindex}==\"1\", TAG+=\"test_tag\"
+TAG==\"test_tag\", ENV{TAGGED}=\"1\"
+KERNELS==\"lo\", SUBSYSTEMS==\"net\", ATTRS{ifindex}==\"1\", ENV{PARENT}=\"$id\"
+KERNELS==\"lo\", SUBSYSTEMS==\"block\", ENV{PARENT_WRONG}=\"1\"
+TEST==\"ifindex\", ENV{TEST}=\"1\"
+TEST==\"not_exist\", ENV{TEST_WRONG}=\"1\"
+CONST{arch}==\"?*\", ENV{ARCH}=\"1\"
+KERNEL==\"lo\", GOTO=\"skip\"
+ENV{SKIPPED}=\"1\"
+LABEL=\"skip\"
+ENV{AFTER_LABEL}=\"1\"
+",
+        );
+
+        let mgr = execute_on_loopback("test_apply_match_tokens", "add");
+        assert_eq!(property(&mgr, "MATCHED").unwrap(), "1 2");
+        assert!(property(&mgr, "WRONG").is_none());
+        assert_eq!(property(&mgr, "NOMATCH").unwrap(), "1");
+        assert_eq!(property(&mgr, "TAGGED").unwrap(), "1");
+        assert_eq!(property(&mgr, "PARENT").unwrap(), "lo");
+        assert!(property(&mgr, "PARENT_WRONG").is_none());
+        assert_eq!(property(&mgr, "TEST").unwrap(), "1");
+        assert!(property(&mgr, "TEST_WRONG").is_none());
+        assert_eq!(property(&mgr, "ARCH").unwrap(), "1");
+        assert!(property(&mgr, "SKIPPED").is_none());
+        assert_eq!(property(&mgr, "AFTER_LABEL").unwrap(), "1");
+
+        clear_test_rules_dir("test_apply_match_tokens");
+    }
+
+    #[test]
+    #[ignore = "needs /sys/devices/virtual/net/lo"]
+    fn test_apply_program_and_import() {
+        clear_test_rules_dir("test_apply_program_and_import");
+        create_test_rules_dir(
+            "test_apply_program_and_import",
+            "PROGRAM==\"/bin/echo hello $kernel\", RESULT==\"hello*\", ENV{RESULT}=\"$result{1}\"
+PROGRAM==\"/bin/false\", ENV{PROGRAM_WRONG}=\"1\"
+IMPORT{program}=\"/bin/echo PROGRAM_KEY=yes\"
+IMPORT{file}=\"test_apply_program_and_import/import.env\"
+IMPORT{cmdline}=\"test_not_exist_key\", ENV{CMDLINE}=\"1\"
+IMPORT{builtin}=\"example\", ENV{BUILTIN}=\"1\"
+IMPORT{builtin}==\"path_id\", ENV{PATH_ID}=\"1\"
+",
+        );
+        fs::write(
+            "test_apply_program_and_import/import.env",
+            "# comment\nFILE_KEY=\"quoted value\"\n",
+        )
+        .unwrap();
+
+        let mgr = execute_on_loopback("test_apply_program_and_import", "add");
+        assert_eq!(property(&mgr, "RESULT").unwrap(), "lo");
+        assert!(property(&mgr, "PROGRAM_WRONG").is_none());
+        assert_eq!(property(&mgr, "PROGRAM_KEY").unwrap(), "yes");
+        assert_eq!(property(&mgr, "FILE_KEY").unwrap(), "quoted value");
+        assert!(property(&mgr, "CMDLINE").is_none());
+        assert_eq!(property(&mgr, "BUILTIN").unwrap(), "1");
+        assert!(property(&mgr, "PATH_ID").is_none());
+
+        clear_test_rules_dir("test_apply_program_and_import");
+    }
+
+    #[test]
+    #[ignore = "needs /sys/devices/virtual/net/lo"]
+    fn test_apply_assign_tokens() {
+        clear_test_rules_dir("test_apply_assign_tokens");
+        create_test_rules_dir(
+            "test_apply_assign_tokens",
+            "OWNER:=\"root\", GROUP=\"root\", MODE=\"0660\"
+OWNER=\"1\", MODE=\"0600\"
+SYMLINK+=\"net/$kernel\", SYMLINK+=\"net/ifindex-$attr{ifindex}\"
+SYMLINK==\"net/lo\", ENV{LINKED}=\"1\"
+NAME=\"lo_new\", ENV{NEW_NAME}=\"$name\"
+TAG+=\"tag1\", TAG+=\"tag2\"
+TAG-=\"tag1\"
+RUN+=\"/bin/true\", RUN{builtin}+=\"path_id\"
+RUN+=\"/bin/true\"
+",
+        );
+
+        let mut mgr = execute_on_loopback("test_apply_assign_tokens", "add");
+        {
+            let unit = mgr.current_unit.as_ref().unwrap();
+            assert_eq!(unit.uid, Some(0));
+            assert_eq!(unit.gid, Some(0));
+            assert_eq!(unit.mode, Some(0o600));
+            assert_eq!(unit.name, "lo_new");
+            assert_eq!(unit.run_list.len(), 2);
+
+            let device = unit.device.lock().unwrap();
+            let mut links: Vec<&String> = device.devlinks.iter().collect();
+            links.sort();
+            assert_eq!(links, vec!["/dev/net/ifindex-1", "/dev/net/lo"]);
+            assert!(device.current_tags.contains("tag2"));
+            assert!(!device.current_tags.contains("tag1"));
+        }
+        assert_eq!(property(&mgr, "NEW_NAME").unwrap(), "lo_new");
+        assert_eq!(property(&mgr, "LINKED").unwrap(), "1");
+        mgr.execute_run().unwrap();
+
+        clear_test_rules_dir("test_apply_assign_tokens");
+    }
+
+    #[test]
+    #[ignore = "needs /sys/devices/virtual/net/lo"]
+    fn test_apply_rules_on_remove() {
+        clear_test_rules_dir("test_apply_rules_on_remove");
+        create_test_rules_dir(
+            "test_apply_rules_on_remove",
+            "ACTION==\"remove\", ENV{REMOVED}=\"1\"\n",
+        );
+
+        let mgr = execute_on_loopback("test_apply_rules_on_remove", "remove");
+        assert_eq!(property(&mgr, "REMOVED").unwrap(), "1");
+
+        clear_test_rules_dir("test_apply_rules_on_remove");
+    }
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(
+            split_args("/bin/sh -c 'echo a  b' \"c d\""),
+            vec!["/bin/sh", "-c", "echo a  b", "c d"]
+        );
+        assert_eq!(
+            parse_key_value("  KEY = 'value' "),
+            Some(("KEY".to_string(), "value".to_string()))
+        );
+        assert_eq!(parse_key_value("# KEY=value"), None);
+        assert_eq!(parse_key_value("KEY=\"value"), None);
+        assert_eq!(replace_chars("a b/c*d", "/"), "a_b/c_d");
+        assert_eq!(replace_whitespace_chars(" a  b "), "a_b");
+        assert_eq!(
+            sysctl_path("net.ipv4.ip_forward"),
+            "/proc/sys/net/ipv4/ip_forward"
+        );
+        assert!(resolve_subsystem_device("[net]ifindex").is_err());
+    }
+}
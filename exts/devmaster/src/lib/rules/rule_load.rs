@@ -230,18 +230,61 @@ impl RuleFile {
                 offset += 1;
             } else {
                 full_line.push_str(line);
-                let line = RuleLine::load_line(
+                let line_number = (line_number + 1 - offset) as u32;
+                match RuleLine::load_line(
                     full_line.to_string(),
-                    (line_number + 1 - offset) as u32,
+                    line_number,
                     self_ptr.clone(),
                     rules.clone(),
-                )
-                .unwrap();
-                self.add_line(line);
+                ) {
+                    Ok(line) => self.add_line(line),
+                    Err(e) => {
+                        log::error!(
+                            "{}:{} ignore invalid line: {}",
+                            self.file_name,
+                            line_number,
+                            e
+                        );
+                    }
+                }
                 full_line.clear();
                 offset = 0;
             }
         }
+
+        self.resolve_goto();
+    }
+
+    /// find the target line of each 'GOTO' token,
+    /// the label should be declared in the following lines of the same rule file
+    pub(crate) fn resolve_goto(&self) {
+        let mut line = self.lines.clone();
+        while let Some(l) = line {
+            let next = l.read().unwrap().next.clone();
+
+            let goto_label = l.read().unwrap().goto_label.clone();
+            if let Some(label) = goto_label {
+                let mut target = next.clone();
+                while let Some(t) = target {
+                    if t.read().unwrap().label.as_ref() == Some(&label) {
+                        l.write().unwrap().goto_line = Some(t);
+                        break;
+                    }
+                    target = t.read().unwrap().next.clone();
+                }
+
+                if l.read().unwrap().goto_line.is_none() {
+                    log::error!(
+                        "{}:{} GOTO=\"{}\" has no matching label, ignoring.",
+                        self.file_name,
+                        l.read().unwrap().line_number,
+                        label
+                    );
+                }
+            }
+
+            line = next;
+        }
     }
 
     /// push the rule line to the tail of linked list
@@ -324,9 +367,15 @@ value:  {}",
                 value,
             );
 
-            // if the token is 'GOTO' or 'LABEL', parse_token will return a IgnoreError
-            // the following tokens in this line, if any, will be skipped
-            let rule_token = RuleToken::parse_token(key, attr, op, value, rules.clone())?;
+            // the tokens that can be ignored, e.g., 'OWNER' without rules object, will be skipped
+            let rule_token = match RuleToken::parse_token(key, attr, op, value, rules.clone()) {
+                Ok(t) => t,
+                Err(Error::IgnoreError { msg }) => {
+                    log::debug!("{}", msg);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
             match rule_token.r#type {
                 TokenType::Goto => {
                     rule_line.goto_label = Some(rule_token.value);
@@ -583,7 +632,7 @@ impl RuleToken {
                 }
             }
             "CONST" => {
-                if attr.is_none() || matches!(attr.as_ref().unwrap().as_str(), "arch" | "virt") {
+                if attr.is_none() || !matches!(attr.as_ref().unwrap().as_str(), "arch" | "virt") {
                     return Err(Error::RulesLoadError {
                         msg: "Key 'CONST' has invalid attribute.".to_string(),
                     });
@@ -711,9 +760,9 @@ impl RuleToken {
                         log::warn!("{}", e);
                     }
 
-                    Ok(RuleToken::new(TokenType::AssignAttr, op, attr, value))?
+                    Ok(RuleToken::new(TokenType::AssignSysctl, op, attr, value))?
                 } else {
-                    Ok(RuleToken::new(TokenType::MatchAttr, op, attr, value))?
+                    Ok(RuleToken::new(TokenType::MatchSysctl, op, attr, value))?
                 }
             }
             "KERNELS" => {
@@ -729,7 +778,7 @@ impl RuleToken {
                 }
 
                 Ok(RuleToken::new(
-                    TokenType::MatchParentsSubsystem,
+                    TokenType::MatchParentsKernel,
                     op,
                     None,
                     value,
@@ -1251,28 +1300,27 @@ impl RuleToken {
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use basic::logger::init_log_to_console;
     use log::LevelFilter;
 
     use super::*;
     use std::{fs, path::Path, thread::JoinHandle};
 
-    fn create_test_rules_dir(dir: &'static str) {
-        assert!(fs::create_dir(dir).is_ok());
-        assert!(fs::write(
-            format!("{}/test.rules", dir),
-            "ACTION == \"change\", SYMLINK += \"test1\"
+    const TEST_RULES: &str = "ACTION == \"change\", SYMLINK += \"test1\"
 ACTION == \"change\", SYMLINK += \"test11\", \\
 SYMLINK += \"test111\"
 ACTION == \"change\", SYMLINK += \"test1111\", \\
 SYMLINK += \"test11111\", \\
-SYMLINK += \"test111111\"",
-        )
-        .is_ok());
+SYMLINK += \"test111111\"";
+
+    /// create the directory with a test.rules of the given content
+    pub(crate) fn create_test_rules_dir(dir: &'static str, rules: &str) {
+        assert!(fs::create_dir(dir).is_ok());
+        assert!(fs::write(format!("{}/test.rules", dir), rules).is_ok());
     }
 
-    fn clear_test_rules_dir(dir: &'static str) {
+    pub(crate) fn clear_test_rules_dir(dir: &'static str) {
         if Path::new(dir).exists() {
             assert!(fs::remove_dir_all(dir).is_ok());
         }
@@ -1282,7 +1330,7 @@ SYMLINK += \"test111111\"",
     fn test_rules_new() {
         init_log_to_console("test_rules_new", LevelFilter::Debug);
         clear_test_rules_dir("test_rules_new");
-        create_test_rules_dir("test_rules_new");
+        create_test_rules_dir("test_rules_new", TEST_RULES);
         let rules = Rules::load_rules(&DEFAULT_RULES_DIRS, ResolveNameTime::Early);
         println!("{}", rules.read().unwrap());
         clear_test_rules_dir("test_rules_new");
@@ -1442,7 +1490,7 @@ SYMLINK += \"test111111\"",
 
     #[test]
     fn test_rules_share_among_threads() {
-        create_test_rules_dir("test_rules_share_among_threads");
+        create_test_rules_dir("test_rules_share_among_threads", TEST_RULES);
         let rules = Rules::new(
             &["test_rules_new_1", "test_rules_new_2"],
             ResolveNameTime::Early,
@@ -21,6 +21,47 @@ use lazy_static::lazy_static;
 use regex::Regex;
 
 /// check whether the formatters in the value are valid
+/// the characters allowed in the strings read from sysfs attributes and programs
+pub(crate) const ALLOWED_CHARS_INPUT: &str = "/ $%?,";
+
+/// replace the unsafe characters with '_', the characters in allow list are kept
+pub(crate) fn replace_chars(s: &str, allow: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "#+-.:=@_".contains(c) || allow.contains(c) {
+                c
+            } else if !c.is_ascii() {
+                // keep the valid utf8 characters
+                c
+            } else if c.is_whitespace() && allow.contains(' ') {
+                ' '
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// strip the leading and trailing whitespace, and replace the whitespace in the middle with '_'
+pub(crate) fn replace_whitespace_chars(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+/// encode the unsafe characters as '\xNN', the valid utf8 characters are kept
+pub(crate) fn encode_string(s: &str) -> String {
+    let mut ret = String::new();
+
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() || "#+-.:=@_".contains(c) || !c.is_ascii() {
+            ret.push(c);
+        } else {
+            ret.push_str(&format!("\\x{:02x}", c as u8));
+        }
+    }
+
+    ret
+}
+
 pub(crate) fn check_value_format(key: &str, value: &str, nonempty: bool) -> Result<()> {
     if nonempty && value.is_empty() {
         return Err(Error::RulesLoadError {
@@ -122,7 +163,7 @@ macro_rules! device_trace {
 mod tests {
     use device::Device;
 
-    use super::check_value_format;
+    use super::{check_value_format, encode_string};
 
     #[test]
     fn test_check_value_format() {
@@ -167,6 +208,15 @@ mod tests {
         check_value_format("", "aaa$result{0}bbb", false).unwrap();
     }
 
+    #[test]
+    fn test_encode_string() {
+        assert_eq!(encode_string("My Disk/1"), "My\\x20Disk\\x2f1");
+        assert_eq!(
+            encode_string("\u{4e2d}\u{6587}-label_#1"),
+            "\u{4e2d}\u{6587}-label_#1"
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_device_trace() {
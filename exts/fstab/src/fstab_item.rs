@@ -10,8 +10,9 @@
 // NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
 // See the Mulan PSL v2 for more details.
 
-//! fstab_item encapsulates six fields and status of /etc/fstab.
+//! fstab_item encapsulates the six fields of /etc/fstab.
 
+use basic::string::octal_unescape;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
@@ -30,28 +31,58 @@ pub struct FSTabItem {
     pub dump: i8,
     /// * pass: check priority
     pub pass: i8,
-    /// * state: Mount status: `0`- not mounted, `1`- mounted successfully, `-1`- failed to mount
-    pub state: i8,
 }
 
+/// the tags which can be used in place of the device path
+const DEVICE_TAGS: [(&str, &str); 4] = [
+    ("UUID=", "/dev/disk/by-uuid/"),
+    ("LABEL=", "/dev/disk/by-label/"),
+    ("PARTUUID=", "/dev/disk/by-partuuid/"),
+    ("PARTLABEL=", "/dev/disk/by-partlabel/"),
+];
+
 impl FSTabItem {
-    /// create
+    /// create, dump and pass are optional and default to 0
     pub fn new(input: Vec<&str>) -> Self {
-        let mut real_path = String::from(input[0]);
-        if real_path.starts_with("UUID") {
-            let uuid = String::from(&real_path["UUID".len() + 1..]);
-            real_path = String::from("/dev/disk/by-uuid/") + &uuid;
+        let mut real_path = octal_unescape(input[0]);
+        for (tag, dir) in DEVICE_TAGS {
+            if let Some(value) = real_path.strip_prefix(tag) {
+                real_path = String::from(dir) + value;
+                break;
+            }
         }
         FSTabItem {
             device_spec: real_path,
-            mount_point: String::from(input[1]),
+            mount_point: octal_unescape(input[1]),
             fs_type: String::from(input[2]),
             options: String::from(input[3]),
-            dump: String::from(input[4]).parse().unwrap(),
-            pass: String::from(input[5]).parse().unwrap(),
-            state: 0,
+            dump: input.get(4).and_then(|v| v.parse().ok()).unwrap_or(0),
+            pass: input.get(5).and_then(|v| v.parse().ok()).unwrap_or(0),
         }
     }
+
+    /// whether the option is set, like `nofail`
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.split(',').any(|o| o == name)
+    }
+
+    /// the values of the option in the form of `name=value`, like `x-systemd.requires=`
+    pub fn option_values(&self, name: &str) -> Vec<String> {
+        self.options
+            .split(',')
+            .filter_map(|o| o.strip_prefix(name)?.strip_prefix('='))
+            .map(octal_unescape)
+            .collect()
+    }
+
+    /// the options passed to mount, without the ones only used by sysmaster
+    pub fn mount_options(&self) -> String {
+        self.options
+            .split(',')
+            .filter(|o| !o.is_empty() && !o.starts_with("x-systemd."))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 }
 
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
@@ -68,12 +99,16 @@ where
 pub fn parse(filename: &str) -> Vec<FSTabItem> {
     let mut res: Vec<FSTabItem> = Vec::new();
     if let Ok(lines) = read_lines(filename) {
-        for item_raw in lines.flatten() {
+        for item_raw in lines.map_while(io::Result::ok) {
             let item = item_raw.trim();
             if item.starts_with('#') || item.is_empty() {
                 continue;
             }
             let mount: Vec<&str> = item.split_whitespace().collect();
+            if mount.len() < 4 {
+                log::warn!("Ignoring the invalid line in {}: {}", filename, item);
+                continue;
+            }
             let fstab_item = FSTabItem::new(mount);
             res.push(fstab_item);
         }
@@ -89,7 +124,7 @@ mod tests {
     use std::io::prelude::*;
     use std::path::Path;
 
-    use super::parse;
+    use super::{parse, FSTabItem};
 
     #[test]
     fn it_works() {
@@ -157,4 +192,21 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_new() {
+        let item = FSTabItem::new(vec![
+            "LABEL=my\\040data",
+            "/mnt/my\\040data",
+            "xfs",
+            "nofail,x-systemd.requires=/srv,x-systemd.device-timeout=5",
+        ]);
+        assert_eq!(item.device_spec, "/dev/disk/by-label/my data");
+        assert_eq!(item.mount_point, "/mnt/my data");
+        assert_eq!((item.dump, item.pass), (0, 0));
+        assert!(item.has_option("nofail"));
+        assert!(!item.has_option("noauto"));
+        assert_eq!(item.option_values("x-systemd.requires"), vec!["/srv"]);
+        assert_eq!(item.mount_options(), "nofail");
+    }
 }
@@ -0,0 +1,391 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! generator converts the fstab entries to the mount and swap units.
+
+use crate::fstab_item::FSTabItem;
+use basic::path_lookup::LookupPaths;
+use basic::special::{LOCAL_FS_TARGET, NETWORK_ONLINE_TARGET, REMOTE_FS_TARGET, SWAP_TARGET};
+use basic::time_util::{parse_timespan, USEC_PER_SEC};
+use basic::unit_name::{unit_name_build, unit_name_from_path, unit_name_path_escape};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+
+const MOUNT_BIN: &str = "/usr/bin/mount";
+const FSCK_ROOT_SERVICE: &str = "fsck-root.service";
+const REMOUNT_ROOT_SERVICE: &str = "remount-root.service";
+const HEADER: &str = "# Automatically generated by fstab\n";
+
+/// the filesystems which need the network
+const NETWORK_FS_TYPES: [&str; 11] = [
+    "afs",
+    "ceph",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "sshfs",
+    "ncpfs",
+    "ncp",
+    "nfs",
+    "nfs4",
+    "glusterfs",
+];
+
+/// Generator writes the units to the output directory
+pub struct Generator {
+    dir: PathBuf,
+    search_path: Vec<String>,
+}
+
+impl Generator {
+    /// create the generator, search_path is used to find the fsck services
+    pub fn new(dir: &str, search_path: Vec<String>) -> Self {
+        Generator {
+            dir: PathBuf::from(dir),
+            search_path,
+        }
+    }
+
+    /// create the generator with the search path of the system
+    pub fn with_system_lookup(dir: &str) -> Self {
+        let mut lookup_path = LookupPaths::new();
+        lookup_path.init_lookup_paths();
+        Self::new(dir, lookup_path.search_path)
+    }
+
+    /// generate the unit of one fstab entry
+    pub fn add_item(&self, item: &FSTabItem) -> io::Result<()> {
+        if item.fs_type == "swap" {
+            return self.add_swap(item);
+        }
+
+        if !item.mount_point.starts_with('/') {
+            log::warn!(
+                "Ignoring {}, the mount point {} is not an absolute path",
+                item.device_spec,
+                item.mount_point
+            );
+            return Ok(());
+        }
+
+        if unit_name_path_escape(&item.mount_point) == "-" {
+            return self.add_root(item);
+        }
+        self.add_mount(item)
+    }
+
+    fn add_mount(&self, item: &FSTabItem) -> io::Result<()> {
+        let name = unit_name_from_path(&item.mount_point, ".mount");
+        let nofail = item.has_option("nofail");
+        let remote =
+            item.has_option("_netdev") || NETWORK_FS_TYPES.contains(&item.fs_type.as_str());
+        let target = if remote {
+            REMOTE_FS_TARGET
+        } else {
+            LOCAL_FS_TARGET
+        };
+
+        let mut requires = Vec::new();
+        let mut wants = Vec::new();
+        let mut after = Vec::new();
+        let mut before = Vec::new();
+        for value in item.option_values("x-systemd.requires") {
+            let unit = if value.starts_with('/') {
+                unit_name_from_path(&value, ".mount")
+            } else {
+                value
+            };
+            requires.push(unit.clone());
+            after.push(unit);
+        }
+        if remote {
+            wants.push(NETWORK_ONLINE_TARGET.to_string());
+            after.push(NETWORK_ONLINE_TARGET.to_string());
+        }
+        if item.pass > 0 {
+            if let Some(fsck) = self.fsck_service(&item.device_spec) {
+                requires.push(fsck.clone());
+                after.push(fsck);
+            }
+        }
+        if !nofail {
+            before.push(target.to_string());
+        }
+
+        let mut content = String::from(HEADER);
+        content += "[Unit]\n";
+        content += &format!("Description={}\n", quote(&item.mount_point));
+        content += &format!("Documentation={}\n", quote("man:fstab(5)"));
+        push_list(&mut content, "Wants", &wants);
+        push_list(&mut content, "Requires", &requires);
+        push_list(&mut content, "After", &after);
+        push_list(&mut content, "Before", &before);
+        content += "\n[Mount]\n";
+        content += &format!("What={}\n", quote(&item.device_spec));
+        content += &format!("Where={}\n", quote(&item.mount_point));
+        content += &format!("Type={}\n", quote(&item.fs_type));
+        content += &format!("Options={}\n", quote(&item.mount_options()));
+
+        self.write_unit(&name, &content)?;
+        self.add_device_timeout(item)?;
+        self.link(item, &name, target)
+    }
+
+    fn add_swap(&self, item: &FSTabItem) -> io::Result<()> {
+        if !item.device_spec.starts_with('/') {
+            log::warn!(
+                "Ignoring swap {}, it is not an absolute path",
+                item.device_spec
+            );
+            return Ok(());
+        }
+
+        let name = unit_name_from_path(&item.device_spec, ".swap");
+        let mut content = String::from(HEADER);
+        content += "[Unit]\n";
+        content += &format!("Description={}\n", quote(&item.device_spec));
+        content += &format!("Documentation={}\n", quote("man:fstab(5)"));
+        if !item.has_option("nofail") {
+            content += &format!("Before={}\n", quote(SWAP_TARGET));
+        }
+        content += "\n[Swap]\n";
+        content += &format!("What={}\n", quote(&item.device_spec));
+        content += &format!("Options={}\n", quote(&item.mount_options()));
+
+        self.write_unit(&name, &content)?;
+        self.add_device_timeout(item)?;
+        self.link(item, &name, SWAP_TARGET)
+    }
+
+    /// the root filesystem is mounted before sysmaster starts, remount it with the configured options
+    fn add_root(&self, item: &FSTabItem) -> io::Result<()> {
+        let mut options = String::from("remount");
+        let mount_options = item.mount_options();
+        if !mount_options.is_empty() {
+            options = format!("{options},{mount_options}");
+        }
+
+        let mut after = Vec::new();
+        if item.pass > 0 && self.unit_exists(FSCK_ROOT_SERVICE) {
+            after.push(FSCK_ROOT_SERVICE.to_string());
+        }
+
+        let mut content = String::from(HEADER);
+        content += "[Unit]\n";
+        content += &format!("Description={}\n", quote("Remount Root File System"));
+        content += &format!("Documentation={}\n", quote("man:fstab(5)"));
+        push_list(&mut content, "After", &after);
+        content += &format!("Before={}\n", quote(LOCAL_FS_TARGET));
+        content += "\n[Service]\n";
+        content += &format!("Type={}\n", quote("oneshot"));
+        content += "RemainAfterExit=true\n";
+        content += &format!(
+            "ExecStart={}\n",
+            quote(&format!("{MOUNT_BIN} / -o {options}"))
+        );
+
+        self.write_unit(REMOUNT_ROOT_SERVICE, &content)?;
+        self.add_symlink(&format!("{LOCAL_FS_TARGET}.requires"), REMOUNT_ROOT_SERVICE)
+    }
+
+    /// the fsck service of the device, if it is installed
+    fn fsck_service(&self, what: &str) -> Option<String> {
+        if !what.starts_with("/dev/") {
+            return None;
+        }
+        let template = "fsck@.service";
+        if !self.unit_exists(template) {
+            return None;
+        }
+        Some(unit_name_build(
+            "fsck",
+            &unit_name_path_escape(what),
+            "service",
+        ))
+    }
+
+    fn unit_exists(&self, name: &str) -> bool {
+        self.search_path
+            .iter()
+            .any(|dir| Path::new(dir).join(name).exists())
+    }
+
+    /// x-systemd.device-timeout= configures how long to wait for the device
+    fn add_device_timeout(&self, item: &FSTabItem) -> io::Result<()> {
+        let value = match item.option_values("x-systemd.device-timeout").pop() {
+            None => return Ok(()),
+            Some(v) => v,
+        };
+        // JobTimeoutSec is in seconds, the rest of a second is rounded up
+        let sec = match parse_timespan(&value, USEC_PER_SEC) {
+            Err(_) => {
+                log::warn!("Ignoring the invalid x-systemd.device-timeout={}", value);
+                return Ok(());
+            }
+            Ok(v) => v.div_ceil(USEC_PER_SEC),
+        };
+        if !item.device_spec.starts_with("/dev/") {
+            return Ok(());
+        }
+
+        let dropin = self.dir.join(format!(
+            "{}.d",
+            unit_name_from_path(&item.device_spec, ".device")
+        ));
+        fs::create_dir_all(&dropin)?;
+        fs::write(
+            dropin.join("50-device-timeout.conf"),
+            format!("{HEADER}[Unit]\nJobTimeoutSec={sec}\n"),
+        )
+    }
+
+    /// noauto units are only started on demand, nofail units don't fail the target
+    fn link(&self, item: &FSTabItem, name: &str, target: &str) -> io::Result<()> {
+        if item.has_option("noauto") {
+            return Ok(());
+        }
+        let dir = if item.has_option("nofail") {
+            format!("{target}.wants")
+        } else {
+            format!("{target}.requires")
+        };
+        self.add_symlink(&dir, name)
+    }
+
+    fn add_symlink(&self, dir: &str, name: &str) -> io::Result<()> {
+        let dir = self.dir.join(dir);
+        fs::create_dir_all(&dir)?;
+        symlink(self.dir.join(name), dir.join(name))
+    }
+
+    fn write_unit(&self, name: &str, content: &str) -> io::Result<()> {
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(self.dir.join(name))
+        {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                log::warn!("Unit {} is duplicated in fstab, ignoring.", name);
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+        file.write_all(content.as_bytes())
+    }
+}
+
+fn push_list(content: &mut String, key: &str, units: &[String]) {
+    if !units.is_empty() {
+        *content += &format!("{}={}\n", key, quote(&units.join(";")));
+    }
+}
+
+/// quote the value as a toml basic string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(dir: &Path, name: &str) -> String {
+        fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn test_generate() {
+        let root = std::env::temp_dir().join(format!("fstab-generator-{}", std::process::id()));
+        let (dir, lib) = (root.join("out"), root.join("lib"));
+        fs::create_dir_all(&dir).unwrap();
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(lib.join("fsck@.service"), "").unwrap();
+
+        let generator = Generator::new(
+            dir.to_str().unwrap(),
+            vec![lib.to_string_lossy().to_string()],
+        );
+        let items = [
+            vec!["/dev/sda1", "/", "ext4", "defaults", "1", "1"],
+            vec![
+                "/dev/sda2",
+                "/boot",
+                "ext4",
+                "defaults,x-systemd.device-timeout=1min",
+                "1",
+                "2",
+            ],
+            vec![
+                "srv:/export",
+                "/mnt/nfs",
+                "nfs",
+                "nofail,x-systemd.requires=/boot",
+            ],
+            vec!["/dev/sda3", "/mnt/data", "xfs", "noauto"],
+            vec!["/dev/sda4", "none", "swap", "defaults", "0", "0"],
+        ];
+        for item in items {
+            generator.add_item(&FSTabItem::new(item)).unwrap();
+        }
+
+        let root_service = read(&dir, REMOUNT_ROOT_SERVICE);
+        assert!(root_service.contains("ExecStart=\"/usr/bin/mount / -o remount,defaults\""));
+        assert!(!dir.join("-.mount").exists());
+        assert!(dir
+            .join("local-fs.target.requires")
+            .join(REMOUNT_ROOT_SERVICE)
+            .exists());
+
+        let boot = read(&dir, "boot.mount");
+        assert!(boot.contains("Requires=\"fsck@dev-sda2.service\""));
+        assert!(boot.contains("Before=\"local-fs.target\""));
+        assert!(boot.contains("Options=\"defaults\""));
+        assert!(dir.join("local-fs.target.requires/boot.mount").exists());
+        assert_eq!(
+            read(&dir, "dev-sda2.device.d/50-device-timeout.conf"),
+            format!("{HEADER}[Unit]\nJobTimeoutSec=60\n")
+        );
+
+        let nfs = read(&dir, "mnt-nfs.mount");
+        assert!(nfs.contains("Wants=\"network-online.target\""));
+        assert!(nfs.contains("Requires=\"boot.mount\""));
+        assert!(nfs.contains("After=\"boot.mount;network-online.target\""));
+        assert!(!nfs.contains("Before="));
+        assert!(dir.join("remote-fs.target.wants/mnt-nfs.mount").exists());
+
+        assert!(dir.join("mnt-data.mount").exists());
+        assert!(!dir.join("local-fs.target.requires/mnt-data.mount").exists());
+
+        let swap = read(&dir, "dev-sda4.swap");
+        assert!(swap.contains("What=\"/dev/sda4\""));
+        assert!(dir.join("swap.target.requires/dev-sda4.swap").exists());
+
+        assert!(generator
+            .add_item(&FSTabItem::new(vec![
+                "/dev/sdb1",
+                "/boot",
+                "ext4",
+                "defaults"
+            ]))
+            .is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+}
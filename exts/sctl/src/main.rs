@@ -12,6 +12,7 @@
 
 //!
 
+use basic::credential_util;
 use clap::Parser;
 use cmdproto::{
     error::ERROR_CODE_MASK_PRINT_STDOUT,
@@ -21,6 +22,7 @@ use cmdproto::{
     },
 };
 use std::io::Write;
+use std::path::Path;
 use std::{
     net::{SocketAddr, TcpStream},
     process::{ExitCode, Termination},
@@ -73,6 +75,17 @@ enum SubCmd {
         units: Vec<String>,
     },
 
+    /// [unit] remove the cache, state, logs, runtime or configuration directories of the unit
+    #[clap(display_order = 6)]
+    Clean {
+        #[clap(required = true)]
+        units: Vec<String>,
+
+        /// what to remove, one of cache, state, logs, runtime, configuration and all, cache and runtime if not given
+        #[clap(long, multiple_occurrences = true, use_value_delimiter = true)]
+        what: Vec<String>,
+    },
+
     /// [manager] list all units
     ListUnits {},
 
@@ -129,6 +142,19 @@ enum SubCmd {
         #[clap(required = true)]
         unit_file: Vec<String>,
     },
+
+    /// [credential] encrypt a credential with the host key, used by LoadCredentialEncrypted
+    EncryptCredential {
+        /// the credential id, must be the same as the one in LoadCredentialEncrypted
+        #[clap(long, required = true)]
+        name: String,
+
+        /// the file holding the plain text
+        input: String,
+
+        /// the file to write the encrypted credential to
+        output: String,
+    },
 }
 
 /// Generate CommandRequest based on parsed args
@@ -144,6 +170,8 @@ fn generate_command_request(args: Args) -> Option<CommandRequest> {
 
         SubCmd::Reload { units } => CommandRequest::new_unitcomm(unit_comm::Action::Reload, units),
 
+        SubCmd::Clean { units, what } => CommandRequest::new_unitcomm_clean(units, what),
+
         SubCmd::Mask { unit_file } => {
             CommandRequest::new_unitfile(unit_file::Action::Mask, unit_file)
         }
@@ -174,6 +202,8 @@ fn generate_command_request(args: Args) -> Option<CommandRequest> {
         SubCmd::DaemonReexec {} => CommandRequest::new_mngrcomm(mngr_comm::Action::Reexec),
 
         SubCmd::ListUnits {} => CommandRequest::new_mngrcomm(mngr_comm::Action::Listunits),
+
+        SubCmd::EncryptCredential { .. } => return None,
     };
     Some(command_request)
 }
@@ -213,9 +243,41 @@ impl Termination for Result {
     }
 }
 
+/// encrypt the credential locally, sysmaster is not involved
+fn encrypt_credential(name: &str, input: &str, output: &str) -> Result {
+    let plain = match std::fs::read(input) {
+        Ok(v) => v,
+        Err(e) => {
+            let err_msg = format!("Failed to read {input}: {e}");
+            return Result::Failure(err_msg, e.raw_os_error().unwrap_or(1) as u32);
+        }
+    };
+
+    let key_file = Path::new(credential_util::CREDENTIAL_HOST_KEY);
+    let data = match credential_util::encrypt_credential(key_file, name, &plain) {
+        Ok(v) => v,
+        Err(e) => return Result::Failure(format!("Failed to encrypt the credential: {e}"), 1),
+    };
+
+    if let Err(e) = std::fs::write(output, data) {
+        let err_msg = format!("Failed to write {output}: {e}");
+        return Result::Failure(err_msg, e.raw_os_error().unwrap_or(1) as u32);
+    }
+    Result::OK
+}
+
 fn main() -> Result {
     let args = Args::parse();
 
+    if let SubCmd::EncryptCredential {
+        name,
+        input,
+        output,
+    } = &args.subcmd
+    {
+        return encrypt_credential(name, input, output);
+    }
+
     let command_request = match generate_command_request(args) {
         None => {
             return Result::Failure(
@@ -0,0 +1,173 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Encrypt and decrypt the credentials passed to the services, the key is derived
+//! from a secret file only readable by root.
+
+use crate::error::*;
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// the secret file that the credential key is derived from
+pub const CREDENTIAL_HOST_KEY: &str = "/var/lib/sysmaster/credential.secret";
+/// the directory that the credentials of a unit are placed in
+pub const CREDENTIALS_DIR: &str = "/run/sysmaster/credentials";
+
+// the encrypted credential: magic + nonce + ciphertext with the tag
+const CREDENTIAL_MAGIC: &[u8; 8] = b"SMCRED\x01\x00";
+const NONCE_SIZE: usize = 12;
+const HOST_KEY_SIZE: usize = 32;
+
+/// the credential name is used as the file name, only letters, digits, "_", "-"
+/// and "." are allowed, and it can't be "." or ".."
+pub fn credential_name_valid(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.len() <= 255
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn random_bytes(buf: &mut [u8]) -> Result<()> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let ret = unsafe {
+            libc::getrandom(
+                buf[filled..].as_mut_ptr() as *mut libc::c_void,
+                buf.len() - filled,
+                0,
+            )
+        };
+        if ret < 0 {
+            let errno = nix::errno::Errno::last();
+            if errno == nix::errno::Errno::EINTR {
+                continue;
+            }
+            return Err(Error::Nix { source: errno });
+        }
+        filled += ret as usize;
+    }
+    Ok(())
+}
+
+// read the host key, create it if it doesn't exist and create is true
+fn host_key(path: &Path, create: bool) -> Result<Aes256Gcm> {
+    let secret = match std::fs::read(path) {
+        Ok(v) => v,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && create => {
+            let mut secret = vec![0u8; HOST_KEY_SIZE];
+            random_bytes(&mut secret)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).context(IoSnafu)?;
+            }
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o400)
+                .open(path)
+                .context(IoSnafu)?;
+            file.write_all(&secret).context(IoSnafu)?;
+            secret
+        }
+        Err(e) => return Err(Error::Io { source: e }),
+    };
+    if secret.len() < HOST_KEY_SIZE {
+        return Err(Error::Invalid {
+            what: format!("the credential host key {:?} is too short", path),
+        });
+    }
+
+    let key = Sha256::digest(&secret);
+    Aes256Gcm::new_from_slice(&key).map_err(|_| Error::Invalid {
+        what: "invalid credential key".to_string(),
+    })
+}
+
+/// encrypt the credential with the host key, the name is authenticated too, so the
+/// encrypted credential can't be used as another one. The host key is created if needed.
+pub fn encrypt_credential(key_file: &Path, name: &str, plain: &[u8]) -> Result<Vec<u8>> {
+    let cipher = host_key(key_file, true)?;
+    let mut nonce = [0u8; NONCE_SIZE];
+    random_bytes(&mut nonce)?;
+
+    let payload = Payload {
+        msg: plain,
+        aad: name.as_bytes(),
+    };
+    let encrypted = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| Error::Invalid {
+            what: format!("failed to encrypt the credential {name}"),
+        })?;
+
+    let mut data = CREDENTIAL_MAGIC.to_vec();
+    data.extend_from_slice(&nonce);
+    data.extend(encrypted);
+    Ok(data)
+}
+
+/// decrypt the credential encrypted by encrypt_credential
+pub fn decrypt_credential(key_file: &Path, name: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let header = CREDENTIAL_MAGIC.len() + NONCE_SIZE;
+    if data.len() < header || !data.starts_with(CREDENTIAL_MAGIC) {
+        return Err(Error::Invalid {
+            what: format!("the credential {name} is not encrypted by sysmaster"),
+        });
+    }
+
+    let cipher = host_key(key_file, false)?;
+    let payload = Payload {
+        msg: &data[header..],
+        aad: name.as_bytes(),
+    };
+    cipher
+        .decrypt(
+            Nonce::from_slice(&data[CREDENTIAL_MAGIC.len()..header]),
+            payload,
+        )
+        .map_err(|_| Error::Invalid {
+            what: format!("failed to decrypt the credential {name}"),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_encrypt() {
+        assert!(credential_name_valid("password"));
+        assert!(!credential_name_valid(".."));
+        assert!(!credential_name_valid("a/b"));
+        assert!(credential_name_valid("tls.key-1_a"));
+        assert!(!credential_name_valid("a b"));
+        assert!(!credential_name_valid("a\\b"));
+        assert!(!credential_name_valid("\u{5bc6}\u{94a5}"));
+        assert!(!credential_name_valid(""));
+
+        let key = std::env::temp_dir().join(format!("test-credential-{}", std::process::id()));
+        assert!(decrypt_credential(&key, "foo", b"SMCRED\x01\x00").is_err());
+
+        let data = encrypt_credential(&key, "foo", b"secret").unwrap();
+        assert_eq!(decrypt_credential(&key, "foo", &data).unwrap(), b"secret");
+        // the name is authenticated
+        assert!(decrypt_credential(&key, "bar", &data).is_err());
+        std::fs::remove_file(&key).unwrap();
+    }
+}
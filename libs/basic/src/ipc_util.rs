@@ -0,0 +1,159 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Functions to remove the IPC objects owned by a user
+
+use crate::error::*;
+use nix::unistd::{Gid, Uid};
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// the System V IPC kinds listed in /proc/sysvipc
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SysvIpc {
+    Shm,
+    Sem,
+    Msg,
+}
+
+impl SysvIpc {
+    fn proc_file(&self) -> &'static str {
+        match self {
+            SysvIpc::Shm => "/proc/sysvipc/shm",
+            SysvIpc::Sem => "/proc/sysvipc/sem",
+            SysvIpc::Msg => "/proc/sysvipc/msg",
+        }
+    }
+
+    // the column of the owner uid, the column of the owner gid follows
+    fn uid_column(&self) -> usize {
+        match self {
+            SysvIpc::Shm => 7,
+            SysvIpc::Sem => 4,
+            SysvIpc::Msg => 7,
+        }
+    }
+
+    fn remove(&self, id: i32) -> bool {
+        let ret = unsafe {
+            match self {
+                SysvIpc::Shm => libc::shmctl(id, libc::IPC_RMID, std::ptr::null_mut()),
+                SysvIpc::Sem => libc::semctl(id, 0, libc::IPC_RMID),
+                SysvIpc::Msg => libc::msgctl(id, libc::IPC_RMID, std::ptr::null_mut()),
+            }
+        };
+        ret == 0
+    }
+}
+
+/// parse the content of /proc/sysvipc/*, return the ids owned by the uid or the gid
+fn sysvipc_ids(kind: SysvIpc, content: &str, uid: Uid, gid: Gid) -> Vec<i32> {
+    let col = kind.uid_column();
+    let mut ids = Vec::new();
+    // the first line is the header
+    for line in content.lines().skip(1) {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() <= col + 1 {
+            continue;
+        }
+        let (id, owner, group) = match (
+            fields[1].parse::<i32>(),
+            fields[col].parse::<u32>(),
+            fields[col + 1].parse::<u32>(),
+        ) {
+            (Ok(i), Ok(u), Ok(g)) => (i, u, g),
+            _ => continue,
+        };
+        if owner == uid.as_raw() || group == gid.as_raw() {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+fn remove_files_owned(dir: &Path, uid: Uid, gid: Gid) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let meta = match entry.metadata() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if meta.uid() == uid.as_raw() || meta.gid() == gid.as_raw() {
+            if let Err(e) = std::fs::remove_file(entry.path()) {
+                log::warn!("Failed to remove {:?}: {e}", entry.path());
+            }
+        }
+    }
+}
+
+/// remove the System V IPC objects, the POSIX shared memory and message queues owned by the user
+pub fn remove_ipc(uid: Uid, gid: Gid) -> Result<()> {
+    // never remove the objects of root
+    if uid.is_root() || gid.as_raw() == 0 {
+        return Ok(());
+    }
+
+    for kind in [SysvIpc::Shm, SysvIpc::Sem, SysvIpc::Msg] {
+        let content = match std::fs::read_to_string(kind.proc_file()) {
+            Ok(v) => v,
+            // the kernel is built without System V IPC
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Io { source: e }),
+        };
+        for id in sysvipc_ids(kind, &content, uid, gid) {
+            if !kind.remove(id) {
+                log::warn!("Failed to remove the {:?} IPC object {id}", kind);
+            }
+        }
+    }
+
+    remove_files_owned(Path::new("/dev/shm"), uid, gid);
+    remove_files_owned(Path::new("/dev/mqueue"), uid, gid);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysvipc_ids() {
+        let shm = "       key      shmid perms                  size  cpid  lpid nattch   uid   gid  cuid  cgid      atime      dtime      ctime                   rss                  swap\n\
+                   0          1   600               524288  1000  1001      2  61184 61184  61184 61184 0 0 0 0 0\n\
+                   0          2   600               524288  1000  1001      2     0     0     0     0 0 0 0 0 0\n";
+        assert_eq!(
+            sysvipc_ids(
+                SysvIpc::Shm,
+                shm,
+                Uid::from_raw(61184),
+                Gid::from_raw(61184)
+            ),
+            vec![1]
+        );
+
+        let sem = "       key      semid perms      nsems   uid   gid  cuid  cgid      otime      ctime\n\
+                   0          5   600            1 61184 61184 61184 61184 0 0\n";
+        assert_eq!(
+            sysvipc_ids(
+                SysvIpc::Sem,
+                sem,
+                Uid::from_raw(61184),
+                Gid::from_raw(61184)
+            ),
+            vec![5]
+        );
+        assert!(sysvipc_ids(SysvIpc::Sem, sem, Uid::from_raw(1), Gid::from_raw(1)).is_empty());
+    }
+}
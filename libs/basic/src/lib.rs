@@ -13,6 +13,7 @@
 //!
 pub mod condition;
 pub mod conf_parser;
+pub mod credential_util;
 pub mod device;
 pub mod env_cargo;
 pub mod errno_util;
@@ -21,6 +22,7 @@ pub mod fd_util;
 pub mod file_util;
 pub mod fs_util;
 pub mod io_util;
+pub mod ipc_util;
 pub mod logger;
 pub mod macros;
 pub mod mount_util;
@@ -29,7 +31,9 @@ pub mod path_lookup;
 pub mod path_util;
 pub mod proc_cmdline;
 pub mod process_util;
+pub mod ratelimit;
 pub mod rlimit_util;
+pub mod seccomp_util;
 pub mod security;
 pub mod show_table;
 pub mod socket_util;
@@ -37,6 +41,7 @@ pub mod special;
 pub mod stat_util;
 pub mod string;
 pub mod time_util;
+pub mod unit_name;
 pub mod user_group_util;
 pub mod virtualize;
 pub use error::*;
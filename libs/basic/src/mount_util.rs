@@ -12,10 +12,15 @@
 
 //!
 use crate::error::*;
+use crate::string::octal_unescape;
+use libmount::mountinfo;
 use nix::{
     fcntl::AtFlags,
+    mount::MsFlags,
     sys::stat::{fstatat, SFlag},
 };
+use std::fs;
+use std::path::{Path, PathBuf};
 
 ///
 pub fn mount_point_fd_valid(fd: i32, file_name: &str, flags: AtFlags) -> Result<bool> {
@@ -40,3 +45,90 @@ pub fn mount_point_fd_valid(fd: i32, file_name: &str, flags: AtFlags) -> Result<
 
     Ok(f_stat.st_dev != d_stat.st_dev)
 }
+
+/// parse the content of /proc/swaps, return the active swaps in the order they are turned on
+pub fn parse_proc_swaps(content: &str) -> Vec<String> {
+    // the first line is the header: Filename Type Size Used Priority
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(octal_unescape)
+        .collect()
+}
+
+/// parse the content of /proc/self/mountinfo, return the mount points and their mount flags
+/// in the order they are mounted
+pub fn parse_mountinfo(content: &str) -> Vec<(PathBuf, MsFlags)> {
+    mountinfo::Parser::new(content.as_bytes())
+        .filter_map(|mount| match mount {
+            Ok(mount) => {
+                let flags = MsFlags::from_bits_truncate(mount.get_flags());
+                Some((PathBuf::from(mount.mount_point.into_owned()), flags))
+            }
+            Err(e) => {
+                log::debug!("Failed to parse the mount info: {e}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// the file system types mounted at the path, in the order of /proc/self/mountinfo
+pub fn mount_point_fstypes(path: &str) -> Vec<String> {
+    let content = match fs::read_to_string("/proc/self/mountinfo") {
+        Ok(c) => c,
+        Err(_) => return Vec::new(),
+    };
+
+    mountinfo::Parser::new(content.as_bytes())
+        .filter_map(|m| m.ok())
+        .filter(|m| Path::new(&m.mount_point) == Path::new(path))
+        .map(|m| m.fstype.to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let content = "22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw\n\
+                       23 22 0:5 / /dev rw,nosuid shared:2 - devtmpfs devtmpfs rw\n\
+                       24 22 0:6 / /mnt/a\\040b ro,nosuid,nodev,noexec shared:3 - tmpfs tmpfs rw\n\
+                       25 22 0:7 / /mnt/a\\134040b rw - tmpfs tmpfs rw\n";
+        let mounts = parse_mountinfo(content);
+        assert_eq!(mounts.len(), 4);
+        assert_eq!(mounts[0], (PathBuf::from("/"), MsFlags::MS_RELATIME));
+        assert_eq!(mounts[1], (PathBuf::from("/dev"), MsFlags::MS_NOSUID));
+        assert_eq!(
+            mounts[2],
+            (
+                PathBuf::from("/mnt/a b"),
+                MsFlags::MS_RDONLY | MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC
+            )
+        );
+        assert_eq!(mounts[3], (PathBuf::from("/mnt/a\\040b"), MsFlags::empty()));
+    }
+
+    #[test]
+    fn test_mount_point_fstypes() {
+        assert!(!mount_point_fstypes("/").is_empty());
+        assert!(mount_point_fstypes("/not/a/mount/point").is_empty());
+    }
+
+    #[test]
+    fn test_parse_proc_swaps() {
+        let content = "Filename\t\t\t\tType\t\tSize\t\tUsed\t\tPriority
+/dev/dm-1                               partition\t8388604\t\t0\t\t-2
+/swap\\040file                            file\t\t1048572\t\t0\t\t10
+/a\\134040b                              file\t\t1048572\t\t0\t\t-3
+";
+        assert_eq!(
+            parse_proc_swaps(content),
+            vec!["/dev/dm-1", "/swap file", "/a\\040b"]
+        );
+        assert!(parse_proc_swaps("Filename Type Size Used Priority\n").is_empty());
+    }
+}
@@ -71,11 +71,11 @@ pub fn parse_ifindex(s: String) -> Result<u32> {
     })
 }
 
-/// parse the string into mode_t
+/// parse the octal string into mode_t
 pub fn parse_mode(mode: &str) -> Result<mode_t> {
-    match mode.parse::<mode_t>() {
+    match mode_t::from_str_radix(mode, 8) {
         Ok(v) => {
-            if v > 7777 {
+            if v > 0o7777 {
                 return Err(Error::Nix {
                     source: nix::errno::Errno::ERANGE,
                 });
@@ -88,3 +88,65 @@ pub fn parse_mode(mode: &str) -> Result<mode_t> {
         }),
     }
 }
+
+/// parse the size like string with an optional binary suffix into bytes,
+/// e.g. "512", "4K", "1G", the suffixes are based on 1024
+pub fn parse_size(s: &str) -> Result<u64> {
+    let s = s.trim();
+    let pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = s.split_at(pos);
+    if number.is_empty() {
+        return Err(Error::Invalid {
+            what: format!("invalid size: {}", s),
+        });
+    }
+
+    let factor: u64 = match suffix.trim() {
+        "" | "B" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        "P" => 1 << 50,
+        "E" => 1 << 60,
+        _ => {
+            return Err(Error::Invalid {
+                what: format!("invalid size suffix: {}", s),
+            })
+        }
+    };
+
+    number
+        .parse::<u64>()?
+        .checked_mul(factor)
+        .ok_or(Error::Nix {
+            source: nix::errno::Errno::ERANGE,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("0660").unwrap(), 0o660);
+        assert_eq!(parse_mode("755").unwrap(), 0o755);
+        assert!(parse_mode("0800").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("512B").unwrap(), 512);
+        assert_eq!(parse_size("4K").unwrap(), 4096);
+        assert_eq!(parse_size("2M").unwrap(), 2 * 1024 * 1024);
+        assert_eq!(parse_size("1G").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("K").is_err());
+        assert!(parse_size("4X").is_err());
+        assert!(parse_size("-4K").is_err());
+        assert!(parse_size("100E").is_err());
+    }
+}
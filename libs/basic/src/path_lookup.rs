@@ -19,6 +19,12 @@ pub const ETC_SYSTEM_PATH: &str = "/etc/sysmaster";
 pub const RUN_SYSTEM_PATH: &str = "/run/sysmaster";
 /// unit lookup path in /usr/lib
 pub const LIB_SYSTEM_PATH: &str = "/usr/lib/sysmaster";
+/// the output directory of generators, lower priority than the unit lookup paths above
+pub const GENERATOR_PATH: &str = "/run/sysmaster/generator";
+/// the output directory of generators, higher priority than the unit lookup paths above
+pub const GENERATOR_EARLY_PATH: &str = "/run/sysmaster/generator.early";
+/// the output directory of generators, the lowest priority
+pub const GENERATOR_LATE_PATH: &str = "/run/sysmaster/generator.late";
 
 /// struct LookupPaths
 #[derive(Debug, Clone)]
@@ -77,9 +83,17 @@ impl LookupPaths {
             self.search_path.push(tmp_str[0].to_string());
             self.preset_path.push(tmp_str[0].to_string());
         }
+        self.generator = GENERATOR_PATH.to_string();
+        self.generator_early = GENERATOR_EARLY_PATH.to_string();
+        self.generator_late = GENERATOR_LATE_PATH.to_string();
+
+        // the earlier path has the higher priority
+        self.search_path.push(self.generator_early.clone());
         self.search_path.push(LIB_SYSTEM_PATH.to_string());
         self.search_path.push(RUN_SYSTEM_PATH.to_string());
         self.search_path.push(ETC_SYSTEM_PATH.to_string());
+        self.search_path.push(self.generator.clone());
+        self.search_path.push(self.generator_late.clone());
 
         self.preset_path
             .push(format!("{}/{}", ETC_SYSTEM_PATH, "system-preset"));
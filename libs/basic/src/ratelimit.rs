@@ -0,0 +1,99 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! A simple rate limiter: at most `burst` events are allowed in every `interval` microseconds
+
+use std::time::Instant;
+
+/// the rate limiter, it's disabled if the interval or the burst is 0
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimit {
+    interval: u64,
+    burst: u32,
+    begin: Option<Instant>,
+    nums: u32,
+}
+
+impl RateLimit {
+    /// create a rate limiter with the interval in microseconds
+    pub fn new(interval: u64, burst: u32) -> Self {
+        RateLimit {
+            interval,
+            burst,
+            begin: None,
+            nums: 0,
+        }
+    }
+
+    /// count one event, return false if the limit is hit in the current interval
+    pub fn ratelimit_below(&mut self) -> bool {
+        if !self.ratelimit_enabled() {
+            return true;
+        }
+
+        let now = Instant::now();
+        if self.begin.is_none()
+            || now.duration_since(self.begin.unwrap()).as_micros() > self.interval as u128
+        {
+            self.begin = Some(now);
+            self.nums = 1;
+            return true;
+        }
+
+        if self.nums < self.burst {
+            self.nums += 1;
+            return true;
+        }
+
+        false
+    }
+
+    /// return true if both the interval and the burst are set
+    pub fn ratelimit_enabled(&self) -> bool {
+        self.interval > 0 && self.burst > 0
+    }
+
+    /// the microseconds left before the current interval ends
+    pub fn ratelimit_left(&self) -> u64 {
+        match self.begin {
+            None => 0,
+            Some(begin) => {
+                let elapsed = Instant::now().duration_since(begin).as_micros() as u64;
+                self.interval.saturating_sub(elapsed)
+            }
+        }
+    }
+
+    /// update the interval and the burst, the counting in the current interval is kept
+    pub fn init_from_config(&mut self, interval: u64, burst: u32) {
+        self.interval = interval;
+        self.burst = burst;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimit;
+    #[test]
+    fn test_ratelimit() {
+        let mut tmp = RateLimit::new(0, 0);
+        assert!(tmp.ratelimit_below());
+        assert_eq!(tmp.ratelimit_left(), 0);
+
+        let mut tmp2 = RateLimit::new(3000000, 2);
+        assert!(tmp2.ratelimit_below());
+        assert!(tmp2.ratelimit_below());
+        assert!(!tmp2.ratelimit_below());
+        assert!(tmp2.ratelimit_left() > 0);
+        assert!(tmp2.ratelimit_left() <= 3000000);
+    }
+}
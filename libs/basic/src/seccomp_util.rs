@@ -0,0 +1,338 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! A small seccomp filter builder: the listed system calls fail with an errno when
+//! their arguments match, all the others are allowed. The rules are written with the
+//! native system call numbers, so the calls of the other architectures, like the compat
+//! i386 ones and the x32 ones on x86_64, kill the process instead of bypassing them.
+
+use crate::error::*;
+use libc::sock_filter;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH: u32 = 0xc000_00f3;
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+const AUDIT_ARCH: u32 = 0;
+
+// the x32 system calls share AUDIT_ARCH_X86_64, they are told apart by this bit
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+// the offsets in struct seccomp_data
+const DATA_NR: u32 = 0;
+const DATA_ARCH: u32 = 4;
+const DATA_ARGS: u32 = 16;
+
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+
+/// when the rule matches, the system call arguments are compared with the low 32 bits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SeccompArg {
+    /// match all the calls
+    Any,
+    /// match if arg & mask == value
+    MaskedEq(u8, u32, u32),
+    /// match if arg & mask != value
+    MaskedNe(u8, u32, u32),
+    /// match if arg is one of the values
+    In(u8, Vec<u32>),
+    /// match if arg is none of the values
+    NotIn(u8, Vec<u32>),
+}
+
+/// a seccomp filter made of rules
+#[derive(Default)]
+pub struct SeccompFilter {
+    rules: Vec<(i64, SeccompArg, i32)>,
+}
+
+fn stmt(code: u32, k: u32) -> sock_filter {
+    sock_filter {
+        code: code as u16,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
+    sock_filter {
+        code: code as u16,
+        jt,
+        jf,
+        k,
+    }
+}
+
+fn load(offset: u32) -> sock_filter {
+    stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, offset)
+}
+
+fn jeq(k: u32, jt: u8, jf: u8) -> sock_filter {
+    jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, k, jt, jf)
+}
+
+fn load_arg(index: u8) -> sock_filter {
+    let offset = DATA_ARGS + 8 * index as u32;
+    // the low 32 bits of the 64 bits argument
+    #[cfg(target_endian = "big")]
+    let offset = offset + 4;
+    load(offset)
+}
+
+impl SeccompFilter {
+    /// create an empty filter that allows all the calls
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// fail the system call with errno if the argument matches
+    pub fn add_rule(&mut self, nr: i64, arg: SeccompArg, errno: i32) {
+        self.rules.push((nr, arg, errno));
+    }
+
+    /// return true if no rule is added
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn program(&self) -> Result<Vec<sock_filter>> {
+        let kill = stmt(libc::BPF_RET | libc::BPF_K, SECCOMP_RET_KILL_PROCESS);
+        #[allow(unused_mut)]
+        let mut prog = vec![load(DATA_ARCH), jeq(AUDIT_ARCH, 1, 0), kill];
+        #[cfg(target_arch = "x86_64")]
+        prog.extend([
+            load(DATA_NR),
+            jump(
+                libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                1,
+            ),
+            kill,
+        ]);
+
+        for (nr, arg, errno) in &self.rules {
+            let ret = stmt(
+                libc::BPF_RET | libc::BPF_K,
+                libc::SECCOMP_RET_ERRNO | (*errno as u32 & 0xffff),
+            );
+            let mut body = Vec::new();
+            match arg {
+                SeccompArg::Any => body.push(ret),
+                SeccompArg::MaskedEq(i, mask, value) | SeccompArg::MaskedNe(i, mask, value) => {
+                    let (jt, jf) = match arg {
+                        SeccompArg::MaskedEq(..) => (0, 1),
+                        _ => (1, 0),
+                    };
+                    body.push(load_arg(*i));
+                    body.push(stmt(libc::BPF_ALU | libc::BPF_AND | libc::BPF_K, *mask));
+                    body.push(jeq(*value, jt, jf));
+                    body.push(ret);
+                }
+                SeccompArg::In(i, values) | SeccompArg::NotIn(i, values) => {
+                    if values.len() >= u8::MAX as usize {
+                        return Err(Error::Invalid {
+                            what: "too many values in the seccomp rule".to_string(),
+                        });
+                    }
+                    let n = values.len();
+                    body.push(load_arg(*i));
+                    for (j, v) in values.iter().enumerate() {
+                        // jump to the return for In, and over it for NotIn
+                        body.push(jeq(*v, (n - j) as u8, 0));
+                    }
+                    if matches!(arg, SeccompArg::In(..)) {
+                        body.push(stmt(libc::BPF_JMP | libc::BPF_JA, 1));
+                    }
+                    body.push(ret);
+                }
+            }
+
+            if body.len() >= u8::MAX as usize {
+                return Err(Error::Invalid {
+                    what: "the seccomp rule is too long".to_string(),
+                });
+            }
+            prog.push(load(DATA_NR));
+            prog.push(jeq(*nr as u32, 0, body.len() as u8));
+            prog.extend(body);
+        }
+
+        prog.push(stmt(libc::BPF_RET | libc::BPF_K, SECCOMP_RET_ALLOW));
+        Ok(prog)
+    }
+
+    /// install the filter to the current thread, it's kept across execve(2). Without
+    /// CAP_SYS_ADMIN, no_new_privs must be set before.
+    pub fn load(&self) -> Result<()> {
+        if self.rules.is_empty() {
+            return Ok(());
+        }
+        if AUDIT_ARCH == 0 {
+            return Err(Error::Invalid {
+                what: "seccomp is not supported on this architecture".to_string(),
+            });
+        }
+
+        let mut prog = self.program()?;
+        let fprog = libc::sock_fprog {
+            len: prog.len() as u16,
+            filter: prog.as_mut_ptr(),
+        };
+        let ret = unsafe {
+            libc::prctl(
+                libc::PR_SET_SECCOMP,
+                libc::SECCOMP_MODE_FILTER,
+                &fprog as *const libc::sock_fprog,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::Nix {
+                source: nix::Error::last(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AUDIT_ARCH_I386: u32 = 0x4000_0003;
+
+    // run the program against the system call like the kernel does
+    fn run(prog: &[sock_filter], arch: u32, nr: i64, args: [u32; 6]) -> u32 {
+        // struct seccomp_data in 32 bits words: nr, arch, instruction_pointer, args
+        let mut data = [0u32; 16];
+        data[(DATA_NR / 4) as usize] = nr as u32;
+        data[(DATA_ARCH / 4) as usize] = arch;
+        for (i, arg) in args.iter().enumerate() {
+            data[(load_arg(i as u8).k / 4) as usize] = *arg;
+        }
+
+        let mut acc = 0u32;
+        let mut pc = 0;
+        loop {
+            let ins = prog[pc];
+            pc += 1;
+            let code = ins.code as u32;
+            let jump = |cond: bool| if cond { ins.jt } else { ins.jf } as usize;
+            if code == libc::BPF_LD | libc::BPF_W | libc::BPF_ABS {
+                acc = data[(ins.k / 4) as usize];
+            } else if code == libc::BPF_ALU | libc::BPF_AND | libc::BPF_K {
+                acc &= ins.k;
+            } else if code == libc::BPF_JMP | libc::BPF_JA {
+                pc += ins.k as usize;
+            } else if code == libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K {
+                pc += jump(acc == ins.k);
+            } else if code == libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K {
+                pc += jump(acc >= ins.k);
+            } else if code == libc::BPF_RET | libc::BPF_K {
+                return ins.k;
+            } else {
+                panic!("unexpected instruction {code:#x}");
+            }
+        }
+    }
+
+    fn errno(errno: i32) -> u32 {
+        libc::SECCOMP_RET_ERRNO | errno as u32
+    }
+
+    #[test]
+    fn test_seccomp_program() {
+        let mut filter = SeccompFilter::new();
+        assert!(filter.is_empty());
+        let prog = filter.program().unwrap();
+        assert_eq!(
+            run(&prog, AUDIT_ARCH, libc::SYS_getpid, [0; 6]),
+            SECCOMP_RET_ALLOW
+        );
+
+        filter.add_rule(libc::SYS_syslog, SeccompArg::Any, libc::EPERM);
+        filter.add_rule(
+            libc::SYS_mprotect,
+            SeccompArg::MaskedEq(2, libc::PROT_EXEC as u32, libc::PROT_EXEC as u32),
+            libc::EPERM,
+        );
+        filter.add_rule(
+            libc::SYS_socket,
+            SeccompArg::In(0, vec![libc::AF_PACKET as u32, libc::AF_NETLINK as u32]),
+            libc::EAFNOSUPPORT,
+        );
+        filter.add_rule(
+            libc::SYS_socket,
+            SeccompArg::NotIn(0, vec![libc::AF_UNIX as u32, libc::AF_INET as u32]),
+            libc::EPROTONOSUPPORT,
+        );
+        let prog = filter.program().unwrap();
+
+        let native = |nr, args| run(&prog, AUDIT_ARCH, nr, args);
+        assert_eq!(native(libc::SYS_getpid, [0; 6]), SECCOMP_RET_ALLOW);
+        assert_eq!(native(libc::SYS_syslog, [0; 6]), errno(libc::EPERM));
+
+        let prot = (libc::PROT_READ | libc::PROT_EXEC) as u32;
+        assert_eq!(
+            native(libc::SYS_mprotect, [0, 0, prot, 0, 0, 0]),
+            errno(libc::EPERM)
+        );
+        let prot = libc::PROT_READ as u32;
+        assert_eq!(
+            native(libc::SYS_mprotect, [0, 0, prot, 0, 0, 0]),
+            SECCOMP_RET_ALLOW
+        );
+
+        let socket = |family: i32| native(libc::SYS_socket, [family as u32, 0, 0, 0, 0, 0]);
+        assert_eq!(socket(libc::AF_NETLINK), errno(libc::EAFNOSUPPORT));
+        assert_eq!(socket(libc::AF_INET6), errno(libc::EPROTONOSUPPORT));
+        assert_eq!(socket(libc::AF_UNIX), SECCOMP_RET_ALLOW);
+        assert_eq!(socket(libc::AF_INET), SECCOMP_RET_ALLOW);
+    }
+
+    #[test]
+    fn test_seccomp_foreign_arch() {
+        let mut filter = SeccompFilter::new();
+        filter.add_rule(libc::SYS_syslog, SeccompArg::Any, libc::EPERM);
+        let prog = filter.program().unwrap();
+
+        // the numbers of the compat system calls differ, like socketcall(2) of i386,
+        // none of them can be checked by the rules
+        for nr in [libc::SYS_syslog, libc::SYS_getpid, 102] {
+            assert_eq!(
+                run(&prog, AUDIT_ARCH_I386, nr, [0; 6]),
+                SECCOMP_RET_KILL_PROCESS
+            );
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(
+            run(
+                &prog,
+                AUDIT_ARCH,
+                libc::SYS_syslog | X32_SYSCALL_BIT as i64,
+                [0; 6]
+            ),
+            SECCOMP_RET_KILL_PROCESS
+        );
+    }
+}
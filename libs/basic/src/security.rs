@@ -14,12 +14,18 @@
 
 #[allow(unused_imports)]
 use std::{
+    ffi::CString,
     fs::File,
-    io::{BufReader, Read},
-    os::{raw::c_int, unix::prelude::AsRawFd},
-    path::Path,
+    io::{BufReader, Read, Write},
+    os::{
+        raw::c_int,
+        unix::prelude::{AsRawFd, OsStrExt, RawFd},
+    },
+    path::{Path, PathBuf},
 };
 
+use crate::error::*;
+
 use nix::{
     errno::Errno,
     sys::{
@@ -130,3 +136,148 @@ pub fn uefi_secureboot_enabled() -> bool {
 pub fn tpm2_enabled() -> bool {
     Path::new("/sys/class/tpmrm").exists()
 }
+
+const PROC_ATTR: &str = "/proc/thread-self/attr";
+
+const SELINUX_FS: &str = "/sys/fs/selinux";
+
+// the attribute of the LSM, the per LSM directory of the stacking kernels is preferred
+fn proc_attr(lsm: &str, attr: &str) -> PathBuf {
+    let stacked = Path::new(PROC_ATTR).join(lsm).join(attr);
+    match stacked.exists() {
+        true => stacked,
+        false => Path::new(PROC_ATTR).join(attr),
+    }
+}
+
+// the attribute must be written in one write(2)
+fn write_proc_attr(path: &Path, value: &str) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .context(IoSnafu)?;
+    file.write_all(value.as_bytes()).context(IoSnafu)
+}
+
+fn trim_label(buf: &[u8]) -> String {
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..end]).trim().to_string()
+}
+
+/// set the selinux context of the next execve(2), like setexeccon(3)
+pub fn selinux_set_exec_context(context: &str) -> Result<()> {
+    write_proc_attr(&Path::new(PROC_ATTR).join("exec"), context)
+}
+
+/// return the selinux context of the current process, like getcon(3)
+pub fn selinux_current_context() -> Result<String> {
+    let buf = std::fs::read(Path::new(PROC_ATTR).join("current")).context(IoSnafu)?;
+    Ok(trim_label(&buf))
+}
+
+/// return the selinux context of the file, like getfilecon(3)
+pub fn selinux_file_context(path: &Path) -> Result<String> {
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::Invalid {
+        what: format!("invalid path {:?}", path),
+    })?;
+    let name = CString::new("security.selinux").unwrap();
+    let mut buf = [0u8; 256];
+    let ret = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+        )
+    };
+    if ret < 0 {
+        return Err(Error::Nix {
+            source: Errno::last(),
+        });
+    }
+    Ok(trim_label(&buf[..ret as usize]))
+}
+
+/// return the selinux context of the peer of the connected socket, like getpeercon(3)
+pub fn selinux_peer_context(fd: RawFd) -> Result<String> {
+    let mut buf = [0u8; 256];
+    let mut len = buf.len() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERSEC,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::Nix {
+            source: Errno::last(),
+        });
+    }
+    Ok(trim_label(&buf[..len as usize]))
+}
+
+/// compute the context of the new object of the class, like security_compute_create(3)
+pub fn selinux_compute_create(scon: &str, tcon: &str, class: &str) -> Result<String> {
+    let root = Path::new(SELINUX_FS);
+    let index =
+        std::fs::read_to_string(root.join("class").join(class).join("index")).context(IoSnafu)?;
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(root.join("create"))
+        .context(IoSnafu)?;
+    file.write_all(format!("{} {} {}", scon, tcon, index.trim()).as_bytes())
+        .context(IoSnafu)?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).context(IoSnafu)?;
+    Ok(trim_label(&buf))
+}
+
+/// replace the MLS range of the context with the one of the other context, the context
+/// is kept if the other one has no range
+pub fn selinux_with_range_of(context: &str, other: &str) -> String {
+    let range = match other.splitn(4, ':').nth(3) {
+        None => return context.to_string(),
+        Some(r) => r,
+    };
+    let base: Vec<&str> = context.splitn(4, ':').take(3).collect();
+    format!("{}:{}", base.join(":"), range)
+}
+
+/// set the apparmor profile of the next execve(2), like aa_change_onexec(2)
+pub fn apparmor_change_onexec(profile: &str) -> Result<()> {
+    write_proc_attr(&proc_attr("apparmor", "exec"), &format!("exec {}", profile))
+}
+
+/// set the smack label of the current process, it's kept across execve(2). Smack has no
+/// label for the next execve(2), so this needs CAP_MAC_ADMIN.
+pub fn smack_set_process_label(label: &str) -> Result<()> {
+    write_proc_attr(&proc_attr("smack", "current"), label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selinux_with_range_of() {
+        assert_eq!(
+            selinux_with_range_of(
+                "system_u:system_r:foo_t:s0",
+                "user_u:user_r:user_t:s0-s0:c1"
+            ),
+            "system_u:system_r:foo_t:s0-s0:c1"
+        );
+        assert_eq!(
+            selinux_with_range_of("system_u:system_r:foo_t:s0", "user_u:user_r:user_t"),
+            "system_u:system_r:foo_t:s0"
+        );
+        assert_eq!(
+            selinux_with_range_of("system_u:system_r:foo_t", "user_u:user_r:user_t:s1"),
+            "system_u:system_r:foo_t:s1"
+        );
+    }
+}
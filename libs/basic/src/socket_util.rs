@@ -16,7 +16,7 @@ use nix::{
     errno::Errno,
     sys::socket::{self, sockopt, AddressFamily},
 };
-use std::{os::unix::prelude::RawFd, path::Path};
+use std::{ffi::OsString, os::unix::prelude::RawFd, path::Path};
 
 ///
 pub fn ipv6_is_supported() -> bool {
@@ -88,3 +88,78 @@ pub fn set_keepalive_probes(fd: RawFd, v: u32) -> Result<()> {
 pub fn set_broadcast_state(fd: RawFd, v: bool) -> Result<()> {
     socket::setsockopt(fd, sockopt::Broadcast, &v).context(NixSnafu)
 }
+
+fn setsockopt_int(fd: RawFd, level: libc::c_int, name: libc::c_int, v: libc::c_int) -> Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &v as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    Errno::result(ret).map(drop).context(NixSnafu)
+}
+
+/// Set whether the IPv6 socket only accepts IPv6 connections
+pub fn set_ipv6_only(fd: RawFd, v: bool) -> Result<()> {
+    socket::setsockopt(fd, sockopt::Ipv6V6Only, &v).context(NixSnafu)
+}
+
+/// Bind the socket to the network interface
+pub fn set_bind_to_device(fd: RawFd, ifname: &str) -> Result<()> {
+    socket::setsockopt(fd, sockopt::BindToDevice, &OsString::from(ifname)).context(NixSnafu)
+}
+
+/// Allow binding to an address which is not configured yet
+pub fn set_free_bind(fd: RawFd, family: AddressFamily, v: bool) -> Result<()> {
+    match family {
+        AddressFamily::Inet => setsockopt_int(fd, libc::IPPROTO_IP, libc::IP_FREEBIND, v as i32),
+        AddressFamily::Inet6 => {
+            setsockopt_int(fd, libc::IPPROTO_IPV6, libc::IPV6_FREEBIND, v as i32)
+        }
+        _ => Err(Error::Nix {
+            source: Errno::EAFNOSUPPORT,
+        }),
+    }
+}
+
+/// Allow binding to a non-local address, used by transparent proxies
+pub fn set_transparent(fd: RawFd, family: AddressFamily, v: bool) -> Result<()> {
+    match family {
+        AddressFamily::Inet => socket::setsockopt(fd, sockopt::IpTransparent, &v).context(NixSnafu),
+        AddressFamily::Inet6 => {
+            setsockopt_int(fd, libc::IPPROTO_IPV6, libc::IPV6_TRANSPARENT, v as i32)
+        }
+        _ => Err(Error::Nix {
+            source: Errno::EAFNOSUPPORT,
+        }),
+    }
+}
+
+/// Allow multiple sockets to bind to the same address and port
+pub fn set_reuse_port(fd: RawFd, v: bool) -> Result<()> {
+    socket::setsockopt(fd, sockopt::ReusePort, &v).context(NixSnafu)
+}
+
+/// Set the type of service field of the outgoing IPv4 packets
+pub fn set_ip_tos(fd: RawFd, v: i32) -> Result<()> {
+    setsockopt_int(fd, libc::IPPROTO_IP, libc::IP_TOS, v)
+}
+
+/// Set the time to live or hop limit of the outgoing packets
+pub fn set_ip_ttl(fd: RawFd, family: AddressFamily, v: i32) -> Result<()> {
+    match family {
+        AddressFamily::Inet => socket::setsockopt(fd, sockopt::Ipv4Ttl, &v).context(NixSnafu),
+        AddressFamily::Inet6 => socket::setsockopt(fd, sockopt::Ipv6Ttl, &v).context(NixSnafu),
+        _ => Err(Error::Nix {
+            source: Errno::EAFNOSUPPORT,
+        }),
+    }
+}
+
+/// Set the firewall mark of the outgoing packets
+pub fn set_mark(fd: RawFd, v: u32) -> Result<()> {
+    socket::setsockopt(fd, sockopt::Mark, &v).context(NixSnafu)
+}
@@ -23,6 +23,14 @@ pub const SOCKETS_TARGET: &str = "sockets.target";
 pub const SYSINIT_TARGET: &str = "sysinit.target";
 /// the basic start target
 pub const BASIC_TARGET: &str = "basic.target";
+/// the local file systems target
+pub const LOCAL_FS_TARGET: &str = "local-fs.target";
+/// the remote file systems target
+pub const REMOTE_FS_TARGET: &str = "remote-fs.target";
+/// the swap target
+pub const SWAP_TARGET: &str = "swap.target";
+/// the target reached when the network is up
+pub const NETWORK_ONLINE_TARGET: &str = "network-online.target";
 
 /// Special user boot targets */
 pub const MULTI_USER_TARGET: &str = "multi-user.target";
@@ -20,3 +20,46 @@ pub fn new_line_break(s: &mut String) {
         *s += "\n";
     }
 }
+
+/// Decode the octal escapes like `\040` for the space, which the kernel and
+/// /etc/fstab use for the space, tab, newline and backslash in a path.
+/// An escape whose value does not fit in a byte is kept as it is.
+pub fn octal_unescape(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let digits = bytes.get(i + 1..i + 4).unwrap_or_default();
+        if bytes[i] == b'\\'
+            && digits.len() == 3
+            && digits.iter().all(|d| (b'0'..=b'7').contains(d))
+        {
+            let v = digits
+                .iter()
+                .fold(0u32, |acc, d| acc * 8 + u32::from(d - b'0'));
+            if let Ok(v) = u8::try_from(v) {
+                res.push(v);
+                i += 4;
+                continue;
+            }
+        }
+        res.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&res).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::octal_unescape;
+
+    #[test]
+    fn test_octal_unescape() {
+        assert_eq!(octal_unescape("/swap\\040file"), "/swap file");
+        assert_eq!(octal_unescape("a\\011b\\"), "a\tb\\");
+        assert_eq!(octal_unescape("\\08x"), "\\08x");
+        assert_eq!(octal_unescape("\\777"), "\\777");
+        // an escaped backslash is not decoded twice
+        assert_eq!(octal_unescape("a\\134040b"), "a\\040b");
+    }
+}
@@ -11,12 +11,23 @@
 // See the Mulan PSL v2 for more details.
 
 //!
+use crate::error::*;
 use std::time::SystemTime;
 
 const USEC_INFINITY: u128 = u128::MAX;
 
 /// usec per sec
 pub const USEC_PER_SEC: u64 = 1000000;
+/// usec per msec
+pub const USEC_PER_MSEC: u64 = 1000;
+/// usec per minute
+pub const USEC_PER_MINUTE: u64 = 60 * USEC_PER_SEC;
+/// usec per hour
+pub const USEC_PER_HOUR: u64 = 60 * USEC_PER_MINUTE;
+/// usec per day
+pub const USEC_PER_DAY: u64 = 24 * USEC_PER_HOUR;
+/// usec per week
+pub const USEC_PER_WEEK: u64 = 7 * USEC_PER_DAY;
 
 ///
 pub fn timespec_load(systime: SystemTime) -> u128 {
@@ -25,3 +36,83 @@ pub fn timespec_load(systime: SystemTime) -> u128 {
         Err(_) => USEC_INFINITY,
     }
 }
+
+fn timespan_unit(unit: &str) -> Option<u64> {
+    let usec = match unit {
+        "us" | "usec" => 1,
+        "ms" | "msec" => USEC_PER_MSEC,
+        "s" | "sec" | "second" | "seconds" => USEC_PER_SEC,
+        "m" | "min" | "minute" | "minutes" => USEC_PER_MINUTE,
+        "h" | "hr" | "hour" | "hours" => USEC_PER_HOUR,
+        "d" | "day" | "days" => USEC_PER_DAY,
+        "w" | "week" | "weeks" => USEC_PER_WEEK,
+        _ => return None,
+    };
+    Some(usec)
+}
+
+/// parse the time span like "1min 30s" into usec, a number without unit is
+/// multiplied by default_unit, which is also in usec
+pub fn parse_timespan(s: &str, default_unit: u64) -> Result<u64> {
+    let mut s = s.trim();
+    if s.is_empty() {
+        return Err(Error::Invalid {
+            what: "empty time span".to_string(),
+        });
+    }
+
+    let mut total: u64 = 0;
+    while !s.is_empty() {
+        let pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        if pos == 0 {
+            return Err(Error::Invalid {
+                what: format!("invalid time span: {}", s),
+            });
+        }
+        let number = s[..pos].parse::<u64>()?;
+        s = s[pos..].trim_start();
+
+        let end = s
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(s.len());
+        let unit = match &s[..end] {
+            "" => default_unit,
+            unit => timespan_unit(unit).ok_or(Error::Invalid {
+                what: format!("invalid time unit: {}", unit),
+            })?,
+        };
+        s = s[end..].trim_start();
+
+        total = number
+            .checked_mul(unit)
+            .and_then(|v| v.checked_add(total))
+            .ok_or(Error::Nix {
+                source: nix::errno::Errno::ERANGE,
+            })?;
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timespan() {
+        assert_eq!(
+            parse_timespan("10", USEC_PER_SEC).unwrap(),
+            10 * USEC_PER_SEC
+        );
+        assert_eq!(parse_timespan("10", 1).unwrap(), 10);
+        assert_eq!(parse_timespan("500ms", 1).unwrap(), 500 * USEC_PER_MSEC);
+        assert_eq!(
+            parse_timespan("1min 30s", 1).unwrap(),
+            USEC_PER_MINUTE + 30 * USEC_PER_SEC
+        );
+        assert_eq!(parse_timespan("2h", 1).unwrap(), 2 * USEC_PER_HOUR);
+        assert!(parse_timespan("", 1).is_err());
+        assert!(parse_timespan("10 light-years", 1).is_err());
+        assert!(parse_timespan("s", 1).is_err());
+    }
+}
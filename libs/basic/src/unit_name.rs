@@ -0,0 +1,176 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! Functions for unit names: template and instance names, like "foo@.service" and "foo@bar.service",
+//! and names derived from paths, like "/mnt/data" -> "mnt-data.mount"
+
+/// split the unit name into (prefix, instance, suffix), the instance is None if the name has no '@'
+fn unit_name_split(name: &str) -> Option<(&str, Option<&str>, &str)> {
+    let dot = name.rfind('.')?;
+    let (stem, suffix) = (&name[..dot], &name[dot + 1..]);
+    if stem.is_empty() || suffix.is_empty() {
+        return None;
+    }
+
+    match stem.find('@') {
+        None => Some((stem, None, suffix)),
+        Some(0) => None,
+        Some(at) => Some((&stem[..at], Some(&stem[at + 1..]), suffix)),
+    }
+}
+
+/// return true if the name is a template, like "foo@.service"
+pub fn unit_name_is_template(name: &str) -> bool {
+    matches!(unit_name_split(name), Some((_, Some(""), _)))
+}
+
+/// return true if the name is an instance of a template, like "foo@bar.service"
+pub fn unit_name_is_instance(name: &str) -> bool {
+    matches!(unit_name_split(name), Some((_, Some(i), _)) if !i.is_empty())
+}
+
+/// return the template name of an instance, "foo@bar.service" -> "foo@.service"
+pub fn unit_name_template(name: &str) -> Option<String> {
+    match unit_name_split(name) {
+        Some((prefix, Some(instance), suffix)) if !instance.is_empty() => {
+            Some(format!("{prefix}@.{suffix}"))
+        }
+        _ => None,
+    }
+}
+
+/// build an instance name from prefix, instance and suffix, ("foo", "bar", "service") -> "foo@bar.service"
+pub fn unit_name_build(prefix: &str, instance: &str, suffix: &str) -> String {
+    format!("{prefix}@{instance}.{suffix}")
+}
+
+/// escape a path into a unit name prefix, "/dev/disk/by-label/foo" -> "dev-disk-by\\x2dlabel-foo", "/" -> "-"
+pub fn unit_name_path_escape(path: &str) -> String {
+    let trimmed = path
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect::<Vec<_>>()
+        .join("/");
+    if trimmed.is_empty() {
+        return "-".to_string();
+    }
+
+    let mut res = String::new();
+    for (i, c) in trimmed.bytes().enumerate() {
+        match c {
+            b'/' => res.push('-'),
+            b'.' if i == 0 => res.push_str("\\x2e"),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b':' | b'_' | b'.' => res.push(c as char),
+            _ => res.push_str(&format!("\\x{c:02x}")),
+        }
+    }
+    res
+}
+
+/// build a unit name from a path and a suffix, ("/mnt/data", ".mount") -> "mnt-data.mount"
+pub fn unit_name_from_path(path: &str, suffix: &str) -> String {
+    format!("{}{}", unit_name_path_escape(path), suffix)
+}
+
+/// get the path back from a unit name built by unit_name_from_path, "mnt-data.mount" -> "/mnt/data"
+pub fn unit_name_to_path(name: &str) -> Option<String> {
+    let (prefix, _) = name.rsplit_once('.')?;
+    if prefix == "-" {
+        return Some("/".to_string());
+    }
+
+    let bytes = prefix.as_bytes();
+    let mut res = vec![b'/'];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' => res.push(b'/'),
+            b'\\' if bytes.get(i + 1) == Some(&b'x') => {
+                let hex = std::str::from_utf8(bytes.get(i + 2..i + 4)?).ok()?;
+                res.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            c => res.push(c),
+        }
+        i += 1;
+    }
+    String::from_utf8(res).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_name_template() {
+        assert!(unit_name_is_template("foo@.service"));
+        assert!(!unit_name_is_template("foo@bar.service"));
+        assert!(!unit_name_is_template("foo.service"));
+        assert!(unit_name_is_instance("foo@bar.service"));
+        assert!(!unit_name_is_instance("@bar.service"));
+
+        assert_eq!(
+            unit_name_template("foo@bar.service"),
+            Some("foo@.service".to_string())
+        );
+        assert_eq!(
+            unit_name_template("foo@0-127.0.0.1:80-127.0.0.1:4000.service"),
+            Some("foo@.service".to_string())
+        );
+        assert_eq!(unit_name_template("foo@.service"), None);
+        assert_eq!(unit_name_template("foo.service"), None);
+        assert_eq!(
+            unit_name_build("foo", "0-1-2", "service"),
+            "foo@0-1-2.service"
+        );
+    }
+
+    #[test]
+    fn test_unit_name_from_path() {
+        assert_eq!(unit_name_from_path("/", ".mount"), "-.mount");
+        assert_eq!(unit_name_from_path("//", ".mount"), "-.mount");
+        assert_eq!(unit_name_from_path("/mnt/data", ".mount"), "mnt-data.mount");
+        assert_eq!(
+            unit_name_from_path("/mnt//data/", ".mount"),
+            "mnt-data.mount"
+        );
+        assert_eq!(
+            unit_name_from_path("/dev/disk/by-label/foo bar", ".device"),
+            "dev-disk-by\\x2dlabel-foo\\x20bar.device"
+        );
+        assert_eq!(
+            unit_name_from_path("/.hidden", ".mount"),
+            "\\x2ehidden.mount"
+        );
+    }
+
+    #[test]
+    fn test_unit_name_to_path() {
+        assert_eq!(unit_name_to_path("-.mount"), Some("/".to_string()));
+        assert_eq!(
+            unit_name_to_path("mnt-data.mount"),
+            Some("/mnt/data".to_string())
+        );
+        assert_eq!(
+            unit_name_to_path("dev-disk-by\\x2duuid-1234.swap"),
+            Some("/dev/disk/by-uuid/1234".to_string())
+        );
+        for path in ["/dev/mapper/vg-swap", "/.hidden/a b", "/swapfile"] {
+            assert_eq!(
+                unit_name_to_path(&unit_name_from_path(path, ".swap")),
+                Some(path.to_string())
+            );
+        }
+        assert_eq!(unit_name_to_path("dev-sda\\x2.swap"), None);
+        assert_eq!(unit_name_to_path("noSuffix"), None);
+    }
+}
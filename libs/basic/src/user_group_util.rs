@@ -15,6 +15,10 @@
 use crate::error::*;
 use nix::libc::uid_t;
 use nix::unistd::{Gid, Group, Uid, User};
+use std::ffi::CString;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 /// Parse a string as UID
 pub fn parse_uid(uid_str: &String) -> Result<User> {
@@ -169,3 +173,115 @@ pub fn get_group_creds(group: &String) -> Result<Group> {
         what: "invalid group name".to_string(),
     })
 }
+
+/// the range of the uids and gids allocated for DynamicUser
+pub const DYNAMIC_UID_MIN: uid_t = 61184;
+/// the range of the uids and gids allocated for DynamicUser
+pub const DYNAMIC_UID_MAX: uid_t = 65519;
+/// every allocated uid is recorded by a file named by the uid, which contains the user name
+pub const DYNAMIC_UID_DIR: &str = "/run/sysmaster/dynamic-uid";
+
+/// allocate an unused uid for the dynamic user, the gid is the same as the uid.
+/// the uid already allocated to the same name is reused.
+pub fn dynamic_user_acquire(name: &str) -> Result<Uid> {
+    dynamic_user_acquire_in(Path::new(DYNAMIC_UID_DIR), name)
+}
+
+/// release the uid allocated to the dynamic user
+pub fn dynamic_user_release(uid: Uid) {
+    let _ = std::fs::remove_file(Path::new(DYNAMIC_UID_DIR).join(uid.to_string()));
+}
+
+fn dynamic_user_lookup_in(dir: &Path, name: &str) -> Option<Uid> {
+    for entry in std::fs::read_dir(dir).ok()?.flatten() {
+        let uid = match entry.file_name().to_string_lossy().parse::<uid_t>() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        if matches!(std::fs::read_to_string(entry.path()), Ok(n) if n == name) {
+            return Some(Uid::from_raw(uid));
+        }
+    }
+    None
+}
+
+fn dynamic_user_acquire_in(dir: &Path, name: &str) -> Result<Uid> {
+    std::fs::create_dir_all(dir).context(IoSnafu)?;
+    if let Some(uid) = dynamic_user_lookup_in(dir, name) {
+        return Ok(uid);
+    }
+
+    // start from a position derived from the name, so the same name tends to get the same uid
+    let range = DYNAMIC_UID_MAX - DYNAMIC_UID_MIN + 1;
+    let start = name
+        .bytes()
+        .fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32))
+        % range;
+    for i in 0..range {
+        let uid = DYNAMIC_UID_MIN + (start + i) % range;
+        // skip the ids that are used by the static users and groups
+        if matches!(User::from_uid(Uid::from_raw(uid)), Ok(Some(_)))
+            || matches!(Group::from_gid(Gid::from_raw(uid)), Ok(Some(_)))
+        {
+            continue;
+        }
+
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(dir.join(uid.to_string()))
+        {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(Error::Io { source: e }),
+        };
+        file.write_all(name.as_bytes()).context(IoSnafu)?;
+        return Ok(Uid::from_raw(uid));
+    }
+
+    Err(Error::Invalid {
+        what: "no free uid for the dynamic user".to_string(),
+    })
+}
+
+/// build the user and the group of the dynamic user, which are not in the user database
+pub fn dynamic_user_creds(name: &str, uid: Uid) -> (User, Group) {
+    let user = User {
+        name: name.to_string(),
+        passwd: CString::default(),
+        uid,
+        gid: Gid::from_raw(uid.as_raw()),
+        gecos: CString::new("Dynamic User").unwrap(),
+        dir: PathBuf::from("/"),
+        shell: PathBuf::from("/sbin/nologin"),
+    };
+    let group = Group {
+        name: name.to_string(),
+        passwd: CString::default(),
+        gid: Gid::from_raw(uid.as_raw()),
+        mem: Vec::new(),
+    };
+    (user, group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dynamic_user_acquire() {
+        let dir = std::env::temp_dir().join(format!("test-dynamic-uid-{}", std::process::id()));
+        let uid = dynamic_user_acquire_in(&dir, "foo").unwrap();
+        assert!((DYNAMIC_UID_MIN..=DYNAMIC_UID_MAX).contains(&uid.as_raw()));
+        assert_eq!(dynamic_user_acquire_in(&dir, "foo").unwrap(), uid);
+        assert_eq!(dynamic_user_lookup_in(&dir, "foo"), Some(uid));
+
+        let other = dynamic_user_acquire_in(&dir, "bar").unwrap();
+        assert_ne!(other, uid);
+
+        let (user, group) = dynamic_user_creds("foo", uid);
+        assert_eq!(user.gid.as_raw(), uid.as_raw());
+        assert_eq!(group.name, "foo");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -101,7 +101,7 @@ fn cgtype_to_path(cg_type: CgType) -> &'static str {
 }
 
 #[cfg(feature = "linux")]
-fn cg_abs_path(cg_path: &PathBuf, suffix: &PathBuf) -> Result<PathBuf> {
+pub(crate) fn cg_abs_path(cg_path: &PathBuf, suffix: &PathBuf) -> Result<PathBuf> {
     let cg_type = cg_type()?;
     if cg_type == CgType::None {
         return Err(Error::NotFound {
@@ -115,7 +115,7 @@ fn cg_abs_path(cg_path: &PathBuf, suffix: &PathBuf) -> Result<PathBuf> {
 }
 
 #[cfg(feature = "hongmeng")]
-fn cg_abs_path(cg_path: &PathBuf, suffix: &PathBuf) -> Result<PathBuf> {
+pub(crate) fn cg_abs_path(cg_path: &PathBuf, suffix: &PathBuf) -> Result<PathBuf> {
     let path_buf: PathBuf = PathBuf::from(CG_BASE_DIR);
     Ok(path_buf.join(cg_path).join(suffix))
 }
@@ -0,0 +1,777 @@
+// Copyright (c) 2022 Huawei Technologies Co.,Ltd. All rights reserved.
+//
+// sysMaster is licensed under Mulan PSL v2.
+// You can use this software according to the terms and conditions of the Mulan
+// PSL v2.
+// You may obtain a copy of Mulan PSL v2 at:
+//         http://license.coscl.org.cn/MulanPSL2
+// THIS SOFTWARE IS PROVIDED ON AN "AS IS" BASIS, WITHOUT WARRANTIES OF ANY
+// KIND, EITHER EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO
+// NON-INFRINGEMENT, MERCHANTABILITY OR FIT FOR A PARTICULAR PURPOSE.
+// See the Mulan PSL v2 for more details.
+
+//! The IP access control and accounting of a cgroup, done by the BPF programs
+//! attached to the ingress and egress of the cgroup. A packet is allowed if the
+//! remote address matches IPAddressAllow, otherwise denied if it matches
+//! IPAddressDeny, otherwise allowed.
+
+use crate::cgroup::cg_abs_path;
+use crate::error::*;
+use nix::fcntl::{open, OFlag};
+use nix::libc;
+use nix::sys::stat::Mode;
+use std::fmt;
+use std::net::IpAddr;
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+const BPF_MAP_CREATE: libc::c_int = 0;
+const BPF_MAP_LOOKUP_ELEM: libc::c_int = 1;
+const BPF_MAP_UPDATE_ELEM: libc::c_int = 2;
+const BPF_PROG_LOAD: libc::c_int = 5;
+const BPF_PROG_ATTACH: libc::c_int = 8;
+const BPF_PROG_DETACH: libc::c_int = 9;
+const BPF_PROG_GET_FD_BY_ID: libc::c_int = 13;
+const BPF_MAP_GET_FD_BY_ID: libc::c_int = 14;
+const BPF_OBJ_GET_INFO_BY_FD: libc::c_int = 15;
+const BPF_PROG_QUERY: libc::c_int = 16;
+
+const BPF_MAP_TYPE_ARRAY: u32 = 2;
+const BPF_MAP_TYPE_LPM_TRIE: u32 = 11;
+const BPF_F_NO_PREALLOC: u32 = 1;
+const BPF_PROG_TYPE_CGROUP_SKB: u32 = 8;
+const BPF_CGROUP_INET_INGRESS: u32 = 0;
+const BPF_CGROUP_INET_EGRESS: u32 = 1;
+const BPF_F_ALLOW_MULTI: u32 = 2;
+
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+const BPF_FUNC_MAP_LOOKUP_ELEM: i32 = 1;
+const BPF_FUNC_SKB_LOAD_BYTES: i32 = 26;
+
+// the offsets in struct __sk_buff
+const SKB_LEN: i16 = 0;
+const SKB_PROTOCOL: i16 = 16;
+
+// the registers
+const R0: u8 = 0;
+const R1: u8 = 1;
+const R2: u8 = 2;
+const R3: u8 = 3;
+const R4: u8 = 4;
+const R6: u8 = 6;
+const R7: u8 = 7;
+const FP: u8 = 10;
+
+// the key of the LPM tries on the stack: the prefix length followed by the address
+const KEY_OFFSET: i16 = -24;
+const ACCT_KEY_OFFSET: i16 = -4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct BpfInsn {
+    code: u8,
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+fn insn(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> BpfInsn {
+    BpfInsn {
+        code,
+        regs: dst | (src << 4),
+        off,
+        imm,
+    }
+}
+
+fn mov_reg(dst: u8, src: u8) -> BpfInsn {
+    insn(0xbf, dst, src, 0, 0)
+}
+
+fn mov_imm(dst: u8, imm: i32) -> BpfInsn {
+    insn(0xb7, dst, 0, 0, imm)
+}
+
+fn add_imm(dst: u8, imm: i32) -> BpfInsn {
+    insn(0x07, dst, 0, 0, imm)
+}
+
+fn load_word(dst: u8, src: u8, off: i16) -> BpfInsn {
+    insn(0x61, dst, src, off, 0)
+}
+
+fn store_word_imm(dst: u8, off: i16, imm: i32) -> BpfInsn {
+    insn(0x62, dst, 0, off, imm)
+}
+
+fn atomic_add_dword(dst: u8, src: u8, off: i16) -> BpfInsn {
+    insn(0xdb, dst, src, off, 0)
+}
+
+fn jeq_imm(dst: u8, imm: i32, off: i16) -> BpfInsn {
+    insn(0x15, dst, 0, off, imm)
+}
+
+fn jne_imm(dst: u8, imm: i32, off: i16) -> BpfInsn {
+    insn(0x55, dst, 0, off, imm)
+}
+
+fn ja(off: i16) -> BpfInsn {
+    insn(0x05, 0, 0, off, 0)
+}
+
+fn call(func: i32) -> BpfInsn {
+    insn(0x85, 0, 0, 0, func)
+}
+
+fn exit() -> BpfInsn {
+    insn(0x95, 0, 0, 0, 0)
+}
+
+fn load_map_fd(dst: u8, fd: RawFd) -> [BpfInsn; 2] {
+    [
+        insn(0x18, dst, BPF_PSEUDO_MAP_FD, 0, fd),
+        BpfInsn::default(),
+    ]
+}
+
+fn bpf<T>(cmd: libc::c_int, attr: &mut T) -> Result<libc::c_long> {
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            cmd,
+            attr as *mut T,
+            std::mem::size_of::<T>() as u32,
+        )
+    };
+    if ret < 0 {
+        return Err(Error::Nix {
+            source: nix::errno::Errno::last(),
+        });
+    }
+    Ok(ret)
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapElemAttr {
+    map_fd: u32,
+    pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+    prog_name: [u8; 16],
+    prog_ifindex: u32,
+    expected_attach_type: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgAttachAttr {
+    target_fd: u32,
+    attach_bpf_fd: u32,
+    attach_type: u32,
+    attach_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct GetFdByIdAttr {
+    id: u32,
+    next_id: u32,
+    open_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ObjInfoAttr {
+    bpf_fd: u32,
+    info_len: u32,
+    info: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgQueryAttr {
+    target_fd: u32,
+    attach_type: u32,
+    query_flags: u32,
+    attach_flags: u32,
+    prog_ids: u64,
+    prog_cnt: u32,
+}
+
+fn map_create(map_type: u32, key_size: u32, value_size: u32, max_entries: u32) -> Result<RawFd> {
+    let mut attr = MapCreateAttr {
+        map_type,
+        key_size,
+        value_size,
+        max_entries,
+        map_flags: match map_type {
+            BPF_MAP_TYPE_LPM_TRIE => BPF_F_NO_PREALLOC,
+            _ => 0,
+        },
+    };
+    Ok(bpf(BPF_MAP_CREATE, &mut attr)? as RawFd)
+}
+
+fn map_update(fd: RawFd, key: &[u8], value: &[u8]) -> Result<()> {
+    let mut attr = MapElemAttr {
+        map_fd: fd as u32,
+        key: key.as_ptr() as u64,
+        value: value.as_ptr() as u64,
+        ..Default::default()
+    };
+    bpf(BPF_MAP_UPDATE_ELEM, &mut attr)?;
+    Ok(())
+}
+
+fn map_lookup(fd: RawFd, key: &[u8], value: &mut [u8]) -> Result<()> {
+    let mut attr = MapElemAttr {
+        map_fd: fd as u32,
+        key: key.as_ptr() as u64,
+        value: value.as_mut_ptr() as u64,
+        ..Default::default()
+    };
+    bpf(BPF_MAP_LOOKUP_ELEM, &mut attr)?;
+    Ok(())
+}
+
+fn prog_load(prog: &[BpfInsn]) -> Result<RawFd> {
+    let license = b"GPL\0";
+    let mut attr = ProgLoadAttr {
+        prog_type: BPF_PROG_TYPE_CGROUP_SKB,
+        insn_cnt: prog.len() as u32,
+        insns: prog.as_ptr() as u64,
+        license: license.as_ptr() as u64,
+        ..Default::default()
+    };
+    Ok(bpf(BPF_PROG_LOAD, &mut attr)? as RawFd)
+}
+
+/// the id of the program or the map, both bpf_prog_info and bpf_map_info start with the type and the id
+fn obj_id(fd: RawFd) -> Result<u32> {
+    let mut info = [0u32; 2];
+    let mut attr = ObjInfoAttr {
+        bpf_fd: fd as u32,
+        info_len: std::mem::size_of_val(&info) as u32,
+        info: info.as_mut_ptr() as u64,
+    };
+    bpf(BPF_OBJ_GET_INFO_BY_FD, &mut attr)?;
+    Ok(info[1])
+}
+
+fn obj_fd_by_id(cmd: libc::c_int, id: u32) -> Result<RawFd> {
+    let mut attr = GetFdByIdAttr {
+        id,
+        ..Default::default()
+    };
+    Ok(bpf(cmd, &mut attr)? as RawFd)
+}
+
+/// the ids of the programs attached to the cgroup with the attach type
+fn prog_query(cgroup_fd: RawFd, attach_type: u32) -> Result<Vec<u32>> {
+    let mut ids = [0u32; 64];
+    let mut attr = ProgQueryAttr {
+        target_fd: cgroup_fd as u32,
+        attach_type,
+        prog_ids: ids.as_mut_ptr() as u64,
+        prog_cnt: ids.len() as u32,
+        ..Default::default()
+    };
+    bpf(BPF_PROG_QUERY, &mut attr)?;
+    Ok(ids[..attr.prog_cnt as usize].to_vec())
+}
+
+/// an IP address prefix in IPAddressAllow or IPAddressDeny
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpPrefix {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpPrefix {
+    /// parse a space separated list, the special names "any", "localhost",
+    /// "link-local" and "multicast" are supported
+    pub fn parse_list(s: &str) -> Result<Vec<IpPrefix>> {
+        let mut prefixes = Vec::new();
+        for word in s.split_whitespace() {
+            let names: &[&str] = match word {
+                "any" => &["0.0.0.0/0", "::/0"],
+                "localhost" => &["127.0.0.0/8", "::1/128"],
+                "link-local" => &["169.254.0.0/16", "fe80::/64"],
+                "multicast" => &["224.0.0.0/4", "ff00::/8"],
+                _ => {
+                    prefixes.push(IpPrefix::from_str(word)?);
+                    continue;
+                }
+            };
+            for name in names {
+                prefixes.push(IpPrefix::from_str(name)?);
+            }
+        }
+        Ok(prefixes)
+    }
+
+    fn key(&self) -> Vec<u8> {
+        let mut key = (self.prefix_len as u32).to_ne_bytes().to_vec();
+        match self.addr {
+            IpAddr::V4(a) => key.extend(a.octets()),
+            IpAddr::V6(a) => key.extend(a.octets()),
+        }
+        key
+    }
+}
+
+impl FromStr for IpPrefix {
+    type Err = Error;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || Error::DataFormat {
+            data: format!("invalid IP address prefix: {s}"),
+        };
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr = IpAddr::from_str(addr).map_err(|_| invalid())?;
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match len {
+            Some(len) => len.parse::<u8>().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix_len > max {
+            return Err(invalid());
+        }
+        Ok(IpPrefix { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for IpPrefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// the bytes and packets counted by IPAccounting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IpCounters {
+    /// received bytes
+    pub ingress_bytes: u64,
+    /// received packets
+    pub ingress_packets: u64,
+    /// sent bytes
+    pub egress_bytes: u64,
+    /// sent packets
+    pub egress_packets: u64,
+}
+
+// the lookups of one address family, the maps are None if no prefix is configured
+struct FamilyMaps {
+    protocol: u16,
+    prefix_len: i32,
+    size: i32,
+    allow: Option<RawFd>,
+    deny: Option<RawFd>,
+}
+
+impl FamilyMaps {
+    fn lookup(fd: RawFd, matched: i16) -> Vec<BpfInsn> {
+        let mut prog = load_map_fd(R1, fd).to_vec();
+        prog.push(mov_reg(R2, FP));
+        prog.push(add_imm(R2, KEY_OFFSET as i32));
+        prog.push(call(BPF_FUNC_MAP_LOOKUP_ELEM));
+        prog.push(jne_imm(R0, 0, matched));
+        prog
+    }
+
+    // set R7 to 0 if the packet is denied, the address is at offset of the packet
+    fn block(&self, offset: i32) -> Vec<BpfInsn> {
+        if self.allow.is_none() && self.deny.is_none() {
+            return Vec::new();
+        }
+
+        // the jumps to the end of the block are patched at last
+        let mut prog = vec![
+            store_word_imm(FP, KEY_OFFSET, self.prefix_len),
+            mov_reg(R1, R6),
+            mov_imm(R2, offset),
+            mov_reg(R3, FP),
+            add_imm(R3, KEY_OFFSET as i32 + 4),
+            mov_imm(R4, self.size),
+            call(BPF_FUNC_SKB_LOAD_BYTES),
+            jne_imm(R0, 0, i16::MAX),
+        ];
+        if let Some(fd) = self.allow {
+            prog.extend(Self::lookup(fd, i16::MAX));
+        }
+        if let Some(fd) = self.deny {
+            // jump over the verdict if not matched
+            prog.extend(Self::lookup(fd, 1));
+            prog.push(ja(1));
+            prog.push(mov_imm(R7, 0));
+        }
+
+        let len = prog.len();
+        for (i, insn) in prog.iter_mut().enumerate() {
+            if insn.off == i16::MAX {
+                insn.off = (len - i - 1) as i16;
+            }
+        }
+        prog
+    }
+
+    // check the protocol and run the block, fall through to the end
+    fn program(&self, offset: i32) -> Vec<BpfInsn> {
+        let block = self.block(offset);
+        if block.is_empty() {
+            return block;
+        }
+        let mut prog = vec![
+            load_word(R1, R6, SKB_PROTOCOL),
+            jne_imm(R1, self.protocol.to_be() as i32, block.len() as i16),
+        ];
+        prog.extend(block);
+        prog
+    }
+}
+
+/// the ids of the BPF objects of a firewall, the programs stay attached to the cgroup
+/// across the reexecution of the manager and are found again by the ids
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IpFirewallIds {
+    /// the attach type and the id of each program
+    pub progs: Vec<(u32, u32)>,
+    /// the id of the map of IPAccounting
+    pub accounting: Option<u32>,
+}
+
+/// the BPF programs attached to a cgroup
+pub struct IpFirewall {
+    cgroup_fd: RawFd,
+    progs: Vec<(u32, RawFd)>,
+    maps: Vec<RawFd>,
+    accounting: Option<RawFd>,
+}
+
+impl IpFirewall {
+    fn create_trie(prefixes: &[&IpPrefix], size: u32) -> Result<Option<RawFd>> {
+        if prefixes.is_empty() {
+            return Ok(None);
+        }
+        let fd = map_create(BPF_MAP_TYPE_LPM_TRIE, 4 + size, 8, prefixes.len() as u32)?;
+        for p in prefixes {
+            if let Err(e) = map_update(fd, &p.key(), &1u64.to_ne_bytes()) {
+                basic::fd_util::close(fd);
+                return Err(e);
+            }
+        }
+        Ok(Some(fd))
+    }
+
+    fn family_maps(
+        &mut self,
+        allow: &[IpPrefix],
+        deny: &[IpPrefix],
+        v4: bool,
+    ) -> Result<FamilyMaps> {
+        let select = |list: &[IpPrefix]| -> Vec<IpPrefix> {
+            list.iter()
+                .filter(|p| p.addr.is_ipv4() == v4)
+                .copied()
+                .collect()
+        };
+        let (allow, deny) = (select(allow), select(deny));
+        let size = if v4 { 4 } else { 16 };
+        let allow = Self::create_trie(&allow.iter().collect::<Vec<_>>(), size)?;
+        self.maps.extend(allow);
+        let deny = Self::create_trie(&deny.iter().collect::<Vec<_>>(), size)?;
+        self.maps.extend(deny);
+        Ok(FamilyMaps {
+            protocol: if v4 { libc::ETH_P_IP } else { libc::ETH_P_IPV6 } as u16,
+            prefix_len: size as i32 * 8,
+            size: size as i32,
+            allow,
+            deny,
+        })
+    }
+
+    fn program(&self, v4: &FamilyMaps, v6: &FamilyMaps, ingress: bool) -> Vec<BpfInsn> {
+        let mut prog = vec![mov_reg(R6, R1), mov_imm(R7, 1)];
+
+        if let Some(fd) = self.accounting {
+            let index = if ingress { 0 } else { 1 };
+            prog.push(store_word_imm(FP, ACCT_KEY_OFFSET, index));
+            prog.extend(load_map_fd(R1, fd));
+            prog.push(mov_reg(R2, FP));
+            prog.push(add_imm(R2, ACCT_KEY_OFFSET as i32));
+            prog.push(call(BPF_FUNC_MAP_LOOKUP_ELEM));
+            prog.push(jeq_imm(R0, 0, 4));
+            prog.push(load_word(R1, R6, SKB_LEN));
+            prog.push(atomic_add_dword(R0, R1, 0));
+            prog.push(mov_imm(R1, 1));
+            prog.push(atomic_add_dword(R0, R1, 8));
+        }
+
+        // the remote address is the source of the ingress and the destination of the egress
+        let (off4, off6) = if ingress { (12, 8) } else { (16, 24) };
+        prog.extend(v4.program(off4));
+        prog.extend(v6.program(off6));
+
+        prog.push(mov_reg(R0, R7));
+        prog.push(exit());
+        prog
+    }
+
+    /// load the programs and attach them to the cgroup, the programs are detached
+    /// when the firewall is dropped
+    pub fn install(
+        cg_path: &PathBuf,
+        allow: &[IpPrefix],
+        deny: &[IpPrefix],
+        accounting: bool,
+    ) -> Result<IpFirewall> {
+        let path = cg_abs_path(cg_path, &PathBuf::new())?;
+        let cgroup_fd =
+            open(&path, OFlag::O_RDONLY | OFlag::O_DIRECTORY, Mode::empty()).context(NixSnafu)?;
+        let mut firewall = IpFirewall {
+            cgroup_fd,
+            progs: Vec::new(),
+            maps: Vec::new(),
+            accounting: None,
+        };
+
+        if accounting {
+            let fd = map_create(BPF_MAP_TYPE_ARRAY, 4, 16, 2)?;
+            firewall.maps.push(fd);
+            firewall.accounting = Some(fd);
+        }
+        let v4 = firewall.family_maps(allow, deny, true)?;
+        let v6 = firewall.family_maps(allow, deny, false)?;
+
+        for (attach_type, ingress) in [
+            (BPF_CGROUP_INET_INGRESS, true),
+            (BPF_CGROUP_INET_EGRESS, false),
+        ] {
+            let prog = firewall.program(&v4, &v6, ingress);
+            let fd = prog_load(&prog)?;
+            let mut attr = ProgAttachAttr {
+                target_fd: cgroup_fd as u32,
+                attach_bpf_fd: fd as u32,
+                attach_type,
+                attach_flags: BPF_F_ALLOW_MULTI,
+            };
+            let ret = bpf(BPF_PROG_ATTACH, &mut attr);
+            if ret.is_err() {
+                basic::fd_util::close(fd);
+            }
+            ret?;
+            firewall.progs.push((attach_type, fd));
+        }
+        Ok(firewall)
+    }
+
+    /// the ids to find the programs again after the reexecution
+    pub fn ids(&self) -> Result<IpFirewallIds> {
+        let mut ids = IpFirewallIds::default();
+        for (attach_type, fd) in &self.progs {
+            ids.progs.push((*attach_type, obj_id(*fd)?));
+        }
+        if let Some(fd) = self.accounting {
+            ids.accounting = Some(obj_id(fd)?);
+        }
+        Ok(ids)
+    }
+
+    /// take over the programs installed before the reexecution, so they are not attached twice
+    /// and the counters go on. The programs which are taken over are detached on failure.
+    pub fn adopt(cg_path: &PathBuf, ids: &IpFirewallIds) -> Result<IpFirewall> {
+        let path = cg_abs_path(cg_path, &PathBuf::new())?;
+        let cgroup_fd =
+            open(&path, OFlag::O_RDONLY | OFlag::O_DIRECTORY, Mode::empty()).context(NixSnafu)?;
+        let mut firewall = IpFirewall {
+            cgroup_fd,
+            progs: Vec::new(),
+            maps: Vec::new(),
+            accounting: None,
+        };
+
+        for (attach_type, id) in &ids.progs {
+            // the id may be reused by another program once ours is gone
+            if !prog_query(cgroup_fd, *attach_type)?.contains(id) {
+                return Err(Error::Nix {
+                    source: nix::errno::Errno::ENOENT,
+                });
+            }
+            let fd = obj_fd_by_id(BPF_PROG_GET_FD_BY_ID, *id)?;
+            firewall.progs.push((*attach_type, fd));
+        }
+        if let Some(id) = ids.accounting {
+            let fd = obj_fd_by_id(BPF_MAP_GET_FD_BY_ID, id)?;
+            firewall.maps.push(fd);
+            firewall.accounting = Some(fd);
+        }
+        Ok(firewall)
+    }
+
+    /// return the counters if IPAccounting is on
+    pub fn counters(&self) -> Option<IpCounters> {
+        let fd = self.accounting?;
+        let mut values = [[0u8; 16]; 2];
+        for (i, value) in values.iter_mut().enumerate() {
+            if map_lookup(fd, &(i as u32).to_ne_bytes(), value).is_err() {
+                return None;
+            }
+        }
+        let read = |v: &[u8]| u64::from_ne_bytes(v.try_into().unwrap_or_default());
+        Some(IpCounters {
+            ingress_bytes: read(&values[0][..8]),
+            ingress_packets: read(&values[0][8..]),
+            egress_bytes: read(&values[1][..8]),
+            egress_packets: read(&values[1][8..]),
+        })
+    }
+}
+
+impl Drop for IpFirewall {
+    fn drop(&mut self) {
+        for (attach_type, fd) in &self.progs {
+            let mut attr = ProgAttachAttr {
+                target_fd: self.cgroup_fd as u32,
+                attach_bpf_fd: *fd as u32,
+                attach_type: *attach_type,
+                attach_flags: BPF_F_ALLOW_MULTI,
+            };
+            // the cgroup may be removed already
+            let _ = bpf(BPF_PROG_DETACH, &mut attr);
+            basic::fd_util::close(*fd);
+        }
+        for fd in &self.maps {
+            basic::fd_util::close(*fd);
+        }
+        basic::fd_util::close(self.cgroup_fd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_prefix() {
+        let prefixes = IpPrefix::parse_list("10.0.0.0/8 localhost fe80::1").unwrap();
+        assert_eq!(prefixes.len(), 4);
+        assert_eq!(prefixes[0].to_string(), "10.0.0.0/8");
+        assert_eq!(prefixes[2].to_string(), "::1/128");
+        assert_eq!(prefixes[3].to_string(), "fe80::1/128");
+        assert_eq!(prefixes[0].key(), [8, 0, 0, 0, 10, 0, 0, 0]);
+        assert!(IpPrefix::from_str("10.0.0.0/33").is_err());
+        assert!(IpPrefix::from_str("host").is_err());
+    }
+
+    #[test]
+    fn test_firewall_program() {
+        let v4 = FamilyMaps {
+            protocol: libc::ETH_P_IP as u16,
+            prefix_len: 32,
+            size: 4,
+            allow: Some(3),
+            deny: Some(4),
+        };
+        let block = v4.block(12);
+        // key, load bytes, allow lookup, deny lookup and verdict
+        assert_eq!(block.len(), 8 + 6 + 8);
+        // a failed load and a matched allow lookup both jump to the end
+        assert_eq!(block[7].off as usize, block.len() - 8);
+        assert_eq!(block[13].off as usize, block.len() - 14);
+        assert_eq!(block[block.len() - 1], mov_imm(R7, 0));
+
+        let v6 = FamilyMaps {
+            protocol: libc::ETH_P_IPV6 as u16,
+            prefix_len: 128,
+            size: 16,
+            allow: None,
+            deny: None,
+        };
+        assert!(v6.program(8).is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs the privilege to load the BPF programs"]
+    fn test_firewall_load() {
+        let v4 = FamilyMaps {
+            protocol: libc::ETH_P_IP as u16,
+            prefix_len: 32,
+            size: 4,
+            allow: None,
+            deny: None,
+        };
+        let v6 = FamilyMaps {
+            protocol: libc::ETH_P_IPV6 as u16,
+            prefix_len: 128,
+            size: 16,
+            allow: None,
+            deny: None,
+        };
+
+        // the verifier checks the programs
+        let maps: Vec<RawFd> = (0..2)
+            .map(|_| map_create(BPF_MAP_TYPE_LPM_TRIE, 8, 8, 1).unwrap())
+            .collect();
+        let acct = map_create(BPF_MAP_TYPE_ARRAY, 4, 16, 2).unwrap();
+        let firewall = IpFirewall {
+            cgroup_fd: -1,
+            progs: Vec::new(),
+            maps: Vec::new(),
+            accounting: Some(acct),
+        };
+        let v4 = FamilyMaps {
+            allow: Some(maps[0]),
+            deny: Some(maps[1]),
+            ..v4
+        };
+        for ingress in [true, false] {
+            let fd = prog_load(&firewall.program(&v4, &v6, ingress)).unwrap();
+            assert!(obj_id(fd).unwrap() > 0);
+            basic::fd_util::close(fd);
+        }
+        assert_eq!(firewall.counters(), Some(IpCounters::default()));
+
+        // the map is found again by its id
+        let id = obj_id(acct).unwrap();
+        let fd = obj_fd_by_id(BPF_MAP_GET_FD_BY_ID, id).unwrap();
+        assert_eq!(obj_id(fd).unwrap(), id);
+        basic::fd_util::close(fd);
+
+        maps.into_iter().for_each(basic::fd_util::close);
+        std::mem::forget(firewall);
+        basic::fd_util::close(acct);
+    }
+}
@@ -19,6 +19,7 @@ compile_error!("feature linux and hongmeng cannot be enabled at the same time");
 use bitflags::bitflags;
 mod cgroup;
 pub mod error;
+mod firewall;
 pub use crate::cgroup::cg_attach;
 pub use crate::cgroup::cg_controllers;
 pub use crate::cgroup::cg_create;
@@ -30,6 +31,7 @@ pub use crate::cgroup::cg_kill_recursive;
 pub use crate::cgroup::cg_type;
 pub use crate::cgroup::CgController;
 pub use crate::cgroup::CG_BASE_DIR;
+pub use crate::firewall::{IpCounters, IpFirewall, IpFirewallIds, IpPrefix};
 
 bitflags! {
     /// the flag that operate on the cgroup controller
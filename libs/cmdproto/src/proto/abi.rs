@@ -48,6 +48,9 @@ pub struct UnitComm {
     pub action: i32,
     #[prost(string, repeated, tag="2")]
     pub units: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// what to remove for CLEAN, e.g. cache, state
+    #[prost(string, repeated, tag="3")]
+    pub what: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 /// Nested message and enum types in `UnitComm`.
 pub mod unit_comm {
@@ -61,6 +64,7 @@ pub mod unit_comm {
         Restart = 3,
         Reload = 4,
         Kill = 5,
+        Clean = 6,
     }
 }
 #[rustfmt::skip]
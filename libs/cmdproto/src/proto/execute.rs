@@ -44,6 +44,8 @@ pub trait ExecuterAction {
     fn restart(&self, unit_name: &str) -> Result<(), Self::Error>;
     /// reload the unit_name
     fn reload(&self, unit_name: &str) -> Result<(), Self::Error>;
+    /// remove the directories of unit_name, what is like "cache", "state"
+    fn clean(&self, unit_name: &str, what: &[String]) -> Result<(), Self::Error>;
     /// show the status of unit_name
     fn status(&self, unit_name: &str) -> Result<Self::Status, Self::Error>;
     /// list all units
@@ -173,6 +175,15 @@ impl Executer for UnitComm {
                     }
                 }
             }
+            unit_comm::Action::Clean => {
+                for unit in units {
+                    if let Err(e) = manager.clean(&unit, &self.what) {
+                        new_line_break(&mut reply);
+                        reply = format!("{reply}Failed to clean {unit}: {e}");
+                        error_code = e.into() as u32;
+                    }
+                }
+            }
             _ => todo!(),
         }
         CommandResponse {
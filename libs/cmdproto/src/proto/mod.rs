@@ -31,6 +31,18 @@ impl CommandRequest {
             request_data: Some(RequestData::Ucomm(UnitComm {
                 action: action.into(),
                 units,
+                what: Vec::new(),
+            })),
+        }
+    }
+
+    /// Create a new command request for cleaning the directories of units
+    pub fn new_unitcomm_clean(units: Vec<String>, what: Vec<String>) -> Self {
+        Self {
+            request_data: Some(RequestData::Ucomm(UnitComm {
+                action: unit_comm::Action::Clean.into(),
+                units,
+                what,
             })),
         }
     }
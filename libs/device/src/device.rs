@@ -30,6 +30,9 @@ use crate::{error::Error, DeviceAction};
 /// database directory path
 pub const DB_DIRECTORY_PATH: &str = "/run/udev/data/";
 
+/// tags directory path, the ids of the tagged devices are under the subdirectory of each tag
+pub const TAGS_DIRECTORY_PATH: &str = "/run/udev/tags/";
+
 /// Device
 #[derive(Debug, Clone)]
 pub struct Device {
@@ -117,6 +120,8 @@ pub struct Device {
     pub driver_set: bool,
     /// whether the database is loaded
     pub db_loaded: bool,
+    /// whether the database should be kept when the device is reinitialized
+    pub db_persist: bool,
 
     /// whether the device object is initialized
     pub is_initialized: bool,
@@ -174,6 +179,7 @@ impl Device {
             property_tags_outdated: true,
             is_initialized: false,
             db_loaded: false,
+            db_persist: false,
             sealed: false,
             database_version: 0,
             devlink_priority: 0,
@@ -213,7 +219,11 @@ impl Device {
                 // "DEVGUID" => {}
                 // "DISKSEQ" => {}
                 // "DEVLINKS" => {}
-                "TAGS" | "CURRENT_TAGS" => {}
+                "TAGS" | "CURRENT_TAGS" => {
+                    for tag in value.split(':').filter(|t| !t.is_empty()) {
+                        device.add_tag(tag.to_string(), key == "CURRENT_TAGS")?;
+                    }
+                }
                 _ => {
                     device.add_property_internal(key.to_string(), value.to_string())?;
                 }
@@ -1244,6 +1254,59 @@ impl Device {
 
         Ok(())
     }
+
+    /// write the properties, tags and devlinks of the device into database
+    pub fn update_db(&mut self) -> Result<(), Error> {
+        let id = self.get_device_id().map_err(|e| Error::Nix {
+            msg: format!("update_db failed: failed to get_device_id ({})", e),
+            source: e.get_errno(),
+        })?;
+
+        self.write_db_internal_filename(DB_DIRECTORY_PATH.to_string() + &id)
+    }
+
+    /// remove the database of the device
+    pub fn delete_db(&mut self) -> Result<(), Error> {
+        let id = self.get_device_id().map_err(|e| Error::Nix {
+            msg: format!("delete_db failed: failed to get_device_id ({})", e),
+            source: e.get_errno(),
+        })?;
+
+        remove_file_if_exists(&(DB_DIRECTORY_PATH.to_string() + &id))
+    }
+
+    /// add or remove the device id under the directory of each tag,
+    /// the tags only existing in the old device are removed when adding
+    pub fn update_tag_index(&mut self, old: Option<&Device>, add: bool) -> Result<(), Error> {
+        let id = self.get_device_id().map_err(|e| Error::Nix {
+            msg: format!("update_tag_index failed: failed to get_device_id ({})", e),
+            source: e.get_errno(),
+        })?;
+
+        if add {
+            if let Some(old) = old {
+                for tag in old.all_tags.difference(&self.all_tags) {
+                    remove_file_if_exists(&format!("{}{}/{}", TAGS_DIRECTORY_PATH, tag, id))?;
+                }
+            }
+        }
+
+        for tag in self.all_tags.iter() {
+            let path = format!("{}{}/{}", TAGS_DIRECTORY_PATH, tag, id);
+            if add {
+                fs::create_dir_all(format!("{}{}", TAGS_DIRECTORY_PATH, tag))
+                    .and_then(|_| fs::write(&path, ""))
+                    .map_err(|e| Error::Nix {
+                        msg: format!("update_tag_index failed: failed to create {} ({})", path, e),
+                        source: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+                    })?;
+            } else {
+                remove_file_if_exists(&path)?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// internal methods
@@ -1494,7 +1557,7 @@ impl Device {
     }
 
     /// add tag to the device object
-    pub(crate) fn add_tag(&mut self, tag: String, both: bool) -> Result<(), Error> {
+    pub fn add_tag(&mut self, tag: String, both: bool) -> Result<(), Error> {
         self.all_tags.insert(tag.clone());
 
         if both {
@@ -1504,14 +1567,33 @@ impl Device {
         Ok(())
     }
 
+    /// remove the tag from the current tags of the device object
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.current_tags.remove(tag);
+        self.property_tags_outdated = true;
+    }
+
+    /// remove all tags of the device object
+    pub fn cleanup_tags(&mut self) {
+        self.all_tags.clear();
+        self.current_tags.clear();
+        self.property_tags_outdated = true;
+    }
+
     /// add devlink records to the device object
-    pub(crate) fn add_devlink(&mut self, devlink: String) -> Result<(), Error> {
+    pub fn add_devlink(&mut self, devlink: String) -> Result<(), Error> {
         self.devlinks.insert(devlink);
         self.property_devlinks_outdated = true;
 
         Ok(())
     }
 
+    /// remove all devlink records of the device object
+    pub fn cleanup_devlinks(&mut self) {
+        self.devlinks.clear();
+        self.property_devlinks_outdated = true;
+    }
+
     /// get properties nulstr, if it is out of date, update it
     pub(crate) fn get_properties_nulstr(&mut self) -> Result<(&Vec<u8>, usize), Error> {
         self.update_properties_bufs()?;
@@ -1792,7 +1874,7 @@ impl Device {
     }
 
     /// set the initialized timestamp
-    pub(crate) fn set_usec_initialized(&mut self, time: u64) -> Result<(), Error> {
+    pub fn set_usec_initialized(&mut self, time: u64) -> Result<(), Error> {
         self.add_property_internal("USEC_INITIALIZED".to_string(), time.to_string())?;
         self.usec_initialized = time;
         Ok(())
@@ -1899,7 +1981,7 @@ impl Device {
 
     /// get the device id
     /// device id is used to identify hwdb file in /run/devmaster/data/
-    pub(crate) fn get_device_id(&mut self) -> Result<String, Error> {
+    pub fn get_device_id(&mut self) -> Result<String, Error> {
         if self.device_id.is_empty() {
             let subsystem = self.get_subsystem().map_err(|e| Error::Nix {
                 msg: format!("get_device_id failed: get_subsystem ({})", e),
@@ -1954,46 +2036,41 @@ impl Device {
         if self.property_devlinks_outdated {
             let devlinks: Vec<String> = self.devlinks.clone().into_iter().collect();
             let devlinks: String = devlinks.join(" ");
-            if !devlinks.is_empty() {
-                self.add_property_internal("DEVLINKS".to_string(), devlinks)
-                    .map_err(|e| Error::Nix {
-                        msg: format!(
-                            "properties_prepare failed: add_property_internal DEVLINKS ({})",
-                            e
-                        ),
-                        source: e.get_errno(),
-                    })?;
+            // an empty value removes the outdated property
+            self.add_property_internal("DEVLINKS".to_string(), devlinks)
+                .map_err(|e| Error::Nix {
+                    msg: format!(
+                        "properties_prepare failed: add_property_internal DEVLINKS ({})",
+                        e
+                    ),
+                    source: e.get_errno(),
+                })?;
 
-                self.property_devlinks_outdated = false;
-            }
+            self.property_devlinks_outdated = false;
         }
 
         if self.property_tags_outdated {
             let tags: Vec<String> = self.all_tags.clone().into_iter().collect();
             let tags: String = tags.join(":");
-            if !tags.is_empty() {
-                self.add_property_internal("TAGS".to_string(), tags)
-                    .map_err(|e| Error::Nix {
-                        msg: format!(
-                            "properties_prepare failed: add_property_internal TAGS ({})",
-                            e
-                        ),
-                        source: e.get_errno(),
-                    })?;
-            }
+            self.add_property_internal("TAGS".to_string(), tags)
+                .map_err(|e| Error::Nix {
+                    msg: format!(
+                        "properties_prepare failed: add_property_internal TAGS ({})",
+                        e
+                    ),
+                    source: e.get_errno(),
+                })?;
 
             let tags: Vec<String> = self.current_tags.clone().into_iter().collect();
             let tags: String = tags.join(":");
-            if !tags.is_empty() {
-                self.add_property_internal("CURRENT_TAGS".to_string(), tags)
-                    .map_err(|e| Error::Nix {
-                        msg: format!(
-                            "properties_prepare failed: add_property_internal CURRENT_TAGS ({})",
-                            e
-                        ),
-                        source: e.get_errno(),
-                    })?;
-            }
+            self.add_property_internal("CURRENT_TAGS".to_string(), tags)
+                .map_err(|e| Error::Nix {
+                    msg: format!(
+                        "properties_prepare failed: add_property_internal CURRENT_TAGS ({})",
+                        e
+                    ),
+                    source: e.get_errno(),
+                })?;
 
             self.property_tags_outdated = false;
         }
@@ -2002,7 +2079,7 @@ impl Device {
     }
 
     /// read database
-    pub(crate) fn read_db(&mut self) -> Result<(), Error> {
+    pub fn read_db(&mut self) -> Result<(), Error> {
         self.read_db_internal(false).map_err(|e| Error::Nix {
             msg: format!("read_db failed: failed to read_db_internal ({})", e),
             source: e.get_errno(),
@@ -2081,6 +2158,81 @@ impl Device {
         Ok(())
     }
 
+    /// write database into specific file
+    /// the empty database of devices without device node or network interface is removed
+    pub(crate) fn write_db_internal_filename(&mut self, filename: String) -> Result<(), Error> {
+        let has_info = !self.properties_db.is_empty()
+            || !self.all_tags.is_empty()
+            || !self.current_tags.is_empty()
+            || !self.devlinks.is_empty()
+            || self.devlink_priority != 0;
+
+        if !has_info && self.devnum == 0 && self.ifindex == 0 {
+            return remove_file_if_exists(&filename);
+        }
+
+        let mut content = String::new();
+
+        if self.devnum != 0 {
+            let mut devlinks: Vec<&String> = self.devlinks.iter().collect();
+            devlinks.sort();
+            for devlink in devlinks {
+                content += &format!("S:{}\n", devlink.trim_start_matches("/dev/"));
+            }
+
+            if self.devlink_priority != 0 {
+                content += &format!("L:{}\n", self.devlink_priority);
+            }
+        }
+
+        if self.usec_initialized > 0 {
+            content += &format!("I:{}\n", self.usec_initialized);
+        }
+
+        let mut properties: Vec<(&String, &String)> = self.properties_db.iter().collect();
+        properties.sort();
+        for (k, v) in properties {
+            content += &format!("E:{}={}\n", k, v);
+        }
+
+        let mut tags: Vec<&String> = self.all_tags.iter().collect();
+        tags.sort();
+        for tag in tags {
+            content += &format!("G:{}\n", tag);
+        }
+
+        let mut tags: Vec<&String> = self.current_tags.iter().collect();
+        tags.sort();
+        for tag in tags {
+            content += &format!("Q:{}\n", tag);
+        }
+
+        content += "V:1\n";
+
+        // write into a temporary file and rename it, in case of reading a partial database
+        let tmp = format!("{}.tmp", filename);
+        let mode = if self.db_persist { 0o1644 } else { 0o644 };
+        let ret = Path::new(&filename)
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&tmp, content))
+            .and_then(|_| {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&tmp, fs::Permissions::from_mode(mode))
+            })
+            .and_then(|_| fs::rename(&tmp, &filename));
+
+        if let Err(e) = ret {
+            let _ = fs::remove_file(&tmp);
+            return Err(Error::Nix {
+                msg: format!("write_db_internal_filename failed: db {} ({})", filename, e),
+                source: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+            });
+        }
+
+        Ok(())
+    }
+
     /// handle database line
     pub(crate) fn handle_db_line(&mut self, key: &str, value: &str) -> Result<(), Error> {
         match key {
@@ -2099,15 +2251,18 @@ impl Device {
                     })?;
             }
             "E" => {
-                let tokens: Vec<_> = value.split('=').collect();
-                if tokens.len() != 2 {
-                    return Err(Error::Nix {
-                        msg: format!("handle_db_line failed: failed to parse property {}", value),
-                        source: Errno::EINVAL,
-                    });
-                }
-
-                let (k, v) = (tokens[0], tokens[1]);
+                let (k, v) = match value.split_once('=') {
+                    Some(kv) => kv,
+                    None => {
+                        return Err(Error::Nix {
+                            msg: format!(
+                                "handle_db_line failed: failed to parse property {}",
+                                value
+                            ),
+                            source: Errno::EINVAL,
+                        });
+                    }
+                };
 
                 self.add_property_internal(k.to_string(), v.to_string())
                     .map_err(|e| Error::Nix {
@@ -2117,6 +2272,13 @@ impl Device {
                         ),
                         source: e.get_errno(),
                     })?;
+
+                // keep the properties from database, in case of writing the database back
+                self.add_property_aux(k.to_string(), v.to_string(), true)
+                    .map_err(|e| Error::Nix {
+                        msg: format!("handle_db_line failed: failed to add_property_aux ({})", e),
+                        source: e.get_errno(),
+                    })?;
             }
             "I" => {
                 let time = value.parse::<u64>().map_err(|e| Error::Nix {
@@ -2172,6 +2334,18 @@ impl Device {
     }
 }
 
+/// remove the file, it is fine if the file does not exist
+fn remove_file_if_exists(path: &str) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(Error::Nix {
+            msg: format!("failed to remove {} ({})", path, e),
+            source: Errno::from_i32(e.raw_os_error().unwrap_or(libc::EINVAL)),
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::BorrowMut;
@@ -2401,6 +2575,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_nulstr_tags() {
+        let nulstr = b"ACTION=add\0DEVPATH=/devices/virtual/mem/null\0SUBSYSTEM=mem\0MAJOR=1\0MINOR=3\0TAGS=:systemd:sysmaster:\0CURRENT_TAGS=:systemd:\0";
+        let mut device = Device::from_nulstr(nulstr).unwrap();
+        assert!(device.has_tag("systemd".to_string()).unwrap());
+        assert!(device.has_tag("sysmaster".to_string()).unwrap());
+        assert!(!device.has_tag("seat".to_string()).unwrap());
+        assert!(device.has_current_tag("systemd".to_string()).unwrap());
+        assert!(!device.has_current_tag("sysmaster".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_write_db() {
+        let nulstr =
+            b"ACTION=add\0DEVPATH=/devices/virtual/mem/null\0SUBSYSTEM=mem\0MAJOR=1\0MINOR=3\0";
+        let mut device = Device::from_nulstr(nulstr).unwrap();
+        device
+            .add_property("ID_TEST".to_string(), "a=b".to_string())
+            .unwrap();
+        device.add_tag("systemd".to_string(), true).unwrap();
+        device.add_devlink("/dev/test/null".to_string()).unwrap();
+        device.devlink_priority = 10;
+        device.usec_initialized = 1000;
+
+        let filename = "test_write_db".to_string();
+        device.write_db_internal_filename(filename.clone()).unwrap();
+
+        let mut db = Device::new();
+        db.read_db_internal_filename(filename.clone()).unwrap();
+        assert_eq!(db.properties_db.get("ID_TEST").unwrap(), "a=b");
+        assert!(db.all_tags.contains("systemd"));
+        assert!(db.current_tags.contains("systemd"));
+        assert!(db.devlinks.contains("/dev/test/null"));
+        assert_eq!(db.devlink_priority, 10);
+        assert_eq!(db.usec_initialized, 1000);
+        assert_eq!(db.database_version, 1);
+
+        // the empty database of devices without device node is removed
+        let mut device = Device::new();
+        device.write_db_internal_filename(filename.clone()).unwrap();
+        assert!(!Path::new(&filename).exists());
+    }
+
     /// test whether Device::from_mode_and_devnum can create Device instance normally
     #[ignore]
     #[test]
@@ -12,7 +12,11 @@
 
 //! enumerate /sys to collect devices
 //!
-use crate::{device::Device, error::Error, utils::*};
+use crate::{
+    device::{Device, TAGS_DIRECTORY_PATH},
+    error::Error,
+    utils::*,
+};
 use bitflags::bitflags;
 use nix::errno::Errno;
 use std::{
@@ -931,9 +935,76 @@ impl DeviceEnumerator {
     }
 
     /// scan devices for a single tag
-    pub(crate) fn scan_devices_tag(&mut self, _tag: String) -> Result<(), Error> {
-        todo!("scan_devices_tag has not been implemented.");
-        // Ok(())
+    pub(crate) fn scan_devices_tag(&mut self, tag: String) -> Result<(), Error> {
+        let path = format!("{}{}", TAGS_DIRECTORY_PATH, tag);
+        let entries = match std::fs::read_dir(&path) {
+            Ok(ret) => ret,
+            Err(e) => {
+                let errno = e.raw_os_error().unwrap_or_default();
+                if errno == libc::ENOENT {
+                    return Ok(());
+                }
+                return Err(Error::Nix {
+                    msg: format!("scan_devices_tag failed: read directory {}", path),
+                    source: Errno::from_i32(errno),
+                });
+            }
+        };
+
+        let mut ret = Result::<(), Error>::Ok(());
+        for entry in entries {
+            let entry = match entry {
+                Ok(e) => e,
+                Err(e) => {
+                    ret = Err(Error::Nix {
+                        msg: format!("scan_devices_tag failed: read entries under {}", path),
+                        source: Errno::from_i32(e.raw_os_error().unwrap_or_default()),
+                    });
+                    continue;
+                }
+            };
+
+            let id = entry.file_name().to_string_lossy().to_string();
+            if id.starts_with('.') {
+                continue;
+            }
+
+            let mut device = match Device::from_device_id(id.clone()) {
+                Ok(d) => d,
+                Err(e) => {
+                    // the device is removed after it is tagged
+                    if e.get_errno() != Errno::ENODEV && e.get_errno() != Errno::ENOENT {
+                        ret = Err(Error::Nix {
+                            msg: format!("scan_devices_tag failed: from_device_id {} ({})", id, e),
+                            source: e.get_errno(),
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            // the tag is matched by the directory already
+            match self.test_matches(&mut device, MatchFlag::ALL - MatchFlag::TAG) {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    ret = Err(Error::Nix {
+                        msg: format!("scan_devices_tag failed: test_matches ({})", e),
+                        source: e.get_errno(),
+                    });
+                    continue;
+                }
+            }
+
+            if let Err(e) = self.add_device(Arc::new(Mutex::new(device))) {
+                ret = Err(Error::Nix {
+                    msg: format!("scan_devices_tag failed: add_device ({})", e),
+                    source: e.get_errno(),
+                });
+            }
+        }
+
+        ret
     }
 
     /// scan devices tags
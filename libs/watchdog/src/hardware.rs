@@ -27,6 +27,10 @@ pub trait Watchdog {
     fn close(&mut self) -> io::Result<i32>;
     /// feed the dog.
     fn feed(&mut self) -> io::Result<()>;
+    /// the timeout currently in effect, None if the watchdog is not configured.
+    fn timeout(&self) -> Option<Duration>;
+    /// release the device without disabling it, the watchdog keeps counting down.
+    fn release(&mut self);
 }
 
 /// Then define a structure to implement this trait. The implementation of the hardware watchdog depends on the corresponding hardware.
@@ -126,8 +130,8 @@ impl HardwareWatchdog {
         Ok(self.file.as_ref().unwrap().as_raw_fd())
     }
 
-    #[allow(dead_code)]
-    fn set_device(&mut self, device: String) {
+    /// change the watchdog device, the new device is opened on the next operation
+    pub fn set_device(&mut self, device: String) {
         if self.device != device {
             self.device = device;
             self.file = None
@@ -222,6 +226,20 @@ impl Watchdog for HardwareWatchdog {
         self.last_feed = Some(Instant::now());
         Ok(())
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        if !self.open {
+            return None;
+        }
+        self.timeout
+    }
+
+    fn release(&mut self) {
+        // Closing the device without disabling it first, the kernel keeps the
+        // watchdog running and resets the machine if nobody opens it again.
+        self.open = false;
+        self.file = None;
+    }
 }
 
 /// ERRNO_IS_NOT_SUPPORTED
@@ -298,6 +316,17 @@ mod tests {
         watchdog.config(None).unwrap();
     }
 
+    #[test]
+    fn test_not_watchdog_device() {
+        // /dev/null is a character device which does not support the watchdog ioctls
+        let mut watchdog = HardwareWatchdog::default();
+        watchdog.set_device("/dev/null".to_string());
+        assert!(watchdog.config(Some(Duration::from_secs(10))).is_err());
+        assert_eq!(watchdog.timeout(), None);
+        assert!(watchdog.feed().is_err());
+        assert_eq!(watchdog.close().unwrap(), 0);
+    }
+
     #[test]
     fn test_set_device() {
         if !has_watchdog0() {
@@ -8,7 +8,7 @@ install_dir=/usr/lib/sysmaster
 install -Dm0755 -t /usr/bin ${target_dir}/sctl || exit 1
 install -Dm0755 ${target_dir}/init /init || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/sysmaster || exit 1
-install -Dm0755 -t ${install_dir} ${target_dir}/fstab || exit 1
+install -Dm0755 -t ${install_dir}/system-generators ${target_dir}/fstab || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/sysmonitor || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/random_seed || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/rc-local-generator || exit 1
@@ -17,10 +17,17 @@ install -Dm0755 -t ${install_dir} ${target_dir}/basic.target || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/Multi-user.target || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/shutdown.target || exit 1
 install -Dm0755 -t ${install_dir} ${target_dir}/sysinit.target || exit 1
+install -Dm0755 -t ${install_dir} ${target_dir}/local-fs.target || exit 1
+install -Dm0755 -t ${install_dir} ${target_dir}/remote-fs.target || exit 1
+install -Dm0755 -t ${install_dir} ${target_dir}/swap.target || exit 1
+install -Dm0755 -t ${install_dir} ${target_dir}/network-online.target || exit 1
 
 strip ${target_dir}/lib*.so
 
 install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libmount.so || exit 1
+install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libswap.so || exit 1
+install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libautomount.so || exit 1
+install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libdevice_unit.so || exit 1
 install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libservice.so || exit 1
 install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libsocket.so || exit 1
 install -Dm0644 -t ${install_dir}/plugin ${target_dir}/libtarget.so || exit 1